@@ -9,5 +9,24 @@ pub(crate) async fn execute(mut req: Request) -> Result {
         .await
         .extract_with_context(&req)?;
     res.set_data(&data);
+    res.compute_etag(true);
+    Ok(res.into())
+}
+
+pub(crate) async fn export(mut req: Request) -> Result {
+    let mut query = Query::default();
+    let _: Response = req.query_validation(&mut query)?;
+    let body: Map = req.parse_body().await?;
+    let data = task::execute_union_query(&query, body)
+        .await
+        .extract_with_context(&req)?;
+    let mut columns = Vec::new();
+    for key in data.iter().flat_map(|row| row.keys()) {
+        if !columns.contains(&key.as_str()) {
+            columns.push(key.as_str());
+        }
+    }
+    let mut res = Response::csv(&data, &columns).provide_context(&req);
+    res.set_attachment_filename("tasks.csv");
     Ok(res.into())
 }
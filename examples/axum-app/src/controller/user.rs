@@ -7,7 +7,7 @@ pub(crate) async fn new(mut req: Request) -> Result {
     let mut user = User::new();
     let mut res: Response = req.model_validation(&mut user).await?;
 
-    user.upsert().await.extract_with_context(&req)?;
+    user.insert().await.extract_with_context(&req)?;
     let data = json!({
         "method": req.request_method().as_ref(),
         "path": req.request_path(),
@@ -9,5 +9,6 @@ fn main() {
     zino::AxumCluster::boot()
         .register(router::routes())
         .spawn(schedule::jobs())
-        .run(schedule::async_jobs())
+        .schedule(schedule::async_jobs())
+        .run(Vec::new())
 }
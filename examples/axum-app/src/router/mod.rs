@@ -16,7 +16,9 @@ pub(crate) fn routes() -> Vec<Router> {
     routes.push(controller);
 
     // Task controller.
-    let controller = Router::new().route("/task/execute", post(task::execute));
+    let controller = Router::new()
+        .route("/task/execute", post(task::execute))
+        .route("/task/export", post(task::export));
     routes.push(controller);
 
     // Stats controller.
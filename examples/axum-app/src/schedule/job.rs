@@ -1,4 +1,6 @@
-use zino::{BoxFuture, DateTime, JsonObjectExt, Map, Query, Schema, Uuid};
+use zino::{
+    Application, AxumCluster, BoxFuture, DateTime, Error, JsonObjectExt, Map, Query, Schema, Uuid,
+};
 use zino_model::User;
 
 pub(super) fn every_15s(job_id: Uuid, job_data: &mut Map, _last_tick: DateTime) {
@@ -9,7 +11,7 @@ pub(super) fn every_15s(job_id: Uuid, job_data: &mut Map, _last_tick: DateTime)
     job_data.upsert("current", DateTime::now().to_string());
     job_data.upsert("counter", counter);
     tracing::debug!(
-        job_data = format!("{job_data:?}"),
+        job_data = format!("{:?}", job_data.redacted(AxumCluster::redacted_keys())),
         "job {job_id} is executed every 15 seconds"
     );
 }
@@ -22,12 +24,16 @@ pub(super) fn every_20s(job_id: Uuid, job_data: &mut Map, _last_tick: DateTime)
     job_data.upsert("current", DateTime::now().to_string());
     job_data.upsert("counter", counter);
     tracing::debug!(
-        job_data = format!("{job_data:?}"),
+        job_data = format!("{:?}", job_data.redacted(AxumCluster::redacted_keys())),
         "job {job_id} is executed every 20 seconds"
     );
 }
 
-pub(super) fn every_30s(job_id: Uuid, job_data: &mut Map, _last_tick: DateTime) -> BoxFuture {
+pub(super) fn every_30s(
+    job_id: Uuid,
+    job_data: &mut Map,
+    _last_tick: DateTime,
+) -> BoxFuture<Result<(), Error>> {
     let counter = job_data
         .get("counter")
         .map(|c| c.as_u64().unwrap_or_default() + 1)
@@ -35,16 +41,15 @@ pub(super) fn every_30s(job_id: Uuid, job_data: &mut Map, _last_tick: DateTime)
     job_data.upsert("current", DateTime::now().to_string());
     job_data.upsert("counter", counter);
     tracing::debug!(
-        job_data = format!("{job_data:?}"),
+        job_data = format!("{:?}", job_data.redacted(AxumCluster::redacted_keys())),
         "async job {job_id} is executed every 30 seconds"
     );
 
     Box::pin(async {
         let query = Query::default();
         let columns = [("*", true), ("roles", true)];
-        match User::count_as(&query, &columns).await {
-            Ok(mut map) => job_data.append(&mut map),
-            Err(err) => tracing::error!("fail to count users: {err}"),
-        }
+        let mut map = User::count_as(&query, &columns).await?;
+        job_data.append(&mut map);
+        Ok(())
     })
 }
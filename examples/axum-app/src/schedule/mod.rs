@@ -1,4 +1,5 @@
-use zino::{AsyncCronJob, CronJob};
+use std::time::Duration;
+use zino::{CronJob, FallibleAsyncCronJob, Job};
 
 mod job;
 
@@ -9,6 +10,9 @@ pub(crate) fn jobs() -> Vec<(&'static str, CronJob)> {
     ]
 }
 
-pub(crate) fn async_jobs() -> Vec<(&'static str, AsyncCronJob)> {
-    vec![("0/30 * * * * *", job::every_30s as AsyncCronJob)]
+pub(crate) fn async_jobs() -> Vec<Job> {
+    let mut job = Job::new_fallible_async("0/30 * * * * *", job::every_30s as FallibleAsyncCronJob);
+    job.set_max_retries(3);
+    job.set_retry_delay(Duration::from_secs(1));
+    vec![job]
 }
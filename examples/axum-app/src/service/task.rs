@@ -1,8 +1,23 @@
+use std::sync::OnceLock;
 use zino::{Error, JsonObjectExt, Map, Query, Schema};
-use zino_core::connector::{DataFrameExecutor, GlobalConnector};
+use zino_core::{
+    connector::{DataFrameExecutor, GlobalConnector},
+    request::SingleFlight,
+};
 use zino_model::User;
 
+/// Concurrent union queries which share the results.
+static UNION_QUERIES: OnceLock<SingleFlight<Vec<Map>>> = OnceLock::new();
+
 pub(crate) async fn execute_union_query(query: &Query, body: Map) -> Result<Vec<Map>, Error> {
+    let key = format!("{query:?}:{body:?}");
+    UNION_QUERIES
+        .get_or_init(SingleFlight::new)
+        .run(&key, || union_query(query, body))
+        .await
+}
+
+async fn union_query(query: &Query, body: Map) -> Result<Vec<Map>, Error> {
     let records = User::find(query).await?;
     let connector = GlobalConnector::get("mock")
         .and_then(|data_source| data_source.get_arrow_connector())
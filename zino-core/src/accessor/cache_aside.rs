@@ -0,0 +1,207 @@
+use crate::{datetime::DateTime, error::Error, extend::JsonObjectExt, request::SingleFlight, Map};
+use opendal::{ErrorKind::NotFound, Operator};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{future::Future, sync::LazyLock, time::Duration};
+
+/// Reads the value cached at `path` by the operator of the accessor `name`.
+/// If it is missing or has expired, the value is loaded by the loader
+/// and written back with the time-to-live.
+///
+/// The concurrent misses of the same path for the accessor are de-duplicated
+/// by a [`SingleFlight`] within the process, and the waiting callers share the value
+/// loaded by the first one, so that the loader is called only once. The failures of the cache
+/// are logged and treated as misses, and the errors of the loader are not cached.
+pub(super) async fn cache_aside<T, F, Fut>(
+    name: &str,
+    operator: &Operator,
+    path: &str,
+    ttl: Duration,
+    loader: F,
+) -> Result<T, Error>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    if let Some(value) = read_cached(operator, path).await {
+        return serde_json::from_value(value).map_err(Error::from);
+    }
+
+    let key = format!("{name}:{path}");
+    let value = CACHE_FLIGHTS
+        .run(&key, || async {
+            if let Some(value) = read_cached(operator, path).await {
+                return Ok(value);
+            }
+
+            let value = serde_json::to_value(loader().await?)?;
+            write_cached(operator, path, &value, ttl).await;
+            Ok::<_, Error>(value)
+        })
+        .await?;
+    serde_json::from_value(value).map_err(Error::from)
+}
+
+/// Reads the cached value if it exists and has not expired.
+async fn read_cached(operator: &Operator, path: &str) -> Option<Value> {
+    let bytes = match operator.read(path).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            if err.kind() != NotFound {
+                tracing::warn!("fail to read the cached value of `{path}`: {err}");
+            }
+            return None;
+        }
+    };
+    let mut entry = serde_json::from_slice::<Map>(&bytes).ok()?;
+    let expires_at = entry.get_i64("expires_at")?;
+    if expires_at <= DateTime::now().timestamp_millis() {
+        return None;
+    }
+    entry.remove("value")
+}
+
+/// Writes the value into the cache with the time-to-live.
+async fn write_cached(operator: &Operator, path: &str, value: &Value, ttl: Duration) {
+    let ttl = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+    let expires_at = DateTime::now().timestamp_millis().saturating_add(ttl);
+    let mut entry = Map::from_entry("expires_at", expires_at);
+    entry.upsert("value", value.clone());
+    match serde_json::to_vec(&entry) {
+        Ok(bytes) => {
+            if let Err(err) = operator.write(path, bytes).await {
+                tracing::warn!("fail to write the cached value of `{path}`: {err}");
+            }
+        }
+        Err(err) => tracing::warn!("fail to serialize the cached value of `{path}`: {err}"),
+    }
+}
+
+/// Loads of the cached values in flight, keyed by the accessor name and the path.
+static CACHE_FLIGHTS: LazyLock<SingleFlight<Value>> = LazyLock::new(SingleFlight::new);
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_aside, CACHE_FLIGHTS};
+    use crate::{accessor::GlobalAccessor, error::Error, extend::JsonObjectExt, Map};
+    use futures::{executor::block_on, future, FutureExt};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        task::Poll,
+        time::Duration,
+    };
+    use toml::Table;
+
+    /// Yields to the other futures once.
+    async fn yield_now() {
+        let mut yielded = false;
+        future::poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn it_loads_values_on_misses() {
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let ttl = Duration::from_secs(60);
+        let loads = &AtomicUsize::new(0);
+        let load_user = move || async move {
+            loads.fetch_add(1, Relaxed);
+            Ok::<_, Error>(Map::from_entry("name", "alice"))
+        };
+        block_on(async {
+            let user = cache_aside("memory", &operator, "user/1", ttl, load_user)
+                .await
+                .unwrap();
+            assert_eq!(user.get_str("name"), Some("alice"));
+            assert_eq!(loads.load(Relaxed), 1);
+
+            let user = cache_aside("memory", &operator, "user/1", ttl, load_user)
+                .await
+                .unwrap();
+            assert_eq!(user.get_str("name"), Some("alice"));
+            assert_eq!(loads.load(Relaxed), 1);
+
+            cache_aside("memory", &operator, "user/2", Duration::ZERO, load_user)
+                .await
+                .unwrap();
+            cache_aside("memory", &operator, "user/2", Duration::ZERO, load_user)
+                .await
+                .unwrap();
+            assert_eq!(loads.load(Relaxed), 3);
+
+            let result = cache_aside("memory", &operator, "user/3", ttl, || async {
+                Err::<Map, _>(Error::new("the user does not exist"))
+            })
+            .await;
+            assert!(result.is_err());
+            cache_aside("memory", &operator, "user/3", ttl, load_user)
+                .await
+                .unwrap();
+            assert_eq!(loads.load(Relaxed), 4);
+        });
+    }
+
+    #[test]
+    fn it_loads_concurrent_misses_once() {
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let ttl = Duration::from_secs(60);
+        let loads = &AtomicUsize::new(0);
+        let load_report = move || async move {
+            loads.fetch_add(1, Relaxed);
+            yield_now().await;
+            Ok::<_, Error>(vec![1, 2, 3])
+        };
+        let callers =
+            (0..10).map(|_| cache_aside("memory", &operator, "report/daily", ttl, load_report));
+        let results = block_on(future::join_all(callers));
+        assert!(results.into_iter().all(|r| r.unwrap() == [1, 2, 3]));
+        assert_eq!(loads.load(Relaxed), 1);
+        assert!(!CACHE_FLIGHTS.is_in_flight("memory:report/daily"));
+
+        // The misses of the same path for different accessors are not shared.
+        let other_operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let callers = [("memory", &operator), ("backup", &other_operator)]
+            .map(|(name, operator)| cache_aside(name, operator, "report/weekly", ttl, load_report));
+        let results = block_on(future::join_all(callers));
+        assert!(results.into_iter().all(|r| r.unwrap() == [1, 2, 3]));
+        assert_eq!(loads.load(Relaxed), 3);
+    }
+
+    #[test]
+    fn it_cleans_up_cancelled_loads() {
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let ttl = Duration::from_secs(60);
+        let load_report = || future::pending::<Result<Vec<i32>, Error>>();
+        let mut load = Box::pin(cache_aside(
+            "memory",
+            &operator,
+            "report/monthly",
+            ttl,
+            load_report,
+        ));
+        assert!((&mut load).now_or_never().is_none());
+        assert!(CACHE_FLIGHTS.is_in_flight("memory:report/monthly"));
+
+        drop(load);
+        assert!(!CACHE_FLIGHTS.is_in_flight("memory:report/monthly"));
+
+        let load_report = || async { Ok::<_, Error>(vec![7, 8, 9]) };
+        let result = block_on(cache_aside(
+            "memory",
+            &operator,
+            "report/monthly",
+            ttl,
+            load_report,
+        ));
+        assert_eq!(result.unwrap(), [7, 8, 9]);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::datetime::DateTime;
+use opendal::Metadata;
+use serde::Serialize;
+
+/// An entry of the listing result for the storage service.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    /// Path.
+    path: String,
+    /// Content length.
+    content_length: u64,
+    /// Last modified time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<DateTime>,
+    /// A flag to indicate whether the entry is a directory.
+    is_dir: bool,
+}
+
+impl Entry {
+    /// Creates a new instance with the path and metadata.
+    pub(super) fn new(path: impl Into<String>, metadata: &Metadata) -> Self {
+        Self {
+            path: path.into(),
+            content_length: metadata.content_length(),
+            last_modified: metadata
+                .last_modified()
+                .map(|dt| DateTime::from_timestamp(dt.unix_timestamp())),
+            is_dir: metadata.mode().is_dir(),
+        }
+    }
+
+    /// Returns the path.
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the content length.
+    #[inline]
+    pub fn content_length(&self) -> u64 {
+        self.content_length
+    }
+
+    /// Returns the last modified time.
+    #[inline]
+    pub fn last_modified(&self) -> Option<DateTime> {
+        self.last_modified
+    }
+
+    /// Returns `true` if the entry is a directory.
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+}
@@ -0,0 +1,60 @@
+use std::{error, fmt};
+
+/// An error which can be returned when building an operator for the storage service
+/// or performing a conditional operation.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AccessorError {
+    /// The scheme is not supported or its feature flag is not enabled.
+    UnsupportedScheme(String),
+    /// A mandatory field is absent in the config.
+    MissingConfig {
+        /// Scheme of the storage service.
+        scheme: String,
+        /// Key of the absent field.
+        key: String,
+    },
+    /// The name of the accessor has already been registered.
+    AlreadyRegistered(String),
+    /// The object at the path already exists.
+    AlreadyExists(String),
+    /// The object at the path does not match the precondition.
+    ConditionNotMatch(String),
+    /// An error from the storage backend.
+    Backend(opendal::Error),
+}
+
+impl fmt::Display for AccessorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedScheme(scheme) => write!(f, "scheme `{scheme}` is unsupported"),
+            Self::MissingConfig { scheme, key } => {
+                write!(f, "the `{key}` field should be specified for `{scheme}`")
+            }
+            Self::AlreadyRegistered(name) => {
+                write!(f, "accessor `{name}` has already been registered")
+            }
+            Self::AlreadyExists(path) => write!(f, "object `{path}` already exists"),
+            Self::ConditionNotMatch(path) => {
+                write!(f, "object `{path}` does not match the precondition")
+            }
+            Self::Backend(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl error::Error for AccessorError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Backend(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<opendal::Error> for AccessorError {
+    #[inline]
+    fn from(err: opendal::Error) -> Self {
+        Self::Backend(err)
+    }
+}
@@ -0,0 +1,40 @@
+/// Options for listing the entries for the storage service.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListOptions {
+    /// Maximum number of entries.
+    limit: Option<usize>,
+    /// A flag to indicate whether the entries are listed recursively.
+    recursive: bool,
+}
+
+impl ListOptions {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of entries.
+    #[inline]
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = Some(limit);
+    }
+
+    /// Sets the flag to list the entries recursively.
+    #[inline]
+    pub fn set_recursive(&mut self, recursive: bool) {
+        self.recursive = recursive;
+    }
+
+    /// Returns the maximum number of entries.
+    #[inline]
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Returns `true` if the entries are listed recursively.
+    #[inline]
+    pub fn is_recursive(&self) -> bool {
+        self.recursive
+    }
+}
@@ -0,0 +1,158 @@
+use metrics::{Counter, Gauge, Histogram, Key, KeyName, Recorder, SharedString, Unit};
+use opendal::{layers::MetricsLayer, raw::Accessor, Layer};
+use std::cell::RefCell;
+
+/// A layer which emits the metrics of [`MetricsLayer`] under an optional namespace.
+///
+/// The metrics are registered when the layer is built, and the `opendal` prefix
+/// of their names is replaced by the namespace in the [`NamespacedRecorder`].
+/// So the namespace takes effect only if the recorder is installed by the metrics exporter.
+#[derive(Debug, Clone, Default)]
+pub(super) struct NamespacedMetricsLayer {
+    /// Namespace of the metric names.
+    namespace: Option<String>,
+}
+
+impl NamespacedMetricsLayer {
+    /// Creates a new instance with the optional namespace.
+    #[inline]
+    pub(super) fn new(namespace: Option<String>) -> Self {
+        Self { namespace }
+    }
+
+    /// Returns the namespace of the metric names.
+    #[inline]
+    pub(super) fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+}
+
+impl<A: Accessor> Layer<A> for NamespacedMetricsLayer {
+    type LayeredAccessor = <MetricsLayer as Layer<A>>::LayeredAccessor;
+
+    fn layer(&self, inner: A) -> Self::LayeredAccessor {
+        let namespace = METRICS_NAMESPACE.with(|cell| cell.replace(self.namespace.clone()));
+        let accessor = MetricsLayer.layer(inner);
+        METRICS_NAMESPACE.with(|cell| cell.replace(namespace));
+        accessor
+    }
+}
+
+/// A recorder which renames the metrics registered by a [`NamespacedMetricsLayer`]
+/// with the namespace, and delegates the others to the inner recorder as they are.
+#[derive(Debug)]
+pub(crate) struct NamespacedRecorder<R> {
+    /// Inner recorder.
+    inner: R,
+}
+
+impl<R: Recorder> NamespacedRecorder<R> {
+    /// Creates a new instance with the inner recorder.
+    #[inline]
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the key renamed with the current namespace if there is one.
+    fn rename_key(key: &Key) -> Option<Key> {
+        let name = namespaced_name(key.name())?;
+        let labels = key.labels().cloned().collect::<Vec<_>>();
+        Some(Key::from_parts(name, labels))
+    }
+
+    /// Returns the key name renamed with the current namespace if there is one.
+    fn rename_key_name(key: KeyName) -> KeyName {
+        match namespaced_name(key.as_str()) {
+            Some(name) => KeyName::from(name),
+            None => key,
+        }
+    }
+}
+
+impl<R: Recorder> Recorder for NamespacedRecorder<R> {
+    #[inline]
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner
+            .describe_counter(Self::rename_key_name(key), unit, description);
+    }
+
+    #[inline]
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner
+            .describe_gauge(Self::rename_key_name(key), unit, description);
+    }
+
+    #[inline]
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner
+            .describe_histogram(Self::rename_key_name(key), unit, description);
+    }
+
+    #[inline]
+    fn register_counter(&self, key: &Key) -> Counter {
+        match Self::rename_key(key) {
+            Some(key) => self.inner.register_counter(&key),
+            None => self.inner.register_counter(key),
+        }
+    }
+
+    #[inline]
+    fn register_gauge(&self, key: &Key) -> Gauge {
+        match Self::rename_key(key) {
+            Some(key) => self.inner.register_gauge(&key),
+            None => self.inner.register_gauge(key),
+        }
+    }
+
+    #[inline]
+    fn register_histogram(&self, key: &Key) -> Histogram {
+        match Self::rename_key(key) {
+            Some(key) => self.inner.register_histogram(&key),
+            None => self.inner.register_histogram(key),
+        }
+    }
+}
+
+/// Returns the metric name with the `opendal` prefix replaced by the namespace
+/// if a [`NamespacedMetricsLayer`] is being built in the current thread.
+fn namespaced_name(name: &str) -> Option<String> {
+    METRICS_NAMESPACE.with(|cell| {
+        let namespace = cell.borrow();
+        let namespace = namespace.as_deref()?;
+        let name = name.strip_prefix("opendal_").unwrap_or(name);
+        Some(format!("{namespace}_{name}"))
+    })
+}
+
+thread_local! {
+    /// Namespace of the metrics layer being built in the current thread.
+    static METRICS_NAMESPACE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NamespacedRecorder, METRICS_NAMESPACE};
+    use metrics::{Key, Label, Recorder};
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    #[test]
+    fn it_renames_metrics_in_namespace() {
+        let prometheus_recorder = PrometheusBuilder::new().build_recorder();
+        let handle = prometheus_recorder.handle();
+        let recorder = NamespacedRecorder::new(prometheus_recorder);
+        let labels = vec![Label::new("service", "memory")];
+
+        METRICS_NAMESPACE.with(|cell| cell.replace(Some("tenant".to_owned())));
+        let key = Key::from_parts("opendal_requests_total", labels.clone());
+        recorder.register_counter(&key).increment(1);
+        METRICS_NAMESPACE.with(|cell| cell.replace(None));
+
+        let key = Key::from_parts("opendal_requests_total", labels);
+        recorder.register_counter(&key).increment(1);
+
+        let metrics = handle.render();
+        assert!(metrics.contains(r#"tenant_requests_total{service="memory"} 1"#));
+        assert!(metrics.contains(r#"opendal_requests_total{service="memory"} 1"#));
+        assert!(!metrics.contains("tenant_opendal"));
+    }
+}
@@ -6,11 +6,13 @@
 //! |---------------|------------------------------------------|-----------------------|
 //! | `azblob`      | Azure Storage Blob services.             | `accessor`            |
 //! | `azdfs`       | Azure Data Lake Storage Gen2 services.   | `accessor`            |
+//! | `b2`          | Backblaze B2 services via the S3 API.    | `accessor`            |
 //! | `dashmap`     | Dashmap backend.                         | `accessor-dashmap`    |
 //! | `fs`          | POSIX alike file system.                 | `accessor`            |
 //! | `ftp`         | FTP and FTPS.                            | `accessor-ftp`        |
 //! | `gcs`         | Google Cloud Storage services.           | `accessor`            |
 //! | `ghac`        | Github Action Cache services.            | `accessor`            |
+//! | `hdfs`        | Hadoop Distributed File System.          | `accessor-hdfs`       |
 //! | `ipfs`        | InterPlanetary File System HTTP gateway. | `accessor-ipfs`       |
 //! | `ipmfs`       | InterPlanetary File System MFS API.      | `accessor`            |
 //! | `memcached`   | Memcached services.                      | `accessor-memcached`  |
@@ -19,28 +21,85 @@
 //! | `moka`        | Moka backend.                            | `accessor-moka`       |
 //! | `obs`         | Huawei Cloud Object Storage services.    | `accessor`            |
 //! | `oss`         | Aliyun Object Storage Service.           | `accessor`            |
+//! | `r2`          | Cloudflare R2 services.                  | `accessor`            |
 //! | `redis`       | Redis services.                          | `accessor-redis`      |
 //! | `s3`          | AWS S3 alike services.                   | `accessor`            |
+//! | `sftp`        | SFTP services.                           | `accessor-sftp`       |
 //! | `sled`        | Sled services.                           | `accessor-sled`       |
 //! | `webdav`      | WebDAV services.                         | `accessor`            |
 //! | `webhdfs`     | WebHDFS services.                        | `accessor`            |
 //!
+//! ## Backblaze B2
+//!
+//! The version of `opendal` in use does not provide a dedicated B2 service,
+//! so the `b2` scheme is backed by the S3-compatible API of Backblaze B2
+//! with the endpoint `https://s3.{region}.backblazeb2.com`.
+//! The bucket is addressed by its name instead of the `bucket-id` of the native API,
+//! and the `application-key-id` and `application-key` are used as the S3 credentials.
+//!
+//! ## Registry of operators
+//!
+//! The operators are built from the `[[accessor]]` tables in the config at startup,
+//! and new ones can be registered at runtime via [`GlobalAccessor::register()`].
+//! Since an [`Operator`] is cheap to clone, the lookup methods return an owned operator
+//! instead of a `&'static` reference.
+//!
+//! ## Confinement of paths
+//!
+//! The path-based methods of [`GlobalAccessor`], such as [`read()`](GlobalAccessor::read),
+//! [`write()`](GlobalAccessor::write), [`delete()`](GlobalAccessor::delete)
+//! and [`list()`](GlobalAccessor::list), re-anchor the paths under the `root` of the operator.
+//! Absolute paths are treated as relative to the root, and the `..` segments are rejected,
+//! so that a tenant can not access the objects of others.
+//!
 
 use crate::{extend::TomlTableExt, state::State};
+use bytes::Bytes;
+use futures::{
+    future, io,
+    stream::{BoxStream, StreamExt, TryStreamExt},
+};
 use opendal::{
-    layers::{MetricsLayer, RetryLayer, TracingLayer},
+    layers::{ConcurrentLimitLayer, RetryLayer, TimeoutLayer, TracingLayer},
+    raw::PresignedRequest,
     services::{Azblob, Azdfs, Fs, Gcs, Ghac, Ipmfs, Memory, Obs, Oss, Webdav, Webhdfs, S3},
-    Error,
-    ErrorKind::Unsupported,
-    Operator,
+    BlockingOperator, Capability, Error,
+    ErrorKind::{NotFound, PermissionDenied, Unexpected, Unsupported},
+    Metadata, Metakey, Operator,
 };
-use std::sync::LazyLock;
-use toml::Table;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    borrow::Cow,
+    future::Future,
+    sync::{LazyLock, Once},
+    time::Duration,
+};
+use toml::{value::Array, Table};
+
+mod cache_aside;
+mod entry;
+mod error;
+mod list_options;
+mod metrics_layer;
+mod presign_cache;
+mod registry;
+
+pub use entry::Entry;
+pub use error::AccessorError;
+pub use list_options::ListOptions;
+pub use presign_cache::PresignCache;
+
+pub(crate) use metrics_layer::NamespacedRecorder;
+
+use metrics_layer::NamespacedMetricsLayer;
+use registry::OperatorRegistry;
 
 #[cfg(feature = "accessor-dashmap")]
 use opendal::services::Dashmap;
 #[cfg(feature = "accessor-ftp")]
 use opendal::services::Ftp;
+#[cfg(feature = "accessor-hdfs")]
+use opendal::services::Hdfs;
 #[cfg(feature = "accessor-ipfs")]
 use opendal::services::Ipfs;
 #[cfg(feature = "accessor-memcached")]
@@ -49,6 +108,8 @@ use opendal::services::Memcached;
 use opendal::services::Moka;
 #[cfg(feature = "accessor-redis")]
 use opendal::services::Redis;
+#[cfg(feature = "accessor-sftp")]
+use opendal::services::Sftp;
 #[cfg(feature = "accessor-sled")]
 use opendal::services::Sled;
 
@@ -58,8 +119,13 @@ pub struct GlobalAccessor;
 
 impl GlobalAccessor {
     /// Constructs a new operator with the configuration for the specific storage service,
-    /// returning an error if it fails.
-    pub fn try_new_operator(scheme: &'static str, config: &Table) -> Result<Operator, Error> {
+    /// returning an error if it fails. The mandatory fields for the scheme are validated
+    /// to be present and non-empty.
+    pub fn try_new_operator(scheme: &str, config: &Table) -> Result<Operator, AccessorError> {
+        for key in Self::required_keys(scheme) {
+            Self::get_required_str(scheme, config, key)?;
+        }
+
         let operator = match scheme {
             "azblob" => {
                 let mut builder = Azblob::default();
@@ -102,6 +168,27 @@ impl GlobalAccessor {
                 }
                 Ok(Operator::new(builder)?.finish())
             }
+            "b2" => {
+                // Backblaze B2 is accessed via its S3-compatible API, where the bucket
+                // is addressed by its name, so that the `bucket-id` is not needed.
+                let region = Self::get_required_str(scheme, config, "region")?;
+                let bucket = Self::get_required_str(scheme, config, "bucket")?;
+                let endpoint = format!("https://s3.{region}.backblazeb2.com");
+                let mut builder = S3::default();
+                if let Some(root) = config.get_str("root") {
+                    builder.root(root);
+                }
+                builder.bucket(bucket);
+                builder.endpoint(&endpoint);
+                builder.region(region);
+                if let Some(application_key_id) = config.get_str("application-key-id") {
+                    builder.access_key_id(application_key_id);
+                }
+                if let Some(application_key) = State::decrypt_secret(config, "application-key") {
+                    builder.secret_access_key(application_key.as_ref());
+                }
+                Ok(Operator::new(builder)?.finish())
+            }
             #[cfg(feature = "accessor-dashmap")]
             "dashmap" => {
                 let builder = Dashmap::default();
@@ -166,6 +253,17 @@ impl GlobalAccessor {
                 }
                 Ok(Operator::new(builder)?.finish())
             }
+            #[cfg(feature = "accessor-hdfs")]
+            "hdfs" => {
+                let mut builder = Hdfs::default();
+                if let Some(root) = config.get_str("root") {
+                    builder.root(root);
+                }
+                if let Some(name_node) = config.get_str("name-node") {
+                    builder.name_node(name_node);
+                }
+                Ok(Operator::new(builder)?.finish())
+            }
             #[cfg(feature = "accessor-ipfs")]
             "ipfs" => {
                 let mut builder = Ipfs::default();
@@ -242,8 +340,8 @@ impl GlobalAccessor {
                 if let Some(access_key_id) = config.get_str("access-key-id") {
                     builder.access_key_id(access_key_id);
                 }
-                if let Some(secret_access_key) = config.get_str("secret_access_key") {
-                    builder.secret_access_key(secret_access_key);
+                if let Some(secret) = Self::get_obs_secret_access_key(config) {
+                    builder.secret_access_key(secret.as_ref());
                 }
                 Ok(Operator::new(builder)?.finish())
             }
@@ -264,8 +362,28 @@ impl GlobalAccessor {
                 if let Some(access_key_id) = config.get_str("access-key-id") {
                     builder.access_key_id(access_key_id);
                 }
-                if let Some(access_key_secret) = config.get_str("access-key-secret") {
-                    builder.access_key_secret(access_key_secret);
+                if let Some(secret) = State::decrypt_secret(config, "access-key-secret") {
+                    builder.access_key_secret(secret.as_ref());
+                }
+                Ok(Operator::new(builder)?.finish())
+            }
+            "r2" => {
+                let account_id = Self::get_required_str(scheme, config, "account-id")?;
+                let endpoint = format!("https://{account_id}.r2.cloudflarestorage.com");
+                let mut builder = S3::default();
+                if let Some(root) = config.get_str("root") {
+                    builder.root(root);
+                }
+                if let Some(bucket) = config.get_str("bucket") {
+                    builder.bucket(bucket);
+                }
+                builder.endpoint(&endpoint);
+                builder.region(config.get_str("region").unwrap_or("auto"));
+                if let Some(access_key_id) = config.get_str("access-key-id") {
+                    builder.access_key_id(access_key_id);
+                }
+                if let Some(secret) = State::decrypt_secret(config, "secret-access-key") {
+                    builder.secret_access_key(secret.as_ref());
                 }
                 Ok(Operator::new(builder)?.finish())
             }
@@ -309,8 +427,8 @@ impl GlobalAccessor {
                 if let Some(access_key_id) = config.get_str("access-key-id") {
                     builder.access_key_id(access_key_id);
                 }
-                if let Some(secret_access_key) = config.get_str("secret-access-key") {
-                    builder.secret_access_key(secret_access_key);
+                if let Some(secret) = State::decrypt_secret(config, "secret-access-key") {
+                    builder.secret_access_key(secret.as_ref());
                 }
                 if let Some(role_arn) = config.get_str("role-arn") {
                     builder.role_arn(role_arn);
@@ -320,6 +438,26 @@ impl GlobalAccessor {
                 }
                 Ok(Operator::new(builder)?.finish())
             }
+            #[cfg(feature = "accessor-sftp")]
+            "sftp" => {
+                let mut builder = Sftp::default();
+                if let Some(root) = config.get_str("root") {
+                    builder.root(root);
+                }
+                if let Some(endpoint) = config.get_str("endpoint") {
+                    builder.endpoint(endpoint);
+                }
+                if let Some(user) = config.get_str("user") {
+                    builder.user(user);
+                }
+                if let Some(key) = config.get_str("key") {
+                    builder.key(key);
+                }
+                if let Some(strategy) = config.get_str("known-hosts-strategy") {
+                    builder.known_hosts_strategy(strategy);
+                }
+                Ok(Operator::new(builder)?.finish())
+            }
             #[cfg(feature = "accessor-sled")]
             "sled" => {
                 let mut builder = Sled::default();
@@ -357,43 +495,1255 @@ impl GlobalAccessor {
                 }
                 Ok(Operator::new(builder)?.finish())
             }
-            _ => Err(Error::new(Unsupported, "scheme is unsupported")),
+            _ => Err(AccessorError::UnsupportedScheme(scheme.to_owned())),
         };
-        operator.map(|op| {
-            op.layer(TracingLayer)
-                .layer(MetricsLayer)
-                .layer(RetryLayer::new())
+        let mut operator = operator?.layer(TracingLayer);
+        if let Some(metrics_layer) = Self::new_metrics_layer(config) {
+            operator = operator.layer(metrics_layer);
+        }
+        if let Some(max_concurrency) = config.get_usize("max-concurrency").filter(|&n| n > 0) {
+            operator = operator.layer(ConcurrentLimitLayer::new(max_concurrency));
+        }
+        Ok(operator
+            .layer(Self::new_retry_layer(config))
+            .layer(Self::new_timeout_layer(config)))
+    }
+
+    /// Constructs a new metrics layer unless the `metrics` setting is `false` in the config.
+    /// The metric names are prefixed by the optional `metrics-namespace` setting
+    /// instead of `opendal`.
+    fn new_metrics_layer(config: &Table) -> Option<NamespacedMetricsLayer> {
+        config.get_bool("metrics").unwrap_or(true).then(|| {
+            let namespace = config.get_str("metrics-namespace").map(|s| s.to_owned());
+            NamespacedMetricsLayer::new(namespace)
+        })
+    }
+
+    /// Constructs a new retry layer with the retry settings in the config.
+    /// The defaults of the layer are kept for the absent settings.
+    fn new_retry_layer(config: &Table) -> RetryLayer {
+        let (max_retries, min_delay, max_delay) = Self::parse_retry_settings(config);
+        let mut retry_layer = RetryLayer::new();
+        if let Some(max_retries) = max_retries {
+            retry_layer = retry_layer.with_max_times(max_retries);
+        }
+        if let Some(min_delay) = min_delay {
+            retry_layer = retry_layer.with_min_delay(min_delay);
+        }
+        if let Some(max_delay) = max_delay {
+            retry_layer = retry_layer.with_max_delay(max_delay);
+        }
+        retry_layer
+    }
+
+    /// Parses the optional `max-retries`, `retry-min-delay` and `retry-max-delay`
+    /// settings in the config.
+    fn parse_retry_settings(config: &Table) -> (Option<usize>, Option<Duration>, Option<Duration>) {
+        let max_retries = config.get_usize("max-retries");
+        let min_delay = config.get_duration("retry-min-delay");
+        let max_delay = config.get_duration("retry-max-delay");
+        (max_retries, min_delay, max_delay)
+    }
+
+    /// Constructs a new timeout layer with the optional `timeout` and `io-timeout`
+    /// settings in the config. A zero duration is treated as absent.
+    fn new_timeout_layer(config: &Table) -> TimeoutLayer {
+        let timeout = config
+            .get_duration("timeout")
+            .filter(|d| !d.is_zero())
+            .unwrap_or_else(|| Duration::from_secs(30));
+        let io_timeout = config
+            .get_duration("io-timeout")
+            .filter(|d| !d.is_zero())
+            .unwrap_or_else(|| Duration::from_secs(10));
+        TimeoutLayer::new()
+            .with_timeout(timeout)
+            .with_io_timeout(io_timeout)
+    }
+
+    /// Decrypts the secret access key for the `obs` scheme.
+    /// The legacy `secret_access_key` field is still accepted but deprecated.
+    fn get_obs_secret_access_key(config: &Table) -> Option<Cow<'_, str>> {
+        State::decrypt_secret(config, "secret-access-key").or_else(|| {
+            let secret = State::decrypt_secret(config, "secret_access_key")?;
+            static DEPRECATION_WARNING: Once = Once::new();
+            DEPRECATION_WARNING.call_once(|| {
+                tracing::warn!(
+                    "the `secret_access_key` field for the `obs` accessor is deprecated, \
+                        use `secret-access-key` instead"
+                );
+            });
+            Some(secret)
         })
     }
 
+    /// Returns the keys of the mandatory fields in the config for the scheme.
+    fn required_keys(scheme: &str) -> &'static [&'static str] {
+        match scheme {
+            "azblob" => &["container"],
+            "azdfs" => &["filesystem"],
+            "b2" => &["region", "bucket"],
+            "ftp" | "memcached" | "sftp" | "webdav" => &["endpoint"],
+            "gcs" | "s3" => &["bucket"],
+            "hdfs" => &["name-node"],
+            "minio" | "obs" | "oss" => &["bucket", "endpoint"],
+            "r2" => &["account-id", "bucket"],
+            _ => &[],
+        }
+    }
+
+    /// Extracts the non-empty string corresponding to the mandatory key in the config,
+    /// returning an error which indicates the absent key if it fails.
+    fn get_required_str<'a>(
+        scheme: &str,
+        config: &'a Table,
+        key: &str,
+    ) -> Result<&'a str, AccessorError> {
+        config
+            .get_str(key)
+            .filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| AccessorError::MissingConfig {
+                scheme: scheme.to_owned(),
+                key: key.to_owned(),
+            })
+    }
+
+    /// Registers a new operator for the storage service at runtime,
+    /// returning an error if it fails to build or the name has already been registered.
+    pub fn register(
+        name: impl Into<String>,
+        scheme: &str,
+        config: &Table,
+    ) -> Result<(), AccessorError> {
+        let name = name.into();
+        let operator = Self::try_new_operator(scheme, config)?;
+        if GLOBAL_ACCESSOR.insert(name.as_str(), operator) {
+            Ok(())
+        } else {
+            Err(AccessorError::AlreadyRegistered(name))
+        }
+    }
+
+    /// Deregisters the operator for the storage service.
+    /// Returns `true` if it has been removed. Note that the `memory` accessor is always present.
+    pub fn deregister(name: &str) -> bool {
+        name != "memory" && GLOBAL_ACCESSOR.remove(name).is_some()
+    }
+
     /// Gets the operator for the specific storage service.
     #[inline]
-    pub fn get(name: &'static str) -> Option<&'static Operator> {
-        GLOBAL_ACCESSOR
+    pub fn get(name: &str) -> Option<Operator> {
+        GLOBAL_ACCESSOR.get(name)
+    }
+
+    /// Gets the blocking operator for the specific storage service, which shares
+    /// the same layers as the async operator. It returns `None` if the operator does not exist
+    /// or the service does not support blocking operations.
+    ///
+    /// The blocking operator is intended for sync contexts such as startup scripts.
+    /// It must not be called from within an async context, since it blocks the current thread.
+    pub fn get_blocking(name: &str) -> Option<BlockingOperator> {
+        Self::get(name)
+            .filter(|operator| operator.info().full_capability().blocking)
+            .map(|operator| operator.blocking())
+    }
+
+    /// Gets the operator for the specific storage service,
+    /// falling back to the default operator if it does not exist.
+    ///
+    /// The default operator is determined in the following precedence:
+    ///
+    /// 1. The accessor specified by the top-level `default-accessor` field in the config
+    /// 2. The always-present `memory` accessor
+    #[inline]
+    pub fn get_or_default(name: &str) -> Operator {
+        Self::get_or_default_in(&GLOBAL_ACCESSOR, name, *DEFAULT_ACCESSOR)
+    }
+
+    /// Returns the name of the default storage accessor,
+    /// which is determined in the same precedence as [`get_or_default()`](Self::get_or_default).
+    #[inline]
+    pub fn default_name() -> &'static str {
+        *DEFAULT_ACCESSOR
+    }
+
+    /// Gets the operator in the registry, falling back to the operator
+    /// with the default name and then the `memory` operator.
+    fn get_or_default_in(registry: &OperatorRegistry, name: &str, default_name: &str) -> Operator {
+        registry
+            .get(name)
+            .or_else(|| registry.get(default_name))
+            .or_else(|| registry.get("memory"))
+            .expect("the `memory` accessor should always be present")
+    }
+
+    /// Gets the operator for the specific storage service,
+    /// returning an error if it does not exist.
+    #[inline]
+    pub fn try_get(name: &str) -> Result<Operator, Error> {
+        Self::try_get_in(&GLOBAL_ACCESSOR, name)
+    }
+
+    /// Gets the operator in the registry, returning an error if it does not exist.
+    fn try_get_in(registry: &OperatorRegistry, name: &str) -> Result<Operator, Error> {
+        registry
+            .get(name)
+            .ok_or_else(|| Error::new(NotFound, "accessor does not exist"))
+    }
+
+    /// Returns the capabilities of the operator for the specific storage service,
+    /// which can be used to check whether an operation is supported before calling it.
+    #[inline]
+    pub fn capabilities(name: &str) -> Option<Capability> {
+        Self::capabilities_in(&GLOBAL_ACCESSOR, name)
+    }
+
+    /// Returns the capabilities of the operator for the storage service in the registry.
+    fn capabilities_in(registry: &OperatorRegistry, name: &str) -> Option<Capability> {
+        registry
+            .get(name)
+            .map(|operator| operator.info().full_capability())
+    }
+
+    /// Returns `true` if the operator for the specific storage service supports presigning.
+    #[inline]
+    pub fn supports_presign(name: &str) -> bool {
+        Self::supports_presign_in(&GLOBAL_ACCESSOR, name)
+    }
+
+    /// Returns `true` if the operator for the storage service in the registry
+    /// supports presigning.
+    fn supports_presign_in(registry: &OperatorRegistry, name: &str) -> bool {
+        Self::capabilities_in(registry, name).is_some_and(|capability| capability.presign)
+    }
+
+    /// Performs a lightweight check against every operator concurrently,
+    /// and returns the results for all the storage services.
+    /// It is not fatal so that the caller can decide whether to abort or not.
+    #[inline]
+    pub async fn check_all() -> Vec<(String, Result<(), Error>)> {
+        Self::check_all_in(&GLOBAL_ACCESSOR).await
+    }
+
+    /// Performs a lightweight check against every operator in the registry concurrently.
+    async fn check_all_in(registry: &OperatorRegistry) -> Vec<(String, Result<(), Error>)> {
+        let checks = registry
+            .snapshot()
+            .into_iter()
+            .map(|(name, operator)| async move {
+                let result = operator.check().await;
+                if let Err(err) = &result {
+                    tracing::error!("fail to check the `{name}` accessor: {err}");
+                }
+                (name, result)
+            });
+        future::join_all(checks).await
+    }
+
+    /// Presigns an operation to read the object at `path` for the storage service,
+    /// which can be used to grant a temporary access without sharing the credentials.
+    #[inline]
+    pub fn presign_read(
+        name: &str,
+        path: &str,
+        expire: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        Self::presign_read_in(&GLOBAL_ACCESSOR, name, path, expire)
+    }
+
+    /// Presigns an operation to read the object at `path` for the storage service in the registry.
+    fn presign_read_in(
+        registry: &OperatorRegistry,
+        name: &str,
+        path: &str,
+        expire: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        let operator = Self::get_presign_operator_in(registry, name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        operator.presign_read(&path, expire)
+    }
+
+    /// Presigns an operation to write the object at `path` for the storage service,
+    /// which can be used to grant a temporary access without sharing the credentials.
+    #[inline]
+    pub fn presign_write(
+        name: &str,
+        path: &str,
+        expire: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        Self::presign_write_in(&GLOBAL_ACCESSOR, name, path, expire)
+    }
+
+    /// Presigns an operation to write the object at `path` for the storage service in the registry.
+    fn presign_write_in(
+        registry: &OperatorRegistry,
+        name: &str,
+        path: &str,
+        expire: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        let operator = Self::get_presign_operator_in(registry, name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        operator.presign_write(&path, expire)
+    }
+
+    /// Removes the objects at `paths` for the storage service in batch.
+    /// For the services without native batch support,
+    /// it falls back to removing the objects sequentially.
+    #[inline]
+    pub async fn remove_all(name: &str, paths: &[String]) -> Result<(), Error> {
+        Self::remove_all_in(&GLOBAL_ACCESSOR, name, paths).await
+    }
+
+    /// Removes the objects at `paths` for the storage service in the registry in batch.
+    async fn remove_all_in(
+        registry: &OperatorRegistry,
+        name: &str,
+        paths: &[String],
+    ) -> Result<(), Error> {
+        let operator = Self::try_get_in(registry, name)?;
+        let root = operator.info().root();
+        let paths = paths
             .iter()
-            .find_map(|(key, operator)| (key == &name).then_some(operator))
+            .map(|path| Self::anchor_path(&root, path))
+            .collect::<Result<Vec<_>, _>>()?;
+        operator.remove(paths).await
+    }
+
+    /// Removes the objects at `paths` for the storage service one by one,
+    /// and returns a list of the paths which fail to be removed with the errors.
+    #[inline]
+    pub async fn remove_each(
+        name: &str,
+        paths: &[String],
+    ) -> Result<Vec<(String, Error)>, Error> {
+        Self::remove_each_in(&GLOBAL_ACCESSOR, name, paths).await
+    }
+
+    /// Removes the objects at `paths` for the storage service in the registry one by one.
+    /// The paths are checked before any object is removed.
+    async fn remove_each_in(
+        registry: &OperatorRegistry,
+        name: &str,
+        paths: &[String],
+    ) -> Result<Vec<(String, Error)>, Error> {
+        let operator = Self::try_get_in(registry, name)?;
+        let root = operator.info().root();
+        let anchored_paths = paths
+            .iter()
+            .map(|path| Self::anchor_path(&root, path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut failures = Vec::new();
+        for (path, anchored_path) in paths.iter().zip(anchored_paths) {
+            if let Err(err) = operator.delete(&anchored_path).await {
+                failures.push((path.to_owned(), err));
+            }
+        }
+        Ok(failures)
+    }
+
+    /// Transfers the object at `from_path` for the storage service `from` to
+    /// the object at `to_path` for the storage service `to`, and returns
+    /// the number of bytes copied. The bytes are streamed without buffering the full payload.
+    #[inline]
+    pub async fn transfer(
+        from: &str,
+        from_path: &str,
+        to: &str,
+        to_path: &str,
+    ) -> Result<u64, Error> {
+        Self::transfer_in(&GLOBAL_ACCESSOR, from, from_path, to, to_path).await
+    }
+
+    /// Transfers the object between the storage services in the registry,
+    /// and returns the number of bytes copied.
+    async fn transfer_in(
+        registry: &OperatorRegistry,
+        from: &str,
+        from_path: &str,
+        to: &str,
+        to_path: &str,
+    ) -> Result<u64, Error> {
+        let source = Self::try_get_in(registry, from)?;
+        let destination = Self::try_get_in(registry, to)?;
+        let source_path = Self::anchor_path(&source.info().root(), from_path)?;
+        let destination_path = Self::anchor_path(&destination.info().root(), to_path)?;
+        let reader = source.reader(&source_path).await?;
+        let mut writer = destination.writer(&destination_path).await?;
+        let bytes = io::copy(reader, &mut writer).await.map_err(|err| {
+            let message = format!("fail to transfer `{from_path}` to `{to_path}`: {err}");
+            Error::new(Unexpected, &message)
+        })?;
+        writer.close().await?;
+        Ok(bytes)
+    }
+
+    /// Reads the object at `path` for the storage service.
+    pub async fn read(name: &str, path: &str) -> Result<Vec<u8>, Error> {
+        let operator = Self::try_get(name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        operator.read(&path).await
+    }
+
+    /// Writes the bytes into the object at `path` for the storage service.
+    pub async fn write(name: &str, path: &str, bytes: impl Into<Bytes>) -> Result<(), Error> {
+        let operator = Self::try_get(name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        operator.write(&path, bytes.into()).await
+    }
+
+    /// Writes the bytes into the object at `path` for the storage service
+    /// if it does not exist, returning an `AlreadyExists` error otherwise.
+    ///
+    /// Since the conditional writes are not provided by the backends natively,
+    /// the existence is checked before writing, which is not atomic under concurrent writes.
+    pub async fn write_if_not_exists(
+        name: &str,
+        path: &str,
+        bytes: impl Into<Bytes>,
+    ) -> Result<(), AccessorError> {
+        let operator = Self::try_get(name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        Self::check_precondition(&path, operator.stat(&path).await, None)?;
+        operator.write(&path, bytes.into()).await?;
+        Ok(())
+    }
+
+    /// Writes the bytes into the object at `path` for the storage service
+    /// if its ETag matches, returning a `ConditionNotMatch` error otherwise.
+    /// It returns an unsupported error if the service does not provide ETags.
+    ///
+    /// Since the conditional writes are not provided by the backends natively,
+    /// the ETag is checked before writing, which is not atomic under concurrent writes.
+    pub async fn write_if_match(
+        name: &str,
+        path: &str,
+        etag: &str,
+        bytes: impl Into<Bytes>,
+    ) -> Result<(), AccessorError> {
+        let operator = Self::try_get(name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        Self::check_precondition(&path, operator.stat(&path).await, Some(etag))?;
+        operator.write(&path, bytes.into()).await?;
+        Ok(())
+    }
+
+    /// Checks the precondition with the metadata of the object.
+    /// The object should not exist if the ETag is `None`.
+    fn check_precondition(
+        path: &str,
+        metadata: Result<Metadata, Error>,
+        etag: Option<&str>,
+    ) -> Result<(), AccessorError> {
+        match (metadata, etag) {
+            (Ok(_), None) => Err(AccessorError::AlreadyExists(path.to_owned())),
+            (Err(err), None) if err.kind() == NotFound => Ok(()),
+            (Ok(metadata), Some(etag)) => {
+                let current_etag = metadata.etag().ok_or_else(|| {
+                    Error::new(Unsupported, "etag is unsupported for the accessor")
+                })?;
+                fn normalize(etag: &str) -> &str {
+                    etag.trim_start_matches("W/").trim_matches('"')
+                }
+                if normalize(current_etag) == normalize(etag) {
+                    Ok(())
+                } else {
+                    Err(AccessorError::ConditionNotMatch(path.to_owned()))
+                }
+            }
+            (Err(err), Some(_)) if err.kind() == NotFound => {
+                Err(AccessorError::ConditionNotMatch(path.to_owned()))
+            }
+            (Err(err), _) => Err(err.into()),
+        }
+    }
+
+    /// Deletes the object at `path` for the storage service.
+    pub async fn delete(name: &str, path: &str) -> Result<(), Error> {
+        let operator = Self::try_get(name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        operator.delete(&path).await
+    }
+
+    /// Reads the value cached at `key` for the storage service. On a cache miss,
+    /// the value is loaded by the loader and written back as JSON with the time-to-live.
+    /// The loader is called only once for the concurrent misses of the same key,
+    /// so that a model can be cached by loading it with `try_get_model` in the loader.
+    pub async fn cache_aside<T, F, Fut>(
+        name: &str,
+        key: &str,
+        ttl: Duration,
+        loader: F,
+    ) -> Result<T, crate::error::Error>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, crate::error::Error>>,
+    {
+        let operator = Self::try_get(name)?;
+        let path = Self::anchor_path(&operator.info().root(), key)?;
+        cache_aside::cache_aside(name, &operator, &path, ttl, loader).await
+    }
+
+    /// Writes the bytes into the object at `path` for the storage service,
+    /// with the `content-type` inferred from the path extension.
+    /// It falls back to `application/octet-stream` for unknown extensions.
+    pub async fn write_with_guess(
+        name: &str,
+        path: &str,
+        bytes: impl Into<Bytes>,
+    ) -> Result<(), Error> {
+        let operator = Self::try_get(name)?;
+        let path = &Self::anchor_path(&operator.info().root(), path)?;
+        let content_type = Self::guess_content_type(path);
+        operator
+            .write_with(path, bytes.into())
+            .content_type(content_type)
+            .await
+    }
+
+    /// Normalizes the path relative to the root of an operator. The root is stripped
+    /// if the path starts with it, and the `.` segments and duplicate slashes are removed.
+    /// It returns an error if the path contains the `..` segments.
+    pub(crate) fn anchor_path(root: &str, path: &str) -> Result<String, Error> {
+        let root = root.trim_matches('/');
+        let mut relative_path = path.trim_start_matches('/');
+        if path.starts_with('/') &&
+            !root.is_empty() &&
+            let Some(stripped_path) = relative_path.strip_prefix(root) &&
+            (stripped_path.is_empty() || stripped_path.starts_with('/'))
+        {
+            relative_path = stripped_path;
+        }
+
+        let mut segments = Vec::new();
+        for segment in relative_path.split('/') {
+            match segment {
+                "" | "." => (),
+                ".." => {
+                    let message = format!("path `{path}` should not contain `..` segments");
+                    return Err(Error::new(PermissionDenied, &message));
+                }
+                _ => segments.push(segment),
+            }
+        }
+        if segments.is_empty() {
+            Ok("/".to_owned())
+        } else if path.ends_with('/') {
+            Ok(segments.join("/") + "/")
+        } else {
+            Ok(segments.join("/"))
+        }
+    }
+
+    /// Guesses the `content-type` from the path extension.
+    pub(crate) fn guess_content_type(path: &str) -> &'static str {
+        mime_guess::from_path(path)
+            .first_raw()
+            .unwrap_or("application/octet-stream")
+    }
+
+    /// Lists the entries under the `path` for the storage service.
+    pub async fn list(
+        name: &str,
+        path: &str,
+        options: ListOptions,
+    ) -> Result<Vec<Entry>, Error> {
+        Self::lister(name, path, options).await?.try_collect().await
+    }
+
+    /// Returns a stream of the entries under the `path` for the storage service,
+    /// which can be used as an async cursor without buffering all the entries.
+    #[inline]
+    pub async fn lister(
+        name: &str,
+        path: &str,
+        options: ListOptions,
+    ) -> Result<BoxStream<'static, Result<Entry, Error>>, Error> {
+        Self::lister_in(&GLOBAL_ACCESSOR, name, path, options).await
+    }
+
+    /// Returns a stream of the entries under the `path` for the storage service in the registry.
+    async fn lister_in(
+        registry: &OperatorRegistry,
+        name: &str,
+        path: &str,
+        options: ListOptions,
+    ) -> Result<BoxStream<'static, Result<Entry, Error>>, Error> {
+        let operator = Self::try_get_in(registry, name)?;
+        let path = Self::anchor_path(&operator.info().root(), path)?;
+        let lister = if options.is_recursive() {
+            operator.scan(&path).await?
+        } else {
+            operator.list(&path).await?
+        };
+        let stream = lister.and_then(move |entry| {
+            let operator = operator.clone();
+            async move {
+                let metakey = Metakey::Mode | Metakey::ContentLength | Metakey::LastModified;
+                let metadata = operator.metadata(&entry, metakey).await?;
+                Ok(Entry::new(entry.path(), &metadata))
+            }
+        });
+        if let Some(limit) = options.limit() {
+            Ok(stream.take(limit).boxed())
+        } else {
+            Ok(stream.boxed())
+        }
+    }
+
+    /// Gets the operator for the specific storage service in the registry,
+    /// returning an error if it does not exist or does not support presigning.
+    fn get_presign_operator_in(registry: &OperatorRegistry, name: &str) -> Result<Operator, Error> {
+        let operator = Self::try_get_in(registry, name)?;
+        if operator.info().full_capability().presign {
+            Ok(operator)
+        } else {
+            Err(Error::new(Unsupported, "presign is unsupported for the accessor"))
+        }
     }
 }
 
 /// Global storage accessor.
-static GLOBAL_ACCESSOR: LazyLock<Vec<(&'static str, Operator)>> = LazyLock::new(|| {
-    let mut operators = Vec::new();
-    let memory_operator = Operator::new(Memory::default())
-        .expect("fail to create an operator for the memory accessor")
-        .layer(TracingLayer)
-        .layer(MetricsLayer)
-        .layer(RetryLayer::new())
-        .finish();
-    operators.push(("memory", memory_operator));
+static GLOBAL_ACCESSOR: LazyLock<OperatorRegistry> = LazyLock::new(|| {
+    let registry = OperatorRegistry::new();
+    let memory_operator = GlobalAccessor::try_new_operator("memory", &Table::new())
+        .expect("fail to create an operator for the memory accessor");
+    registry.insert("memory", memory_operator);
 
     if let Some(accessors) = State::shared().config().get_array("accessor") {
-        for accessor in accessors.iter().filter_map(|v| v.as_table()) {
-            let scheme = accessor.get_str("scheme").unwrap_or("unkown");
-            let name = accessor.get_str("name").unwrap_or(scheme);
-            let operator = GlobalAccessor::try_new_operator(scheme, accessor)
-                .unwrap_or_else(|err| panic!("fail to build `{scheme}` operator: {err}"));
-            operators.push((name, operator));
+        register_accessors(&registry, accessors);
+    }
+    registry
+});
+
+/// Registers the operators for the accessors in the config.
+/// If an operator fails to build, it panics unless the accessor is marked as `optional`,
+/// in which case the error is logged and the accessor is skipped.
+fn register_accessors(registry: &OperatorRegistry, accessors: &Array) {
+    for accessor in accessors.iter().filter_map(|v| v.as_table()) {
+        let scheme = accessor.get_str("scheme").unwrap_or("unkown");
+        let name = accessor.get_str("name").unwrap_or(scheme);
+        let operator = match GlobalAccessor::try_new_operator(scheme, accessor) {
+            Ok(operator) => operator,
+            Err(err) if accessor.get_bool("optional").unwrap_or(false) => {
+                tracing::error!("fail to build the optional accessor `{name}`: {err}");
+                continue;
+            }
+            Err(err) => panic!("fail to build `{scheme}` operator: {err}"),
+        };
+        if !registry.insert(name, operator) {
+            tracing::warn!("accessor `{name}` has already been registered");
         }
     }
-    operators
+}
+
+/// Name of the default storage accessor.
+static DEFAULT_ACCESSOR: LazyLock<&'static str> = LazyLock::new(|| {
+    let default_accessor = State::shared()
+        .config()
+        .get_str("default-accessor")
+        .unwrap_or("memory");
+    if GLOBAL_ACCESSOR.get(default_accessor).is_some() {
+        default_accessor
+    } else {
+        tracing::warn!("default accessor `{default_accessor}` does not exist");
+        "memory"
+    }
 });
+
+#[cfg(test)]
+mod tests {
+    use super::{register_accessors, AccessorError, GlobalAccessor, ListOptions, OperatorRegistry};
+    use crate::extend::TomlTableExt;
+    use futures::{executor::block_on, TryStreamExt};
+    use opendal::{
+        ErrorKind::{NotFound, PermissionDenied, Unsupported},
+        Scheme,
+    };
+    use std::time::Duration;
+    use toml::Table;
+
+    /// Creates a registry with the memory operators for the names.
+    fn new_memory_registry(names: &[&str]) -> OperatorRegistry {
+        let registry = OperatorRegistry::new();
+        for &name in names {
+            let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+            registry.insert(name, operator);
+        }
+        registry
+    }
+
+    #[test]
+    fn it_presigns_read_and_write() {
+        let config = r#"
+            bucket = "test"
+            endpoint = "https://s3.amazonaws.com"
+            region = "us-east-1"
+            access-key-id = "access_key_id"
+            secret-access-key = "secret_access_key"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let operator = GlobalAccessor::try_new_operator("s3", &config).unwrap();
+        assert!(operator.info().full_capability().presign);
+
+        let expire = Duration::from_secs(3600);
+        let request = operator.presign_read("avatar.png", expire).unwrap();
+        assert_eq!(request.method(), "GET");
+        assert!(request.uri().to_string().contains("avatar.png"));
+
+        let config = r#"
+            bucket = "test"
+            endpoint = "https://oss-cn-hangzhou.aliyuncs.com"
+            presign-endpoint = "https://cdn.example.com"
+            access-key-id = "access_key_id"
+            access-key-secret = "access_key_secret"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let operator = GlobalAccessor::try_new_operator("oss", &config).unwrap();
+        assert!(operator.info().full_capability().presign);
+
+        let request = operator.presign_write("avatar.png", expire).unwrap();
+        assert_eq!(request.method(), "PUT");
+        assert!(request.uri().to_string().contains("cdn.example.com"));
+
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        assert!(!operator.info().full_capability().presign);
+    }
+
+    #[test]
+    fn it_anchors_presigned_paths() {
+        let registry = new_memory_registry(&["memory"]);
+        let config = r#"
+            bucket = "test"
+            endpoint = "https://s3.amazonaws.com"
+            region = "us-east-1"
+            access-key-id = "access_key_id"
+            secret-access-key = "secret_access_key"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let operator = GlobalAccessor::try_new_operator("s3", &config).unwrap();
+        registry.insert("s3", operator);
+
+        let expire = Duration::from_secs(3600);
+        let request =
+            GlobalAccessor::presign_read_in(&registry, "s3", "/avatars/./bob.png", expire).unwrap();
+        assert!(request.uri().to_string().contains("avatars/bob.png"));
+
+        let err =
+            GlobalAccessor::presign_read_in(&registry, "s3", "../secrets.txt", expire).unwrap_err();
+        assert_eq!(err.kind(), PermissionDenied);
+        let err = GlobalAccessor::presign_write_in(&registry, "s3", "avatars/../../x", expire)
+            .unwrap_err();
+        assert_eq!(err.kind(), PermissionDenied);
+
+        let err =
+            GlobalAccessor::presign_read_in(&registry, "memory", "avatar.png", expire).unwrap_err();
+        assert_eq!(err.kind(), Unsupported);
+    }
+
+    #[test]
+    fn it_requires_account_id_for_r2() {
+        let config = r#"
+            bucket = "test"
+            access-key-id = "access_key_id"
+            secret-access-key = "secret_access_key"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let err = GlobalAccessor::try_new_operator("r2", &config).unwrap_err();
+        assert!(matches!(
+            err,
+            AccessorError::MissingConfig { ref scheme, ref key } if scheme == "r2" && key == "account-id"
+        ));
+        assert!(err.to_string().contains("account-id"));
+
+        let err = GlobalAccessor::try_new_operator("s4", &config).unwrap_err();
+        assert!(matches!(err, AccessorError::UnsupportedScheme(ref scheme) if scheme == "s4"));
+
+        let mut config = config;
+        config.insert("account-id".to_owned(), "account_id".into());
+        assert!(GlobalAccessor::try_new_operator("r2", &config).is_ok());
+    }
+
+    #[test]
+    fn it_builds_b2_via_s3_api() {
+        let config = r#"
+            region = "us-west-004"
+            bucket = "test"
+            application-key-id = "application_key_id"
+            application-key = "application_key"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let operator = GlobalAccessor::try_new_operator("b2", &config).unwrap();
+        assert_eq!(operator.info().scheme(), Scheme::S3);
+        assert_eq!(operator.info().name(), "test");
+
+        let expire = Duration::from_secs(3600);
+        let request = operator.presign_read("avatar.png", expire).unwrap();
+        let uri = request.uri().to_string();
+        assert!(uri.starts_with("https://s3.us-west-004.backblazeb2.com/"));
+        assert!(uri.contains("application_key_id"));
+
+        for key in ["region", "bucket"] {
+            let mut config = config.clone();
+            config.remove(key);
+            let err = GlobalAccessor::try_new_operator("b2", &config).unwrap_err();
+            assert!(matches!(
+                err,
+                AccessorError::MissingConfig { ref scheme, key: ref k } if scheme == "b2" && k == key
+            ));
+        }
+    }
+
+    #[test]
+    fn it_validates_required_keys() {
+        let schemes = [
+            ("azblob", vec!["container"]),
+            ("gcs", vec!["bucket"]),
+            ("obs", vec!["bucket", "endpoint"]),
+            ("oss", vec!["bucket", "endpoint"]),
+            ("s3", vec!["bucket"]),
+            ("webdav", vec!["endpoint"]),
+        ];
+        for (scheme, keys) in schemes {
+            let mut config = Table::new();
+            for key in &keys {
+                config.insert(key.to_string(), "https://example.com".into());
+            }
+            for key in keys {
+                let mut config = config.clone();
+                config.insert(key.to_owned(), " ".into());
+                let err = GlobalAccessor::try_new_operator(scheme, &config).unwrap_err();
+                assert!(matches!(
+                    err,
+                    AccessorError::MissingConfig { scheme: ref s, key: ref k } if s == scheme && k == key
+                ));
+
+                config.remove(key);
+                let err = GlobalAccessor::try_new_operator(scheme, &config).unwrap_err();
+                assert_eq!(
+                    err.to_string(),
+                    format!("the `{key}` field should be specified for `{scheme}`")
+                );
+            }
+        }
+        assert!(GlobalAccessor::try_new_operator("fs", &Table::new()).is_ok());
+    }
+
+    #[test]
+    fn it_limits_concurrent_operations() {
+        let config = r#"
+            max-concurrency = 1
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert!(GlobalAccessor::try_new_operator("memory", &config).is_ok());
+
+        let config = r#"
+            max-concurrency = 0
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert!(GlobalAccessor::try_new_operator("memory", &config).is_ok());
+    }
+
+    #[test]
+    fn it_skips_optional_accessors_failing_to_build() {
+        let config = r#"
+            [[accessor]]
+            scheme = "fs"
+            name = "assets"
+            root = "./assets"
+
+            [[accessor]]
+            scheme = "s3"
+            name = "tenant"
+            optional = true
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let registry = OperatorRegistry::new();
+        register_accessors(&registry, config.get_array("accessor").unwrap());
+        assert!(registry.get("assets").is_some());
+        assert!(registry.get("tenant").is_none());
+    }
+
+    #[test]
+    fn it_reads_via_blocking_operator() {
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        assert!(operator.info().full_capability().blocking);
+
+        let blocking_operator = operator.blocking();
+        blocking_operator
+            .write("seeds/users.csv", "id,name\n1,alice\n")
+            .unwrap();
+        assert_eq!(
+            blocking_operator.read("seeds/users.csv").unwrap(),
+            b"id,name\n1,alice\n"
+        );
+    }
+
+    #[test]
+    fn it_checks_write_preconditions() {
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let blocking_operator = operator.blocking();
+        let path = "locks/daily-report";
+        let metadata = blocking_operator.stat(path);
+        assert!(GlobalAccessor::check_precondition(path, metadata, None).is_ok());
+
+        blocking_operator.write(path, "worker-1").unwrap();
+        let metadata = blocking_operator.stat(path);
+        let err = GlobalAccessor::check_precondition(path, metadata, None).unwrap_err();
+        assert!(matches!(err, AccessorError::AlreadyExists(ref p) if p == path));
+
+        let metadata = blocking_operator.stat("locks/weekly-report");
+        let err = GlobalAccessor::check_precondition(path, metadata, Some("\"v1\"")).unwrap_err();
+        assert!(matches!(err, AccessorError::ConditionNotMatch(_)));
+    }
+
+    #[test]
+    fn it_builds_with_default_timeout() {
+        let config = r#"
+            timeout = "0s"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert!(GlobalAccessor::try_new_operator("memory", &config).is_ok());
+        assert!(GlobalAccessor::try_new_operator("memory", &Table::new()).is_ok());
+    }
+
+    #[test]
+    fn it_parses_retry_settings() {
+        let config = r#"
+            max-retries = 5
+            retry-min-delay = "200ms"
+            retry-max-delay = "10s"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert_eq!(
+            GlobalAccessor::parse_retry_settings(&config),
+            (
+                Some(5),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_secs(10))
+            )
+        );
+        assert!(GlobalAccessor::try_new_operator("memory", &config).is_ok());
+
+        let config = r#"
+            max-retries = 5
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert_eq!(
+            GlobalAccessor::parse_retry_settings(&config),
+            (Some(5), None, None)
+        );
+        assert_eq!(
+            GlobalAccessor::parse_retry_settings(&Table::new()),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn it_anchors_paths_under_root() {
+        let root = "/tenants/acme/";
+        assert_eq!(
+            GlobalAccessor::anchor_path(root, "docs/./report.pdf").unwrap(),
+            "docs/report.pdf"
+        );
+        assert_eq!(
+            GlobalAccessor::anchor_path(root, "/tenants/acme/docs/report.pdf").unwrap(),
+            "docs/report.pdf"
+        );
+        assert_eq!(
+            GlobalAccessor::anchor_path(root, "/tenants/acme-corp/report.pdf").unwrap(),
+            "tenants/acme-corp/report.pdf"
+        );
+        assert_eq!(
+            GlobalAccessor::anchor_path(root, "/etc//passwd").unwrap(),
+            "etc/passwd"
+        );
+        assert_eq!(GlobalAccessor::anchor_path(root, "docs/").unwrap(), "docs/");
+        assert_eq!(GlobalAccessor::anchor_path(root, "/").unwrap(), "/");
+        assert_eq!(GlobalAccessor::anchor_path("/", "a/b").unwrap(), "a/b");
+
+        for path in [
+            "../globex/report.pdf",
+            "docs/../../globex",
+            "/tenants/acme/..",
+        ] {
+            let err = GlobalAccessor::anchor_path(root, path).unwrap_err();
+            assert_eq!(err.kind(), PermissionDenied);
+        }
+    }
+
+    #[test]
+    fn it_guesses_content_type() {
+        assert_eq!(GlobalAccessor::guess_content_type("photo.png"), "image/png");
+        assert_eq!(
+            GlobalAccessor::guess_content_type("docs/readme.unknown"),
+            "application/octet-stream",
+        );
+    }
+
+    #[test]
+    fn it_reads_obs_secret_access_key() {
+        let config = r#"
+            bucket = "test"
+            endpoint = "https://obs.cn-north-4.myhuaweicloud.com"
+            access-key-id = "access_key_id"
+            secret-access-key = "secret_access_key"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert_eq!(
+            GlobalAccessor::get_obs_secret_access_key(&config).as_deref(),
+            Some("secret_access_key"),
+        );
+        assert!(GlobalAccessor::try_new_operator("obs", &config).is_ok());
+
+        let legacy_config = r#"
+            secret_access_key = "legacy_secret_access_key"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert_eq!(
+            GlobalAccessor::get_obs_secret_access_key(&legacy_config).as_deref(),
+            Some("legacy_secret_access_key"),
+        );
+    }
+
+    #[test]
+    fn it_removes_objects_in_batch() {
+        let registry = new_memory_registry(&["memory"]);
+        let operator = registry.get("memory").unwrap();
+        let paths = ["logs/1.txt", "logs/2.txt", "logs/3.txt"].map(String::from);
+        block_on(async {
+            for path in &paths {
+                operator.write(path, "log").await.unwrap();
+            }
+            GlobalAccessor::remove_all_in(&registry, "memory", &paths[..2])
+                .await
+                .unwrap();
+            assert!(!operator.is_exist("logs/1.txt").await.unwrap());
+            assert!(!operator.is_exist("logs/2.txt").await.unwrap());
+            assert!(operator.is_exist("logs/3.txt").await.unwrap());
+
+            let failures = GlobalAccessor::remove_each_in(&registry, "memory", &paths)
+                .await
+                .unwrap();
+            assert!(failures.is_empty());
+            assert!(!operator.is_exist("logs/3.txt").await.unwrap());
+
+            operator.write("logs/4.txt", "log").await.unwrap();
+            let paths = ["/logs/./4.txt", "logs/../../etc/passwd"].map(String::from);
+            let err = GlobalAccessor::remove_all_in(&registry, "memory", &paths)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), PermissionDenied);
+            let err = GlobalAccessor::remove_each_in(&registry, "memory", &paths)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), PermissionDenied);
+            assert!(operator.is_exist("logs/4.txt").await.unwrap());
+
+            GlobalAccessor::remove_all_in(&registry, "memory", &paths[..1])
+                .await
+                .unwrap();
+            assert!(!operator.is_exist("logs/4.txt").await.unwrap());
+
+            let err = GlobalAccessor::remove_all_in(&registry, "archive", &paths)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), NotFound);
+            let err = GlobalAccessor::remove_each_in(&registry, "archive", &paths)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), NotFound);
+        });
+    }
+
+    #[test]
+    fn it_lists_entries_with_options() {
+        let registry = &new_memory_registry(&["memory"]);
+        let operator = registry.get("memory").unwrap();
+        let list = move |options: ListOptions| async move {
+            let lister = GlobalAccessor::lister_in(registry, "memory", "/docs/", options)
+                .await
+                .unwrap();
+            let mut entries = lister.try_collect::<Vec<_>>().await.unwrap();
+            entries.sort_by(|a, b| a.path().cmp(b.path()));
+            entries
+        };
+        block_on(async {
+            let paths = [
+                "docs/a.txt",
+                "docs/b.txt",
+                "docs/drafts/c.txt",
+                "logs/1.txt",
+            ];
+            for path in paths {
+                operator.write(path, "text").await.unwrap();
+            }
+
+            let entries = list(ListOptions::new()).await;
+            let files = entries.iter().filter(|e| !e.is_dir()).collect::<Vec<_>>();
+            assert_eq!(files.len(), 2);
+            assert_eq!(files[0].path(), "docs/a.txt");
+            assert_eq!(files[0].content_length(), 4);
+            assert_eq!(files[1].path(), "docs/b.txt");
+            assert!(entries
+                .iter()
+                .any(|e| e.is_dir() && e.path() == "docs/drafts/"));
+
+            let mut options = ListOptions::new();
+            options.set_recursive(true);
+            let entries = list(options).await;
+            let files = entries
+                .iter()
+                .filter(|e| !e.is_dir())
+                .map(|e| e.path())
+                .collect::<Vec<_>>();
+            assert_eq!(files, ["docs/a.txt", "docs/b.txt", "docs/drafts/c.txt"]);
+
+            let mut options = ListOptions::new();
+            options.set_limit(2);
+            assert_eq!(list(options).await.len(), 2);
+
+            let options = ListOptions::new();
+            let result = GlobalAccessor::lister_in(registry, "memory", "../logs/", options).await;
+            assert_eq!(result.err().map(|err| err.kind()), Some(PermissionDenied));
+        });
+    }
+
+    #[test]
+    fn it_falls_back_to_default_operator() {
+        let registry = new_memory_registry(&["memory", "assets"]);
+        let assets_operator = registry.get("assets").unwrap().blocking();
+        assets_operator.write("logo.png", "png").unwrap();
+
+        let is_assets = |name: &str, default_name: &str| {
+            GlobalAccessor::get_or_default_in(&registry, name, default_name)
+                .blocking()
+                .is_exist("logo.png")
+                .unwrap()
+        };
+        assert!(is_assets("assets", "memory"));
+        assert!(is_assets("uploads", "assets"));
+        assert!(!is_assets("uploads", "memory"));
+        assert!(!is_assets("uploads", "cdn"));
+    }
+
+    #[test]
+    fn it_reports_capabilities() {
+        let registry = new_memory_registry(&["memory"]);
+        let config = r#"
+            bucket = "test"
+            region = "us-east-1"
+            access-key-id = "access_key_id"
+            secret-access-key = "secret_access_key"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let operator = GlobalAccessor::try_new_operator("s3", &config).unwrap();
+        registry.insert("s3", operator);
+
+        let capability = GlobalAccessor::capabilities_in(&registry, "memory").unwrap();
+        assert!(capability.blocking);
+        assert!(!capability.presign);
+        assert!(!GlobalAccessor::supports_presign_in(&registry, "memory"));
+
+        let capability = GlobalAccessor::capabilities_in(&registry, "s3").unwrap();
+        assert!(capability.presign);
+        assert!(GlobalAccessor::supports_presign_in(&registry, "s3"));
+
+        assert!(GlobalAccessor::capabilities_in(&registry, "gcs").is_none());
+        assert!(!GlobalAccessor::supports_presign_in(&registry, "gcs"));
+    }
+
+    #[test]
+    fn it_checks_all_operators() {
+        let registry = new_memory_registry(&["memory", "assets", "uploads"]);
+        let mut results = block_on(GlobalAccessor::check_all_in(&registry));
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let names = results
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["assets", "memory", "uploads"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        assert!(block_on(GlobalAccessor::check_all_in(&OperatorRegistry::new())).is_empty());
+    }
+
+    #[test]
+    fn it_transfers_objects() {
+        let registry = &new_memory_registry(&["memory", "archive"]);
+        let source = registry.get("memory").unwrap();
+        let destination = registry.get("archive").unwrap();
+        let transfer = |from_path, to_path| {
+            GlobalAccessor::transfer_in(registry, "memory", from_path, "archive", to_path)
+        };
+        block_on(async {
+            let content = "x".repeat(64 * 1024);
+            source.write("logs/app.log", content.clone()).await.unwrap();
+            let bytes = transfer("logs/app.log", "2023/app.log").await.unwrap();
+            assert_eq!(bytes, content.len() as u64);
+            assert_eq!(
+                destination.read("2023/app.log").await.unwrap(),
+                content.as_bytes()
+            );
+
+            source.write("logs/empty.log", Vec::new()).await.unwrap();
+            let bytes = transfer("logs/empty.log", "2023/empty.log").await.unwrap();
+            assert_eq!(bytes, 0);
+            assert!(destination.read("2023/empty.log").await.unwrap().is_empty());
+
+            let err = transfer("logs/missing.log", "2023/missing.log")
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), NotFound);
+            assert!(!destination.is_exist("2023/missing.log").await.unwrap());
+
+            let result = GlobalAccessor::transfer_in(
+                registry,
+                "memory",
+                "logs/app.log",
+                "backup",
+                "2023/app.log",
+            )
+            .await;
+            assert_eq!(result.unwrap_err().kind(), NotFound);
+        });
+    }
+
+    #[test]
+    fn it_toggles_metrics_layer() {
+        let metrics_layer = GlobalAccessor::new_metrics_layer(&Table::new()).unwrap();
+        assert!(metrics_layer.namespace().is_none());
+
+        let config = r#"
+            metrics = false
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert!(GlobalAccessor::new_metrics_layer(&config).is_none());
+        assert!(GlobalAccessor::try_new_operator("memory", &config).is_ok());
+
+        let config = r#"
+            metrics-namespace = "tenant"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        let metrics_layer = GlobalAccessor::new_metrics_layer(&config).unwrap();
+        assert_eq!(metrics_layer.namespace(), Some("tenant"));
+        assert!(GlobalAccessor::try_new_operator("memory", &config).is_ok());
+    }
+}
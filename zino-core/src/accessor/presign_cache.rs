@@ -0,0 +1,270 @@
+use super::GlobalAccessor;
+use crate::{
+    datetime::DateTime,
+    extend::{JsonObjectExt, TomlTableExt},
+    state::State,
+    Map,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use opendal::{raw::PresignedRequest, Error, ErrorKind::NotFound, Operator};
+use sha2::{Digest, Sha256};
+use std::{sync::LazyLock, time::Duration};
+use toml::Table;
+
+/// A cache of the presigned URLs, which is stored by an accessor.
+///
+/// The URL presigned for an operation on the object with the same expiration
+/// is reused until it is within the safety margin of expiring, so that the hot objects are not re-signed
+/// for every download. The shared cache is configured by the `presign-cache` table,
+/// in which the `store` defaults to `memory` and the `safety-margin` defaults to `1m`.
+/// The failures of the store are logged and treated as misses.
+#[derive(Debug, Clone)]
+pub struct PresignCache {
+    /// Name of the accessor used to store the presigned URLs.
+    store: String,
+    /// Safety margin before the presigned URLs expire.
+    safety_margin: Duration,
+}
+
+impl PresignCache {
+    /// Creates a new instance with the name of the accessor and the safety margin.
+    #[inline]
+    pub fn new(store: impl Into<String>, safety_margin: Duration) -> Self {
+        Self {
+            store: store.into(),
+            safety_margin,
+        }
+    }
+
+    /// Creates a new instance with the config.
+    pub fn with_config(config: &Table) -> Self {
+        let store = config.get_str("store").unwrap_or("memory");
+        let safety_margin = config
+            .get_duration("safety-margin")
+            .unwrap_or_else(|| Duration::from_secs(60));
+        Self::new(store, safety_margin)
+    }
+
+    /// Returns a reference to the shared presign cache.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        LazyLock::force(&SHARED_PRESIGN_CACHE)
+    }
+
+    /// Returns the name of the accessor used to store the presigned URLs.
+    #[inline]
+    pub fn store(&self) -> &str {
+        &self.store
+    }
+
+    /// Returns the safety margin before the presigned URLs expire.
+    #[inline]
+    pub fn safety_margin(&self) -> Duration {
+        self.safety_margin
+    }
+
+    /// Returns the URL presigned to read the object at `path` for the storage service.
+    /// The cached URL is returned if it does not expire within the safety margin.
+    pub async fn presign_read(
+        &self,
+        name: &str,
+        path: &str,
+        expire: Duration,
+    ) -> Result<String, Error> {
+        self.presign(name, path, "GET", expire, || {
+            GlobalAccessor::presign_read(name, path, expire)
+        })
+        .await
+    }
+
+    /// Returns the URL presigned to write the object at `path` for the storage service.
+    /// The cached URL is returned if it does not expire within the safety margin.
+    pub async fn presign_write(
+        &self,
+        name: &str,
+        path: &str,
+        expire: Duration,
+    ) -> Result<String, Error> {
+        self.presign(name, path, "PUT", expire, || {
+            GlobalAccessor::presign_write(name, path, expire)
+        })
+        .await
+    }
+
+    /// Returns the URL presigned for the method, and caches it in the store.
+    async fn presign<F>(
+        &self,
+        name: &str,
+        path: &str,
+        method: &str,
+        expire: Duration,
+        presigner: F,
+    ) -> Result<String, Error>
+    where
+        F: FnOnce() -> Result<PresignedRequest, Error>,
+    {
+        let sign = || presigner().map(|req| req.uri().to_string());
+        match GlobalAccessor::try_get(&self.store) {
+            Ok(operator) => {
+                let cache_path = cache_path(name, method, path, expire);
+                presign_cached(&operator, &cache_path, expire, self.safety_margin, sign).await
+            }
+            Err(err) => {
+                tracing::warn!("fail to get the presign cache `{}`: {err}", self.store);
+                sign()
+            }
+        }
+    }
+}
+
+/// Returns the path of the object which stores the presigned URL.
+/// The expiration is a part of the key, so that a URL presigned for a short period
+/// is not returned to the caller which requests a longer one.
+fn cache_path(name: &str, method: &str, path: &str, expire: Duration) -> String {
+    let expire = expire.as_secs();
+    let digest = Sha256::digest(format!("{method} {name} {path} {expire}").as_bytes());
+    format!("presign/{}", URL_SAFE_NO_PAD.encode(digest))
+}
+
+/// Reads the presigned URL cached at `cache_path` by the operator if it does not
+/// expire within the safety margin, and presigns a new one otherwise.
+async fn presign_cached<F>(
+    operator: &Operator,
+    cache_path: &str,
+    expire: Duration,
+    safety_margin: Duration,
+    presigner: F,
+) -> Result<String, Error>
+where
+    F: FnOnce() -> Result<String, Error>,
+{
+    let now = DateTime::now().timestamp_millis();
+    match operator.read(cache_path).await {
+        Ok(bytes) => {
+            let safety_margin = i64::try_from(safety_margin.as_millis()).unwrap_or(i64::MAX);
+            if let Ok(entry) = serde_json::from_slice::<Map>(&bytes) &&
+                let Some(url) = entry.get_str("url") &&
+                let Some(expires_at) = entry.get_i64("expires_at") &&
+                expires_at.saturating_sub(safety_margin) > now
+            {
+                return Ok(url.to_owned());
+            }
+        }
+        Err(err) if err.kind() != NotFound => {
+            tracing::warn!("fail to read the presigned URL: {err}");
+        }
+        Err(_) => (),
+    }
+
+    let url = presigner()?;
+    let expire = i64::try_from(expire.as_millis()).unwrap_or(i64::MAX);
+    let mut entry = Map::from_entry("url", url.as_str());
+    entry.upsert("expires_at", now.saturating_add(expire));
+    match serde_json::to_vec(&entry) {
+        Ok(bytes) => {
+            if let Err(err) = operator.write(cache_path, bytes).await {
+                tracing::warn!("fail to write the presigned URL: {err}");
+            }
+        }
+        Err(err) => tracing::warn!("fail to serialize the presigned URL: {err}"),
+    }
+    Ok(url)
+}
+
+/// Shared presign cache.
+static SHARED_PRESIGN_CACHE: LazyLock<PresignCache> = LazyLock::new(|| {
+    State::shared()
+        .config()
+        .get_table("presign-cache")
+        .map(PresignCache::with_config)
+        .unwrap_or_else(|| PresignCache::with_config(&Table::new()))
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_path, presign_cached};
+    use crate::accessor::GlobalAccessor;
+    use futures::executor::block_on;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        time::Duration,
+    };
+    use toml::Table;
+
+    #[test]
+    fn it_reuses_presigned_urls() {
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let signings = &AtomicUsize::new(0);
+        let presigner = || {
+            let signing = signings.fetch_add(1, Relaxed) + 1;
+            Ok(format!("https://zino.example.com/intro.mp4?sig={signing}"))
+        };
+        let expire = Duration::from_secs(60 * 60);
+        let path = cache_path("s3", "GET", "intro.mp4", expire);
+        assert_ne!(path, cache_path("s3", "PUT", "intro.mp4", expire));
+        assert_ne!(path, cache_path("oss", "GET", "intro.mp4", expire));
+
+        let safety_margin = Duration::from_secs(5 * 60);
+        block_on(async {
+            let url = presign_cached(&operator, &path, expire, safety_margin, presigner)
+                .await
+                .unwrap();
+            let cached_url = presign_cached(&operator, &path, expire, safety_margin, presigner)
+                .await
+                .unwrap();
+            assert_eq!(cached_url, url);
+            assert_eq!(signings.load(Relaxed), 1);
+
+            // The cached URL expires within the safety margin.
+            let safety_margin = Duration::from_secs(2 * 60 * 60);
+            let refreshed_url = presign_cached(&operator, &path, expire, safety_margin, presigner)
+                .await
+                .unwrap();
+            assert_ne!(refreshed_url, url);
+            assert_eq!(signings.load(Relaxed), 2);
+
+            let safety_margin = Duration::from_secs(5 * 60);
+            let cached_url = presign_cached(&operator, &path, expire, safety_margin, presigner)
+                .await
+                .unwrap();
+            assert_eq!(cached_url, refreshed_url);
+            assert_eq!(signings.load(Relaxed), 2);
+        });
+    }
+
+    #[test]
+    fn it_separates_presigned_urls_by_expiration() {
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let signings = &AtomicUsize::new(0);
+        let presigner = || {
+            let signing = signings.fetch_add(1, Relaxed) + 1;
+            Ok(format!("https://zino.example.com/intro.mp4?sig={signing}"))
+        };
+        let safety_margin = Duration::from_secs(60);
+        block_on(async {
+            let expire = Duration::from_secs(5 * 60);
+            let path = cache_path("s3", "GET", "intro.mp4", expire);
+            let url = presign_cached(&operator, &path, expire, safety_margin, presigner)
+                .await
+                .unwrap();
+            assert_eq!(signings.load(Relaxed), 1);
+
+            // The URL presigned for 5 minutes is not reused for a request of 1 day.
+            let long_expire = Duration::from_secs(24 * 60 * 60);
+            let long_path = cache_path("s3", "GET", "intro.mp4", long_expire);
+            assert_ne!(long_path, path);
+            let long_url =
+                presign_cached(&operator, &long_path, long_expire, safety_margin, presigner)
+                    .await
+                    .unwrap();
+            assert_ne!(long_url, url);
+            assert_eq!(signings.load(Relaxed), 2);
+
+            let cached_url = presign_cached(&operator, &path, expire, safety_margin, presigner)
+                .await
+                .unwrap();
+            assert_eq!(cached_url, url);
+            assert_eq!(signings.load(Relaxed), 2);
+        });
+    }
+}
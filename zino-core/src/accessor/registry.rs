@@ -0,0 +1,82 @@
+use opendal::Operator;
+use parking_lot::RwLock;
+use std::collections::{hash_map, HashMap};
+
+/// A registry of named operators which can be accessed concurrently.
+#[derive(Default)]
+pub(super) struct OperatorRegistry {
+    /// Operators.
+    operators: RwLock<HashMap<String, Operator>>,
+}
+
+impl OperatorRegistry {
+    /// Creates a new instance.
+    #[inline]
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets a clone of the operator with the name.
+    #[inline]
+    pub(super) fn get(&self, name: &str) -> Option<Operator> {
+        self.operators.read().get(name).cloned()
+    }
+
+    /// Inserts the operator with the name if it has not been registered.
+    /// Returns `true` if the operator is inserted.
+    pub(super) fn insert(&self, name: impl Into<String>, operator: Operator) -> bool {
+        match self.operators.write().entry(name.into()) {
+            hash_map::Entry::Occupied(_) => false,
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(operator);
+                true
+            }
+        }
+    }
+
+    /// Removes the operator with the name, and returns it if it exists.
+    #[inline]
+    pub(super) fn remove(&self, name: &str) -> Option<Operator> {
+        self.operators.write().remove(name)
+    }
+
+    /// Returns a snapshot of the named operators.
+    pub(super) fn snapshot(&self) -> Vec<(String, Operator)> {
+        self.operators
+            .read()
+            .iter()
+            .map(|(name, operator)| (name.to_owned(), operator.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OperatorRegistry;
+    use opendal::{services::Memory, Operator};
+    use std::thread;
+
+    #[test]
+    fn it_registers_operators_concurrently() {
+        let registry = OperatorRegistry::new();
+        thread::scope(|s| {
+            for i in 0..8 {
+                let registry = &registry;
+                s.spawn(move || {
+                    for j in 0..100 {
+                        let name = format!("tenant-{i}-{j}");
+                        let operator = Operator::new(Memory::default()).unwrap().finish();
+                        assert!(registry.insert(name.clone(), operator.clone()));
+                        assert!(!registry.insert(name.clone(), operator));
+                        assert!(registry.get(&name).is_some());
+                        if j % 2 == 0 {
+                            assert!(registry.remove(&name).is_some());
+                            assert!(registry.get(&name).is_none());
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(registry.snapshot().len(), 8 * 50);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{datetime::DateTime, error::Error, extend::JsonObjectExt, BoxFuture, Map};
+use futures::future;
+use http::StatusCode;
+use parking_lot::RwLock;
+use serde_json::Value;
+use std::sync::LazyLock;
+
+/// A function pointer of the probe which checks the components of a subsystem.
+pub type HealthProbe = fn() -> BoxFuture<'static, Vec<(String, Result<(), Error>)>>;
+
+/// A registry of the probes to check the health of subsystems.
+///
+/// The database pools, the accessors and the job scheduler are registered by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HealthCheck;
+
+impl HealthCheck {
+    /// Registers a probe for the readiness check.
+    #[inline]
+    pub fn register(probe: HealthProbe) {
+        HEALTH_PROBES.write().push(probe);
+    }
+
+    /// Returns the liveness of the application, which does not depend on the subsystems.
+    #[inline]
+    pub fn liveness() -> HealthStatus {
+        HealthStatus::new(Vec::new())
+    }
+
+    /// Runs all the registered probes concurrently, and returns the readiness of the application.
+    pub async fn readiness() -> HealthStatus {
+        let probes = HEALTH_PROBES.read().clone();
+        let components = future::join_all(probes.into_iter().map(|probe| probe()))
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+        HealthStatus::new(components)
+    }
+}
+
+/// Health status aggregated from the results of the components.
+#[derive(Debug)]
+pub struct HealthStatus {
+    /// Results of the components.
+    components: Vec<(String, Result<(), Error>)>,
+    /// Checked time.
+    checked_at: DateTime,
+}
+
+impl HealthStatus {
+    /// Creates a new instance with the results of the components.
+    #[inline]
+    pub fn new(components: Vec<(String, Result<(), Error>)>) -> Self {
+        Self {
+            components,
+            checked_at: DateTime::now(),
+        }
+    }
+
+    /// Returns `true` if all the components are healthy.
+    #[inline]
+    pub fn is_healthy(&self) -> bool {
+        self.components.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Returns the status code: `200 OK` if all the components are healthy,
+    /// and `503 Service Unavailable` otherwise.
+    #[inline]
+    pub fn status_code(&self) -> StatusCode {
+        if self.is_healthy() {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+
+    /// Converts `self` into a JSON object with the details of the components.
+    pub fn into_map(self) -> Map {
+        let healthy = self.is_healthy();
+        let mut components = Map::new();
+        for (name, result) in self.components {
+            let mut component = Map::new();
+            match result {
+                Ok(_) => {
+                    component.upsert("status", "ok");
+                }
+                Err(err) => {
+                    component.upsert("status", "error");
+                    component.upsert("message", err.to_string());
+                }
+            }
+            components.upsert(name, component);
+        }
+
+        let mut map = Map::new();
+        map.upsert("status", if healthy { "ok" } else { "unavailable" });
+        map.upsert("checked_at", self.checked_at);
+        map.upsert("components", components);
+        map
+    }
+}
+
+impl From<HealthStatus> for Value {
+    #[inline]
+    fn from(status: HealthStatus) -> Self {
+        Value::Object(status.into_map())
+    }
+}
+
+/// Checks the database connection pools.
+#[cfg(feature = "orm")]
+fn check_database() -> BoxFuture<'static, Vec<(String, Result<(), Error>)>> {
+    Box::pin(crate::database::check_pools())
+}
+
+/// Checks the storage services of the accessors.
+#[cfg(feature = "accessor")]
+fn check_accessors() -> BoxFuture<'static, Vec<(String, Result<(), Error>)>> {
+    Box::pin(async {
+        crate::accessor::GlobalAccessor::check_all()
+            .await
+            .into_iter()
+            .map(|(name, result)| (format!("accessor.{name}"), result.map_err(Error::from)))
+            .collect()
+    })
+}
+
+/// Checks the job scheduler.
+fn check_scheduler() -> BoxFuture<'static, Vec<(String, Result<(), Error>)>> {
+    Box::pin(async { vec![("scheduler".to_owned(), crate::schedule::check_heartbeat())] })
+}
+
+/// Registered health probes.
+static HEALTH_PROBES: LazyLock<RwLock<Vec<HealthProbe>>> = LazyLock::new(|| {
+    let mut probes: Vec<HealthProbe> = vec![check_scheduler];
+    #[cfg(feature = "orm")]
+    probes.push(check_database);
+    #[cfg(feature = "accessor")]
+    probes.push(check_accessors);
+    RwLock::new(probes)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{HealthCheck, HealthStatus};
+    use crate::{error::Error, extend::JsonObjectExt};
+    use http::StatusCode;
+
+    #[test]
+    fn it_aggregates_health_status() {
+        let readiness = HealthStatus::new(vec![
+            ("database.main".to_owned(), Ok(())),
+            (
+                "accessor.s3".to_owned(),
+                Err(Error::new("fail to connect to the storage service")),
+            ),
+            ("scheduler".to_owned(), Ok(())),
+        ]);
+        assert!(!readiness.is_healthy());
+        assert_eq!(readiness.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let map = readiness.into_map();
+        assert_eq!(map.get_str("status"), Some("unavailable"));
+        let components = map.get_object("components").unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(
+            components
+                .get_object("database.main")
+                .unwrap()
+                .get_str("status"),
+            Some("ok")
+        );
+        let accessor = components.get_object("accessor.s3").unwrap();
+        assert_eq!(accessor.get_str("status"), Some("error"));
+        assert_eq!(
+            accessor.get_str("message"),
+            Some("fail to connect to the storage service")
+        );
+
+        let liveness = HealthCheck::liveness();
+        assert!(liveness.is_healthy());
+        assert_eq!(liveness.status_code(), StatusCode::OK);
+        assert_eq!(liveness.into_map().get_str("status"), Some("ok"));
+    }
+}
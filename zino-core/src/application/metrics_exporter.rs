@@ -1,21 +1,51 @@
 use super::Application;
 use crate::extend::TomlTableExt;
-use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use metrics::Recorder;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
 use metrics_exporter_tcp::TcpBuilder;
-use std::{net::IpAddr, time::Duration};
+use std::{net::IpAddr, sync::OnceLock, thread, time::Duration};
+
+/// An exporter which renders the metrics for a scrape endpoint.
+///
+/// It is enabled by setting `metrics.endpoint` for the Prometheus exporter,
+/// in which case the metrics are served by the application instead of a separate listener.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsExporter;
+
+impl MetricsExporter {
+    /// Returns the path of the scrape endpoint if it has been configured.
+    #[inline]
+    pub fn endpoint() -> Option<&'static str> {
+        METRICS_ENDPOINT.get().map(|s| s.as_str())
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    /// It returns `None` if the exporter is not installed for the scrape endpoint.
+    #[inline]
+    pub fn render() -> Option<String> {
+        PROMETHEUS_HANDLE.get().map(|handle| handle.render())
+    }
+}
 
 /// Initializes the metrics exporters.
 pub(super) fn init<APP: Application + ?Sized>() {
     if let Some(metrics) = APP::config().get_table("metrics") {
         let exporter = metrics.get_str("exporter").unwrap_or_default();
         if exporter == "prometheus" {
-            let mut builder = if let Some(endpoint) = metrics.get_str("push-gateway") {
+            let push_gateway = metrics.get_str("push-gateway");
+            let endpoint = metrics
+                .get_str("endpoint")
+                .filter(|_| push_gateway.is_none());
+            let mut builder = if let Some(push_gateway) = push_gateway {
                 let interval = metrics
                     .get_duration("interval")
                     .unwrap_or_else(|| Duration::from_secs(60));
                 PrometheusBuilder::new()
-                    .with_push_gateway(endpoint, interval)
+                    .with_push_gateway(push_gateway, interval)
                     .expect("fail to configure the exporter to run in push gateway mode")
+            } else if let Some(endpoint) = endpoint {
+                tracing::warn!(exporter, "serve metrics on the endpoint `{endpoint}`");
+                PrometheusBuilder::new()
             } else {
                 let host = metrics.get_str("host").unwrap_or("127.0.0.1");
                 let port = metrics.get_u16("port").unwrap_or(9000);
@@ -66,9 +96,32 @@ pub(super) fn init<APP: Application + ?Sized>() {
                         .unwrap_or_else(|err| panic!("invalid IP address `{addr}`: {err}"));
                 }
             }
-            builder
-                .install()
-                .expect("fail to install Prometheus exporter");
+            if let Some(endpoint) = endpoint {
+                let recorder = builder.build_recorder();
+                PROMETHEUS_HANDLE.set(recorder.handle()).ok();
+                METRICS_ENDPOINT.set(endpoint.to_owned()).ok();
+                install_recorder(recorder);
+            } else {
+                // The exporter should be built in the context of a runtime
+                // and then driven by a dedicated thread.
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("fail to build the runtime for Prometheus exporter");
+                let (recorder, exporter) = {
+                    let _guard = runtime.enter();
+                    builder.build().expect("fail to build Prometheus exporter")
+                };
+                thread::Builder::new()
+                    .name("metrics-exporter-prometheus".to_owned())
+                    .spawn(move || {
+                        if let Err(err) = runtime.block_on(exporter) {
+                            tracing::error!("fail to run Prometheus exporter: {err}");
+                        }
+                    })
+                    .expect("fail to spawn the thread for Prometheus exporter");
+                install_recorder(recorder);
+            }
         } else if exporter == "tcp" {
             let host = metrics.get_str("host").unwrap_or("127.0.0.1");
             let port = metrics.get_u16("port").unwrap_or(9000);
@@ -76,11 +129,93 @@ pub(super) fn init<APP: Application + ?Sized>() {
             let host_addr = host
                 .parse::<IpAddr>()
                 .unwrap_or_else(|err| panic!("invalid host address `{host}`: {err}"));
-            TcpBuilder::new()
+            let recorder = TcpBuilder::new()
                 .listen_address((host_addr, port))
                 .buffer_size(Some(buffer_size))
-                .install()
-                .expect("fail to install TCP exporter");
+                .build()
+                .expect("fail to build TCP exporter");
+            install_recorder(recorder);
+        }
+    }
+}
+
+/// Installs the recorder as the global recorder.
+/// The metrics of the accessors are renamed by their namespaces if the feature is enabled.
+fn install_recorder<R: Recorder + 'static>(recorder: R) {
+    #[cfg(feature = "accessor")]
+    let recorder = crate::accessor::NamespacedRecorder::new(recorder);
+    metrics::set_boxed_recorder(Box::new(recorder)).expect("fail to install the metrics recorder");
+}
+
+/// Handle of the Prometheus recorder used to render the metrics.
+static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Path of the scrape endpoint.
+static METRICS_ENDPOINT: OnceLock<String> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::{MetricsExporter, PROMETHEUS_HANDLE};
+    use metrics_exporter_prometheus::PrometheusBuilder;
+
+    #[test]
+    fn it_renders_prometheus_metrics() {
+        assert!(MetricsExporter::render().is_none());
+        let recorder = PrometheusBuilder::new().build_recorder();
+        PROMETHEUS_HANDLE.set(recorder.handle()).ok();
+        #[cfg(feature = "accessor")]
+        let recorder = crate::accessor::NamespacedRecorder::new(recorder);
+        metrics::set_boxed_recorder(Box::new(recorder)).unwrap();
+
+        metrics::increment_counter!("zino_http_requests_total", "method" => "GET");
+        metrics::gauge!("zino_db_pool_waiters", 0.0, "pool" => "main");
+        #[cfg(feature = "accessor")]
+        {
+            use crate::accessor::GlobalAccessor;
+            use futures::executor::block_on;
+            use toml::Table;
+
+            let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+            block_on(async {
+                operator.write("metrics.txt", "zino").await.unwrap();
+                assert_eq!(operator.read("metrics.txt").await.unwrap(), b"zino");
+            });
+
+            let config = r#"
+                metrics-namespace = "tenant"
+            "#
+            .parse::<Table>()
+            .unwrap();
+            let operator = GlobalAccessor::try_new_operator("memory", &config).unwrap();
+            block_on(async {
+                operator.write("metrics.txt", "zino").await.unwrap();
+            });
+        }
+
+        let metrics = MetricsExporter::render().unwrap();
+        let samples = metrics
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for sample in samples {
+            let (series, value) = sample.rsplit_once(' ').unwrap();
+            assert!(value.parse::<f64>().is_ok());
+
+            let name = series.split('{').next().unwrap_or_default();
+            assert!(!name.is_empty());
+            assert!(name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':'));
+            if let Some(labels) = series.strip_prefix(name) {
+                assert!(labels.is_empty() || (labels.starts_with('{') && labels.ends_with('}')));
+            }
+        }
+        assert!(metrics.contains("# TYPE zino_http_requests_total counter"));
+        assert!(metrics.contains("# TYPE zino_db_pool_waiters gauge"));
+        #[cfg(feature = "accessor")]
+        {
+            assert!(metrics.contains("# TYPE opendal_requests_total counter"));
+            assert!(metrics.contains("opendal_requests_duration_seconds"));
+            assert!(metrics.contains("# TYPE tenant_requests_total counter"));
         }
     }
 }
@@ -4,7 +4,7 @@ use crate::{
     datetime::DateTime,
     error::Error,
     extend::{HeaderMapExt, JsonObjectExt, TomlTableExt},
-    schedule::{AsyncCronJob, CronJob, Job, JobScheduler},
+    schedule::{AsyncCronJob, CronJob, Job, JobScheduler, SchedulerHandle},
     state::State,
     trace::TraceContext,
     Map,
@@ -14,6 +14,7 @@ use serde::de::DeserializeOwned;
 use std::{env, path::PathBuf, sync::LazyLock, thread};
 use toml::value::Table;
 
+mod health_check;
 mod metrics_exporter;
 mod secret_key;
 mod system_monitor;
@@ -23,6 +24,9 @@ pub(crate) mod http_client;
 
 pub(crate) use secret_key::SECRET_KEY;
 
+pub use health_check::{HealthCheck, HealthProbe, HealthStatus};
+pub use metrics_exporter::MetricsExporter;
+
 /// Application.
 pub trait Application {
     /// Router.
@@ -31,16 +35,30 @@ pub trait Application {
     /// Registers routes.
     fn register(self, routes: Vec<Self::Router>) -> Self;
 
+    /// Schedules async jobs with more options such as the retry policy.
+    /// The jobs are added to the scheduler returned by [`scheduler()`](Application::scheduler).
+    fn schedule(self, async_jobs: Vec<Job>) -> Self
+    where
+        Self: Sized,
+    {
+        let scheduler = Self::scheduler();
+        for job in async_jobs {
+            scheduler.add(job);
+        }
+        self
+    }
+
     /// Runs the application.
     fn run(self, async_jobs: Vec<(&'static str, AsyncCronJob)>);
 
-    /// Boots the application. It also setups the default secret key,
+    /// Boots the application. It also setups the default secret key, the default time zone,
     /// the tracing subscriber, the metrics exporter and a global HTTP client.
     fn boot() -> Self
     where
         Self: Default,
     {
         secret_key::init::<Self>();
+        crate::datetime::init::<Self>();
         tracing_subscriber::init::<Self>();
         metrics_exporter::init::<Self>();
         http_client::init::<Self>();
@@ -113,6 +131,22 @@ pub trait Application {
         SECRET_KEY.get().expect("fail to get the secret key")
     }
 
+    /// Returns the names of the keys whose values are always redacted in the logs.
+    /// They are configured by `tracing.redacted-keys`,
+    /// and default to `password`, `token` and `secret`.
+    #[inline]
+    fn redacted_keys() -> &'static [&'static str] {
+        REDACTED_KEYS.as_slice()
+    }
+
+    /// Returns a reference to the handle of the scheduler for async jobs,
+    /// which can be used to add one-shot jobs and retrieve the execution statistics
+    /// after the application is running.
+    #[inline]
+    fn scheduler() -> &'static SchedulerHandle {
+        LazyLock::force(&SHARED_SCHEDULER)
+    }
+
     /// Spawns a new thread to run cron jobs.
     fn spawn(self, jobs: Vec<(&'static str, CronJob)>) -> Self
     where
@@ -186,6 +220,16 @@ pub(crate) static APP_DOMAIN: LazyLock<&'static str> = LazyLock::new(|| {
         .unwrap_or("localhost")
 });
 
+/// Names of the keys whose values are redacted in the logs.
+pub(crate) static REDACTED_KEYS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    SHARED_APP_STATE
+        .config()
+        .get_table("tracing")
+        .and_then(|config| config.get_array("redacted-keys"))
+        .map(|keys| keys.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_else(|| vec!["password", "token", "secret"])
+});
+
 /// Project directory.
 pub(crate) static PROJECT_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
     env::var("CARGO_MANIFEST_DIR")
@@ -193,6 +237,9 @@ pub(crate) static PROJECT_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
         .into()
 });
 
+/// Shared handle of the scheduler for async jobs.
+static SHARED_SCHEDULER: LazyLock<SchedulerHandle> = LazyLock::new(SchedulerHandle::new);
+
 /// Shared app state.
 static SHARED_APP_STATE: LazyLock<State> = LazyLock::new(|| {
     let mut state = State::default();
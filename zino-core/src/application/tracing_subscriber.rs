@@ -1,12 +1,15 @@
 use super::Application;
-use crate::extend::TomlTableExt;
+use crate::{extend::TomlTableExt, state::State};
 use std::{fs, io, path::Path, sync::OnceLock};
+use toml::value::Table;
 use tracing::Level;
 use tracing_appender::{non_blocking::WorkerGuard, rolling};
 use tracing_subscriber::{
     filter::{EnvFilter, LevelFilter},
     fmt::{time::OffsetTime, writer::MakeWriterExt},
     layer::SubscriberExt,
+    reload::{self, Handle},
+    Registry,
 };
 
 /// Initializes the tracing subscriber.
@@ -20,11 +23,7 @@ pub(super) fn init<APP: Application + ?Sized>() {
     let local_offset_time = OffsetTime::local_rfc_3339().expect("could not get local offset");
 
     let app_env = APP::env();
-    let mut env_filter = if app_env == "dev" {
-        "info,sqlx=trace,zino=trace,zino_core=trace"
-    } else {
-        "info,sqlx=warn"
-    };
+    let env_filter = new_env_filter(app_env, APP::config());
 
     let mut log_dir = "logs";
     let mut display_target = true;
@@ -36,9 +35,6 @@ pub(super) fn init<APP: Application + ?Sized>() {
         if let Some(dir) = tracing.get_str("log-dir") {
             log_dir = dir;
         }
-        if let Some(filter) = tracing.get_str("filter") {
-            env_filter = filter;
-        }
         display_target = tracing.get_bool("display-target").unwrap_or(true);
         display_filename = tracing.get_bool("display-filename").unwrap_or(false);
         display_line_number = tracing.get_bool("display-line-number").unwrap_or(false);
@@ -75,17 +71,97 @@ pub(super) fn init<APP: Application + ?Sized>() {
         .json()
         .with_current_span(true)
         .with_span_list(display_span_list);
-    let filter_layer = EnvFilter::builder()
-        .with_default_directive(LevelFilter::WARN.into())
-        .parse_lossy(env_filter);
+    let (filter_layer, filter_handle) = reload::Layer::new(env_filter);
     let subscriber = tracing_subscriber::registry()
         .with(filter_layer)
         .with(fmt_layer);
+    #[cfg(feature = "opentelemetry")]
+    let subscriber = subscriber.with(new_opentelemetry_layer::<APP, _>());
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
     TRACING_APPENDER_GUARD
         .set(worker_guard)
         .expect("fail to set the worker guard for the tracing appender");
+    if ENV_FILTER_HANDLE.set(filter_handle).is_ok() {
+        State::on_reload(reload_env_filter::<APP>);
+    }
+}
+
+/// Creates a new env filter with the `tracing.filter` config.
+fn new_env_filter(app_env: &str, config: &Table) -> EnvFilter {
+    let default_filter = if app_env == "dev" {
+        "info,sqlx=trace,zino=trace,zino_core=trace"
+    } else {
+        "info,sqlx=warn"
+    };
+    let env_filter = config
+        .get_table("tracing")
+        .and_then(|tracing| tracing.get_str("filter"))
+        .unwrap_or(default_filter);
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::WARN.into())
+        .parse_lossy(env_filter)
+}
+
+/// Creates a new OpenTelemetry layer which exports the spans to the OTLP collector
+/// configured by the `opentelemetry` table. The root spans are sampled by `sampling-ratio`,
+/// and the spans with a remote parent follow the sampling decision of the parent.
+/// The W3C trace context is used to propagate the spans across HTTP requests.
+#[cfg(feature = "opentelemetry")]
+fn new_opentelemetry_layer<APP, S>(
+) -> Option<tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry::sdk::trace::Tracer>>
+where
+    APP: Application + ?Sized,
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::{
+        global,
+        sdk::{
+            propagation::TraceContextPropagator,
+            trace::{self, Sampler},
+            Resource,
+        },
+        KeyValue,
+    };
+    use opentelemetry_otlp::WithExportConfig;
+
+    let config = APP::config().get_table("opentelemetry")?;
+    let endpoint = config
+        .get_str("endpoint")
+        .unwrap_or("http://127.0.0.1:4318/v1/traces");
+    let sampling_ratio = config.get_f64("sampling-ratio").unwrap_or(1.0);
+    let service_name = config.get_str("service-name").unwrap_or_else(APP::name);
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(sampling_ratio)));
+    let resource = Resource::new([KeyValue::new("service.name", service_name.to_owned())]);
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(sampler)
+                .with_resource(resource),
+        )
+        .install_simple()
+        .expect("fail to install the OTLP exporter");
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Some(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Replaces the env filter after the config is reloaded.
+fn reload_env_filter<APP: Application + ?Sized>(config: &Table) {
+    if let Some(handle) = ENV_FILTER_HANDLE.get() {
+        let env_filter = new_env_filter(APP::env(), config);
+        if let Err(err) = handle.reload(env_filter) {
+            tracing::error!("fail to reload the env filter: {err}");
+        }
+    }
 }
 
 /// Tracing appender guard.
 static TRACING_APPENDER_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Handle for reloading the env filter.
+static ENV_FILTER_HANDLE: OnceLock<Handle<EnvFilter, Registry>> = OnceLock::new();
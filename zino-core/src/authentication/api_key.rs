@@ -0,0 +1,248 @@
+use crate::{error::Error, extend::TomlTableExt, state::State, BoxFuture};
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, sync::LazyLock};
+use toml::value::Table;
+
+/// A function pointer of the resolver which looks up the principal by an API key,
+/// which can be used to validate the keys stored in a model table.
+pub type ApiKeyResolver = fn(String) -> BoxFuture<'static, Result<Option<Principal>, Error>>;
+
+/// An authenticated principal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Principal {
+    /// Principal ID.
+    id: String,
+    /// Roles.
+    roles: Vec<String>,
+}
+
+impl Principal {
+    /// Creates a new instance with the ID.
+    #[inline]
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            roles: Vec::new(),
+        }
+    }
+
+    /// Sets the roles.
+    #[inline]
+    pub fn set_roles(&mut self, roles: Vec<String>) {
+        self.roles = roles;
+    }
+
+    /// Returns the principal ID.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the roles.
+    #[inline]
+    pub fn roles(&self) -> &[String] {
+        &self.roles
+    }
+
+    /// Returns `true` if the principal has the role.
+    #[inline]
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Returns `true` if the principal has any of the roles.
+    #[inline]
+    pub fn has_any_role(&self, roles: &[&str]) -> bool {
+        roles.iter().any(|role| self.has_role(role))
+    }
+
+    /// Returns `true` if the principal has all of the roles.
+    #[inline]
+    pub fn has_all_roles(&self, roles: &[&str]) -> bool {
+        roles.iter().all(|role| self.has_role(role))
+    }
+}
+
+/// A store of the API keys.
+///
+/// The keys are configured by the `api-key.keys` array, in which each entry
+/// has the `key`, `principal`, `roles` and `revoked` fields. Multiple active keys
+/// can be resolved to the same principal so that the keys can be rotated.
+/// The keys not found in the store are validated by the resolver if it is set.
+/// Only the SHA-256 digests of the keys are kept in memory.
+#[derive(Debug, Default)]
+pub struct ApiKeyStore {
+    /// Entries keyed by the digest of the API key.
+    entries: RwLock<HashMap<Vec<u8>, ApiKeyEntry>>,
+    /// Resolver for the keys not found in the store.
+    resolver: RwLock<Option<ApiKeyResolver>>,
+}
+
+impl ApiKeyStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new instance with the config.
+    pub fn with_config(config: &Table) -> Self {
+        let store = Self::new();
+        if let Some(keys) = config.get_array("keys") {
+            for entry in keys.iter().filter_map(|v| v.as_table()) {
+                let Some(key) = entry.get_str("key") else {
+                    tracing::warn!("the `key` field of an API key should be specified");
+                    continue;
+                };
+                let mut principal = Principal::new(entry.get_str("principal").unwrap_or_default());
+                if let Some(roles) = entry.get_array("roles") {
+                    let roles = roles
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .map(|s| s.to_owned())
+                        .collect();
+                    principal.set_roles(roles);
+                }
+                store.insert(key, principal);
+                if entry.get_bool("revoked").unwrap_or(false) {
+                    store.revoke(key);
+                }
+            }
+        }
+        store
+    }
+
+    /// Returns a reference to the shared API key store.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        LazyLock::force(&SHARED_API_KEY_STORE)
+    }
+
+    /// Sets the resolver for the keys not found in the store.
+    #[inline]
+    pub fn set_resolver(&self, resolver: ApiKeyResolver) {
+        *self.resolver.write() = Some(resolver);
+    }
+
+    /// Inserts an active API key for the principal.
+    pub fn insert(&self, key: &str, principal: Principal) {
+        let entry = ApiKeyEntry {
+            principal,
+            revoked: false,
+        };
+        self.entries.write().insert(Self::digest(key), entry);
+    }
+
+    /// Revokes an API key. It returns `false` if the key does not exist.
+    pub fn revoke(&self, key: &str) -> bool {
+        if let Some(entry) = self.entries.write().get_mut(&Self::digest(key)) {
+            entry.revoked = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Resolves the principal of an API key.
+    pub async fn resolve(&self, key: &str) -> Result<Principal, Error> {
+        if let Some(entry) = self.entries.read().get(&Self::digest(key)) {
+            return if entry.revoked {
+                Err(Error::new("the API key has been revoked"))
+            } else {
+                Ok(entry.principal.clone())
+            };
+        }
+
+        let resolver = *self.resolver.read();
+        if let Some(resolver) = resolver &&
+            let Some(principal) = resolver(key.to_owned()).await?
+        {
+            return Ok(principal);
+        }
+        Err(Error::new("the API key is invalid"))
+    }
+
+    /// Returns the digest of an API key.
+    #[inline]
+    fn digest(key: &str) -> Vec<u8> {
+        Sha256::digest(key.as_bytes()).to_vec()
+    }
+}
+
+/// An entry of the API key.
+#[derive(Debug, Clone)]
+struct ApiKeyEntry {
+    /// Principal of the key.
+    principal: Principal,
+    /// A flag indicating whether the key has been revoked.
+    revoked: bool,
+}
+
+/// Shared API key store.
+static SHARED_API_KEY_STORE: LazyLock<ApiKeyStore> = LazyLock::new(|| {
+    State::shared()
+        .config()
+        .get_table("api-key")
+        .map(ApiKeyStore::with_config)
+        .unwrap_or_default()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiKeyStore, Principal};
+    use futures::executor::block_on;
+    use toml::value::Table;
+
+    #[test]
+    fn it_resolves_api_keys() {
+        let config = toml::from_str::<Table>(
+            r#"
+            [[keys]]
+            key = "zk_live_2023"
+            principal = "reporter"
+            roles = ["viewer"]
+
+            [[keys]]
+            key = "zk_live_2024"
+            principal = "reporter"
+            roles = ["viewer"]
+
+            [[keys]]
+            key = "zk_live_2022"
+            principal = "reporter"
+            revoked = true
+            "#,
+        )
+        .unwrap();
+        let store = ApiKeyStore::with_config(&config);
+        block_on(async {
+            let principal = store.resolve("zk_live_2023").await.unwrap();
+            assert_eq!(principal.id(), "reporter");
+            assert!(principal.has_role("viewer"));
+            assert!(!principal.has_role("admin"));
+            assert!(principal.has_any_role(&["admin", "viewer"]));
+            assert!(!principal.has_all_roles(&["admin", "viewer"]));
+            assert_eq!(store.resolve("zk_live_2024").await.unwrap(), principal);
+
+            let err = store.resolve("zk_live_2022").await.unwrap_err();
+            assert_eq!(err.message(), "the API key has been revoked");
+            let err = store.resolve("zk_test").await.unwrap_err();
+            assert_eq!(err.message(), "the API key is invalid");
+
+            assert!(store.revoke("zk_live_2023"));
+            assert!(store.resolve("zk_live_2023").await.is_err());
+            assert!(store.resolve("zk_live_2024").await.is_ok());
+
+            store.set_resolver(|key| {
+                Box::pin(async move {
+                    let principal = (key == "zk_model").then(|| Principal::new("service"));
+                    Ok(principal)
+                })
+            });
+            let principal = store.resolve("zk_model").await.unwrap();
+            assert_eq!(principal.id(), "service");
+            assert!(store.resolve("zk_test").await.is_err());
+        });
+    }
+}
@@ -8,12 +8,16 @@ use hmac::{
 use std::time::Duration;
 
 mod access_key;
+mod api_key;
 mod security_token;
+mod session_cookie;
 mod session_id;
 
 pub(crate) use security_token::ParseSecurityTokenError;
+pub(crate) use session_cookie::SessionCookie;
 
 pub use access_key::{AccessKeyId, SecretAccessKey};
+pub use api_key::{ApiKeyResolver, ApiKeyStore, Principal};
 pub use security_token::SecurityToken;
 pub use session_id::SessionId;
 
@@ -0,0 +1,182 @@
+use crate::{application, extend::TomlTableExt, format::base64, state::State, Map};
+use cookie::{Cookie, SameSite};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::{sync::LazyLock, time::Duration};
+use toml::value::Table;
+
+/// A session cookie whose value is a JSON object signed with HMAC-SHA256.
+#[derive(Debug)]
+pub(crate) struct SessionCookie {
+    /// Cookie name.
+    name: String,
+    /// Max age of the session.
+    max_age: Duration,
+    /// The `Secure` attribute.
+    secure: bool,
+    /// The `HttpOnly` attribute.
+    http_only: bool,
+    /// The `SameSite` attribute.
+    same_site: SameSite,
+    /// The signing key followed by previous keys which are still accepted for verification.
+    keys: Vec<Vec<u8>>,
+}
+
+impl SessionCookie {
+    /// Creates a new instance with the config.
+    /// The default secret key is used when the `secret-key` is not specified.
+    pub(crate) fn with_config(config: &Table, default_secret_key: &[u8]) -> Self {
+        let same_site = match config.get_str("same-site") {
+            Some("strict") => SameSite::Strict,
+            Some("none") => SameSite::None,
+            _ => SameSite::Lax,
+        };
+        let mut keys = vec![config
+            .get_str("secret-key")
+            .map(|key| key.as_bytes())
+            .unwrap_or(default_secret_key)
+            .to_vec()];
+        if let Some(previous_keys) = config.get_array("previous-keys") {
+            let previous_keys = previous_keys.iter().filter_map(|v| v.as_str());
+            keys.extend(previous_keys.map(|key| key.as_bytes().to_vec()));
+        }
+        Self {
+            name: config.get_str("name").unwrap_or("session").to_owned(),
+            max_age: config
+                .get_duration("max-age")
+                .unwrap_or_else(|| Duration::from_secs(24 * 60 * 60)),
+            secure: config.get_bool("secure").unwrap_or(true),
+            http_only: config.get_bool("http-only").unwrap_or(true),
+            same_site,
+            keys,
+        }
+    }
+
+    /// Returns a reference to the shared session cookie configured by the `session` table.
+    #[inline]
+    pub(crate) fn shared() -> &'static Self {
+        LazyLock::force(&SHARED_SESSION_COOKIE)
+    }
+
+    /// Returns the cookie name.
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Encodes the session data as a cookie value which expires after the max age.
+    pub(crate) fn encode(&self, session: &Map, now: i64) -> String {
+        let max_age = self.max_age.as_secs().try_into().unwrap_or(i64::MAX);
+        let mut payload = Map::new();
+        payload.insert("data".to_owned(), session.clone().into());
+        payload.insert("expires".to_owned(), now.saturating_add(max_age).into());
+        let payload = base64::encode(Value::from(payload).to_string());
+        let signature = base64::encode(Self::sign(&self.keys[0], &payload).finalize().into_bytes());
+        format!("{payload}.{signature}")
+    }
+
+    /// Decodes the session data from a cookie value.
+    /// Returns `None` if the signature is not valid for any key or the session has expired.
+    pub(crate) fn decode(&self, value: &str, now: i64) -> Option<Map> {
+        let (payload, signature) = value.split_once('.')?;
+        let signature = base64::decode(signature).ok()?;
+        if !self
+            .keys
+            .iter()
+            .any(|key| Self::sign(key, payload).verify_slice(&signature).is_ok())
+        {
+            return None;
+        }
+
+        let bytes = base64::decode(payload).ok()?;
+        let Ok(Value::Object(mut payload)) = serde_json::from_slice(&bytes) else {
+            return None;
+        };
+        if payload.get("expires").and_then(|v| v.as_i64())? <= now {
+            return None;
+        }
+        match payload.remove("data")? {
+            Value::Object(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Creates a new cookie with the value.
+    pub(crate) fn new_cookie(&self, value: String) -> Cookie<'static> {
+        let max_age = self
+            .max_age
+            .try_into()
+            .unwrap_or(cookie::time::Duration::MAX);
+        Cookie::build(self.name.clone(), value)
+            .path("/")
+            .secure(self.secure)
+            .http_only(self.http_only)
+            .same_site(self.same_site)
+            .max_age(max_age)
+            .finish()
+    }
+
+    /// Returns the HMAC of the payload.
+    fn sign(key: &[u8], payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take key of any size");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+/// Shared session cookie.
+static SHARED_SESSION_COOKIE: LazyLock<SessionCookie> = LazyLock::new(|| {
+    let config = State::shared()
+        .config()
+        .get_table("session")
+        .cloned()
+        .unwrap_or_default();
+    let secret_key = application::SECRET_KEY
+        .get()
+        .expect("the secret key should be initialized");
+    SessionCookie::with_config(&config, secret_key)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::SessionCookie;
+    use crate::{extend::JsonObjectExt, Map};
+    use toml::value::Table;
+
+    #[test]
+    fn it_signs_session_cookies() {
+        let now = 1_680_000_000;
+        let mut session = Map::new();
+        session.upsert("user_id", 1);
+        session.upsert("roles", vec!["admin"]);
+
+        let session_cookie = SessionCookie::with_config(&Table::new(), b"old-secret-key");
+        let value = session_cookie.encode(&session, now);
+        assert_eq!(session_cookie.decode(&value, now), Some(session.clone()));
+        assert_eq!(session_cookie.decode(&value, now + 24 * 60 * 60), None);
+
+        let (payload, signature) = value.split_once('.').unwrap();
+        let tampered_payload = payload.replacen(&payload[..4], "AAAA", 1);
+        assert_eq!(
+            session_cookie.decode(&format!("{tampered_payload}.{signature}"), now),
+            None
+        );
+        assert_eq!(session_cookie.decode(payload, now), None);
+
+        let config = toml::from_str::<Table>(
+            r#"
+            secret-key = "new-secret-key"
+            previous-keys = ["old-secret-key"]
+            "#,
+        )
+        .unwrap();
+        let rotated_session_cookie = SessionCookie::with_config(&config, b"");
+        assert_eq!(
+            rotated_session_cookie.decode(&value, now),
+            Some(session.clone())
+        );
+        let value = rotated_session_cookie.encode(&session, now);
+        assert_eq!(session_cookie.decode(&value, now), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! Models shared by the tests of the database module.
+
+use super::{ConnectionPool, IdStrategy, Schema, Transaction};
+use crate::{
+    error::Error,
+    model::{Column, Model},
+    request::Validation,
+    Map, Uuid,
+};
+use apache_avro::schema::{Name, RecordField, RecordFieldOrder};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::LazyLock};
+
+/// A model with timestamps.
+#[derive(Default, Serialize, Deserialize)]
+pub(super) struct Task {
+    pub(super) id: Uuid,
+}
+
+impl Model for Task {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_map(&mut self, _data: &Map) -> Validation {
+        Validation::new()
+    }
+}
+
+impl Schema for Task {
+    const TYPE_NAME: &'static str = "task";
+    const CREATED_AT_COLUMN: Option<&'static str> = Some("created_at");
+    const UPDATED_AT_COLUMN: Option<&'static str> = Some("updated_at");
+
+    fn schema() -> &'static apache_avro::Schema {
+        &TASK_SCHEMA
+    }
+
+    fn columns() -> &'static [Column<'static>] {
+        &TASK_COLUMNS
+    }
+
+    fn fields() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "status",
+            "roles",
+            "profile",
+            "priority",
+            "created_at",
+            "updated_at",
+        ]
+    }
+
+    fn readonly_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn writeonly_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn primary_key(&self) -> String {
+        self.id.to_string()
+    }
+
+    async fn acquire_reader() -> Result<&'static ConnectionPool, Error> {
+        Err(Error::new("connection to the database is not available"))
+    }
+
+    async fn acquire_writer() -> Result<&'static ConnectionPool, Error> {
+        Err(Error::new("connection to the database is not available"))
+    }
+}
+
+/// Columns of the `Task` model.
+static TASK_COLUMNS: [Column<'static>; 8] = [
+    Column::new("id", "Uuid", None, true, None),
+    Column::new("name", "String", None, true, Some("unique")),
+    Column::new("status", "String", None, true, None),
+    Column::new("roles", "Vec<String>", None, true, None),
+    Column::new("profile", "Map", None, true, None),
+    Column::new("priority", "u32", None, true, None),
+    Column::new("created_at", "DateTime", None, true, None),
+    Column::new("updated_at", "DateTime", None, true, None),
+];
+
+/// Avro schema of the `Task` model.
+static TASK_SCHEMA: LazyLock<apache_avro::Schema> =
+    LazyLock::new(|| new_record_schema("task", &TASK_COLUMNS));
+
+/// A model with generated IDs, soft deletion, optimistic locking and a lifecycle hook.
+#[derive(Default, Serialize, Deserialize)]
+pub(super) struct Note {
+    pub(super) id: Uuid,
+    pub(super) title: String,
+}
+
+impl Model for Note {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_map(&mut self, _data: &Map) -> Validation {
+        Validation::new()
+    }
+}
+
+impl Schema for Note {
+    const TYPE_NAME: &'static str = "note";
+    const ID_STRATEGY: Option<IdStrategy> = Some(IdStrategy::UuidV7);
+    const SOFT_DELETE_COLUMN: Option<&'static str> = Some("deleted_at");
+    const VERSION_COLUMN: Option<&'static str> = Some("version");
+
+    fn schema() -> &'static apache_avro::Schema {
+        &NOTE_SCHEMA
+    }
+
+    fn columns() -> &'static [Column<'static>] {
+        &NOTE_COLUMNS
+    }
+
+    fn fields() -> &'static [&'static str] {
+        &["id", "title", "deleted_at", "version"]
+    }
+
+    fn readonly_fields() -> &'static [&'static str] {
+        &["deleted_at"]
+    }
+
+    fn writeonly_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn primary_key(&self) -> String {
+        self.id.to_string()
+    }
+
+    async fn acquire_reader() -> Result<&'static ConnectionPool, Error> {
+        Err(Error::new("connection to the database is not available"))
+    }
+
+    async fn acquire_writer() -> Result<&'static ConnectionPool, Error> {
+        Err(Error::new("connection to the database is not available"))
+    }
+
+    async fn before_insert(&mut self, _tx: &Transaction) -> Result<(), Error> {
+        if self.title.is_empty() {
+            return Err(Error::new("the title should be nonempty"));
+        }
+        self.title = self.title.trim().to_lowercase();
+        Ok(())
+    }
+}
+
+/// Columns of the `Note` model.
+static NOTE_COLUMNS: [Column<'static>; 4] = [
+    Column::new("id", "Uuid", None, true, None),
+    Column::new("title", "String", None, true, None),
+    Column::new("deleted_at", "Option<DateTime>", None, false, None),
+    Column::new("version", "i64", Some("0"), true, None),
+];
+
+/// Avro schema of the `Note` model.
+static NOTE_SCHEMA: LazyLock<apache_avro::Schema> =
+    LazyLock::new(|| new_record_schema("note", &NOTE_COLUMNS));
+
+/// Creates an Avro record schema with the columns.
+fn new_record_schema(name: &str, columns: &[Column<'static>]) -> apache_avro::Schema {
+    let fields = columns
+        .iter()
+        .enumerate()
+        .map(|(index, col)| RecordField {
+            name: col.name().to_owned(),
+            doc: None,
+            default: None,
+            schema: col.schema(),
+            order: RecordFieldOrder::Ascending,
+            position: index,
+        })
+        .collect();
+    apache_avro::Schema::Record {
+        name: Name {
+            name: name.to_owned(),
+            namespace: None,
+        },
+        aliases: None,
+        doc: None,
+        fields,
+        lookup: BTreeMap::new(),
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{error::Error, extend::TomlTableExt, state::State, Uuid};
+use parking_lot::Mutex;
+use std::{
+    fmt,
+    str::FromStr,
+    sync::LazyLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Custom epoch of the snowflake IDs, which is `2023-01-01T00:00:00Z` in milliseconds.
+const SNOWFLAKE_EPOCH: u64 = 1_672_531_200_000;
+
+/// Crockford's Base32 alphabet used to encode the ULIDs.
+const CROCKFORD_BASE32_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Strategies to generate the primary key of a model.
+///
+/// The UUIDv7s, ULIDs and snowflake IDs are prefixed with the timestamp so that
+/// the new rows are appended to the end of the B-tree index. They are monotonically
+/// increasing within the process even if the IDs are generated in the same millisecond.
+/// The node ID of the snowflake IDs is configured by `database.node-id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IdStrategy {
+    /// Random UUIDv4.
+    UuidV4,
+    /// Time-ordered UUIDv7.
+    UuidV7,
+    /// ULID, which can be stored as a UUID or a 26-character string.
+    Ulid,
+    /// 64-bit snowflake ID composed of the timestamp, node ID and sequence number.
+    Snowflake,
+}
+
+impl IdStrategy {
+    /// Generates a new ID as a 128-bit integer.
+    pub fn generate(self) -> u128 {
+        match self {
+            Self::UuidV4 => Uuid::new_v4().as_u128(),
+            Self::UuidV7 => {
+                let (timestamp, random) = UUID_V7_GENERATOR.lock().next(unix_timestamp());
+                let rand_a = random >> 62;
+                let rand_b = random & ((1 << 62) - 1);
+                (u128::from(timestamp) << 80) | (0x7 << 76) | (rand_a << 64) | (0b10 << 62) | rand_b
+            }
+            Self::Ulid => {
+                let (timestamp, random) = ULID_GENERATOR.lock().next(unix_timestamp());
+                (u128::from(timestamp) << 80) | random
+            }
+            Self::Snowflake => {
+                let id = SNOWFLAKE_GENERATOR
+                    .lock()
+                    .next(unix_timestamp(), *SNOWFLAKE_NODE_ID);
+                u128::from(id)
+            }
+        }
+    }
+
+    /// Generates a new ID as a UUID.
+    #[inline]
+    pub fn generate_uuid(self) -> Uuid {
+        Uuid::from_u128(self.generate())
+    }
+
+    /// Generates a new ID and encodes it as a string. The UUIDs are hyphenated,
+    /// the ULIDs are encoded in Crockford's Base32, and the snowflake IDs are in decimal.
+    pub fn generate_string(self) -> String {
+        let id = self.generate();
+        match self {
+            Self::UuidV4 | Self::UuidV7 => Uuid::from_u128(id).to_string(),
+            Self::Ulid => encode_ulid(id),
+            Self::Snowflake => id.to_string(),
+        }
+    }
+
+    /// Returns the name of the strategy.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UuidV4 => "uuid_v4",
+            Self::UuidV7 => "uuid_v7",
+            Self::Ulid => "ulid",
+            Self::Snowflake => "snowflake",
+        }
+    }
+}
+
+impl fmt::Display for IdStrategy {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for IdStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uuid" | "uuid_v4" => Ok(Self::UuidV4),
+            "uuid_v7" => Ok(Self::UuidV7),
+            "ulid" => Ok(Self::Ulid),
+            "snowflake" => Ok(Self::Snowflake),
+            _ => Err(Error::new(format!(
+                "the ID strategy `{s}` is not supported"
+            ))),
+        }
+    }
+}
+
+/// A generator of the IDs which are prefixed with a 48-bit timestamp in milliseconds.
+/// The random bits are incremented instead if the timestamp does not advance.
+#[derive(Debug)]
+struct MonotonicGenerator {
+    /// Number of the random bits.
+    random_bits: u32,
+    /// Last timestamp in milliseconds.
+    last_timestamp: u64,
+    /// Last random bits.
+    last_random: u128,
+}
+
+impl MonotonicGenerator {
+    /// Creates a new instance with the number of the random bits.
+    const fn new(random_bits: u32) -> Self {
+        Self {
+            random_bits,
+            last_timestamp: 0,
+            last_random: 0,
+        }
+    }
+
+    /// Returns the timestamp and random bits of the next ID.
+    fn next(&mut self, timestamp: u64) -> (u64, u128) {
+        let max_random = (1 << self.random_bits) - 1;
+        if timestamp > self.last_timestamp {
+            // The highest random bit is cleared to leave room for the increments.
+            self.last_timestamp = timestamp;
+            self.last_random = rand::random::<u128>() & (max_random >> 1);
+        } else if self.last_random < max_random {
+            self.last_random += 1;
+        } else {
+            self.last_timestamp += 1;
+            self.last_random = 0;
+        }
+        (self.last_timestamp, self.last_random)
+    }
+}
+
+/// A generator of the snowflake IDs, which consist of a 41-bit timestamp since
+/// the custom epoch, a 10-bit node ID and a 12-bit sequence number.
+#[derive(Debug)]
+struct SnowflakeGenerator {
+    /// Last timestamp since the custom epoch.
+    last_timestamp: u64,
+    /// Sequence number within the millisecond.
+    sequence: u64,
+}
+
+impl SnowflakeGenerator {
+    /// Creates a new instance.
+    const fn new() -> Self {
+        Self {
+            last_timestamp: 0,
+            sequence: 0,
+        }
+    }
+
+    /// Returns the next ID for the node.
+    fn next(&mut self, timestamp: u64, node_id: u16) -> u64 {
+        let timestamp = timestamp.saturating_sub(SNOWFLAKE_EPOCH);
+        if timestamp > self.last_timestamp {
+            self.last_timestamp = timestamp;
+            self.sequence = 0;
+        } else if self.sequence < 0xfff {
+            self.sequence += 1;
+        } else {
+            self.last_timestamp += 1;
+            self.sequence = 0;
+        }
+        (self.last_timestamp << 22) | (u64::from(node_id & 0x3ff) << 12) | self.sequence
+    }
+}
+
+/// Returns the current Unix timestamp in milliseconds.
+#[inline]
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+/// Encodes the ULID as a 26-character string in Crockford's Base32.
+fn encode_ulid(id: u128) -> String {
+    (0..26)
+        .rev()
+        .map(|i| char::from(CROCKFORD_BASE32_ALPHABET[((id >> (i * 5)) & 0x1f) as usize]))
+        .collect()
+}
+
+/// Generator of the UUIDv7s, which have 74 random bits.
+static UUID_V7_GENERATOR: Mutex<MonotonicGenerator> = Mutex::new(MonotonicGenerator::new(74));
+
+/// Generator of the ULIDs, which have 80 random bits.
+static ULID_GENERATOR: Mutex<MonotonicGenerator> = Mutex::new(MonotonicGenerator::new(80));
+
+/// Generator of the snowflake IDs.
+static SNOWFLAKE_GENERATOR: Mutex<SnowflakeGenerator> = Mutex::new(SnowflakeGenerator::new());
+
+/// Node ID of the snowflake IDs.
+static SNOWFLAKE_NODE_ID: LazyLock<u16> = LazyLock::new(|| {
+    State::shared()
+        .config()
+        .get_table("database")
+        .and_then(|config| config.get_u16("node-id"))
+        .unwrap_or_default()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_ulid, IdStrategy, MonotonicGenerator, SnowflakeGenerator};
+
+    #[test]
+    fn it_generates_monotonic_ids() {
+        let (id, next_id) = (IdStrategy::UuidV7.generate(), IdStrategy::UuidV7.generate());
+        assert!(id < next_id);
+        let uuid = IdStrategy::UuidV7.generate_uuid();
+        assert_eq!(uuid.get_version_num(), 7);
+        assert!(uuid.to_string() < IdStrategy::UuidV7.generate_string());
+
+        let (id, next_id) = (IdStrategy::Ulid.generate(), IdStrategy::Ulid.generate());
+        assert!(id < next_id);
+        let ulid = IdStrategy::Ulid.generate_string();
+        assert_eq!(ulid.len(), 26);
+        assert!(ulid < IdStrategy::Ulid.generate_string());
+        assert_eq!(encode_ulid(0), "00000000000000000000000000");
+        assert_eq!(encode_ulid(u128::MAX), "7ZZZZZZZZZZZZZZZZZZZZZZZZZ");
+
+        let mut generator = MonotonicGenerator::new(2);
+        assert_eq!(generator.next(100).0, 100);
+        let ids = (0..8).map(|_| generator.next(100)).collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert!(generator.next(99) > ids[7]);
+
+        let mut generator = SnowflakeGenerator::new();
+        let timestamp = 1_680_000_000_000;
+        let ids = (0..5000)
+            .map(|_| generator.next(timestamp, 7))
+            .collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!((ids[0] >> 12) & 0x3ff, 7);
+        assert!(generator.next(timestamp - 1, 7) > ids[4999]);
+
+        assert_eq!("uuid_v7".parse::<IdStrategy>().unwrap(), IdStrategy::UuidV7);
+        assert_eq!("ulid".parse::<IdStrategy>().unwrap().to_string(), "ulid");
+        assert!("uuid_v1".parse::<IdStrategy>().is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    database::Schema,
+    model::{Column, EncodeColumn},
+};
+use sqlx::Postgres;
+
+/// Metadata of an existing table in the database.
+#[derive(Debug, Clone, Default)]
+pub struct TableInfo {
+    /// Table name.
+    name: String,
+    /// Columns.
+    columns: Vec<ColumnInfo>,
+}
+
+impl TableInfo {
+    /// Creates a new instance without any columns.
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// Adds a column.
+    #[inline]
+    pub fn add_column(&mut self, column: ColumnInfo) {
+        self.columns.push(column);
+    }
+
+    /// Returns the table name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns a reference to the columns.
+    #[inline]
+    pub fn columns(&self) -> &[ColumnInfo] {
+        &self.columns
+    }
+
+    /// Gets the column with the name.
+    #[inline]
+    pub fn get_column(&self, name: &str) -> Option<&ColumnInfo> {
+        self.columns.iter().find(|col| col.name() == name)
+    }
+
+    /// Returns `true` if the table does not exist in the database.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+}
+
+/// Metadata of an existing column in the database.
+#[derive(Debug, Clone)]
+pub struct ColumnInfo {
+    /// Column name.
+    name: String,
+    /// Column type as formatted by the database.
+    column_type: String,
+    /// `NOT NULL` constraint.
+    not_null: bool,
+    /// Default expression.
+    default_value: Option<String>,
+}
+
+impl ColumnInfo {
+    /// Creates a new instance.
+    pub fn new(
+        name: impl Into<String>,
+        column_type: impl Into<String>,
+        not_null: bool,
+        default_value: Option<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            column_type: column_type.into(),
+            not_null,
+            default_value,
+        }
+    }
+
+    /// Returns the name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the column type.
+    #[inline]
+    pub fn column_type(&self) -> &str {
+        &self.column_type
+    }
+
+    /// Returns `true` if the column can not be null.
+    #[inline]
+    pub fn is_not_null(&self) -> bool {
+        self.not_null
+    }
+
+    /// Returns the default expression.
+    #[inline]
+    pub fn default_value(&self) -> Option<&str> {
+        self.default_value.as_deref()
+    }
+}
+
+/// Formats the column definition used in `CREATE TABLE` and `ADD COLUMN`.
+pub(super) fn format_column_definition(col: &Column) -> String {
+    let name = col.name();
+    let column_type = Postgres::column_type(col);
+    let mut column = format!("{name} {column_type}");
+    if let Some(value) = col.default_value() {
+        column = column + " DEFAULT " + &Postgres::format_value(col, value);
+    } else if col.is_not_null() {
+        column += " NOT NULL";
+    }
+    column
+}
+
+/// Formats the `CREATE TABLE` statement for the model.
+pub(super) fn format_create_table<M: Schema>(table_name: &str) -> String {
+    let primary_key_name = M::PRIMARY_KEY_NAME;
+    let columns = M::columns()
+        .iter()
+        .map(format_column_definition)
+        .collect::<Vec<_>>()
+        .join(",\n    ");
+    let mut sql = format!(
+        "CREATE TABLE IF NOT EXISTS {table_name} (\n    {columns},\n    \
+            CONSTRAINT {table_name}_pkey PRIMARY KEY ({primary_key_name})\n);"
+    );
+    if let Some(column_name) = M::DISTRIBUTION_COLUMN {
+        sql += &format!("\nSELECT create_distributed_table('{table_name}', '{column_name}');");
+    }
+    sql
+}
+
+/// Formats the statements which migrate the existing table to the model definition.
+/// Columns which only exist in the database are kept, and default expressions
+/// are compared by presence since the database normalizes them.
+pub(super) fn format_table_diff<M: Schema>(existing: &TableInfo) -> Vec<String> {
+    let table_name = existing.name();
+    if existing.is_empty() {
+        return vec![format_create_table::<M>(table_name)];
+    }
+
+    let mut statements = Vec::new();
+    for col in M::columns() {
+        let column_name = col.name();
+        let Some(info) = existing.get_column(column_name) else {
+            let definition = format_column_definition(col);
+            statements.push(format!(
+                "ALTER TABLE {table_name} ADD COLUMN IF NOT EXISTS {definition};"
+            ));
+            continue;
+        };
+
+        let column_type = Postgres::column_type(col);
+        if normalize_type(column_type) != normalize_type(info.column_type()) {
+            statements.push(format!(
+                "ALTER TABLE {table_name} ALTER COLUMN {column_name} \
+                    TYPE {column_type} USING {column_name}::{column_type};"
+            ));
+        }
+
+        let not_null = col.default_value().is_none() && col.is_not_null();
+        if not_null != info.is_not_null() {
+            let action = if not_null { "SET" } else { "DROP" };
+            statements.push(format!(
+                "ALTER TABLE {table_name} ALTER COLUMN {column_name} {action} NOT NULL;"
+            ));
+        }
+
+        match (col.default_value(), info.default_value()) {
+            (Some(value), None) => {
+                let value = Postgres::format_value(col, value);
+                statements.push(format!(
+                    "ALTER TABLE {table_name} ALTER COLUMN {column_name} SET DEFAULT {value};"
+                ));
+            }
+            (None, Some(_)) => {
+                statements.push(format!(
+                    "ALTER TABLE {table_name} ALTER COLUMN {column_name} DROP DEFAULT;"
+                ));
+            }
+            _ => (),
+        }
+    }
+    statements
+}
+
+/// Normalizes the type aliases to the names reported by `format_type()`.
+fn normalize_type(column_type: &str) -> &str {
+    match column_type {
+        "int" | "int4" => "integer",
+        "int8" => "bigint",
+        "int2" => "smallint",
+        "bool" => "boolean",
+        "float8" => "double precision",
+        "float4" => "real",
+        "timestamptz" => "timestamp with time zone",
+        "timestamptz[]" => "timestamp with time zone[]",
+        _ => column_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_create_table, format_table_diff, ColumnInfo, TableInfo};
+    use crate::database::fixtures::Note;
+
+    #[test]
+    fn it_formats_table_migrations() {
+        let sql = format_create_table::<Note>("note");
+        assert_eq!(
+            sql,
+            "CREATE TABLE IF NOT EXISTS note (\n    id uuid NOT NULL,\n    title text NOT NULL,\n    \
+                deleted_at timestamptz,\n    version bigint DEFAULT 0,\n    \
+                CONSTRAINT note_pkey PRIMARY KEY (id)\n);"
+        );
+        assert_eq!(
+            format_table_diff::<Note>(&TableInfo::new("note")),
+            vec![sql]
+        );
+
+        let mut table_info = TableInfo::new("note");
+        table_info.add_column(ColumnInfo::new("id", "uuid", true, None));
+        table_info.add_column(ColumnInfo::new("title", "character varying", false, None));
+        table_info.add_column(ColumnInfo::new(
+            "deleted_at",
+            "timestamp with time zone",
+            true,
+            Some("now()".to_owned()),
+        ));
+        assert_eq!(
+            format_table_diff::<Note>(&table_info),
+            vec![
+                "ALTER TABLE note ALTER COLUMN title TYPE text USING title::text;",
+                "ALTER TABLE note ALTER COLUMN title SET NOT NULL;",
+                "ALTER TABLE note ALTER COLUMN deleted_at DROP NOT NULL;",
+                "ALTER TABLE note ALTER COLUMN deleted_at DROP DEFAULT;",
+                "ALTER TABLE note ADD COLUMN IF NOT EXISTS version bigint DEFAULT 0;",
+            ]
+        );
+    }
+}
@@ -1,25 +1,38 @@
 //! Database schema and ORM.
 
-use crate::{extend::TomlTableExt, state::State};
+use crate::{error::Error, extend::TomlTableExt, state::State};
+use futures::future;
 use sqlx::{
+    pool::PoolConnection,
     postgres::{PgConnectOptions, PgPool, PgPoolOptions},
     Connection, Database, Pool, Postgres,
 };
-use std::{
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        LazyLock,
-    },
-    time::Duration,
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, LazyLock,
 };
 use toml::value::Table;
 
+mod id_strategy;
+mod migration;
 mod mutation;
+mod optimistic_lock;
+mod pool_config;
 mod postgres;
 mod query;
+mod retry;
 mod schema;
+mod transaction;
 
+#[cfg(test)]
+mod fixtures;
+
+pub use id_strategy::IdStrategy;
+pub use migration::{ColumnInfo, TableInfo};
+pub use optimistic_lock::OptimisticLockError;
+pub use pool_config::PoolConfig;
 pub use schema::Schema;
+pub use transaction::Transaction;
 
 /// A database connection pool.
 #[derive(Debug)]
@@ -33,8 +46,16 @@ where
     database: &'static str,
     /// Pool.
     pool: Pool<DB>,
+    /// Pool config.
+    config: PoolConfig,
+    /// A flag to indicate whether the database is a read replica.
+    replica: bool,
     /// Availability.
-    available: AtomicBool,
+    available: Arc<AtomicBool>,
+    /// Number of the tasks waiting for a connection.
+    waiters: AtomicUsize,
+    /// A flag to indicate whether all the connections are in use.
+    exhausted: AtomicBool,
 }
 
 impl ConnectionPool<Postgres> {
@@ -50,9 +71,20 @@ impl ConnectionPool<Postgres> {
         self.available.store(available, Ordering::Relaxed);
     }
 
+    /// Returns `true` if the database is a read replica.
+    #[inline]
+    pub fn is_replica(&self) -> bool {
+        self.replica
+    }
+
     /// Connects lazily to the database according to the config.
+    /// The databases with the same name consist of a primary and optional read replicas
+    /// specified by `role = "replica"`.
+    /// The prepared statements are cached by each connection with the LRU eviction,
+    /// and the cache size is specified by `statement-cache-capacity`.
     pub fn connect_lazy(application_name: &'static str, config: &'static Table) -> Self {
         let name = config.get_str("name").unwrap_or("main");
+        let replica = config.get_str("role") == Some("replica");
 
         // Connect options.
         let statement_cache_capacity = config.get_usize("statement-cache-capacity").unwrap_or(100);
@@ -83,34 +115,25 @@ impl ConnectionPool<Postgres> {
             .leak();
 
         // Pool options.
-        let max_connections = config.get_u32("max-connections").unwrap_or(16);
-        let min_connections = config.get_u32("min-connections").unwrap_or(2);
-        let max_lifetime = config
-            .get_duration("max-lifetime")
-            .unwrap_or_else(|| Duration::from_secs(60 * 60));
-        let idle_timeout = config
-            .get_duration("idle-timeout")
-            .unwrap_or_else(|| Duration::from_secs(10 * 60));
-        let acquire_timeout = config
-            .get_duration("acquire-timeout")
-            .unwrap_or_else(|| Duration::from_secs(30));
+        let pool_config = PoolConfig::with_config(config);
+        let available = Arc::new(AtomicBool::new(true));
+        let availability = available.clone();
         let pool = PgPoolOptions::new()
-            .max_connections(max_connections)
-            .min_connections(min_connections)
-            .max_lifetime(max_lifetime)
-            .idle_timeout(idle_timeout)
-            .acquire_timeout(acquire_timeout)
+            .max_connections(pool_config.max_connections())
+            .min_connections(pool_config.min_connections())
+            .max_lifetime(pool_config.max_lifetime())
+            .idle_timeout(pool_config.idle_timeout())
+            .acquire_timeout(pool_config.acquire_timeout())
             .test_before_acquire(false)
             .before_acquire(move |conn, meta| {
+                let availability = availability.clone();
                 Box::pin(async move {
-                    if meta.idle_for.as_secs() > 60 &&
-                        let Some(pool) = SHARED_CONNECTION_POOLS.get_pool(name)
-                    {
+                    if meta.idle_for.as_secs() > 60 {
                         if let Err(err) = conn.ping().await {
-                            pool.store_availability(false);
+                            availability.store(false, Ordering::Relaxed);
                             return Err(err);
                         } else {
-                            pool.store_availability(true);
+                            availability.store(true, Ordering::Relaxed);
                         }
                     }
                     Ok(true)
@@ -122,7 +145,11 @@ impl ConnectionPool<Postgres> {
             name,
             database,
             pool,
-            available: AtomicBool::new(true),
+            config: pool_config,
+            replica,
+            available,
+            waiters: AtomicUsize::new(0),
+            exhausted: AtomicBool::new(false),
         }
     }
 
@@ -138,23 +165,128 @@ impl ConnectionPool<Postgres> {
         self.database
     }
 
-    /// Returns a reference to the pool.
+    /// Returns a reference to the pool config.
+    #[inline]
+    pub fn config(&self) -> &PoolConfig {
+        &self.config
+    }
+
+    /// Returns a reference to the pool. The pool metrics are recorded
+    /// since it is called before executing the statements.
     #[inline]
     pub(crate) fn pool(&self) -> &PgPool {
+        self.record_metrics();
         &self.pool
     }
+
+    /// Acquires a connection from the pool.
+    /// It returns an error if no connection is available within the acquire timeout.
+    pub async fn acquire(&self) -> Result<PoolConnection<Postgres>, Error> {
+        self.waiters.fetch_add(1, Ordering::Relaxed);
+        self.record_metrics();
+        let result = self.pool.acquire().await;
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+        self.record_metrics();
+        result.map_err(|err| self.config.acquire_error(self.name, err))
+    }
+
+    /// Acquires a connection and pings the database, and updates the availability.
+    pub async fn check(&self) -> Result<(), Error> {
+        let mut conn = self
+            .acquire()
+            .await
+            .inspect_err(|_| self.store_availability(false))?;
+        let result = conn.ping().await;
+        self.store_availability(result.is_ok());
+        result.map_err(Error::from)
+    }
+
+    /// Emits the gauges of the connections in use, the idle connections and the waiters,
+    /// and logs a warning when all the connections become in use.
+    fn record_metrics(&self) {
+        let size = self.pool.size();
+        let num_idle = self.pool.num_idle();
+        let in_use = size.saturating_sub(num_idle as u32);
+        let waiters = self.waiters.load(Ordering::Relaxed);
+        let name = self.name;
+        metrics::gauge!(
+            "zino_db_pool_connections",
+            f64::from(in_use),
+            "pool" => name,
+            "state" => "in_use",
+        );
+        metrics::gauge!(
+            "zino_db_pool_connections",
+            num_idle as f64,
+            "pool" => name,
+            "state" => "idle",
+        );
+        metrics::gauge!("zino_db_pool_waiters", waiters as f64, "pool" => name);
+
+        let exhausted = self.config.is_exhausted(size, num_idle);
+        if self.exhausted.swap(exhausted, Ordering::Relaxed) != exhausted && exhausted {
+            tracing::warn!(
+                max_connections = self.config.max_connections(),
+                waiters,
+                "all the connections of the `{name}` pool are in use"
+            );
+        }
+    }
+}
+
+/// Routing information of a connection pool.
+trait PoolRoute {
+    /// Returns the name.
+    fn name(&self) -> &str;
+
+    /// Returns `true` if the database is a read replica.
+    fn is_replica(&self) -> bool;
+
+    /// Returns `true` if the connection pool is available.
+    fn is_available(&self) -> bool;
+}
+
+impl PoolRoute for ConnectionPool {
+    #[inline]
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    #[inline]
+    fn is_replica(&self) -> bool {
+        self.replica
+    }
+
+    #[inline]
+    fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
 }
 
 /// A list of database connection pools.
 #[derive(Debug)]
-struct ConnectionPools(Vec<ConnectionPool>);
+struct ConnectionPools<P = ConnectionPool> {
+    /// Connection pools.
+    pools: Vec<P>,
+    /// A counter to select the read replicas in a round-robin fashion.
+    replica_counter: AtomicUsize,
+}
+
+impl<P: PoolRoute> ConnectionPools<P> {
+    /// Creates a new instance.
+    #[inline]
+    fn new(pools: Vec<P>) -> Self {
+        Self {
+            pools,
+            replica_counter: AtomicUsize::new(0),
+        }
+    }
 
-impl ConnectionPools {
-    /// Returns a connection pool with the specific name.
-    pub(crate) fn get_pool(&self, name: &str) -> Option<&ConnectionPool> {
+    /// Returns the primary connection pool with the specific name.
+    pub(crate) fn get_pool(&self, name: &str) -> Option<&P> {
         let mut pool = None;
-        for c in self.0.iter() {
-            if c.name() == name {
+        for c in self.pools.iter() {
+            if c.name() == name && !c.is_replica() {
                 if c.is_available() {
                     return Some(c);
                 } else {
@@ -164,6 +296,21 @@ impl ConnectionPools {
         }
         pool
     }
+
+    /// Returns an available read replica with the specific name in a round-robin fashion.
+    pub(crate) fn get_replica(&self, name: &str) -> Option<&P> {
+        let replicas = self
+            .pools
+            .iter()
+            .filter(|c| c.name() == name && c.is_replica() && c.is_available())
+            .collect::<Vec<_>>();
+        if replicas.is_empty() {
+            None
+        } else {
+            let index = self.replica_counter.fetch_add(1, Ordering::Relaxed) % replicas.len();
+            Some(replicas[index])
+        }
+    }
 }
 
 /// Shared connection pools.
@@ -184,9 +331,32 @@ static SHARED_CONNECTION_POOLS: LazyLock<ConnectionPools> = LazyLock::new(|| {
         .filter_map(|v| v.as_table())
         .map(|database| ConnectionPool::connect_lazy(application_name, database))
         .collect::<Vec<_>>();
-    ConnectionPools(pools)
+    ConnectionPools::new(pools)
 });
 
+/// Checks all the shared connection pools concurrently.
+/// It returns an empty list if the `postgres` field is not configured.
+pub(crate) async fn check_pools() -> Vec<(String, Result<(), Error>)> {
+    if State::shared().config().get_array("postgres").is_none() {
+        return Vec::new();
+    }
+
+    let checks = SHARED_CONNECTION_POOLS.pools.iter().map(|pool| async move {
+        let role = if pool.is_replica() {
+            "replica"
+        } else {
+            "primary"
+        };
+        let name = format!("database.{}.{role}", pool.name());
+        let result = pool.check().await;
+        if let Err(err) = &result {
+            tracing::error!("fail to check the `{name}` connection pool: {err}");
+        }
+        (name, result)
+    });
+    future::join_all(checks).await
+}
+
 /// Database namespace prefix.
 static NAMESPACE_PREFIX: LazyLock<&'static str> = LazyLock::new(|| {
     State::shared()
@@ -196,3 +366,56 @@ static NAMESPACE_PREFIX: LazyLock<&'static str> = LazyLock::new(|| {
         .get_str("namespace")
         .expect("the `database.namespace` field should be a str")
 });
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectionPools, PoolRoute};
+    use std::ptr;
+
+    struct MockPool {
+        name: &'static str,
+        replica: bool,
+        available: bool,
+    }
+
+    impl PoolRoute for MockPool {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn is_replica(&self) -> bool {
+            self.replica
+        }
+
+        fn is_available(&self) -> bool {
+            self.available
+        }
+    }
+
+    #[test]
+    fn it_routes_reads_to_replicas() {
+        let mock_pool = |name, replica, available| MockPool {
+            name,
+            replica,
+            available,
+        };
+        let pools = ConnectionPools::new(vec![
+            mock_pool("main", true, true),
+            mock_pool("main", false, true),
+            mock_pool("main", true, false),
+            mock_pool("main", true, true),
+            mock_pool("auth", false, true),
+        ]);
+        for _ in 0..3 {
+            let primary = pools.get_pool("main").unwrap();
+            assert!(!primary.is_replica());
+        }
+
+        for index in [0, 3, 0, 3] {
+            let replica = pools.get_replica("main").unwrap();
+            assert!(ptr::eq(replica, &pools.pools[index]));
+        }
+        assert!(pools.get_replica("auth").is_none());
+        assert!(pools.get_pool("auth").is_some());
+    }
+}
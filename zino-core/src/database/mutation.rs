@@ -1,6 +1,9 @@
 /// Generates SQL `SET` expressions.
 use super::Schema;
-use crate::model::{EncodeColumn, Mutation};
+use crate::{
+    datetime::DateTime,
+    model::{EncodeColumn, Mutation},
+};
 use sqlx::Postgres;
 
 /// Extension trait for [`Mutation`](crate::model::Mutation).
@@ -107,6 +110,52 @@ impl MutationExt<Postgres> for Mutation {
                 }
             }
         }
+        if let Some(column) = M::VERSION_COLUMN && !updates.contains_key(column) {
+            mutations.push(format!(r#""{column}" = "{column}" + 1"#));
+        }
+        if let Some(column) = M::UPDATED_AT_COLUMN &&
+            !updates.contains_key(column) &&
+            let Some(col) = M::get_column(column)
+        {
+            let value = Postgres::encode_value(col, Some(&DateTime::now().into()));
+            mutations.push(format!(r#""{column}" = {value}"#));
+        }
         mutations.join(", ")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MutationExt;
+    use crate::{
+        database::fixtures::{Note, Task},
+        extend::JsonObjectExt,
+        model::Mutation,
+        Map,
+    };
+    use sqlx::Postgres;
+
+    #[test]
+    fn it_formats_updates() {
+        let format_updates = <Mutation as MutationExt<Postgres>>::format_updates::<Note>;
+        let mutation = Mutation::new(Map::from_entry("title", "draft"));
+        assert_eq!(
+            format_updates(&mutation),
+            r#""title" = 'draft', "version" = "version" + 1"#
+        );
+
+        let mut updates = Map::from_entry("title", "draft");
+        updates.upsert("version", 3);
+        let mutation = Mutation::new(updates);
+        assert_eq!(
+            format_updates(&mutation),
+            r#""title" = 'draft', "version" = 3"#
+        );
+
+        let format_updates = <Mutation as MutationExt<Postgres>>::format_updates::<Task>;
+        let mutation = Mutation::new(Map::from_entry("status", "done"));
+        let updates = format_updates(&mutation);
+        assert!(updates.starts_with(r#""status" = 'done', "updated_at" = '"#));
+        assert!(!updates.contains("created_at"));
+    }
+}
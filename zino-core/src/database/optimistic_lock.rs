@@ -0,0 +1,86 @@
+use crate::error::Error;
+use std::{error, fmt};
+
+/// An error which occurs when a row has been updated by others since it was read.
+/// It is converted by [`Error::from_typed()`] so that it can be detected.
+#[derive(Debug, Clone)]
+pub struct OptimisticLockError {
+    /// Model name.
+    model_name: &'static str,
+    /// Primary key.
+    primary_key: String,
+    /// Stale version.
+    version: i64,
+}
+
+impl OptimisticLockError {
+    /// Creates a new instance.
+    #[inline]
+    pub(super) fn new(model_name: &'static str, primary_key: String, version: i64) -> Self {
+        Self {
+            model_name,
+            primary_key,
+            version,
+        }
+    }
+
+    /// Returns the model name.
+    #[inline]
+    pub fn model_name(&self) -> &'static str {
+        self.model_name
+    }
+
+    /// Returns the primary key.
+    #[inline]
+    pub fn primary_key(&self) -> &str {
+        &self.primary_key
+    }
+
+    /// Returns the stale version.
+    #[inline]
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// Returns `true` if the error is caused by an optimistic lock conflict.
+    /// It can be used to respond with `409 Conflict`.
+    #[inline]
+    pub fn is_conflict(err: &Error) -> bool {
+        err.downcast_ref::<Self>().is_some()
+    }
+}
+
+impl fmt::Display for OptimisticLockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let model_name = self.model_name;
+        let primary_key = &self.primary_key;
+        let version = self.version;
+        write!(
+            f,
+            "optimistic lock conflict: the model `{model_name}` with the primary key `{primary_key}` \
+                has been updated since the version `{version}`"
+        )
+    }
+}
+
+impl error::Error for OptimisticLockError {}
+
+#[cfg(test)]
+mod tests {
+    use super::OptimisticLockError;
+    use crate::error::Error;
+
+    #[test]
+    fn it_detects_conflicts() {
+        let err = Error::from_typed(OptimisticLockError::new("note", "1".to_owned(), 2));
+        assert!(OptimisticLockError::is_conflict(&err));
+
+        let message = err.message().to_owned();
+        let err = err.context("fail to update the note");
+        assert!(OptimisticLockError::is_conflict(&err));
+        assert!(!OptimisticLockError::is_conflict(&Error::new(message)));
+        assert!(!OptimisticLockError::is_conflict(&Error::new(
+            "1 rows are affected"
+        )));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{error::Error, extend::TomlTableExt};
+use std::time::Duration;
+use toml::value::Table;
+
+/// Sizing and timeouts of a database connection pool.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolConfig {
+    /// Max number of connections.
+    max_connections: u32,
+    /// Min number of idle connections.
+    min_connections: u32,
+    /// Max lifetime of a connection.
+    max_lifetime: Duration,
+    /// Max idle duration of a connection.
+    idle_timeout: Duration,
+    /// Max duration to wait for a connection.
+    acquire_timeout: Duration,
+}
+
+impl PoolConfig {
+    /// Creates a new instance with the config.
+    /// The min connections are capped by the max connections.
+    pub fn with_config(config: &Table) -> Self {
+        let max_connections = config.get_u32("max-connections").unwrap_or(16).max(1);
+        let mut min_connections = config.get_u32("min-connections").unwrap_or(2);
+        if min_connections > max_connections {
+            tracing::warn!(
+                "the min connections `{min_connections}` exceed the max connections \
+                    `{max_connections}`"
+            );
+            min_connections = max_connections;
+        }
+        Self {
+            max_connections,
+            min_connections,
+            max_lifetime: config
+                .get_duration("max-lifetime")
+                .unwrap_or_else(|| Duration::from_secs(60 * 60)),
+            idle_timeout: config
+                .get_duration("idle-timeout")
+                .unwrap_or_else(|| Duration::from_secs(10 * 60)),
+            acquire_timeout: config
+                .get_duration("acquire-timeout")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+        }
+    }
+
+    /// Returns the max number of connections.
+    #[inline]
+    pub fn max_connections(&self) -> u32 {
+        self.max_connections
+    }
+
+    /// Returns the min number of idle connections.
+    #[inline]
+    pub fn min_connections(&self) -> u32 {
+        self.min_connections
+    }
+
+    /// Returns the max lifetime of a connection.
+    #[inline]
+    pub fn max_lifetime(&self) -> Duration {
+        self.max_lifetime
+    }
+
+    /// Returns the max idle duration of a connection.
+    #[inline]
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Returns the max duration to wait for a connection.
+    #[inline]
+    pub fn acquire_timeout(&self) -> Duration {
+        self.acquire_timeout
+    }
+
+    /// Returns `true` if all the connections are in use.
+    #[inline]
+    pub(super) fn is_exhausted(&self, size: u32, num_idle: usize) -> bool {
+        size >= self.max_connections && num_idle == 0
+    }
+
+    /// Converts the error of acquiring a connection from the pool named `name`.
+    pub(super) fn acquire_error(&self, name: &str, err: sqlx::Error) -> Error {
+        if let sqlx::Error::PoolTimedOut = err {
+            let acquire_timeout = self.acquire_timeout;
+            let message = format!(
+                "fail to acquire a connection from the `{name}` pool within {acquire_timeout:?}"
+            );
+            tracing::warn!(
+                max_connections = self.max_connections,
+                "the `{name}` pool is exhausted"
+            );
+            Error::new(message)
+        } else {
+            let message = format!("fail to acquire a connection from the `{name}` pool");
+            Error::with_source(message, err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PoolConfig;
+    use std::time::Duration;
+    use toml::value::Table;
+
+    #[test]
+    fn it_sizes_connection_pools() {
+        let config = toml::from_str::<Table>(
+            r#"
+            max-connections = 4
+            min-connections = 8
+            acquire-timeout = "500ms"
+            "#,
+        )
+        .unwrap();
+        let pool_config = PoolConfig::with_config(&config);
+        assert_eq!(pool_config.max_connections(), 4);
+        assert_eq!(pool_config.min_connections(), 4);
+        assert_eq!(pool_config.acquire_timeout(), Duration::from_millis(500));
+        assert_eq!(pool_config.idle_timeout(), Duration::from_secs(600));
+        assert!(!pool_config.is_exhausted(4, 1));
+        assert!(!pool_config.is_exhausted(3, 0));
+        assert!(pool_config.is_exhausted(4, 0));
+
+        let err = pool_config.acquire_error("main", sqlx::Error::PoolTimedOut);
+        assert_eq!(
+            err.message(),
+            "fail to acquire a connection from the `main` pool within 500ms"
+        );
+        assert!(err.source().is_none());
+
+        let err = pool_config.acquire_error("main", sqlx::Error::PoolClosed);
+        assert_eq!(
+            err.message(),
+            "fail to acquire a connection from the `main` pool"
+        );
+        assert!(err.source().is_some());
+    }
+}
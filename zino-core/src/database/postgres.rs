@@ -3,9 +3,13 @@ use crate::{
     Map, Record, Uuid,
 };
 use apache_avro::types::Value as AvroValue;
-use chrono::{DateTime, Local, SecondsFormat};
+use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
-use sqlx::{postgres::PgRow, Column as _, Error, Postgres, Row, TypeInfo};
+use sqlx::{
+    postgres::{PgArguments, PgRow},
+    query::Query,
+    Column as _, Error, Postgres, Row, TypeInfo, ValueRef,
+};
 use std::borrow::Cow;
 
 impl<'a> EncodeColumn<'a> for Postgres {
@@ -19,7 +23,7 @@ impl<'a> EncodeColumn<'a> for Postgres {
             "f64" => "double precision",
             "f32" => "real",
             "String" => "text",
-            "DateTime" => "timestamptz",
+            "DateTime" | "Option<DateTime>" => "timestamptz",
             "Uuid" | "Option<Uuid>" => "uuid",
             "Vec<u8>" => "bytea",
             "Vec<String>" => "text[]",
@@ -100,7 +104,7 @@ impl<'a> EncodeColumn<'a> for Postgres {
                 }
             }
             "String" | "Uuid" | "Option<Uuid>" => format_string(value).into(),
-            "DateTime" => match value {
+            "DateTime" | "Option<DateTime>" => match value {
                 "epoch" => "'epoch'".into(),
                 "now" => "now()".into(),
                 "today" => "date_trunc('day', now())".into(),
@@ -154,14 +158,20 @@ impl<'a> EncodeColumn<'a> for Postgres {
                         let condition = format!(r#"array_length("{field}", 1) = {value}"#);
                         conditions.push(condition);
                     } else if operator == "IN" || operator == "NOT IN" {
-                        if let Some(value) = value.as_array() && !value.is_empty() {
-                            let value = value
-                                .iter()
-                                .map(|v| Self::encode_value(column, Some(v)))
-                                .collect::<Vec<_>>()
-                                .join(",");
-                            let condition = format!(r#""{field}" {operator} ({value})"#);
-                            conditions.push(condition);
+                        if let Some(value) = value.as_array() {
+                            if value.is_empty() {
+                                // An empty list is reduced to a constant since `IN ()` is invalid.
+                                let condition = if operator == "IN" { "FALSE" } else { "TRUE" };
+                                conditions.push(condition.to_owned());
+                            } else {
+                                let value = value
+                                    .iter()
+                                    .map(|v| Self::encode_value(column, Some(v)))
+                                    .collect::<Vec<_>>()
+                                    .join(",");
+                                let condition = format!(r#""{field}" {operator} ({value})"#);
+                                conditions.push(condition);
+                            }
                         }
                     } else {
                         let value = Self::encode_value(column, Some(value));
@@ -231,6 +241,23 @@ impl<'a> EncodeColumn<'a> for Postgres {
                     format!(r#""{field}" = {value}"#)
                 }
             }
+            "Option<DateTime>" => {
+                if let Some(value) = value.as_str() {
+                    if value == "null" {
+                        format!(r#""{field}" IS NULL"#)
+                    } else if value == "notnull" {
+                        format!(r#""{field}" IS NOT NULL"#)
+                    } else {
+                        let value = Self::format_value(column, value);
+                        format!(r#""{field}" = {value}"#)
+                    }
+                } else if value.is_null() {
+                    format!(r#""{field}" IS NULL"#)
+                } else {
+                    let value = Self::encode_value(column, Some(value));
+                    format!(r#""{field}" = {value}"#)
+                }
+            }
             "Uuid" | "Option<Uuid>" => {
                 if let Some(value) = value.as_str() {
                     if value == "null" {
@@ -306,6 +333,11 @@ impl DecodeRow<PgRow> for Map {
         let mut map = Map::with_capacity(columns.len());
         for col in columns {
             let key = col.name();
+            if row.try_get_raw(key)?.is_null() {
+                map.insert(key.to_owned(), JsonValue::Null);
+                continue;
+            }
+
             let value = match col.type_info().name() {
                 "BOOL" => row.try_get_unchecked::<bool, _>(key)?.into(),
                 "INT2" => row.try_get_unchecked::<i16, _>(key)?.into(),
@@ -315,10 +347,8 @@ impl DecodeRow<PgRow> for Map {
                 "FLOAT8" => row.try_get_unchecked::<f64, _>(key)?.into(),
                 "TEXT" | "VARCHAR" => row.try_get_unchecked::<String, _>(key)?.into(),
                 "TIMESTAMPTZ" => {
-                    let datetime = row.try_get_unchecked::<DateTime<Local>, _>(key)?;
-                    datetime
-                        .to_rfc3339_opts(SecondsFormat::Micros, false)
-                        .into()
+                    let datetime = row.try_get_unchecked::<DateTime<Utc>, _>(key)?;
+                    crate::datetime::DateTime::from(datetime).to_string().into()
                 }
                 "UUID" => row.try_get_unchecked::<Uuid, _>(key)?.to_string().into(),
                 "BYTEA" => row.try_get_unchecked::<Vec<u8>, _>(key)?.into(),
@@ -348,6 +378,11 @@ impl DecodeRow<PgRow> for Record {
         let mut record = Record::with_capacity(columns.len());
         for col in columns {
             let field = col.name();
+            if row.try_get_raw(field)?.is_null() {
+                record.push((field.to_owned(), AvroValue::Null));
+                continue;
+            }
+
             let value = match col.type_info().name() {
                 "BOOL" => row.try_get_unchecked::<bool, _>(field)?.into(),
                 "INT4" => row.try_get_unchecked::<i32, _>(field)?.into(),
@@ -356,10 +391,8 @@ impl DecodeRow<PgRow> for Record {
                 "FLOAT8" => row.try_get_unchecked::<f64, _>(field)?.into(),
                 "TEXT" | "VARCHAR" => row.try_get_unchecked::<String, _>(field)?.into(),
                 "TIMESTAMPTZ" => {
-                    let datetime = row.try_get_unchecked::<DateTime<Local>, _>(field)?;
-                    datetime
-                        .to_rfc3339_opts(SecondsFormat::Micros, false)
-                        .into()
+                    let datetime = row.try_get_unchecked::<DateTime<Utc>, _>(field)?;
+                    crate::datetime::DateTime::from(datetime).to_string().into()
                 }
                 // deserialize Avro Uuid value wasn't supported in 0.14.0
                 "UUID" => row.try_get_unchecked::<Uuid, _>(field)?.to_string().into(),
@@ -392,6 +425,73 @@ impl DecodeRow<PgRow> for Record {
 
 /// Formats a string.
 #[inline]
-fn format_string(value: &str) -> String {
+pub(super) fn format_string(value: &str) -> String {
     format!("'{}'", value.replace('\'', "''"))
 }
+
+/// Returns `true` if the value can be bound as a parameter for the column.
+/// Null and empty values are excluded since they may be replaced by the column default.
+pub(super) fn is_bindable(column: &Column<'_>, value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::String(value) => {
+            !(value.is_empty() || value == "null" || column.type_name().starts_with("Vec<"))
+        }
+        _ => true,
+    }
+}
+
+/// Creates a query whose prepared statement is not cached by the connection.
+/// It should be used for the SQL with inline values which is unlikely to be executed again,
+/// so that the reusable statements are not evicted from the statement cache.
+#[inline]
+pub(super) fn one_off_query(sql: &str) -> Query<'_, Postgres, PgArguments> {
+    sqlx::query(sql).persistent(false)
+}
+
+/// Binds a json value as the parameter for the column.
+/// Scalar values are bound as text, which should be cast to the column type in the statement.
+pub(super) fn bind_value<'q>(
+    query: Query<'q, Postgres, PgArguments>,
+    column: &Column<'_>,
+    value: &'q JsonValue,
+) -> Query<'q, Postgres, PgArguments> {
+    match value {
+        JsonValue::String(value) => query.bind(value.as_str()),
+        JsonValue::Array(values) => match column.type_name() {
+            "Vec<u8>" => {
+                let bytes = values
+                    .iter()
+                    .filter_map(|v| v.as_u64().and_then(|n| u8::try_from(n).ok()))
+                    .collect::<Vec<_>>();
+                query.bind(bytes)
+            }
+            "Vec<String>" | "Vec<Uuid>" => {
+                let values = values
+                    .iter()
+                    .map(|v| match v {
+                        JsonValue::String(v) => v.to_owned(),
+                        _ => v.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                query.bind(values)
+            }
+            _ => query.bind(value.to_string()),
+        },
+        _ => query.bind(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::one_off_query;
+    use sqlx::Execute;
+
+    #[test]
+    fn it_builds_one_off_queries() {
+        let sql = "DELETE FROM task WHERE id = '0c4d5a8e-7b6f-4d0a-9e1c-2f3b4a5c6d7e';";
+        let query = one_off_query(sql);
+        assert_eq!(query.sql(), sql);
+        assert!(!query.persistent());
+    }
+}
@@ -1,85 +1,166 @@
-use super::Schema;
+use super::{
+    postgres::{self, format_string},
+    Schema,
+};
 use crate::{
-    model::{EncodeColumn, Query},
+    error::Error,
+    model::{Column, EncodeColumn, Query},
     request::Validation,
     Map,
 };
 use serde_json::Value;
-use sqlx::Postgres;
+use sqlx::{
+    database::HasArguments, postgres::PgArguments, query::Query as SqlxQuery, types::Json,
+    Database, Postgres,
+};
+
+/// A param of the filters to be bound. The column is known for a value in the `IN` list,
+/// while the type of a param of raw filters is inferred from the JSON value.
+pub(super) type FilterParam<'a> = (Option<&'a Column<'static>>, &'a Value);
 
 /// Extension trait for [`Query`](crate::model::Query).
 pub(super) trait QueryExt<DB> {
-    /// Formats projection fields.
-    fn format_fields(&self) -> String;
+    /// Formats projection fields, returning an error if the sort columns
+    /// are not selected in a `DISTINCT` query.
+    fn format_fields(&self) -> Result<String, Error>;
 
     /// Formats the query filters to generate SQL `WHERE` expression.
     fn format_filters<M: Schema>(&self) -> String;
 
+    /// Formats the query filters, and collects the params in the order of the placeholders.
+    fn format_conditions<'a, M: Schema>(&'a self, params: &mut Vec<FilterParam<'a>>) -> String;
+
     /// Formats the query sort to generate SQL `ORDER BY` expression.
     fn format_sort(&self) -> String;
 
     /// Formats the query pagination to generate SQL `LIMIT` expression.
     fn format_pagination(&self) -> String;
 
+    /// Formats the union queries to generate SQL `UNION` expressions, returning an error
+    /// if the projection of a union query does not match in count and column types.
+    fn format_unions<M: Schema>(&self, table_name: &str) -> Result<String, Error>;
+
     // Formats the selection with a logic operator.
-    fn format_selection<M: Schema>(selection: &Map, operator: &str) -> String;
+    fn format_selection<'a, M: Schema>(
+        selection: &'a Map,
+        operator: &str,
+        params: &mut Vec<FilterParam<'a>>,
+    ) -> String;
 
     /// Parses text search filter.
     fn parse_text_search(filter: &Map) -> Option<String>;
+
+    /// Prepares the SQL statement and binds the params of the filters in order.
+    fn bind_params<'a, M: Schema>(
+        &'a self,
+        sql: &'a str,
+    ) -> SqlxQuery<'a, DB, <DB as HasArguments<'a>>::Arguments>
+    where
+        DB: Database;
+
+    /// Prepares the SQL statement and binds the params of the filters
+    /// followed by those of the union queries in order.
+    fn bind_union_params<'a, M: Schema>(
+        &'a self,
+        sql: &'a str,
+    ) -> SqlxQuery<'a, DB, <DB as HasArguments<'a>>::Arguments>
+    where
+        DB: Database;
 }
 
 impl QueryExt<Postgres> for Query {
-    fn format_fields(&self) -> String {
+    fn format_fields(&self) -> Result<String, Error> {
         let fields = self.fields();
         if fields.is_empty() {
-            "*".to_owned()
-        } else {
-            fields
+            let projection = if self.is_distinct() {
+                "DISTINCT *"
+            } else {
+                "*"
+            };
+            return Ok(projection.to_owned());
+        }
+
+        let projection = fields
+            .iter()
+            .map(|field| {
+                if let Some((expr, alias)) = field.rsplit_once("=>") {
+                    format!(r#"{expr} AS "{alias}""#)
+                } else {
+                    format!(r#""{field}""#)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.is_distinct() {
+            // Expressions in `ORDER BY` must appear in the select list for `SELECT DISTINCT`.
+            let projected_columns = fields
                 .iter()
                 .map(|field| {
-                    if let Some((expr, alias)) = field.rsplit_once("=>") {
-                        format!(r#"{expr} AS "{alias}""#)
-                    } else {
-                        format!(r#""{field}""#)
-                    }
+                    field
+                        .rsplit_once("=>")
+                        .map_or(field.as_str(), |(_, alias)| alias)
                 })
-                .collect::<Vec<_>>()
-                .join(", ")
+                .collect::<Vec<_>>();
+            for column in sort_columns(self) {
+                if !projected_columns.contains(&column.as_str()) {
+                    return Err(Error::new(format!(
+                        "the sort column `{column}` should be selected in a `DISTINCT` query"
+                    )));
+                }
+            }
+            Ok(format!("DISTINCT {projection}"))
+        } else {
+            Ok(projection)
         }
     }
 
+    #[inline]
     fn format_filters<M: Schema>(&self) -> String {
+        self.format_conditions::<M>(&mut Vec::new())
+    }
+
+    fn format_conditions<'a, M: Schema>(&'a self, params: &mut Vec<FilterParam<'a>>) -> String {
         let filters = self.filters();
+        let offset = params.len();
+        params.extend(raw_params(self).into_iter().map(|param| (None, param)));
+        let soft_delete_condition = M::SOFT_DELETE_COLUMN
+            .filter(|&column| !self.includes_trashed() && !filters.contains_key(column))
+            .map(|column| format!(r#""{column}" IS NULL"#));
         if filters.is_empty() {
-            return String::new();
+            return soft_delete_condition
+                .map(|condition| format!(" WHERE {condition}"))
+                .unwrap_or_default();
         }
 
         let (sort_by, ascending) = self.sort_order();
         let mut expression = " ".to_owned();
-        let mut conditions = Vec::with_capacity(filters.len());
+        let mut conditions = Vec::with_capacity(filters.len() + 1);
+        if let Some(condition) = soft_delete_condition {
+            conditions.push(condition);
+        }
         for (key, value) in filters {
             match key.as_str() {
                 "$and" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " AND ");
+                        let condition = Self::format_selection::<M>(selection, " AND ", params);
                         conditions.push(condition);
                     }
                 }
                 "$not" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " AND ");
+                        let condition = Self::format_selection::<M>(selection, " AND ", params);
                         conditions.push(format!("NOT {condition}"));
                     }
                 }
                 "$nor" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " OR ");
+                        let condition = Self::format_selection::<M>(selection, " OR ", params);
                         conditions.push(format!("NOT {condition}"));
                     }
                 }
                 "$or" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " OR ");
+                        let condition = Self::format_selection::<M>(selection, " OR ", params);
                         conditions.push(condition);
                     }
                 }
@@ -96,6 +177,48 @@ impl QueryExt<Postgres> for Query {
                         }
                     }
                 }
+                "$cursor" => {
+                    if let Some(cursor) = value.as_object() {
+                        if let Some(condition) = format_cursor::<M>(cursor) {
+                            conditions.push(condition);
+                        }
+                    }
+                }
+                "$json" => {
+                    if let Some(filter) = value.as_object() {
+                        for (path, value) in filter {
+                            if let Some(condition) = format_json_filter::<M>(path, value) {
+                                conditions.push(condition);
+                            }
+                        }
+                    }
+                }
+                "$contains" => {
+                    if let Some(filter) = value.as_object() {
+                        for (key, value) in filter {
+                            if let Some(condition) = format_containment::<M>(key, value) {
+                                conditions.push(condition);
+                            }
+                        }
+                    }
+                }
+                "$raw" => {
+                    if let Some(filters) = value.as_array() {
+                        conditions.extend(format_raw_filters(filters, offset));
+                    }
+                }
+                "$like" | "$ilike" => {
+                    if let Some(filter) = value.as_object() {
+                        let operator = if key == "$like" { "LIKE" } else { "ILIKE" };
+                        for (key, value) in filter {
+                            if let Some(pattern) = value.as_str() && M::get_column(key).is_some() {
+                                let pattern = format_string(pattern);
+                                let condition = format!(r#""{key}" {operator} {pattern}"#);
+                                conditions.push(condition);
+                            }
+                        }
+                    }
+                }
                 _ => {
                     if let Some(col) = M::get_column(key) {
                         let condition = if key == sort_by {
@@ -104,7 +227,7 @@ impl QueryExt<Postgres> for Query {
                             let value = Postgres::encode_value(col, Some(value));
                             format!(r#""{key}" {operator} {value}"#)
                         } else {
-                            Postgres::format_filter(col, key, value)
+                            format_column_filter(col, key, value, params)
                         };
                         conditions.push(condition);
                     }
@@ -117,11 +240,22 @@ impl QueryExt<Postgres> for Query {
         if let Some(group) = filters.get("$group") {
             let groups = Validation::parse_string_array(group)
                 .unwrap_or_default()
+                .into_iter()
+                .map(|field| {
+                    if is_plain_identifier(field) {
+                        format!(r#""{field}""#)
+                    } else {
+                        field.to_owned()
+                    }
+                })
+                .collect::<Vec<_>>()
                 .join(", ");
-            expression += &format!("GROUP BY {groups}");
+            expression += &format!(" GROUP BY {groups}");
             if let Some(Value::Object(selection)) = filters.get("$match") {
-                let condition = Self::format_selection::<M>(selection, " AND ");
-                expression += &format!("HAVING {condition}");
+                let condition = format_having::<M>(selection, params);
+                if !condition.is_empty() {
+                    expression += &format!(" HAVING {condition}");
+                }
             }
         }
         expression
@@ -130,6 +264,23 @@ impl QueryExt<Postgres> for Query {
     fn format_sort(&self) -> String {
         let (sort_by, ascending) = self.sort_order();
         if sort_by.is_empty() {
+            if let Some(Value::Object(cursor)) = self.filters().get("$cursor") {
+                let columns =
+                    Validation::parse_string_array(cursor.get("$columns")).unwrap_or_default();
+                if !columns.is_empty() {
+                    let sort_order = if cursor.get("$after") == Some(&Value::Bool(false)) {
+                        "DESC"
+                    } else {
+                        "ASC"
+                    };
+                    let sort_by = columns
+                        .iter()
+                        .map(|column| format!(r#""{column}" {sort_order}"#))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    return format!("ORDER BY {sort_by}");
+                }
+            }
             String::new()
         } else {
             let sort_order = if ascending { "ASC" } else { "DESC" };
@@ -144,38 +295,81 @@ impl QueryExt<Postgres> for Query {
 
     fn format_pagination(&self) -> String {
         let (sort_by, _) = self.sort_order();
-        if self.filters().contains_key(sort_by) {
+        let filters = self.filters();
+        if filters.contains_key(sort_by) || filters.contains_key("$cursor") {
             format!("LIMIT {}", self.limit())
         } else {
             format!("LIMIT {} OFFSET {}", self.limit(), self.offset())
         }
     }
 
-    fn format_selection<M: Schema>(selection: &Map, operator: &str) -> String {
+    fn format_unions<M: Schema>(&self, table_name: &str) -> Result<String, Error> {
+        let columns = projected_columns::<M>(self);
+        let mut expression = String::new();
+        let mut params = Vec::new();
+        self.format_conditions::<M>(&mut params);
+        for (query, all) in self.unions() {
+            if query.filters().contains_key("$raw") {
+                return Err(Error::new("raw filters are not supported in a union query"));
+            }
+
+            let union_columns = projected_columns::<M>(query);
+            if union_columns.len() != columns.len() {
+                return Err(Error::new(format!(
+                    "the union query selects {} columns while it is expected to select {}",
+                    union_columns.len(),
+                    columns.len()
+                )));
+            }
+            for (column, union_column) in columns.iter().zip(union_columns.iter()) {
+                if let Some(column_type) = column_type::<M>(column) &&
+                    let Some(union_column_type) = column_type::<M>(union_column) &&
+                    column_type != union_column_type
+                {
+                    return Err(Error::new(format!(
+                        "the union column `{union_column}` of the type `{union_column_type}` \
+                            does not match the column `{column}` of the type `{column_type}`"
+                    )));
+                }
+            }
+
+            let operator = if *all { "UNION ALL" } else { "UNION" };
+            let projection = query.format_fields()?;
+            let filters = query.format_conditions::<M>(&mut params);
+            expression += &format!(" {operator} SELECT {projection} FROM {table_name} {filters}");
+        }
+        Ok(expression)
+    }
+
+    fn format_selection<'a, M: Schema>(
+        selection: &'a Map,
+        operator: &str,
+        params: &mut Vec<FilterParam<'a>>,
+    ) -> String {
         let mut conditions = Vec::with_capacity(selection.len());
         for (key, value) in selection {
             match key.as_str() {
                 "$and" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " AND ");
+                        let condition = Self::format_selection::<M>(selection, " AND ", params);
                         conditions.push(condition);
                     }
                 }
                 "$not" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " AND ");
+                        let condition = Self::format_selection::<M>(selection, " AND ", params);
                         conditions.push(format!("(NOT {condition})"));
                     }
                 }
                 "$nor" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " OR ");
+                        let condition = Self::format_selection::<M>(selection, " OR ", params);
                         conditions.push(format!("(NOT {condition})"));
                     }
                 }
                 "$or" => {
                     if let Some(selection) = value.as_object() {
-                        let condition = Self::format_selection::<M>(selection, " OR ");
+                        let condition = Self::format_selection::<M>(selection, " OR ", params);
                         conditions.push(condition);
                     }
                 }
@@ -188,7 +382,7 @@ impl QueryExt<Postgres> for Query {
                 }
                 _ => {
                     if let Some(col) = M::get_column(key) {
-                        let condition = Postgres::format_filter(col, key, value);
+                        let condition = format_column_filter(col, key, value, params);
                         conditions.push(condition);
                     }
                 }
@@ -204,10 +398,705 @@ impl QueryExt<Postgres> for Query {
     fn parse_text_search(filter: &Map) -> Option<String> {
         let fields = Validation::parse_string_array(filter.get("$fields"))?;
         Validation::parse_string(filter.get("$search")).map(|search| {
-            let text = fields.join(" || ' ' || ");
+            let text = fields
+                .iter()
+                .map(|field| {
+                    if is_plain_identifier(field) {
+                        format!(r#"coalesce("{field}", '')"#)
+                    } else {
+                        field.to_owned()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" || ' ' || ");
             let lang = Validation::parse_string(filter.get("$language"))
-                .unwrap_or_else(|| "english".into());
-            format!("to_tsvector('{lang}', {text}) @@ websearch_to_tsquery('{lang}', '{search}')")
+                .map(|lang| format_string(&lang))
+                .unwrap_or_else(|| "'english'".to_owned());
+            let search = format_string(&search);
+            format!("to_tsvector({lang}, {text}) @@ websearch_to_tsquery({lang}, {search})")
+        })
+    }
+
+    fn bind_params<'a, M: Schema>(&'a self, sql: &'a str) -> SqlxQuery<'a, Postgres, PgArguments> {
+        let mut params = Vec::new();
+        self.format_conditions::<M>(&mut params);
+        bind_filter_params(sql, params)
+    }
+
+    fn bind_union_params<'a, M: Schema>(
+        &'a self,
+        sql: &'a str,
+    ) -> SqlxQuery<'a, Postgres, PgArguments> {
+        let mut params = Vec::new();
+        self.format_conditions::<M>(&mut params);
+        for (query, _) in self.unions() {
+            query.format_conditions::<M>(&mut params);
+        }
+        bind_filter_params(sql, params)
+    }
+}
+
+/// Prepares the SQL statement and binds the params in order.
+fn bind_filter_params<'a>(
+    sql: &'a str,
+    params: Vec<FilterParam<'a>>,
+) -> SqlxQuery<'a, Postgres, PgArguments> {
+    let mut query = sqlx::query(sql);
+    for (col, param) in params {
+        query = if let Some(col) = col {
+            postgres::bind_value(query, col, param)
+        } else {
+            match param {
+                Value::Null => query.bind(Option::<String>::None),
+                Value::Bool(b) => query.bind(*b),
+                Value::Number(n) => {
+                    if let Some(n) = n.as_i64() {
+                        query.bind(n)
+                    } else {
+                        query.bind(n.as_f64())
+                    }
+                }
+                Value::String(s) => query.bind(s.as_str()),
+                _ => query.bind(Json(param)),
+            }
+        };
+    }
+    query
+}
+
+/// Returns the params of raw filters in the order of the placeholders.
+fn raw_params(query: &Query) -> Vec<&Value> {
+    let mut params = Vec::new();
+    if let Some(Value::Array(filters)) = query.filters().get("$raw") {
+        for filter in filters {
+            if let Some(Value::Array(values)) = filter.get("$params") {
+                params.extend(values);
+            }
+        }
+    }
+    params
+}
+
+/// Formats the raw filters and renumbers the placeholders in the fragments
+/// after the number of the preceding params.
+fn format_raw_filters(filters: &[Value], mut offset: usize) -> Vec<String> {
+    let mut conditions = Vec::with_capacity(filters.len());
+    for filter in filters {
+        if let Some(fragment) = filter.get("$fragment").and_then(|v| v.as_str()) {
+            let mut condition = String::with_capacity(fragment.len() + 2);
+            let mut chars = fragment.chars().peekable();
+            condition.push('(');
+            while let Some(ch) = chars.next() {
+                condition.push(ch);
+                if ch == '$' && chars.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                    let mut index = 0;
+                    while let Some(digit) = chars.peek().and_then(|ch| ch.to_digit(10)) {
+                        index = index * 10 + digit as usize;
+                        chars.next();
+                    }
+                    condition += &(index + offset).to_string();
+                }
+            }
+            condition.push(')');
+            conditions.push(condition);
+        }
+        if let Some(Value::Array(params)) = filter.get("$params") {
+            offset += params.len();
+        }
+    }
+    conditions
+}
+
+/// Returns the projected columns of the query, where `*` is expanded to the model fields.
+fn projected_columns<M: Schema>(query: &Query) -> Vec<&str> {
+    let fields = query.fields();
+    if fields.is_empty() {
+        M::fields().to_vec()
+    } else {
+        fields.iter().map(|field| field.as_str()).collect()
+    }
+}
+
+/// Returns the columns in the `ORDER BY` expression of the query.
+fn sort_columns(query: &Query) -> Vec<String> {
+    let (sort_by, _) = query.sort_order();
+    if !sort_by.is_empty() {
+        vec![sort_by.to_owned()]
+    } else if let Some(Value::Object(cursor)) = query.filters().get("$cursor") {
+        Validation::parse_string_array(cursor.get("$columns")).unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Returns the type name of the column ignoring the nullability,
+/// or `None` if the type of the projection field is unknown.
+fn column_type<M: Schema>(field: &str) -> Option<&'static str> {
+    let type_name = M::get_column(field)?.type_name();
+    Some(
+        type_name
+            .strip_prefix("Option<")
+            .and_then(|s| s.strip_suffix('>'))
+            .unwrap_or(type_name),
+    )
+}
+
+/// Returns `true` if the field is a plain identifier which can be quoted.
+fn is_plain_identifier(field: &str) -> bool {
+    !field.is_empty()
+        && field
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+/// Formats the cursor for the keyset pagination.
+fn format_cursor<M: Schema>(cursor: &Map) -> Option<String> {
+    let columns = Validation::parse_string_array(cursor.get("$columns"))?;
+    let values = cursor.get("$values")?.as_array()?;
+    if columns.is_empty() || columns.len() != values.len() {
+        return None;
+    }
+
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut encoded_values = Vec::with_capacity(values.len());
+    for (column, value) in columns.into_iter().zip(values) {
+        let col = M::get_column(column)?;
+        fields.push(format!(r#""{column}""#));
+        encoded_values.push(Postgres::encode_value(col, Some(value)));
+    }
+
+    let operator = if cursor.get("$after") == Some(&Value::Bool(false)) {
+        "<"
+    } else {
+        ">"
+    };
+    if fields.len() == 1 {
+        Some(format!("{} {operator} {}", fields[0], encoded_values[0]))
+    } else {
+        let fields = fields.join(", ");
+        let values = encoded_values.join(", ");
+        Some(format!("({fields}) {operator} ({values})"))
+    }
+}
+
+/// Formats the filter for the column, where the values of `$in` and `$nin` lists
+/// are bound as params instead of being encoded in the SQL.
+fn format_column_filter<'a>(
+    col: &'a Column<'static>,
+    field: &str,
+    value: &'a Value,
+    params: &mut Vec<FilterParam<'a>>,
+) -> String {
+    let filter = match value.as_object() {
+        Some(filter) if col.type_name() != "Map" => filter,
+        _ => return Postgres::format_filter(col, field, value),
+    };
+
+    let mut conditions = Vec::with_capacity(filter.len());
+    let mut other_filter = Map::new();
+    for (name, value) in filter {
+        match (name.as_str(), value.as_array()) {
+            ("$in", Some(values)) => {
+                conditions.push(format_in_list(col, field, "IN", values, params));
+            }
+            ("$nin", Some(values)) => {
+                conditions.push(format_in_list(col, field, "NOT IN", values, params));
+            }
+            _ => {
+                other_filter.insert(name.to_owned(), value.to_owned());
+            }
+        }
+    }
+    if conditions.is_empty() {
+        return Postgres::format_filter(col, field, value);
+    }
+    if !other_filter.is_empty() {
+        let condition = Postgres::format_filter(col, field, &Value::Object(other_filter));
+        conditions.push(condition);
+    }
+    format!("({})", conditions.join(" AND "))
+}
+
+/// Formats the `IN` or `NOT IN` list with placeholders for the bindable values.
+/// An empty list is reduced to a constant since `IN ()` is invalid.
+fn format_in_list<'a>(
+    col: &'a Column<'static>,
+    field: &str,
+    operator: &str,
+    values: &'a [Value],
+    params: &mut Vec<FilterParam<'a>>,
+) -> String {
+    if values.is_empty() {
+        let condition = if operator == "IN" { "FALSE" } else { "TRUE" };
+        return condition.to_owned();
+    }
+
+    let column_type = Postgres::column_type(col);
+    let values = values
+        .iter()
+        .map(|value| {
+            if postgres::is_bindable(col, value) {
+                params.push((Some(col), value));
+                format!("${}::{column_type}", params.len())
+            } else {
+                Postgres::encode_value(col, Some(value)).into_owned()
+            }
         })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(r#""{field}" {operator} ({values})"#)
+}
+
+/// Formats the filter for the nested field of a JSON column.
+fn format_json_filter<M: Schema>(path: &str, value: &Value) -> Option<String> {
+    let mut segments = path.split('.');
+    let field = segments.next()?;
+    if M::get_column(field)?.type_name() != "Map" {
+        return None;
+    }
+
+    let mut expr = format!(r#""{field}""#);
+    for segment in segments {
+        if segment.parse::<usize>().is_ok() {
+            expr += &format!("->{segment}");
+        } else {
+            expr += &format!("->{}", format_string(segment));
+        }
+    }
+
+    let value = format_string(&value.to_string());
+    Some(format!("{expr} = {value}::jsonb"))
+}
+
+/// Formats the containment filter for a JSON column or an array column.
+fn format_containment<M: Schema>(field: &str, value: &Value) -> Option<String> {
+    let col = M::get_column(field)?;
+    let value = if col.type_name() == "Map" {
+        let value = format_string(&value.to_string());
+        format!("{value}::jsonb")
+    } else if col.type_name().starts_with("Vec<") {
+        let values = match value {
+            Value::Array(_) => value.to_owned(),
+            _ => Value::Array(vec![value.to_owned()]),
+        };
+        Postgres::encode_value(col, Some(&values)).into_owned()
+    } else {
+        return None;
+    };
+    Some(format!(r#""{field}" @> {value}"#))
+}
+
+/// Formats the conditions of the `HAVING` clause.
+/// Aggregate expressions such as `count(*)` are compared with numeric values.
+/// The keys which are neither columns nor aggregate expressions are ignored by the selection.
+fn format_having<'a, M: Schema>(selection: &'a Map, params: &mut Vec<FilterParam<'a>>) -> String {
+    let mut conditions = Vec::new();
+    for (key, value) in selection {
+        if key.ends_with(')') {
+            if let Some(condition) = format_aggregate_filter(key, value) {
+                conditions.push(condition);
+            }
+        }
+    }
+
+    let condition =
+        <Query as QueryExt<Postgres>>::format_selection::<M>(selection, " AND ", params);
+    if !condition.is_empty() {
+        conditions.push(condition);
+    }
+    conditions.join(" AND ")
+}
+
+/// Aggregate functions supported in the `HAVING` clause.
+const AGGREGATE_FUNCTIONS: [&str; 5] = ["avg", "count", "max", "min", "sum"];
+
+/// Formats the aggregate expression such as `count(*)` or `max(priority)`. It returns `None`
+/// if the function is not supported or the argument is not a plain identifier.
+fn format_aggregate(expr: &str) -> Option<String> {
+    let (function, arg) = expr.strip_suffix(')')?.split_once('(')?;
+    let function = function.trim().to_ascii_lowercase();
+    if !AGGREGATE_FUNCTIONS.contains(&function.as_str()) {
+        return None;
+    }
+
+    let arg = arg.trim();
+    if arg == "*" && function == "count" {
+        Some("count(*)".to_owned())
+    } else if is_plain_identifier(arg) {
+        Some(format!(r#"{function}("{arg}")"#))
+    } else {
+        None
+    }
+}
+
+/// Formats the filter for an aggregate expression.
+fn format_aggregate_filter(expr: &str, value: &Value) -> Option<String> {
+    let expr = format_aggregate(expr)?;
+    if let Value::Number(value) = value {
+        return Some(format!("{expr} = {value}"));
+    }
+
+    let filter = value.as_object()?;
+    let mut conditions = Vec::with_capacity(filter.len());
+    for (name, value) in filter {
+        let operator = match name.as_str() {
+            "$eq" => "=",
+            "$ne" => "<>",
+            "$lt" => "<",
+            "$lte" => "<=",
+            "$gt" => ">",
+            "$gte" => ">=",
+            _ => continue,
+        };
+        if let Value::Number(value) = value {
+            conditions.push(format!("{expr} {operator} {value}"));
+        }
+    }
+    (!conditions.is_empty()).then(|| conditions.join(" AND "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{raw_params, QueryExt};
+    use crate::{
+        database::fixtures::{Note, Task},
+        datetime::DateTime,
+        extend::JsonObjectExt,
+        model::{LikeMode, Query},
+        Map,
+    };
+    use serde_json::Value;
+    use sqlx::{Execute, Postgres};
+
+    fn format_filters(query: &Query) -> String {
+        <Query as QueryExt<Postgres>>::format_filters::<Task>(query)
+    }
+
+    fn filter_params(query: &Query) -> Vec<&Value> {
+        let mut params = Vec::new();
+        <Query as QueryExt<Postgres>>::format_conditions::<Task>(query, &mut params);
+        params.into_iter().map(|(_, param)| param).collect()
+    }
+
+    #[test]
+    fn it_formats_text_search() {
+        let mut query = Query::default();
+        query.search(&["name", "bio"], "rust' OR 1=1 --");
+
+        let filter = query.filters().get("$text").and_then(|v| v.as_object());
+        let condition =
+            <Query as QueryExt<Postgres>>::parse_text_search(filter.unwrap_or(&Map::new()))
+                .unwrap_or_default();
+        assert!(condition.starts_with(
+            r#"to_tsvector('english', coalesce("name", '') || ' ' || coalesce("bio", ''))"#
+        ));
+        assert!(condition.ends_with("websearch_to_tsquery('english', 'rust'' OR 1=1 --')"));
+    }
+
+    #[test]
+    fn it_formats_group_by() {
+        let mut query = Query::default();
+        query.add_filter("status", "active");
+        query.group_by(&["roles"]);
+        query.having(Map::from_entry("count(*)", Map::from_entry("$gt", 1)));
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE "status" = 'active' GROUP BY "roles" HAVING count(*) > 1"#
+        );
+
+        let mut query = Query::default();
+        query.group_by(&["status", "priority"]);
+        let mut having = Map::new();
+        having.upsert("count(*)", Map::from_entry("$gte", 2));
+        having.upsert("priority", "<3");
+        query.having(having);
+        assert_eq!(
+            format_filters(&query),
+            r#"  GROUP BY "status", "priority" HAVING count(*) >= 2 AND ("priority" < 3)"#
+        );
+
+        let mut query = Query::default();
+        query.group_by(&["status"]);
+        let mut having = Map::new();
+        having.upsert("MAX(priority)", 3);
+        having.upsert("count(*) > 0 OR pg_sleep(10)", 1);
+        having.upsert("sum(priority) OR true)", 1);
+        having.upsert("avg(*)", 1);
+        query.having(having);
+        assert_eq!(
+            format_filters(&query),
+            r#"  GROUP BY "status" HAVING max("priority") = 3"#
+        );
+    }
+
+    #[test]
+    fn it_formats_cursor_pagination() {
+        let mut query = Query::default();
+        query.cursor_after("priority", 5);
+        assert_eq!(format_filters(&query), r#" WHERE "priority" > 5"#);
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_sort(&query),
+            r#"ORDER BY "priority" ASC"#
+        );
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_pagination(&query),
+            "LIMIT 10"
+        );
+
+        let mut query = Query::default();
+        query.set_cursor(&["priority", "name"], vec![5.into(), "alice".into()], false);
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE ("priority", "name") < (5, 'alice')"#
+        );
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_sort(&query),
+            r#"ORDER BY "priority" DESC, "name" DESC"#
+        );
+
+        let page = vec![
+            Map::from_entry("priority", 3),
+            Map::from_entry("priority", 4),
+        ];
+        let cursor = Query::next_cursor(&["priority"], &page);
+        assert_eq!(cursor, Some(vec![4.into()]));
+    }
+
+    #[test]
+    fn it_formats_in_filters() {
+        let mut query = Query::default();
+        query.add_filter_in("status", &[]);
+        assert_eq!(format_filters(&query), " WHERE (FALSE)");
+        assert!(filter_params(&query).is_empty());
+
+        let mut query = Query::default();
+        query.add_filter_not_in("status", &[]);
+        assert_eq!(format_filters(&query), " WHERE (TRUE)");
+        assert!(filter_params(&query).is_empty());
+
+        let mut query = Query::default();
+        query.add_filter_in("status", &["active'; --".into()]);
+        assert_eq!(format_filters(&query), r#" WHERE ("status" IN ($1::text))"#);
+        assert_eq!(filter_params(&query), [&Value::from("active'; --")]);
+
+        let mut query = Query::default();
+        query.add_filter_in("status", &["active".into(), "pending".into()]);
+        query.add_filter_not_in("status", &["pending".into()]);
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE ("status" IN ($1::text, $2::text) AND "status" NOT IN ($3::text))"#
+        );
+        assert_eq!(
+            filter_params(&query),
+            [
+                &Value::from("active"),
+                &Value::from("pending"),
+                &Value::from("pending")
+            ]
+        );
+    }
+
+    #[test]
+    fn it_numbers_in_filters_after_raw_filters() {
+        let mut query = Query::default();
+        query.add_filter_in("priority", &[1.into(), 2.into()]);
+        query.add_raw_filter("\"name\" = $1", &["alice".into()]);
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE ("name" = $1) AND ("priority" IN ($2::int, $3::int))"#
+        );
+        assert_eq!(
+            filter_params(&query),
+            [&Value::from("alice"), &Value::from(1), &Value::from(2)]
+        );
+
+        let mut query = Query::default();
+        query.add_filter_in("status", &["active".into()]);
+        let mut union_query = Query::default();
+        union_query.add_filter_in("status", &["pending".into()]);
+        query.union(union_query);
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_unions::<Task>(&query, "task").unwrap(),
+            r#" UNION SELECT * FROM task  WHERE ("status" IN ($2::text))"#
+        );
+    }
+
+    #[test]
+    fn it_formats_union_queries() {
+        let mut query = Query::default();
+        query.allow_fields(&["id", "name"]);
+        query.add_filter("status", "active");
+        query.set_sort_order("name".to_owned(), true);
+
+        let mut union_query = Query::default();
+        union_query.allow_fields(&["id", "name"]);
+        union_query.add_filter("priority", ">3");
+        query.union_all(union_query);
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_unions::<Task>(&query, "task").unwrap(),
+            r#" UNION ALL SELECT "id", "name" FROM task  WHERE "priority" > 3"#
+        );
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_sort(&query),
+            "ORDER BY name ASC NULLS LAST"
+        );
+
+        let mut union_query = Query::default();
+        union_query.allow_fields(&["id"]);
+        query.union(union_query);
+        assert!(<Query as QueryExt<Postgres>>::format_unions::<Task>(&query, "task").is_err());
+
+        let mut query = Query::default();
+        query.allow_fields(&["id", "priority"]);
+        let mut union_query = Query::default();
+        union_query.allow_fields(&["id", "name"]);
+        query.union(union_query);
+        let err = <Query as QueryExt<Postgres>>::format_unions::<Task>(&query, "task").unwrap_err();
+        assert!(err.message().contains("`name` of the type `String`"));
+    }
+
+    #[test]
+    fn it_formats_range_filters() {
+        let start = "2023-04-01T00:00:00+08:00".parse::<DateTime>().unwrap();
+        let end = "2023-04-30T23:59:59+08:00".parse::<DateTime>().unwrap();
+        let mut query = Query::default();
+        query.add_filter_between("created_at", start, end);
+        assert_eq!(
+            format_filters(&query),
+            format!(r#" WHERE ("created_at" >= '{start}' AND "created_at" <= '{end}')"#)
+        );
+
+        let mut query = Query::default();
+        query.add_filter_gte("priority", 1);
+        query.add_filter_lt("priority", 5);
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE ("priority" >= 1 AND "priority" < 5)"#
+        );
+    }
+
+    #[test]
+    fn it_formats_json_filters() {
+        let mut query = Query::default();
+        query.add_json_filter("profile.address.country", "US");
+        query.add_json_filter("profile.tags.0", "rust'; --");
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE "profile"->'address'->'country' = '"US"'::jsonb AND "#.to_owned()
+                + r#""profile"->'tags'->0 = '"rust''; --"'::jsonb"#
+        );
+
+        let mut query = Query::default();
+        query.add_filter_contains("roles", "admin");
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE "roles" @> ARRAY['admin']::text[]"#
+        );
+
+        let mut query = Query::default();
+        query.add_filter_contains("profile", Map::from_entry("verified", true));
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE "profile" @> '{"verified":true}'::jsonb"#
+        );
+    }
+
+    #[test]
+    fn it_formats_distinct_fields() {
+        let mut query = Query::default();
+        query.distinct(&["roles"]);
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_fields(&query).unwrap(),
+            r#"DISTINCT "roles""#
+        );
+
+        query.distinct(&["status", "priority"]);
+        query.set_sort_order("priority".to_owned(), false);
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_fields(&query).unwrap(),
+            r#"DISTINCT "status", "priority""#
+        );
+
+        query.distinct(&["status", "max(priority)=>max_priority"]);
+        query.set_sort_order("max_priority".to_owned(), false);
+        assert_eq!(
+            <Query as QueryExt<Postgres>>::format_fields(&query).unwrap(),
+            r#"DISTINCT "status", max(priority) AS "max_priority""#
+        );
+
+        query.set_sort_order("created_at".to_owned(), false);
+        let err = <Query as QueryExt<Postgres>>::format_fields(&query).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "the sort column `created_at` should be selected in a `DISTINCT` query"
+        );
+    }
+
+    #[test]
+    fn it_formats_like_filters() {
+        let mut query = Query::default();
+        query.add_filter_like("name", "50%_off", LikeMode::StartsWith);
+        assert_eq!(format_filters(&query), r#" WHERE "name" LIKE '50\%\_off%'"#);
+
+        let mut query = Query::default();
+        query.add_filter_ilike("name", "o'neil%", LikeMode::Contains);
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE "name" ILIKE '%o''neil\%%'"#
+        );
+    }
+
+    #[test]
+    fn it_formats_raw_filters() {
+        let mut query = Query::default();
+        let name = "x' OR '1' = '1";
+        query.add_raw_filter(
+            "lower(\"name\") = lower($1) OR $2 = ANY(\"roles\")",
+            &[name.into(), "admin".into()],
+        );
+        query.add_raw_filter("\"priority\" > $1", &[3.into()]);
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE (lower("name") = lower($1) OR $2 = ANY("roles")) AND ("priority" > $3)"#
+        );
+        assert_eq!(
+            raw_params(&query),
+            [&Value::from(name), &Value::from("admin"), &Value::from(3)]
+        );
+    }
+
+    #[test]
+    fn it_caches_parameterized_statements() {
+        let mut query = Query::default();
+        query.add_raw_filter("\"priority\" > $1", &[3.into()]);
+        let sql = format!("SELECT * FROM task {};", format_filters(&query));
+        for _ in 0..3 {
+            let statement = <Query as QueryExt<Postgres>>::bind_params::<Task>(&query, &sql);
+            assert_eq!(statement.sql(), sql);
+            assert!(statement.persistent());
+        }
+    }
+
+    #[test]
+    fn it_excludes_soft_deleted_rows() {
+        let format_filters = <Query as QueryExt<Postgres>>::format_filters::<Note>;
+        let mut query = Query::default();
+        assert_eq!(format_filters(&query), r#" WHERE "deleted_at" IS NULL"#);
+
+        query.add_filter("id", "0c4d5a8e-7b6f-4d0a-9e1c-2f3b4a5c6d7e");
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE "deleted_at" IS NULL AND "id" = '0c4d5a8e-7b6f-4d0a-9e1c-2f3b4a5c6d7e'"#
+        );
+
+        query.with_trashed();
+        assert_eq!(
+            format_filters(&query),
+            r#" WHERE "id" = '0c4d5a8e-7b6f-4d0a-9e1c-2f3b4a5c6d7e'"#
+        );
+
+        let mut query = Query::default();
+        query.add_filter("deleted_at", "notnull");
+        assert_eq!(format_filters(&query), r#" WHERE "deleted_at" IS NOT NULL"#);
     }
 }
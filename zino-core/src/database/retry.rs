@@ -0,0 +1,251 @@
+use crate::{extend::TomlTableExt, state::State};
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use rand::Rng;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        LazyLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use toml::value::Table;
+
+/// Error codes of the transient failures. The MySQL error `1213` for deadlocks
+/// is reported with the SQLSTATE `40001`.
+const TRANSIENT_ERROR_CODES: [&str; 3] = ["40001", "40P01", "1213"];
+
+/// A policy to retry the statements failing with transient errors,
+/// using the exponential backoff with full jitter.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RetryPolicy {
+    /// Max number of retries. The statements are not retried if it is zero.
+    max_retries: u32,
+    /// Base delay of the backoff.
+    base_delay: Duration,
+    /// Max delay of the backoff.
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a new instance with the config.
+    pub(super) fn with_config(config: &Table) -> Self {
+        Self {
+            max_retries: config.get_u32("max-retries").unwrap_or_default(),
+            base_delay: config
+                .get_duration("retry-base-delay")
+                .unwrap_or_else(|| Duration::from_millis(50)),
+            max_delay: config
+                .get_duration("retry-max-delay")
+                .unwrap_or_else(|| Duration::from_secs(2)),
+        }
+    }
+
+    /// Returns the delay before the retry, which is chosen randomly
+    /// between zero and the exponential backoff.
+    fn backoff(&self, retries: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(retries);
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        delay.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// Runs the operation, and retries it if the error satisfies the predicate.
+    pub(super) async fn retry_if<T, E, F, Fut>(
+        &self,
+        mut operation: F,
+        predicate: impl Fn(&E) -> bool,
+    ) -> Result<T, E>
+    where
+        E: std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retries = 0;
+        loop {
+            match operation().await {
+                Err(err) if retries < self.max_retries && predicate(&err) => {
+                    let delay = self.backoff(retries);
+                    retries += 1;
+                    tracing::warn!(retries, "retry the statement after {delay:?}: {err}");
+                    sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Runs the statement, and retries it with the shared policy if it fails with a transient error.
+/// It should only be used for the statements which are safe to replay.
+#[inline]
+pub(super) async fn retry_transient<T, F, Fut>(operation: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    SHARED_RETRY_POLICY.retry_if(operation, is_transient).await
+}
+
+/// Returns `true` if the error is a transient failure such as a deadlock or a serialization failure.
+fn is_transient(err: &sqlx::Error) -> bool {
+    if let sqlx::Error::Database(err) = err &&
+        let Some(code) = err.code()
+    {
+        is_transient_code(&code)
+    } else {
+        false
+    }
+}
+
+/// Returns `true` if the error code is one of the transient failures.
+#[inline]
+fn is_transient_code(code: &str) -> bool {
+    TRANSIENT_ERROR_CODES.contains(&code)
+}
+
+/// Waits for the duration without blocking the async runtime.
+/// The waiters are woken by the shared timer thread.
+async fn sleep(duration: Duration) {
+    if duration.is_zero() {
+        return;
+    }
+
+    let (sender, receiver) = oneshot::channel();
+    SHARED_TIMER
+        .lock()
+        .send((Instant::now() + duration, sender))
+        .ok();
+    receiver.await.ok();
+}
+
+/// Runs the timer which wakes the waiters at their deadlines in order,
+/// until all the senders of the timer are dropped.
+fn run_timer(receiver: Receiver<(Instant, oneshot::Sender<()>)>) {
+    let mut waiters = BTreeMap::new();
+    let mut sequence = 0_u64;
+    loop {
+        let now = Instant::now();
+        while let Some(entry) = waiters.first_entry() {
+            let (deadline, _) = *entry.key();
+            if deadline > now {
+                break;
+            }
+            entry.remove().send(()).ok();
+        }
+
+        let received = if let Some(&(deadline, _)) = waiters.keys().next() {
+            match receiver.recv_timeout(deadline.saturating_duration_since(now)) {
+                Ok(waiter) => waiter,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match receiver.recv() {
+                Ok(waiter) => waiter,
+                Err(_) => break,
+            }
+        };
+        let (deadline, sender) = received;
+        sequence += 1;
+        waiters.insert((deadline, sequence), sender);
+    }
+}
+
+/// Shared timer for the delays of the retries.
+static SHARED_TIMER: LazyLock<Mutex<Sender<(Instant, oneshot::Sender<()>)>>> =
+    LazyLock::new(|| {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("zino-retry-timer".to_owned())
+            .spawn(move || run_timer(receiver))
+            .expect("fail to spawn the thread for the retry timer");
+        Mutex::new(sender)
+    });
+
+/// Shared retry policy.
+static SHARED_RETRY_POLICY: LazyLock<RetryPolicy> = LazyLock::new(|| {
+    let config = State::shared()
+        .config()
+        .get_table("database")
+        .cloned()
+        .unwrap_or_default();
+    RetryPolicy::with_config(&config)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{is_transient_code, sleep, RetryPolicy};
+    use futures::{executor::block_on, future};
+    use std::{
+        cell::Cell,
+        time::{Duration, Instant},
+    };
+    use toml::value::Table;
+
+    #[test]
+    fn it_retries_transient_errors() {
+        let config = toml::from_str::<Table>(
+            r#"
+            max-retries = 3
+            retry-base-delay = "1ms"
+            "#,
+        )
+        .unwrap();
+        let policy = RetryPolicy::with_config(&config);
+        let predicate = |code: &&str| is_transient_code(code);
+
+        let attempts = &Cell::new(0);
+        let result = block_on(policy.retry_if(
+            || async move {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 3 {
+                    Err("40P01")
+                } else {
+                    Ok(attempts.get())
+                }
+            },
+            predicate,
+        ));
+        assert_eq!(result, Ok(3));
+
+        let attempts = &Cell::new(0);
+        let result = block_on(policy.retry_if(
+            || async move {
+                attempts.set(attempts.get() + 1);
+                Err::<(), _>("23505")
+            },
+            predicate,
+        ));
+        assert_eq!(result, Err("23505"));
+        assert_eq!(attempts.get(), 1);
+
+        let attempts = &Cell::new(0);
+        let result = block_on(policy.retry_if(
+            || async move {
+                attempts.set(attempts.get() + 1);
+                Err::<(), _>("40001")
+            },
+            predicate,
+        ));
+        assert_eq!(result, Err("40001"));
+        assert_eq!(attempts.get(), 4);
+    }
+
+    #[test]
+    fn it_sleeps_on_shared_timer() {
+        let start = Instant::now();
+        block_on(future::join3(
+            sleep(Duration::from_millis(20)),
+            sleep(Duration::from_millis(5)),
+            sleep(Duration::from_millis(10)),
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let start = Instant::now();
+        block_on(sleep(Duration::from_millis(5)));
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+}
@@ -1,15 +1,29 @@
-use super::{mutation::MutationExt, query::QueryExt, ConnectionPool};
+use super::{
+    migration, mutation::MutationExt, postgres, query::QueryExt, retry, ColumnInfo, ConnectionPool,
+    IdStrategy, OptimisticLockError, TableInfo, Transaction,
+};
 use crate::{
+    datetime::DateTime,
     error::Error,
     extend::JsonObjectExt,
     format,
     model::{Column, DecodeRow, EncodeColumn, Model, Mutation, Query},
     request::Validation,
-    Map, Record,
+    Map, Record, Uuid,
+};
+use futures::{
+    stream::{self, BoxStream},
+    FutureExt, Stream, StreamExt, TryStreamExt,
 };
-use futures::TryStreamExt;
 use serde::de::DeserializeOwned;
+use serde_json::Value;
 use sqlx::{postgres::PgRow, Postgres, Row};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    panic::{self, AssertUnwindSafe},
+};
 
 /// Database schema.
 pub trait Schema: 'static + Send + Sync + Model {
@@ -23,6 +37,19 @@ pub trait Schema: 'static + Send + Sync + Model {
     const WRITER_NAME: &'static str = "main";
     /// Optional distribution column. It can be used for Citus to create a distributed table.
     const DISTRIBUTION_COLUMN: Option<&'static str> = None;
+    /// Optional soft delete column. The rows are marked as deleted instead of being removed,
+    /// and they are excluded from the queries by default.
+    const SOFT_DELETE_COLUMN: Option<&'static str> = None;
+    /// Optional version column for optimistic locking. It is increased on every update.
+    const VERSION_COLUMN: Option<&'static str> = None;
+    /// Optional column for the creation time. It is populated on insert.
+    const CREATED_AT_COLUMN: Option<&'static str> = None;
+    /// Optional column for the modification time. It is populated on insert and update.
+    const UPDATED_AT_COLUMN: Option<&'static str> = None;
+    /// Optional strategy to generate the primary key. It is assigned by
+    /// [`assign_primary_key`](Schema::assign_primary_key) before the model is inserted
+    /// if the primary key is nil or empty.
+    const ID_STRATEGY: Option<IdStrategy> = None;
 
     /// Returns a reference to the [Avro schema](apache_avro::schema::Schema).
     fn schema() -> &'static apache_avro::Schema;
@@ -101,6 +128,19 @@ pub trait Schema: 'static + Send + Sync + Model {
             .ok_or_else(|| Error::new("connection to the database is not available"))
     }
 
+    /// Selects the connection pool for the read query. A read replica of the model reader
+    /// is selected in a round-robin fashion unless the query is routed to the primary.
+    async fn select_reader(query: &Query) -> Result<&'static ConnectionPool, Error> {
+        let reader = Self::acquire_reader().await?;
+        if !query.requires_primary() &&
+            let Some(replica) = super::SHARED_CONNECTION_POOLS.get_replica(reader.name())
+        {
+            Ok(replica)
+        } else {
+            Ok(reader)
+        }
+    }
+
     /// Initializes the model writer.
     #[inline]
     fn init_writer() -> Result<&'static ConnectionPool, Error> {
@@ -109,39 +149,118 @@ pub trait Schema: 'static + Send + Sync + Model {
             .ok_or_else(|| Error::new("connection to the database is not available"))
     }
 
+    /// Assigns a new primary key generated by the [`ID_STRATEGY`](Schema::ID_STRATEGY)
+    /// if the primary key is nil or empty, and returns `true` if it is assigned.
+    /// It is called before the `before_insert` hook, so that the hook can refer to the key.
+    fn assign_primary_key(&mut self) -> Result<bool, Error> {
+        if Self::ID_STRATEGY.is_none() {
+            return Ok(false);
+        }
+
+        let mut map = to_map(self)?;
+        if generate_primary_key::<Self>(&mut map) {
+            *self = Self::try_from_map(map)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// A hook running before the model is inserted. The insert is aborted if it fails.
+    /// It runs in the transaction of the insert, so it can execute statements by `tx`.
+    #[inline]
+    async fn before_insert(&mut self, _tx: &Transaction) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// A hook running after the model is inserted. The insert is rolled back if it fails.
+    /// It runs in the transaction of the insert, so it can execute statements by `tx`.
+    #[inline]
+    async fn after_insert(&self, _tx: &Transaction) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// A hook running before the model is updated. The update is aborted if it fails.
+    /// It runs in the transaction of the update, so it can execute statements by `tx`.
+    #[inline]
+    async fn before_update(&mut self, _tx: &Transaction) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// A hook running after the model is updated. The update is rolled back if it fails.
+    /// It runs in the transaction of the update, so it can execute statements by `tx`.
+    #[inline]
+    async fn after_update(&self, _tx: &Transaction) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// A hook running before the model is deleted. The deletion is aborted if it fails.
+    /// It runs in the transaction of the deletion, so it can execute statements by `tx`.
+    #[inline]
+    async fn before_delete(&self, _tx: &Transaction) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// A hook running after the model is deleted. The deletion is rolled back if it fails.
+    /// It runs in the transaction of the deletion, so it can execute statements by `tx`.
+    #[inline]
+    async fn after_delete(&self, _tx: &Transaction) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Validates the field values of the model. All the violations are collected
+    /// instead of failing on the first one.
+    fn validate(&self) -> Result<(), Validation> {
+        Ok(())
+    }
+
     /// Creates table for the model.
     async fn create_table() -> Result<(), Error> {
         let pool = Self::init_writer()?.pool();
+        let sql = Self::create_table_sql();
+        sqlx::query(&sql).execute(pool).await?;
+        Ok(())
+    }
+
+    /// Returns the `CREATE TABLE` statement for the model.
+    fn create_table_sql() -> String {
+        migration::format_create_table::<Self>(Self::table_name())
+    }
+
+    /// Returns the `CREATE TABLE` or `ALTER TABLE` statements which migrate
+    /// the existing table to the model definition.
+    fn diff_sql(existing: &TableInfo) -> Vec<String> {
+        migration::format_table_diff::<Self>(existing)
+    }
+
+    /// Fetches the metadata of the table for the model.
+    /// The columns are empty if the table does not exist.
+    async fn table_info() -> Result<TableInfo, Error> {
+        let pool = Self::acquire_reader().await?.pool();
         let table_name = Self::table_name();
-        let primary_key_name = Self::PRIMARY_KEY_NAME;
-        let columns = Self::columns()
-            .iter()
-            .map(|col| {
-                let name = col.name();
-                let column_type = Postgres::column_type(col);
-                let mut column = format!("{name} {column_type}");
-                if let Some(value) = col.default_value() {
-                    column = column + " DEFAULT " + &Postgres::format_value(col, value);
-                } else if col.is_not_null() {
-                    column += " NOT NULL";
-                }
-                column
-            })
-            .collect::<Vec<_>>()
-            .join(",\n");
-        let mut sql = format!(
+        let sql = format!(
             "
-                CREATE TABLE IF NOT EXISTS {table_name} (
-                    {columns},
-                    CONSTRAINT {table_name}_pkey PRIMARY KEY ({primary_key_name})
-                );
+                SELECT a.attname AS name, format_type(a.atttypid, a.atttypmod) AS column_type,
+                    a.attnotnull AS not_null, pg_get_expr(d.adbin, d.adrelid) AS default_value
+                FROM pg_attribute a LEFT JOIN pg_attrdef d
+                    ON a.attrelid = d.adrelid AND a.attnum = d.adnum
+                WHERE a.attrelid = to_regclass('{table_name}') AND a.attnum > 0
+                    AND NOT a.attisdropped
+                ORDER BY a.attnum;
             "
         );
-        if let Some(column_name) = Self::DISTRIBUTION_COLUMN {
-            sql += &format!("\n SELECT create_distributed_table('{table_name}', '{column_name}');");
+        let mut rows = sqlx::query(&sql).fetch(pool);
+        let mut table_info = TableInfo::new(table_name);
+        while let Some(row) = rows.try_next().await? {
+            let column = ColumnInfo::new(
+                row.try_get::<String, _>("name")?,
+                row.try_get::<String, _>("column_type")?,
+                row.try_get::<bool, _>("not_null")?,
+                row.try_get::<Option<String>, _>("default_value")?,
+            );
+            table_info.add_column(column);
         }
-        sqlx::query(&sql).execute(pool).await?;
-        Ok(())
+        Ok(table_info)
     }
 
     /// Creates indexes for the model.
@@ -159,6 +278,18 @@ pub trait Schema: 'static + Send + Sync + Model {
                     let column = format!("coalesce({column_name}, '')");
                     text_search_languages.push(language);
                     text_search_columns.push((language, column));
+                } else if index_type == "unique" {
+                    let sql = format!(
+                        "
+                            CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS
+                            {table_name}_{column_name}_unique_index ON {table_name} ({column_name});
+                        "
+                    );
+                    rows = sqlx::query(&sql)
+                        .execute(pool)
+                        .await?
+                        .rows_affected()
+                        .max(rows);
                 } else {
                     let sort_order = if index_type == "btree" { " DESC" } else { "" };
                     let sql = format!(
@@ -197,82 +328,79 @@ pub trait Schema: 'static + Send + Sync + Model {
         Ok(rows)
     }
 
-    /// Inserts the model into the table.
-    async fn insert(self) -> Result<(), Error> {
+    /// Runs the model operations in a transaction on the writer.
+    /// The transaction is committed if the closure returns `Ok`,
+    /// and it is rolled back if the closure returns `Err` or panics.
+    async fn transaction<T, F, Fut>(f: F) -> Result<T, Error>
+    where
+        F: FnOnce(Transaction) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
         let pool = Self::acquire_writer().await?.pool();
-        let table_name = Self::table_name();
-        let map = self.into_map();
-        let values = Self::columns()
-            .iter()
-            .map(|col| Postgres::encode_value(col, map.get(col.name())))
-            .collect::<Vec<_>>()
-            .join(",");
-        let fields = Self::fields().join(",");
-        let sql = format!("INSERT INTO {table_name} ({fields}) VALUES ({values});");
-        let query_result = sqlx::query(&sql).execute(pool).await?;
-        let rows_affected = query_result.rows_affected();
-        if rows_affected == 1 {
-            Ok(())
-        } else {
-            Err(Error::new(format!(
-                "{rows_affected} rows are affected while it is expected to affect 1 row"
-            )))
+        let transaction = Transaction::new(pool.begin().await?);
+        let result = AssertUnwindSafe(f(transaction.clone()))
+            .catch_unwind()
+            .await;
+        match result {
+            Ok(Ok(value)) => {
+                transaction.commit().await?;
+                Ok(value)
+            }
+            Ok(Err(err)) => {
+                transaction.rollback().await?;
+                Err(err)
+            }
+            Err(payload) => {
+                if let Err(err) = transaction.rollback().await {
+                    tracing::error!("fail to roll back the transaction: {err}");
+                }
+                panic::resume_unwind(payload)
+            }
         }
     }
 
-    /// Inserts many models into the table.
-    async fn insert_many(models: Vec<Self>) -> Result<u64, Error> {
-        let pool = Self::acquire_writer().await?.pool();
-        let table_name = Self::table_name();
-        let columns = Self::columns();
-        let mut values = Vec::with_capacity(models.len());
-        for model in models.into_iter() {
-            let map = model.into_map();
-            let entries = columns
-                .iter()
-                .map(|col| Postgres::encode_value(col, map.get(col.name())))
-                .collect::<Vec<_>>();
-            values.push(format!("({})", entries.join(",")));
-        }
-
-        let fields = Self::fields().join(",");
-        let values = values.join(",");
-        let sql = format!("INSERT INTO {table_name} ({fields}) VALUES ({values});");
-        let query_result = sqlx::query(&sql).execute(pool).await?;
-        Ok(query_result.rows_affected())
+    /// Inserts the model into the table.
+    /// The lifecycle hooks run in the same transaction as the insert.
+    #[inline]
+    async fn insert(self) -> Result<(), Error> {
+        Self::transaction(|tx| async move { tx.insert(self).await }).await
     }
 
-    /// Updates the model in the table.
-    async fn update(self) -> Result<(), Error> {
+    /// Inserts many models into the table with bound parameters, and returns the number of
+    /// rows inserted. The models are inserted in batches within a transaction, and the number of
+    /// parameters in a batch is kept under the limit of the driver.
+    /// The primary keys are generated by the ID strategy, but the lifecycle hooks are not run.
+    async fn insert_many(models: &[Self]) -> Result<u64, Error> {
+        if models.is_empty() {
+            return Ok(0);
+        }
+
+        let rows = to_batch_rows(models)?;
         let pool = Self::acquire_writer().await?.pool();
         let table_name = Self::table_name();
-        let primary_key_name = Self::PRIMARY_KEY_NAME;
-        let primary_key = self.primary_key();
-        let map = self.into_map();
-        let num_fields = Self::fields().len();
-        let readonly_fields = Self::readonly_fields();
-        let mut mutations = Vec::with_capacity(num_fields - readonly_fields.len());
-        for col in Self::columns() {
-            let field = col.name();
-            if !readonly_fields.contains(&field) {
-                let value = Postgres::encode_value(col, map.get(field));
-                mutations.push(format!("{field} = {value}"));
+        let fields = Self::fields().join(",");
+        let mut transaction = pool.begin().await?;
+        let mut rows_affected = 0;
+        for batch in rows.chunks(max_batch_rows::<Self>()) {
+            let (values, params) = format_batch_values::<Self>(batch);
+            let sql = format!("INSERT INTO {table_name} ({fields}) VALUES {values};");
+            let mut query = sqlx::query(&sql);
+            for (col, value) in params {
+                query = postgres::bind_value(query, col, value);
             }
+            rows_affected += query.execute(&mut transaction).await?.rows_affected();
         }
+        transaction.commit().await?;
+        Ok(rows_affected)
+    }
 
-        let mutations = mutations.join(",");
-        let sql = format!(
-            "UPDATE {table_name} SET {mutations} WHERE {primary_key_name} = '{primary_key}';"
-        );
-        let query_result = sqlx::query(&sql).execute(pool).await?;
-        let rows_affected = query_result.rows_affected();
-        if rows_affected == 1 {
-            Ok(())
-        } else {
-            Err(Error::new(format!(
-                "{rows_affected} rows are affected while it is expected to affect 1 row"
-            )))
-        }
+    /// Updates the model in the table.
+    /// It returns an [`OptimisticLockError`] if the version column is specified
+    /// and the row has been updated by others since it was read.
+    /// The lifecycle hooks run in the same transaction as the update.
+    #[inline]
+    async fn update(self) -> Result<(), Error> {
+        Self::transaction(|tx| async move { tx.update(self).await }).await
     }
 
     /// Updates at most one model selected by the query in the table.
@@ -289,7 +417,8 @@ pub trait Schema: 'static + Send + Sync + Model {
                 (SELECT {primary_key_name} FROM {table_name} {filters} {sort} LIMIT 1);
             "
         );
-        let query_result = sqlx::query(&sql).execute(pool).await?;
+        let query_result =
+            retry::retry_transient(|| query.bind_params::<Self>(&sql).execute(pool)).await?;
         let rows_affected = query_result.rows_affected();
         if rows_affected <= 1 {
             Ok(())
@@ -307,82 +436,86 @@ pub trait Schema: 'static + Send + Sync + Model {
         let filters = query.format_filters::<Self>();
         let updates = mutation.format_updates::<Self>();
         let sql = format!("UPDATE {table_name} SET {updates} {filters};");
-        let query_result = sqlx::query(&sql).execute(pool).await?;
+        let query_result =
+            retry::retry_transient(|| query.bind_params::<Self>(&sql).execute(pool)).await?;
         Ok(query_result.rows_affected())
     }
 
     /// Updates or inserts the model into the table.
+    #[inline]
     async fn upsert(self) -> Result<(), Error> {
-        let pool = Self::acquire_writer().await?.pool();
-        let table_name = Self::table_name();
-        let primary_key_name = Self::PRIMARY_KEY_NAME;
-        let map = self.into_map();
-        let fields = Self::fields();
-        let num_fields = fields.len();
-        let readonly_fields = Self::readonly_fields();
-        let mut values = Vec::with_capacity(num_fields);
-        let mut mutations = Vec::with_capacity(num_fields - readonly_fields.len());
-        for col in Self::columns() {
-            let field = col.name();
-            let value = Postgres::encode_value(col, map.get(field));
-            if !readonly_fields.contains(&field) {
-                mutations.push(format!("{field} = {value}"));
-            }
-            values.push(value);
-        }
+        self.upsert_on(&[Self::PRIMARY_KEY_NAME], &[])
+            .await
+            .map(|_| ())
+    }
 
-        let fields = fields.join(",");
-        let values = values.join(",");
-        let mutations = mutations.join(",");
+    /// Inserts the model into the table, or updates the columns of the existing row
+    /// if it conflicts on the `conflict_columns`. All the writable columns are updated
+    /// if `update_columns` is empty. Returns `true` if the row is inserted.
+    ///
+    /// The conflict target should be the primary key or a column with the `unique` index.
+    /// A composite target is not validated since its unique constraint is unknown to the model.
+    async fn upsert_on(
+        self,
+        conflict_columns: &[&str],
+        update_columns: &[&str],
+    ) -> Result<bool, Error> {
+        self.validate().map_err(Validation::into_error)?;
+        let table_name = Self::table_name();
+        let conflict_clause =
+            format_conflict_clause::<Self>(table_name, conflict_columns, update_columns)?;
+        let pool = Self::acquire_writer().await?.pool();
+        let mut map = self.into_map();
+        generate_primary_key::<Self>(&mut map);
+        refresh_timestamps::<Self>(&mut map, true);
+        let values = Self::columns()
+            .iter()
+            .map(|col| Postgres::encode_value(col, map.get(col.name())))
+            .collect::<Vec<_>>()
+            .join(",");
+        let fields = Self::fields().join(",");
         let sql = format!(
             "
                 INSERT INTO {table_name} ({fields}) VALUES ({values})
-                ON CONFLICT ({primary_key_name}) DO UPDATE SET {mutations};
+                {conflict_clause} RETURNING (xmax = 0) AS inserted;
             "
         );
-        let query_result = sqlx::query(&sql).execute(pool).await?;
-        let rows_affected = query_result.rows_affected();
-        if rows_affected == 1 {
-            Ok(())
+        if let Some(row) = postgres::one_off_query(&sql).fetch_optional(pool).await? {
+            row.try_get("inserted").map_err(Error::from)
         } else {
-            Err(Error::new(format!(
-                "{rows_affected} rows are affected while it is expected to affect 1 row"
-            )))
+            Ok(false)
         }
     }
 
     /// Deletes the model in the table.
+    /// The row is marked as deleted if the soft delete column is specified.
+    /// The lifecycle hooks run in the same transaction as the deletion.
+    #[inline]
     async fn delete(&self) -> Result<(), Error> {
-        let pool = Self::acquire_writer().await?.pool();
-        let table_name = Self::table_name();
-        let primary_key_name = Self::PRIMARY_KEY_NAME;
-        let primary_key = self.primary_key();
-        let sql = format!("DELETE FROM {table_name} WHERE {primary_key_name} = '{primary_key}';");
-        let query_result = sqlx::query(&sql).execute(pool).await?;
-        let rows_affected = query_result.rows_affected();
-        if rows_affected == 1 {
-            Ok(())
-        } else {
-            Err(Error::new(format!(
-                "{rows_affected} rows are affected while it is expected to affect 1 row"
-            )))
-        }
+        Self::transaction(|tx| async move { tx.delete(self).await }).await
     }
 
     /// Deletes at most one model selected by the query in the table.
+    /// The row is marked as deleted if the soft delete column is specified.
     async fn delete_one(query: &Query) -> Result<(), Error> {
         let pool = Self::acquire_writer().await?.pool();
         let table_name = Self::table_name();
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let filters = query.format_filters::<Self>();
         let sort = query.format_sort();
+        let statement = if let Some(column) = Self::SOFT_DELETE_COLUMN {
+            format!(r#"UPDATE {table_name} SET "{column}" = coalesce("{column}", now())"#)
+        } else {
+            format!("DELETE FROM {table_name}")
+        };
         let sql = format!(
             "
-                DELETE FROM {table_name} WHERE {primary_key_name} IN
+                {statement} WHERE {primary_key_name} IN
                 (SELECT {primary_key_name} FROM {table_name} {filters} {sort} LIMIT 1);
             "
         );
-        let query_result = sqlx::query(&sql).execute(pool).await?;
+        let query_result =
+            retry::retry_transient(|| query.bind_params::<Self>(&sql).execute(pool)).await?;
         let rows_affected = query_result.rows_affected();
         if rows_affected <= 1 {
             Ok(())
@@ -394,30 +527,60 @@ pub trait Schema: 'static + Send + Sync + Model {
     }
 
     /// Deletes many models selected by the query in the table.
+    /// The rows are marked as deleted if the soft delete column is specified.
     async fn delete_many(query: &Query) -> Result<u64, Error> {
         let pool = Self::acquire_writer().await?.pool();
         let table_name = Self::table_name();
         let filters = query.format_filters::<Self>();
-        let sql = format!("DELETE FROM {table_name} {filters};");
-        let query_result = sqlx::query(&sql).execute(pool).await?;
+        let sql = if let Some(column) = Self::SOFT_DELETE_COLUMN {
+            format!(
+                r#"UPDATE {table_name} SET "{column}" = coalesce("{column}", now()) {filters};"#
+            )
+        } else {
+            format!("DELETE FROM {table_name} {filters};")
+        };
+        let query_result =
+            retry::retry_transient(|| query.bind_params::<Self>(&sql).execute(pool)).await?;
         Ok(query_result.rows_affected())
     }
 
+    /// Restores the soft-deleted model in the table.
+    async fn restore(&self) -> Result<(), Error> {
+        let Some(column) = Self::SOFT_DELETE_COLUMN else {
+            return Err(Error::new(format!(
+                "the model `{}` does not support soft delete",
+                Self::TYPE_NAME
+            )));
+        };
+        let pool = Self::acquire_writer().await?.pool();
+        let table_name = Self::table_name();
+        let primary_key_name = Self::PRIMARY_KEY_NAME;
+        let primary_key = self.primary_key();
+        let sql = format!(
+            r#"UPDATE {table_name} SET "{column}" = NULL WHERE {primary_key_name} = '{primary_key}';"#
+        );
+        let query_result = postgres::one_off_query(&sql).execute(pool).await?;
+        let rows_affected = query_result.rows_affected();
+        if rows_affected == 1 {
+            Ok(())
+        } else {
+            Err(Error::new(format!(
+                "{rows_affected} rows are affected while it is expected to affect 1 row"
+            )))
+        }
+    }
+
     /// Finds models selected by the query in the table,
-    /// and decodes it as `Vec<T>`.
+    /// and decodes it as `Vec<T>`. The union queries are combined before sorting and pagination.
     async fn find<T: DecodeRow<PgRow, Error = sqlx::Error>>(
         query: &Query,
     ) -> Result<Vec<T>, Error> {
-        let pool = Self::acquire_reader().await?.pool();
-        let table_name = Self::table_name();
-        let projection = query.format_fields();
-        let filters = query.format_filters::<Self>();
-        let sort = query.format_sort();
-        let pagination = query.format_pagination();
-        let sql = format!("SELECT {projection} FROM {table_name} {filters} {sort} {pagination};");
-        let mut rows = sqlx::query(&sql).fetch(pool);
-        let mut data = Vec::new();
-        while let Some(row) = rows.try_next().await? {
+        let pool = Self::select_reader(query).await?.pool();
+        let sql = format_select::<Self>(query, Self::table_name())?;
+        let rows = retry::retry_transient(|| query.bind_union_params::<Self>(&sql).fetch_all(pool))
+            .await?;
+        let mut data = Vec::with_capacity(rows.len());
+        for row in rows {
             data.push(T::decode_row(&row)?);
         }
         Ok(data)
@@ -430,18 +593,95 @@ pub trait Schema: 'static + Send + Sync + Model {
         serde_json::from_value(data.into()).map_err(Error::from)
     }
 
+    /// Explains the execution plan of the query used by [`find`](Schema::find),
+    /// and returns the planner output in the JSON format.
+    /// It is only available with the `orm-explain` feature.
+    #[cfg(feature = "orm-explain")]
+    async fn explain(query: &Query) -> Result<String, Error> {
+        let pool = Self::select_reader(query).await?.pool();
+        let sql = format_select::<Self>(query, Self::table_name())?;
+        let sql = format_explain(&sql, false);
+        let row = query
+            .bind_union_params::<Self>(&sql)
+            .fetch_one(pool)
+            .await?;
+        let plan = row.try_get::<sqlx::types::Json<Value>, _>(0)?;
+        serde_json::to_string_pretty(&plan.0).map_err(Error::from)
+    }
+
+    /// Explains the execution plan of the query used by [`find`](Schema::find)
+    /// with the actual run time statistics. The query is executed,
+    /// and the planner output is returned in the JSON format.
+    /// It is only available with the `orm-explain` feature.
+    #[cfg(feature = "orm-explain")]
+    async fn explain_analyze(query: &Query) -> Result<String, Error> {
+        let pool = Self::select_reader(query).await?.pool();
+        let sql = format_select::<Self>(query, Self::table_name())?;
+        let sql = format_explain(&sql, true);
+        let row = query
+            .bind_union_params::<Self>(&sql)
+            .fetch_one(pool)
+            .await?;
+        let plan = row.try_get::<sqlx::types::Json<Value>, _>(0)?;
+        serde_json::to_string_pretty(&plan.0).map_err(Error::from)
+    }
+
+    /// Fetches the rows selected by the query in the table as a stream.
+    /// The rows are fetched in chunks by a server-side cursor only when the stream is polled,
+    /// and the pagination of the query is ignored. The connection is released
+    /// once the stream is finished or dropped.
+    async fn fetch_stream(query: &Query) -> Result<BoxStream<'static, Result<Map, Error>>, Error> {
+        let pool = Self::select_reader(query).await?.pool();
+        let table_name = Self::table_name();
+        let projection = query.format_fields()?;
+        let filters = query.format_filters::<Self>();
+        let unions = query.format_unions::<Self>(table_name)?;
+        let sort = query.format_sort();
+        let sql = format!(
+            "
+                DECLARE zino_cursor NO SCROLL CURSOR FOR
+                SELECT {projection} FROM {table_name} {filters}{unions} {sort};
+            "
+        );
+        let mut transaction = pool.begin().await?;
+        query
+            .bind_union_params::<Self>(&sql)
+            .execute(&mut transaction)
+            .await?;
+
+        let stream = fetch_in_chunks(
+            transaction,
+            CURSOR_CHUNK_SIZE,
+            |mut transaction| async move {
+                let sql = format!("FETCH {CURSOR_CHUNK_SIZE} FROM zino_cursor;");
+                let rows = sqlx::query(&sql).fetch_all(&mut transaction).await?;
+                let data = rows
+                    .iter()
+                    .map(Map::decode_row)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((transaction, data))
+            },
+        );
+        Ok(stream.boxed())
+    }
+
     /// Finds one model selected by the query in the table,
     /// and decodes it as an instance of type `T`.
     async fn find_one<T: DecodeRow<PgRow, Error = sqlx::Error>>(
         query: &Query,
     ) -> Result<Option<T>, Error> {
-        let pool = Self::acquire_reader().await?.pool();
+        let pool = Self::select_reader(query).await?.pool();
         let table_name = Self::table_name();
-        let projection = query.format_fields();
+        let projection = query.format_fields()?;
         let filters = query.format_filters::<Self>();
+        let unions = query.format_unions::<Self>(table_name)?;
         let sort = query.format_sort();
-        let sql = format!("SELECT {projection} FROM {table_name} {filters} {sort} LIMIT 1;");
-        let data = if let Some(row) = sqlx::query(&sql).fetch_optional(pool).await? {
+        let sql =
+            format!("SELECT {projection} FROM {table_name} {filters}{unions} {sort} LIMIT 1;");
+        let data = if let Some(row) =
+            retry::retry_transient(|| query.bind_union_params::<Self>(&sql).fetch_optional(pool))
+                .await?
+        {
             Some(T::decode_row(&row)?)
         } else {
             None
@@ -465,7 +705,7 @@ pub trait Schema: 'static + Send + Sync + Model {
         data: &mut Vec<Map>,
         columns: [&str; N],
     ) -> Result<u64, Error> {
-        let pool = Self::acquire_reader().await?.pool();
+        let pool = Self::select_reader(query).await?.pool();
         let table_name = Self::table_name();
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let mut values = Vec::new();
@@ -481,10 +721,10 @@ pub trait Schema: 'static + Send + Sync + Model {
             query.append_filters(&mut Map::from_entry(primary_key_name, primary_key_values));
         }
 
-        let projection = query.format_fields();
+        let projection = query.format_fields()?;
         let filters = query.format_filters::<Self>();
         let sql = format!("SELECT {projection} FROM {table_name} {filters};");
-        let mut rows = sqlx::query(&sql).fetch(pool);
+        let mut rows = query.bind_params::<Self>(&sql).fetch(pool);
         let mut associations = Map::new();
         while let Some(row) = rows.try_next().await? {
             let primary_key_value = row.try_get_unchecked::<String, _>(primary_key_name)?;
@@ -520,7 +760,7 @@ pub trait Schema: 'static + Send + Sync + Model {
         data: &mut Map,
         columns: [&str; N],
     ) -> Result<(), Error> {
-        let pool = Self::acquire_reader().await?.pool();
+        let pool = Self::select_reader(query).await?.pool();
         let table_name = Self::table_name();
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let mut values = Vec::new();
@@ -534,10 +774,10 @@ pub trait Schema: 'static + Send + Sync + Model {
             query.append_filters(&mut Map::from_entry(primary_key_name, primary_key_values));
         }
 
-        let projection = query.format_fields();
+        let projection = query.format_fields()?;
         let filters = query.format_filters::<Self>();
         let sql = format!("SELECT {projection} FROM {table_name} {filters};");
-        let mut rows = sqlx::query(&sql).fetch(pool);
+        let mut rows = query.bind_params::<Self>(&sql).fetch(pool);
         let mut associations = Map::new();
         while let Some(row) = rows.try_next().await? {
             let primary_key_value = row.try_get_unchecked::<String, _>(primary_key_name)?;
@@ -564,6 +804,66 @@ pub trait Schema: 'static + Send + Sync + Model {
         Ok(())
     }
 
+    /// Loads the relations specified by [`Query::with`] for the data.
+    /// It is implemented by the derive macro for the declared relations.
+    async fn load_relations(relations: &[String], _data: &mut [Map]) -> Result<u64, Error> {
+        if let Some(relation) = relations.first() {
+            Err(Error::new(format!(
+                "the relation `{relation}` is not declared for the model `{}`",
+                Self::TYPE_NAME
+            )))
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Finds models selected by the query in the table, and eager-loads the relations
+    /// specified by [`Query::with`]. Each relation is loaded with a single query.
+    async fn find_with_relations(query: &Query) -> Result<Vec<Map>, Error> {
+        let mut data = Self::find::<Map>(query).await?;
+        let relations = query.relations();
+        if !relations.is_empty() && !data.is_empty() {
+            Self::load_relations(relations, &mut data).await?;
+        }
+        Ok(data)
+    }
+
+    /// Loads the models in the table of `M` whose `foreign_key` refers to the data
+    /// using a merged select, and attaches them to the data as the `relation` field.
+    async fn load_has_many<M: Schema>(
+        relation: &str,
+        foreign_key: &str,
+        data: &mut [Map],
+    ) -> Result<u64, Error> {
+        let primary_key_name = Self::PRIMARY_KEY_NAME;
+        let mut values = Vec::with_capacity(data.len());
+        for row in data.iter() {
+            if let Some(value) = row.get(primary_key_name) && !values.contains(value) {
+                values.push(value.clone());
+            }
+        }
+
+        let mut related_data = Vec::new();
+        if !values.is_empty() {
+            let table_name = M::table_name();
+            let mut query = M::default_query();
+            query.add_filter_in(foreign_key, &values);
+            let pool = M::select_reader(&query).await?.pool();
+
+            let projection = query.format_fields()?;
+            let filters = query.format_filters::<M>();
+            let sql = format!("SELECT {projection} FROM {table_name} {filters};");
+            let mut rows = query.bind_params::<M>(&sql).fetch(pool);
+            while let Some(row) = rows.try_next().await? {
+                related_data.push(Map::decode_row(&row)?);
+            }
+        }
+
+        let num_rows = related_data.len();
+        attach_related_data(data, primary_key_name, relation, foreign_key, related_data);
+        u64::try_from(num_rows).map_err(Error::from)
+    }
+
     /// Performs a left outer join to another table to filter rows in the "joined" table,
     /// and decodes it as `Vec<T>`.
     async fn lookup<M: Schema, T: DecodeRow<PgRow, Error = sqlx::Error>>(
@@ -571,12 +871,12 @@ pub trait Schema: 'static + Send + Sync + Model {
         left_columns: &[&str],
         right_columns: &[&str],
     ) -> Result<Vec<T>, Error> {
-        let pool = Self::acquire_reader().await?.pool();
+        let pool = Self::select_reader(query).await?.pool();
         let table_name = Self::table_name();
         let model_name = Self::model_name();
         let other_table_name = M::table_name();
         let other_model_name = M::model_name();
-        let projection = query.format_fields();
+        let projection = query.format_fields()?;
         let filters = query.format_filters::<Self>();
         let sort = query.format_sort();
         let pagination = query.format_pagination();
@@ -595,7 +895,7 @@ pub trait Schema: 'static + Send + Sync + Model {
                 ON {on_expressions} {filters} {sort} {pagination};
             "#
         );
-        let mut rows = sqlx::query(&sql).fetch(pool);
+        let mut rows = query.bind_params::<Self>(&sql).fetch(pool);
         let mut data = Vec::new();
         while let Some(row) = rows.try_next().await? {
             data.push(T::decode_row(&row)?);
@@ -620,7 +920,7 @@ pub trait Schema: 'static + Send + Sync + Model {
         query: &Query,
         columns: &[(&str, bool)],
     ) -> Result<T, Error> {
-        let pool = Self::acquire_writer().await?.pool();
+        let pool = Self::select_reader(query).await?.pool();
         let table_name = Self::table_name();
         let filters = query.format_filters::<Self>();
         let projection = columns
@@ -639,7 +939,8 @@ pub trait Schema: 'static + Send + Sync + Model {
             .collect::<Vec<_>>()
             .join(",");
         let sql = format!("SELECT {projection} FROM {table_name} {filters};");
-        let row = sqlx::query(&sql).fetch_one(pool).await?;
+        let row =
+            retry::retry_transient(|| query.bind_params::<Self>(&sql).fetch_one(pool)).await?;
         T::decode_row(&row).map_err(Error::from)
     }
 
@@ -653,6 +954,15 @@ pub trait Schema: 'static + Send + Sync + Model {
         serde_json::from_value(map.into()).map_err(Error::from)
     }
 
+    /// Counts the number of distinct values of the column selected by the query in the table,
+    /// and parses it as an instance of type `T`. The count is named as `{column}_count_distinct`.
+    async fn count_distinct_as<T: DeserializeOwned>(
+        query: &Query,
+        column: &str,
+    ) -> Result<T, Error> {
+        Self::count_as(query, &[(column, true)]).await
+    }
+
     /// Executes the query in the table, and returns the total number of rows affected.
     async fn execute(query: &str, params: Option<&Map>) -> Result<u64, Error> {
         let pool = Self::acquire_reader().await?.pool();
@@ -711,6 +1021,47 @@ pub trait Schema: 'static + Send + Sync + Model {
         }
     }
 
+    /// Finds the models selected by the primary keys in the table, and parses them as `Self`.
+    /// The models are returned in the order of the keys, and the missing ones are skipped.
+    /// The keys are fetched in batches so that the number of parameters is under the limit.
+    async fn find_many_by_ids(ids: &[Uuid]) -> Result<Vec<Self>, Error> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pool = Self::acquire_reader().await?.pool();
+        let table_name = Self::table_name();
+        let primary_key_name = Self::PRIMARY_KEY_NAME;
+        let soft_delete_filter = Self::SOFT_DELETE_COLUMN
+            .map(|column| format!(r#" AND "{column}" IS NULL"#))
+            .unwrap_or_default();
+        let mut models = HashMap::with_capacity(ids.len());
+        for batch in ids.chunks(MAX_BIND_PARAMS) {
+            let placeholders = (1..=batch.len())
+                .map(|index| format!("${index}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            let sql = format!(
+                "
+                    SELECT * FROM {table_name}
+                    WHERE {primary_key_name} IN ({placeholders}){soft_delete_filter};
+                "
+            );
+            let mut query = sqlx::query(&sql);
+            for &id in batch {
+                query = query.bind(id);
+            }
+
+            let mut rows = query.fetch(pool);
+            while let Some(row) = rows.try_next().await? {
+                let id = row.try_get::<Uuid, _>(primary_key_name)?;
+                let record = Record::decode_row(&row)?;
+                models.insert(id, Self::try_from_avro_record(record)?);
+            }
+        }
+        Ok(order_by_keys(ids, models))
+    }
+
     /// Finds one model selected by the primary key in the table, and parses it as `Self`.
     async fn try_get_model(primary_key: &str) -> Result<Self, Error> {
         let pool = Self::acquire_reader().await?.pool();
@@ -721,7 +1072,7 @@ pub trait Schema: 'static + Send + Sync + Model {
                 SELECT * FROM {table_name} WHERE {primary_key_name} = '{primary_key}';
             "
         );
-        if let Some(row) = sqlx::query(&sql).fetch_optional(pool).await? {
+        if let Some(row) = postgres::one_off_query(&sql).fetch_optional(pool).await? {
             let record = Record::decode_row(&row)?;
             Self::try_from_avro_record(record).map_err(Error::from)
         } else {
@@ -732,3 +1083,599 @@ pub trait Schema: 'static + Send + Sync + Model {
         }
     }
 }
+
+/// Converts the model to a json object without consuming it.
+fn to_map<M: Schema>(model: &M) -> Result<Map, Error> {
+    match serde_json::to_value(model)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(Error::new("the model cann't be converted to a json object")),
+    }
+}
+
+/// Converts the models into the rows of a batch insert.
+fn to_batch_rows<M: Schema>(models: &[M]) -> Result<Vec<Map>, Error> {
+    let mut rows = Vec::with_capacity(models.len());
+    for model in models {
+        model.validate().map_err(Validation::into_error)?;
+        let mut map = to_map(model)?;
+        generate_primary_key::<M>(&mut map);
+        refresh_timestamps::<M>(&mut map, true);
+        rows.push(map);
+    }
+    Ok(rows)
+}
+
+/// Generates a primary key by the ID strategy of the model if the primary key is nil or empty.
+/// Returns `true` if the primary key is generated.
+fn generate_primary_key<M: Schema>(data: &mut Map) -> bool {
+    let Some(strategy) = M::ID_STRATEGY else {
+        return false;
+    };
+    let primary_key_name = M::PRIMARY_KEY_NAME;
+    let is_nil = match data.get(primary_key_name) {
+        None | Some(Value::Null) => true,
+        Some(Value::String(value)) => {
+            value.is_empty() || value.parse::<Uuid>().is_ok_and(|id| id.is_nil())
+        }
+        Some(Value::Number(value)) => value.as_u64() == Some(0),
+        _ => false,
+    };
+    if is_nil {
+        let column_type = M::get_column(primary_key_name).map(|col| col.type_name());
+        let primary_key = match column_type {
+            Some("Uuid") => strategy.generate_uuid().to_string().into(),
+            Some("i64" | "u64") => Value::from(strategy.generate() as i64),
+            _ => strategy.generate_string().into(),
+        };
+        data.insert(primary_key_name.to_owned(), primary_key);
+    }
+    is_nil
+}
+
+/// Formats the `SELECT` statement of the query for the model.
+fn format_select<M: Schema>(query: &Query, table_name: &str) -> Result<String, Error> {
+    let projection = query.format_fields()?;
+    let filters = query.format_filters::<M>();
+    let unions = query.format_unions::<M>(table_name)?;
+    let sort = query.format_sort();
+    let pagination = query.format_pagination();
+    Ok(format!(
+        "SELECT {projection} FROM {table_name} {filters}{unions} {sort} {pagination};"
+    ))
+}
+
+/// Formats the `EXPLAIN` statement with the JSON output.
+/// The statement is executed to collect the run time statistics if `analyze` is `true`.
+#[cfg(feature = "orm-explain")]
+fn format_explain(sql: &str, analyze: bool) -> String {
+    if analyze {
+        format!("EXPLAIN (ANALYZE, BUFFERS, FORMAT JSON) {sql}")
+    } else {
+        format!("EXPLAIN (FORMAT JSON) {sql}")
+    }
+}
+
+/// Formats the `INSERT` statement of the model.
+pub(super) fn format_insert<M: Schema>(model: &M) -> Result<String, Error> {
+    let table_name = M::table_name();
+    let mut map = to_map(model)?;
+    refresh_timestamps::<M>(&mut map, true);
+    let values = M::columns()
+        .iter()
+        .map(|col| Postgres::encode_value(col, map.get(col.name())))
+        .collect::<Vec<_>>()
+        .join(",");
+    let fields = M::fields().join(",");
+    Ok(format!(
+        "INSERT INTO {table_name} ({fields}) VALUES ({values});"
+    ))
+}
+
+/// Formats the `UPDATE` statement of the model, and returns it with the version
+/// which is expected to match if the version column is specified.
+pub(super) fn format_update<M: Schema>(model: &M) -> Result<(String, i64), Error> {
+    let table_name = M::table_name();
+    let primary_key_name = M::PRIMARY_KEY_NAME;
+    let primary_key = model.primary_key();
+    let mut map = to_map(model)?;
+    refresh_timestamps::<M>(&mut map, false);
+    let num_fields = M::fields().len();
+    let readonly_fields = M::readonly_fields();
+    let version_column = M::VERSION_COLUMN;
+    let mut mutations = Vec::with_capacity(num_fields - readonly_fields.len());
+    for col in M::columns() {
+        let field = col.name();
+        if version_column == Some(field) {
+            mutations.push(format!(r#""{field}" = "{field}" + 1"#));
+        } else if !readonly_fields.contains(&field) && M::CREATED_AT_COLUMN != Some(field) {
+            let value = Postgres::encode_value(col, map.get(field));
+            mutations.push(format!("{field} = {value}"));
+        }
+    }
+
+    let mutations = mutations.join(",");
+    let version = version_column
+        .and_then(|column| map.get(column))
+        .and_then(|v| v.as_i64())
+        .unwrap_or_default();
+    let sql = if let Some(column) = version_column {
+        format!(
+            r#"
+                UPDATE {table_name} SET {mutations}
+                WHERE {primary_key_name} = '{primary_key}' AND "{column}" = {version};
+            "#
+        )
+    } else {
+        format!("UPDATE {table_name} SET {mutations} WHERE {primary_key_name} = '{primary_key}';")
+    };
+    Ok((sql, version))
+}
+
+/// Formats the `DELETE` statement of the model.
+/// The row is marked as deleted if the soft delete column is specified.
+pub(super) fn format_delete<M: Schema>(model: &M) -> String {
+    let table_name = M::table_name();
+    let primary_key_name = M::PRIMARY_KEY_NAME;
+    let primary_key = model.primary_key();
+    if let Some(column) = M::SOFT_DELETE_COLUMN {
+        format!(
+            r#"
+                UPDATE {table_name} SET "{column}" = coalesce("{column}", now())
+                WHERE {primary_key_name} = '{primary_key}';
+            "#
+        )
+    } else {
+        format!("DELETE FROM {table_name} WHERE {primary_key_name} = '{primary_key}';")
+    }
+}
+
+/// Checks that exactly one row is affected.
+pub(super) fn check_rows_affected(rows_affected: u64) -> Result<(), Error> {
+    if rows_affected == 1 {
+        Ok(())
+    } else {
+        Err(Error::new(format!(
+            "{rows_affected} rows are affected while it is expected to affect 1 row"
+        )))
+    }
+}
+
+/// Checks that exactly one row is updated. It returns an [`OptimisticLockError`]
+/// if no rows are affected and the version column is specified.
+pub(super) fn check_version<M: Schema>(
+    rows_affected: u64,
+    model: &M,
+    version: i64,
+) -> Result<(), Error> {
+    if rows_affected == 0 && M::VERSION_COLUMN.is_some() {
+        let model_name = M::model_name();
+        let primary_key = model.primary_key();
+        let err = OptimisticLockError::new(model_name, primary_key, version);
+        Err(Error::from_typed(err))
+    } else {
+        check_rows_affected(rows_affected)
+    }
+}
+
+/// Refreshes the audit timestamps in the model data.
+/// The creation time is only populated when the model is inserted.
+fn refresh_timestamps<M: Schema>(data: &mut Map, inserted: bool) {
+    let now = DateTime::now();
+    if inserted && let Some(column) = M::CREATED_AT_COLUMN {
+        data.upsert(column, now);
+    }
+    if let Some(column) = M::UPDATED_AT_COLUMN {
+        data.upsert(column, now);
+    }
+}
+
+/// Formats the `ON CONFLICT` clause of an upsert after validating the conflict target.
+fn format_conflict_clause<M: Schema>(
+    table_name: &str,
+    conflict_columns: &[&str],
+    update_columns: &[&str],
+) -> Result<String, Error> {
+    for &column in conflict_columns {
+        if M::get_column(column).is_none() {
+            return Err(Error::new(format!(
+                "the conflict column `{column}` does not exist in the model `{}`",
+                M::TYPE_NAME
+            )));
+        }
+    }
+    match conflict_columns {
+        [] => return Err(Error::new("the conflict target should not be empty")),
+        [column] => {
+            let is_unique =
+                M::get_column(column).and_then(|col| col.index_type()) == Some("unique");
+            if *column != M::PRIMARY_KEY_NAME && !is_unique {
+                return Err(Error::new(format!(
+                    "the conflict column `{column}` is neither the primary key nor a unique column"
+                )));
+            }
+        }
+        _ => (),
+    }
+
+    let readonly_fields = M::readonly_fields();
+    let mut mutations = Vec::new();
+    for col in M::columns() {
+        let field = col.name();
+        if conflict_columns.contains(&field) {
+            continue;
+        }
+        if M::VERSION_COLUMN == Some(field) {
+            mutations.push(format!(r#""{field}" = {table_name}."{field}" + 1"#));
+        } else if update_columns.is_empty() {
+            if !readonly_fields.contains(&field) && M::CREATED_AT_COLUMN != Some(field) {
+                mutations.push(format!(r#""{field}" = excluded."{field}""#));
+            }
+        } else if update_columns.contains(&field) || M::UPDATED_AT_COLUMN == Some(field) {
+            mutations.push(format!(r#""{field}" = excluded."{field}""#));
+        }
+    }
+
+    let conflict_target = conflict_columns
+        .iter()
+        .map(|column| format!(r#""{column}""#))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if mutations.is_empty() {
+        Ok(format!("ON CONFLICT ({conflict_target}) DO NOTHING"))
+    } else {
+        let mutations = mutations.join(", ");
+        Ok(format!(
+            "ON CONFLICT ({conflict_target}) DO UPDATE SET {mutations}"
+        ))
+    }
+}
+
+/// Attaches the related data to the rows as the `relation` field
+/// by matching the `foreign_key` with the primary key.
+fn attach_related_data(
+    data: &mut [Map],
+    primary_key_name: &str,
+    relation: &str,
+    foreign_key: &str,
+    related_data: Vec<Map>,
+) {
+    let mut associations = Map::new();
+    for entry in related_data {
+        if let Some(key) = entry.get(foreign_key).map(format_key) {
+            if let Some(Value::Array(entries)) = associations.get_mut(&key) {
+                entries.push(entry.into());
+            } else {
+                associations.upsert(key, vec![Value::from(entry)]);
+            }
+        }
+    }
+    for row in data {
+        let entries = row
+            .get(primary_key_name)
+            .and_then(|value| associations.get(&format_key(value)))
+            .cloned()
+            .unwrap_or_else(|| Value::Array(Vec::new()));
+        row.upsert(relation, entries);
+    }
+}
+
+/// Formats a key value for matching the primary key and the foreign key.
+fn format_key(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.to_owned(),
+        _ => value.to_string(),
+    }
+}
+
+/// Collects the items in the order of the keys. The missing keys are skipped,
+/// and an item is only collected for the first occurrence of the duplicate keys.
+fn order_by_keys<K: Eq + Hash, T>(keys: &[K], mut items: HashMap<K, T>) -> Vec<T> {
+    keys.iter().filter_map(|key| items.remove(key)).collect()
+}
+
+/// Creates a stream which fetches the items in chunks from the source when it is polled.
+/// The stream is finished when a chunk is not full or an error occurs,
+/// and the source is dropped at that time.
+fn fetch_in_chunks<S, T, F, Fut>(
+    source: S,
+    chunk_size: usize,
+    fetch: F,
+) -> impl Stream<Item = Result<T, Error>>
+where
+    F: FnMut(S) -> Fut,
+    Fut: Future<Output = Result<(S, Vec<T>), Error>>,
+{
+    stream::unfold(
+        (Some(source), fetch),
+        move |(source, mut fetch)| async move {
+            let source = source?;
+            match fetch(source).await {
+                Ok((source, items)) => {
+                    if items.is_empty() {
+                        None
+                    } else {
+                        let source = (items.len() >= chunk_size).then_some(source);
+                        let items = items.into_iter().map(Ok).collect::<Vec<_>>();
+                        Some((stream::iter(items), (source, fetch)))
+                    }
+                }
+                Err(err) => Some((stream::iter(vec![Err(err)]), (None, fetch))),
+            }
+        },
+    )
+    .flatten()
+}
+
+/// Number of rows fetched from a cursor at a time.
+const CURSOR_CHUNK_SIZE: usize = 1000;
+
+/// Maximum number of bound parameters in a statement.
+const MAX_BIND_PARAMS: usize = 65535;
+
+/// Returns the maximum number of rows in a batch insert so that a row is never split.
+fn max_batch_rows<M: Schema>() -> usize {
+    (MAX_BIND_PARAMS / M::columns().len().max(1)).max(1)
+}
+
+/// Formats the rows of a batch insert with placeholders, and returns the params to be bound.
+/// Empty values are encoded in the statement so that the column defaults still apply.
+fn format_batch_values<'a, M: Schema>(
+    rows: &'a [Map],
+) -> (String, Vec<(&'static Column<'static>, &'a Value)>) {
+    let columns = M::columns();
+    let mut values = Vec::with_capacity(rows.len());
+    let mut params = Vec::with_capacity(rows.len() * columns.len());
+    for row in rows {
+        let mut entries = Vec::with_capacity(columns.len());
+        for col in columns {
+            let value = row.get(col.name());
+            if let Some(value) = value && postgres::is_bindable(col, value) {
+                params.push((col, value));
+                let column_type = Postgres::column_type(col);
+                entries.push(format!("${}::{column_type}", params.len()));
+            } else {
+                entries.push(Postgres::encode_value(col, value).into_owned());
+            }
+        }
+        values.push(format!("({})", entries.join(",")));
+    }
+    (values.join(","), params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        attach_related_data, check_version, fetch_in_chunks, format_batch_values,
+        format_conflict_clause, format_delete, format_select, max_batch_rows, order_by_keys,
+        refresh_timestamps, to_batch_rows,
+    };
+    use crate::{
+        database::{
+            fixtures::{Note, Task},
+            OptimisticLockError, Schema, Transaction,
+        },
+        datetime::DateTime,
+        extend::JsonObjectExt,
+        model::Query,
+        Map, Uuid,
+    };
+    use futures::StreamExt;
+    use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+    #[test]
+    fn it_refreshes_timestamps() {
+        let created_at = DateTime::from_timestamp(0);
+        let mut data = Map::from_entry("created_at", created_at);
+        data.upsert("updated_at", created_at);
+        refresh_timestamps::<Task>(&mut data, false);
+        assert_eq!(data.get("created_at"), Some(&created_at.into()));
+        assert_ne!(data.get("updated_at"), Some(&created_at.into()));
+
+        refresh_timestamps::<Task>(&mut data, true);
+        assert_ne!(data.get("created_at"), Some(&created_at.into()));
+    }
+
+    #[test]
+    fn it_formats_batch_inserts() {
+        let rows = (0..10000)
+            .map(|i| {
+                let mut row = Map::from_entry("name", format!("task-{i}"));
+                row.upsert("status", "");
+                row
+            })
+            .collect::<Vec<_>>();
+        let batch_size = max_batch_rows::<Task>();
+        assert_eq!(batch_size, 8191);
+
+        let mut num_rows = 0;
+        for batch in rows.chunks(batch_size) {
+            let (values, params) = format_batch_values::<Task>(batch);
+            assert_eq!(params.len(), batch.len());
+            assert!(values.starts_with("(NULL,$1::text,'',NULL,NULL,NULL,NULL,NULL),"));
+            num_rows += values.matches("),(").count() + 1;
+        }
+        assert_eq!(num_rows, 10000);
+
+        let rows_affected = futures::executor::block_on(Task::insert_many(&[]));
+        assert_eq!(rows_affected.ok(), Some(0));
+    }
+
+    #[test]
+    fn it_assigns_primary_keys_in_batches() {
+        let id = Uuid::new_v4();
+        let mut notes = (0..3)
+            .map(|i| Note {
+                title: format!("note-{i}"),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        notes.push(Note {
+            id,
+            title: "note-3".to_owned(),
+        });
+
+        let rows = to_batch_rows(&notes).unwrap();
+        let ids = rows
+            .iter()
+            .filter_map(|row| row.get_str("id")?.parse::<Uuid>().ok())
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 4);
+        assert!(ids[..3].iter().all(|id| !id.is_nil()));
+        assert!(ids[0] < ids[1] && ids[1] < ids[2]);
+        assert_eq!(ids[3], id);
+
+        let mut note = Note::default();
+        assert_eq!(note.assign_primary_key().ok(), Some(true));
+        assert!(!note.id.is_nil());
+
+        let id = note.id;
+        assert_eq!(note.assign_primary_key().ok(), Some(false));
+        assert_eq!(note.id, id);
+        assert_eq!(Task::default().assign_primary_key().ok(), Some(false));
+    }
+
+    #[test]
+    fn it_orders_models_by_ids() {
+        let ids = (0..5).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        let existing_ids = [ids[3], ids[0], ids[4]];
+        let models = existing_ids
+            .iter()
+            .map(|&id| (id, id.to_string()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            order_by_keys(&ids, models),
+            [ids[0].to_string(), ids[3].to_string(), ids[4].to_string()]
+        );
+
+        let models = futures::executor::block_on(Task::find_many_by_ids(&[]));
+        assert!(models.is_ok_and(|models| models.is_empty()));
+    }
+
+    #[test]
+    fn it_fetches_rows_in_chunks() {
+        let fetches = &Cell::new(0);
+        let fetch = |(offset, cursor): (usize, Rc<()>)| async move {
+            fetches.set(fetches.get() + 1);
+            let end = (offset + 1000).min(3500);
+            let rows = (offset..end)
+                .map(|id| Map::from_entry("id", id))
+                .collect::<Vec<_>>();
+            Ok(((end, cursor), rows))
+        };
+
+        let cursor = Rc::new(());
+        let stream = fetch_in_chunks((0, cursor.clone()), 1000, fetch);
+        let rows = futures::executor::block_on(stream.take(1500).collect::<Vec<_>>());
+        assert_eq!(rows.len(), 1500);
+        assert_eq!(rows[1499].as_ref().ok(), Some(&Map::from_entry("id", 1499)));
+        assert_eq!(fetches.get(), 2);
+        assert_eq!(Rc::strong_count(&cursor), 1);
+
+        fetches.set(0);
+        let stream = fetch_in_chunks((0, cursor.clone()), 1000, fetch);
+        let rows = futures::executor::block_on(stream.collect::<Vec<_>>());
+        assert_eq!(rows.len(), 3500);
+        assert_eq!(fetches.get(), 4);
+        assert_eq!(Rc::strong_count(&cursor), 1);
+    }
+
+    #[cfg(feature = "orm-explain")]
+    #[test]
+    fn it_formats_explain_statements() {
+        use super::format_explain;
+
+        let mut query = Query::default();
+        query.add_filter("status", "active");
+        query.set_sort_order("priority".to_owned(), false);
+        let sql = format_select::<Task>(&query, "task").unwrap();
+        assert!(sql.starts_with(r#"SELECT * FROM task  WHERE "status" = 'active'"#));
+
+        let explain = format_explain(&sql, false);
+        assert_eq!(explain, format!("EXPLAIN (FORMAT JSON) {sql}"));
+        let explain = format_explain(&sql, true);
+        assert_eq!(
+            explain,
+            format!("EXPLAIN (ANALYZE, BUFFERS, FORMAT JSON) {sql}")
+        );
+    }
+
+    #[test]
+    fn it_formats_conflict_clauses() {
+        let clause =
+            format_conflict_clause::<Task>("task", &["name"], &["status"]).unwrap_or_default();
+        assert_eq!(
+            clause,
+            r#"ON CONFLICT ("name") DO UPDATE SET "status" = excluded."status", "#.to_owned()
+                + r#""updated_at" = excluded."updated_at""#
+        );
+
+        let clause = format_conflict_clause::<Task>("task", &["id"], &[]).unwrap_or_default();
+        assert!(clause.starts_with(r#"ON CONFLICT ("id") DO UPDATE SET "name" = excluded."name""#));
+        assert!(!clause.contains("created_at"));
+
+        assert!(format_conflict_clause::<Task>("task", &["status"], &[]).is_err());
+        assert!(format_conflict_clause::<Task>("task", &["email"], &[]).is_err());
+        assert!(format_conflict_clause::<Task>("task", &[], &[]).is_err());
+    }
+
+    #[test]
+    fn it_attaches_related_data() {
+        let mut data = (0..50)
+            .map(|i| Map::from_entry("id", format!("user-{i}")))
+            .collect::<Vec<_>>();
+        let related_data = (0..100)
+            .map(|i| {
+                let mut task = Map::from_entry("id", i);
+                task.upsert("manager_id", format!("user-{}", i % 25));
+                task
+            })
+            .collect::<Vec<_>>();
+        attach_related_data(&mut data, "id", "tasks", "manager_id", related_data);
+
+        let num_tasks = |row: &Map| row.get("tasks").and_then(|v| v.as_array()).map(|v| v.len());
+        assert_eq!(num_tasks(&data[0]), Some(4));
+        assert_eq!(num_tasks(&data[24]), Some(4));
+        assert_eq!(num_tasks(&data[25]), Some(0));
+        assert_eq!(num_tasks(&data[49]), Some(0));
+    }
+
+    #[test]
+    fn it_runs_lifecycle_hooks() {
+        let mut note = Note {
+            title: " Release Notes ".to_owned(),
+            ..Default::default()
+        };
+        let tx = Transaction::finished();
+        assert!(futures::executor::block_on(note.before_insert(&tx)).is_ok());
+        assert_eq!(note.title, "release notes");
+
+        let result = futures::executor::block_on(tx.insert(Note::default()));
+        let message = result.err().map(|err| err.message().to_owned());
+        assert_eq!(message.as_deref(), Some("the title should be nonempty"));
+
+        let result = futures::executor::block_on(Note::default().insert());
+        let message = result.err().map(|err| err.message().to_owned());
+        assert_eq!(
+            message.as_deref(),
+            Some("connection to the database is not available")
+        );
+    }
+
+    #[test]
+    fn it_checks_versions() {
+        let note = Note {
+            title: "draft".to_owned(),
+            ..Default::default()
+        };
+        assert!(
+            format_delete(&note).contains(r#"SET "deleted_at" = coalesce("deleted_at", now())"#)
+        );
+        assert!(check_version(1, &note, 0).is_ok());
+        let err = check_version(0, &note, 0).unwrap_err();
+        assert!(OptimisticLockError::is_conflict(&err));
+
+        let conflict = err.downcast_ref::<OptimisticLockError>().unwrap();
+        assert_eq!(conflict.model_name(), "note");
+        assert_eq!(conflict.version(), 0);
+    }
+}
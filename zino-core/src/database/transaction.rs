@@ -0,0 +1,133 @@
+use super::{postgres, schema, Schema};
+use crate::{error::Error, format, request::Validation, Map};
+use futures::lock::Mutex;
+use sqlx::Postgres;
+use std::{fmt, sync::Arc};
+
+/// A database transaction shared by multiple model operations.
+///
+/// The connection is locked only while a statement is executed, and the lifecycle hooks
+/// are passed the handle itself. So the hooks can execute statements in the same transaction
+/// without deadlocks, and the concurrent operations on the handle are serialized.
+#[derive(Clone)]
+pub struct Transaction {
+    /// Inner transaction, which is taken out when it is committed or rolled back.
+    inner: Arc<Mutex<Option<sqlx::Transaction<'static, Postgres>>>>,
+}
+
+impl Transaction {
+    /// Creates a new instance.
+    #[inline]
+    pub(super) fn new(transaction: sqlx::Transaction<'static, Postgres>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Some(transaction))),
+        }
+    }
+
+    /// Creates a finished instance, which is used to run the lifecycle hooks in the tests.
+    #[cfg(test)]
+    pub(super) fn finished() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Executes the query in the transaction, and returns the total number of rows affected.
+    pub async fn execute(&self, query: &str, params: Option<&Map>) -> Result<u64, Error> {
+        let sql = format::format_query(query, params);
+        self.execute_sql(&sql).await
+    }
+
+    /// Inserts the model into the table in the transaction.
+    pub async fn insert<M: Schema>(&self, mut model: M) -> Result<(), Error> {
+        model.assign_primary_key()?;
+        model.before_insert(self).await?;
+        model.validate().map_err(Validation::into_error)?;
+        let sql = schema::format_insert(&model)?;
+        let rows_affected = self.execute_sql(&sql).await?;
+        schema::check_rows_affected(rows_affected)?;
+        model.after_insert(self).await
+    }
+
+    /// Updates the model in the table in the transaction.
+    /// It returns an [`OptimisticLockError`](super::OptimisticLockError)
+    /// if the version column is specified and the row has been updated by others.
+    pub async fn update<M: Schema>(&self, mut model: M) -> Result<(), Error> {
+        model.before_update(self).await?;
+        model.validate().map_err(Validation::into_error)?;
+        let (sql, version) = schema::format_update(&model)?;
+        let rows_affected = self.execute_sql(&sql).await?;
+        schema::check_version(rows_affected, &model, version)?;
+        model.after_update(self).await
+    }
+
+    /// Deletes the model in the table in the transaction.
+    /// The row is marked as deleted if the soft delete column is specified.
+    pub async fn delete<M: Schema>(&self, model: &M) -> Result<(), Error> {
+        model.before_delete(self).await?;
+        let sql = schema::format_delete(model);
+        let rows_affected = self.execute_sql(&sql).await?;
+        schema::check_rows_affected(rows_affected)?;
+        model.after_delete(self).await
+    }
+
+    /// Commits the transaction.
+    pub(super) async fn commit(&self) -> Result<(), Error> {
+        self.take().await?.commit().await.map_err(Error::from)
+    }
+
+    /// Rolls back the transaction.
+    pub(super) async fn rollback(&self) -> Result<(), Error> {
+        self.take().await?.rollback().await.map_err(Error::from)
+    }
+
+    /// Executes the SQL statement while holding the connection.
+    async fn execute_sql(&self, sql: &str) -> Result<u64, Error> {
+        let mut guard = self.inner.lock().await;
+        let transaction = guard
+            .as_mut()
+            .ok_or_else(|| Error::new("the transaction has been finished"))?;
+        let query_result = postgres::one_off_query(sql).execute(transaction).await?;
+        Ok(query_result.rows_affected())
+    }
+
+    /// Takes the inner transaction out so that it can not be used any more.
+    async fn take(&self) -> Result<sqlx::Transaction<'static, Postgres>, Error> {
+        self.inner
+            .lock()
+            .await
+            .take()
+            .ok_or_else(|| Error::new("the transaction has been finished"))
+    }
+}
+
+impl fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::{fixtures::Note, Schema};
+    use std::cell::Cell;
+
+    #[test]
+    fn it_runs_operations_in_transactions() {
+        let note = Note {
+            title: "draft".to_owned(),
+            ..Default::default()
+        };
+        let executed = Cell::new(false);
+        let result = futures::executor::block_on(Note::transaction(|tx| {
+            executed.set(true);
+            async move { tx.insert(note).await }
+        }));
+        let message = result.err().map(|err| err.message().to_owned());
+        assert_eq!(
+            message.as_deref(),
+            Some("connection to the database is not available")
+        );
+        assert!(!executed.get());
+    }
+}
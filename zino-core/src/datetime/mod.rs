@@ -1,12 +1,18 @@
-//! ISO 8601 combined date and time with local time zone.
+//! ISO 8601 combined date and time with a time zone offset.
 
-use chrono::{format::ParseError, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use crate::{application::Application, error::Error, extend::TomlTableExt};
+use chrono::{
+    format::ParseError, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, SecondsFormat,
+    TimeZone, Utc,
+};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
     fmt,
     ops::{Add, AddAssign, Sub, SubAssign},
     str::FromStr,
+    sync::OnceLock,
     time::Duration,
 };
 
@@ -14,15 +20,24 @@ mod duration;
 
 pub use duration::{parse_duration, ParseDurationError};
 
-/// A wrapper type for [`chrono::DateTime<Local>`](chrono::DateTime).
+/// A wrapper type for [`chrono::DateTime<FixedOffset>`](chrono::DateTime).
+///
+/// The offset is determined by the default time zone, which is specified by
+/// the `timezone` field in the config and falls back to UTC.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-pub struct DateTime(chrono::DateTime<Local>);
+pub struct DateTime(chrono::DateTime<FixedOffset>);
 
 impl DateTime {
-    /// Returns a new instance which corresponds to the current date.
+    /// Returns a new instance which corresponds to the current date in the default time zone.
     #[inline]
     pub fn now() -> Self {
-        Self(Local::now())
+        Self::now_in(default_timezone())
+    }
+
+    /// Returns a new instance which corresponds to the current date in the time zone.
+    #[inline]
+    pub fn now_in(tz: Tz) -> Self {
+        Self(fix_offset(Utc::now().with_timezone(&tz)))
     }
 
     /// Returns a new instance corresponding to a UTC date and time,
@@ -30,7 +45,7 @@ impl DateTime {
     #[inline]
     pub fn from_timestamp(secs: i64) -> Self {
         let dt = NaiveDateTime::from_timestamp_opt(secs, 0).unwrap_or_default();
-        Self(Local.from_utc_datetime(&dt))
+        Self::from_utc_datetime(&dt)
     }
 
     /// Returns a new instance corresponding to a UTC date and time,
@@ -38,7 +53,82 @@ impl DateTime {
     #[inline]
     pub fn from_timestamp_millis(millis: i64) -> Self {
         let dt = NaiveDateTime::from_timestamp_millis(millis).unwrap_or_default();
-        Self(Local.from_utc_datetime(&dt))
+        Self::from_utc_datetime(&dt)
+    }
+
+    /// Returns a new instance from a date and time in the default time zone.
+    fn from_local_datetime(dt: &NaiveDateTime) -> Result<Self, Error> {
+        default_timezone()
+            .from_local_datetime(dt)
+            .earliest()
+            .map(|dt| Self(fix_offset(dt)))
+            .ok_or_else(|| Error::new(format!("`{dt}` does not exist in the default time zone")))
+    }
+
+    /// Returns a new instance in the default time zone from a UTC date and time.
+    #[inline]
+    fn from_utc_datetime(dt: &NaiveDateTime) -> Self {
+        Self(fix_offset(default_timezone().from_utc_datetime(dt)))
+    }
+
+    /// Converts `self` to the same instant in the time zone.
+    #[inline]
+    pub fn with_timezone(&self, tz: Tz) -> Self {
+        Self(fix_offset(self.0.with_timezone(&tz)))
+    }
+
+    /// Returns the offset from UTC in seconds.
+    #[inline]
+    pub fn offset_seconds(&self) -> i32 {
+        self.0.offset().local_minus_utc()
+    }
+
+    /// Parses a date and time by trying the following formats in order:
+    ///
+    /// - RFC 3339, e.g. `2023-04-01T08:00:00+08:00`;
+    /// - `YYYY-MM-DD HH:MM:SS` or `YYYY-MM-DDTHH:MM:SS` with optional fractional seconds,
+    ///   which is interpreted in the default time zone;
+    /// - `YYYY-MM-DD`, which is interpreted as the midnight in the default time zone;
+    /// - RFC 2822, e.g. `Sat, 1 Apr 2023 08:00:00 +0000`;
+    /// - epoch seconds, or epoch milliseconds if the absolute value is not less than `10^11`.
+    ///
+    /// Two-digit years are rejected since they are ambiguous.
+    pub fn parse_flexible(s: &str) -> Result<Self, Error> {
+        let s = s.trim();
+        if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(s) {
+            return Ok(Self(datetime));
+        }
+        if has_full_year(s) {
+            for fmt in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+                if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+                    return Self::from_local_datetime(&dt);
+                }
+            }
+            if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") &&
+                let Some(dt) = date.and_hms_opt(0, 0, 0)
+            {
+                return Self::from_local_datetime(&dt);
+            }
+        }
+        if let Ok(datetime) = chrono::DateTime::parse_from_rfc2822(s) {
+            let year = s
+                .split_whitespace()
+                .skip_while(|token| token.parse::<u8>().is_err())
+                .nth(2);
+            if year.is_some_and(|year| year.len() >= 4) {
+                return Ok(Self(datetime));
+            }
+            return Err(Error::new(format!("two-digit year in `{s}` is ambiguous")));
+        }
+        if let Ok(timestamp) = s.parse::<i64>() {
+            return if timestamp.unsigned_abs() < 100_000_000_000 {
+                Ok(Self::from_timestamp(timestamp))
+            } else {
+                Ok(Self::from_timestamp_millis(timestamp))
+            };
+        }
+        let message = format!("`{s}` is not a supported date and time format");
+        Err(Error::new(message))
     }
 
     /// Returns the number of non-leap seconds since January 1, 1970 0:00:00 UTC.
@@ -53,18 +143,16 @@ impl DateTime {
         self.0.timestamp_millis()
     }
 
-    /// Parses an RFC 2822 date and time.
+    /// Parses an RFC 2822 date and time. The offset in the string is preserved.
     #[inline]
     pub fn parse_utc_str(s: &str) -> Result<Self, ParseError> {
-        let datetime = chrono::DateTime::parse_from_rfc2822(s)?;
-        Ok(Self(datetime.with_timezone(&Local)))
+        chrono::DateTime::parse_from_rfc2822(s).map(Self)
     }
 
-    /// Parses an RFC 3339 and ISO 8601 date and time.
+    /// Parses an RFC 3339 and ISO 8601 date and time. The offset in the string is preserved.
     #[inline]
     pub fn parse_iso_str(s: &str) -> Result<Self, ParseError> {
-        let datetime = chrono::DateTime::parse_from_rfc3339(s)?;
-        Ok(Self(datetime.with_timezone(&Local)))
+        chrono::DateTime::parse_from_rfc3339(s).map(Self)
     }
 
     /// Returns an RFC 2822 date and time string.
@@ -82,6 +170,72 @@ impl DateTime {
         datetime.to_rfc3339_opts(SecondsFormat::Millis, true)
     }
 
+    /// Adds a duration to `self`, returning `None` if overflow occurred.
+    #[inline]
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let duration = chrono::Duration::from_std(duration).ok()?;
+        self.0.checked_add_signed(duration).map(Self)
+    }
+
+    /// Subtracts a duration from `self`, returning `None` if overflow occurred.
+    #[inline]
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        let duration = chrono::Duration::from_std(duration).ok()?;
+        self.0.checked_sub_signed(duration).map(Self)
+    }
+
+    /// Returns the duration elapsed from an earlier date and time to `self`,
+    /// or zero if that date and time is later than `self`.
+    #[inline]
+    pub fn since(&self, earlier: Self) -> Duration {
+        self.0
+            .signed_duration_since(earlier.0)
+            .to_std()
+            .unwrap_or_default()
+    }
+
+    /// Returns a human-readable description relative to the current date,
+    /// such as `2 hours ago` or `in 5 minutes`.
+    #[inline]
+    pub fn humanize(&self) -> String {
+        self.humanize_from(Self::now())
+    }
+
+    /// Returns a human-readable description relative to the base date and time.
+    /// The difference is rounded to the nearest integer of the largest suitable unit.
+    pub fn humanize_from(&self, base: Self) -> String {
+        const UNITS: [(&str, u64, u64); 6] = [
+            ("second", 1_000, 60),
+            ("minute", 60_000, 60),
+            ("hour", 3_600_000, 24),
+            ("day", 86_400_000, 30),
+            ("month", 2_592_000_000, 12),
+            ("year", 31_536_000_000, u64::MAX),
+        ];
+        let millis = self
+            .timestamp_millis()
+            .saturating_sub(base.timestamp_millis());
+        let abs_millis = millis.unsigned_abs();
+        if abs_millis < 1_000 {
+            return "just now".to_owned();
+        }
+
+        let (mut value, mut unit) = (0, "");
+        for (name, unit_millis, max_value) in UNITS {
+            value = (abs_millis + unit_millis / 2) / unit_millis;
+            unit = name;
+            if value < max_value {
+                break;
+            }
+        }
+        let plural = if value == 1 { "" } else { "s" };
+        if millis > 0 {
+            format!("in {value} {unit}{plural}")
+        } else {
+            format!("{value} {unit}{plural} ago")
+        }
+    }
+
     /// Formats the combined date and time with the specified format string.
     /// See [`format::strftime`](chrono::format::strftime) for the supported escape sequences.
     #[inline]
@@ -107,18 +261,38 @@ impl Default for DateTime {
     }
 }
 
+impl From<chrono::DateTime<FixedOffset>> for DateTime {
+    fn from(dt: chrono::DateTime<FixedOffset>) -> Self {
+        Self(dt)
+    }
+}
+
 impl From<chrono::DateTime<Local>> for DateTime {
+    /// Converts the local date and time to the same instant in the default time zone.
     fn from(dt: chrono::DateTime<Local>) -> Self {
-        Self(dt)
+        Self(fix_offset(dt.with_timezone(&default_timezone())))
     }
 }
 
-impl From<DateTime> for chrono::DateTime<Local> {
+impl From<chrono::DateTime<Utc>> for DateTime {
+    /// Converts the UTC date and time to the same instant in the default time zone.
+    fn from(dt: chrono::DateTime<Utc>) -> Self {
+        Self(fix_offset(dt.with_timezone(&default_timezone())))
+    }
+}
+
+impl From<DateTime> for chrono::DateTime<FixedOffset> {
     fn from(dt: DateTime) -> Self {
         dt.0
     }
 }
 
+impl From<DateTime> for chrono::DateTime<Local> {
+    fn from(dt: DateTime) -> Self {
+        dt.0.with_timezone(&Local)
+    }
+}
+
 impl From<DateTime> for Value {
     fn from(dt: DateTime) -> Self {
         Value::String(dt.to_string())
@@ -129,7 +303,7 @@ impl FromStr for DateTime {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        chrono::DateTime::<Local>::from_str(s).map(Self)
+        chrono::DateTime::<FixedOffset>::from_str(s).map(Self)
     }
 }
 
@@ -174,3 +348,156 @@ impl SubAssign<Duration> for DateTime {
         *self = *self - rhs;
     }
 }
+
+/// Initializes the default time zone with the `timezone` field in the config.
+/// The time zone set by the first call is kept.
+pub(crate) fn init<APP: Application + ?Sized>() {
+    if let Some(timezone) = APP::config().get_str("timezone") {
+        let tz = timezone
+            .parse::<Tz>()
+            .unwrap_or_else(|err| panic!("invalid time zone `{timezone}`: {err}"));
+        DEFAULT_TIMEZONE.get_or_init(|| tz);
+    }
+}
+
+/// Returns the default time zone if it has been configured.
+#[inline]
+pub(crate) fn configured_timezone() -> Option<Tz> {
+    DEFAULT_TIMEZONE.get().copied()
+}
+
+/// Returns the default time zone, which falls back to UTC.
+#[inline]
+fn default_timezone() -> Tz {
+    configured_timezone().unwrap_or(Tz::UTC)
+}
+
+/// Returns `true` if the string starts with a four-digit year followed by `-`.
+fn has_full_year(s: &str) -> bool {
+    s.get(..5).is_some_and(|prefix| {
+        prefix.ends_with('-') && prefix[..4].bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// Converts the date and time to the one with a fixed offset.
+#[inline]
+fn fix_offset<T: TimeZone>(dt: chrono::DateTime<T>) -> chrono::DateTime<FixedOffset> {
+    let offset = dt.offset().fix();
+    dt.with_timezone(&offset)
+}
+
+/// Default time zone.
+static DEFAULT_TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::DateTime;
+    use chrono_tz::{America::New_York, Asia::Shanghai, UTC};
+    use std::time::Duration;
+
+    #[test]
+    fn it_converts_timezones() {
+        let datetime = DateTime::parse_iso_str("2023-04-01T08:00:00Z").unwrap();
+        let shanghai_datetime = datetime.with_timezone(Shanghai);
+        let new_york_datetime = shanghai_datetime.with_timezone(New_York);
+        assert_eq!(
+            shanghai_datetime.to_string(),
+            "2023-04-01T16:00:00.000000+08:00"
+        );
+        assert_eq!(
+            new_york_datetime.to_string(),
+            "2023-04-01T04:00:00.000000-04:00"
+        );
+        assert_eq!(new_york_datetime.offset_seconds(), -4 * 3600);
+        assert_eq!(shanghai_datetime, new_york_datetime);
+        assert_eq!(shanghai_datetime.timestamp(), datetime.timestamp());
+
+        let utc_datetime = new_york_datetime.with_timezone(UTC);
+        assert_eq!(utc_datetime.to_string(), "2023-04-01T08:00:00.000000+00:00");
+        assert_eq!(utc_datetime.to_iso_string(), "2023-04-01T08:00:00.000Z");
+        assert_eq!(
+            DateTime::parse_iso_str(&new_york_datetime.to_string()).unwrap(),
+            utc_datetime
+        );
+        assert_eq!(
+            serde_json::to_string(&new_york_datetime).unwrap(),
+            "\"2023-04-01T04:00:00-04:00\""
+        );
+    }
+
+    #[test]
+    fn it_parses_flexible_formats() {
+        let expected = DateTime::parse_iso_str("2023-04-01T08:00:00Z").unwrap();
+        let inputs = [
+            "2023-04-01T16:00:00+08:00",
+            "2023-04-01 08:00:00",
+            "2023-04-01T08:00:00.000",
+            "Sat, 1 Apr 2023 08:00:00 +0000",
+            "1680336000",
+            "1680336000000",
+        ];
+        for input in inputs {
+            assert_eq!(
+                DateTime::parse_flexible(input).unwrap(),
+                expected,
+                "{input}"
+            );
+        }
+        assert_eq!(
+            DateTime::parse_flexible("2023-04-01").unwrap(),
+            DateTime::parse_iso_str("2023-04-01T00:00:00Z").unwrap()
+        );
+        assert_eq!(
+            DateTime::parse_flexible("1680336000123")
+                .unwrap()
+                .timestamp_millis(),
+            1_680_336_000_123
+        );
+
+        assert!(DateTime::parse_flexible("23-04-01 08:00:00").is_err());
+        assert!(DateTime::parse_flexible("Sat, 1 Apr 23 08:00:00 +0000").is_err());
+        let err = DateTime::parse_flexible("yesterday").unwrap_err();
+        assert_eq!(
+            err.message(),
+            "`yesterday` is not a supported date and time format"
+        );
+    }
+
+    #[test]
+    fn it_humanizes_durations() {
+        let now = DateTime::parse_iso_str("2023-04-01T08:00:00Z").unwrap();
+        let one_minute = Duration::from_secs(60);
+        assert_eq!((now - one_minute).humanize_from(now), "1 minute ago");
+        assert_eq!((now + one_minute).humanize_from(now), "in 1 minute");
+        assert_eq!(
+            (now - Duration::from_millis(59_600)).humanize_from(now),
+            "1 minute ago"
+        );
+        assert_eq!(
+            (now - Duration::from_millis(1_400)).humanize_from(now),
+            "1 second ago"
+        );
+        assert_eq!(
+            (now + Duration::from_millis(999)).humanize_from(now),
+            "just now"
+        );
+        assert_eq!(
+            (now - Duration::from_secs(2 * 3600 + 20 * 60)).humanize_from(now),
+            "2 hours ago"
+        );
+        assert_eq!(
+            (now + Duration::from_secs(5 * 60)).humanize_from(now),
+            "in 5 minutes"
+        );
+        assert_eq!(
+            (now - Duration::from_secs(400 * 86400)).humanize_from(now),
+            "1 year ago"
+        );
+
+        let later = now.checked_add(Duration::from_millis(1_500)).unwrap();
+        assert_eq!(later.since(now), Duration::from_millis(1_500));
+        assert_eq!(now.since(later), Duration::ZERO);
+        assert_eq!(later.checked_sub(Duration::from_millis(1_500)), Some(now));
+        assert!(now.checked_add(Duration::MAX).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 //! Type-erased errors with tracing functionalities.
 use crate::SharedString;
-use std::{error, fmt};
+use std::{error, fmt, sync::Arc};
 
 mod source;
 
@@ -13,6 +13,8 @@ pub struct Error {
     message: SharedString,
     /// Error source.
     source: Option<Box<Error>>,
+    /// Typed error which the error is created from.
+    typed_error: Option<Arc<dyn error::Error + Send + Sync>>,
 }
 
 impl Error {
@@ -22,6 +24,18 @@ impl Error {
         Self {
             message: message.into(),
             source: None,
+            typed_error: None,
+        }
+    }
+
+    /// Creates a new instance from the typed error,
+    /// which can be recovered by [`downcast_ref()`](Error::downcast_ref).
+    #[inline]
+    pub fn from_typed<E: error::Error + Send + Sync + 'static>(err: E) -> Self {
+        Self {
+            message: err.to_string().into(),
+            source: err.source().map(|err| Box::new(Self::new(err.to_string()))),
+            typed_error: Some(Arc::new(err)),
         }
     }
 
@@ -31,6 +45,7 @@ impl Error {
         Self {
             message: message.into(),
             source: Some(Box::new(source.into())),
+            typed_error: None,
         }
     }
 
@@ -40,6 +55,7 @@ impl Error {
         Self {
             message: message.into(),
             source: Some(Box::new(self)),
+            typed_error: None,
         }
     }
 
@@ -68,6 +84,14 @@ impl Error {
     pub fn root_source(&self) -> Option<&Error> {
         self.sources().last()
     }
+
+    /// Returns a reference to the typed error of the type `E` in `self` or its sources,
+    /// if the error is created by [`from_typed()`](Error::from_typed).
+    #[inline]
+    pub fn downcast_ref<E: error::Error + 'static>(&self) -> Option<&E> {
+        self.sources()
+            .find_map(|err| err.typed_error.as_deref()?.downcast_ref::<E>())
+    }
 }
 
 impl<E: error::Error + 'static> From<E> for Error {
@@ -76,6 +100,7 @@ impl<E: error::Error + 'static> From<E> for Error {
         Self {
             message: err.to_string().into(),
             source: err.source().map(|err| Box::new(Self::new(err.to_string()))),
+            typed_error: None,
         }
     }
 }
@@ -30,6 +30,10 @@ pub trait HeaderMapExt {
     /// Gets the data type by parsing the `content-type` header.
     fn get_data_type(&self) -> Option<SharedString>;
 
+    /// Selects a data type from the supported types by parsing and comparing
+    /// the `accept` header. It returns `None` if any type is acceptable.
+    fn select_data_type<'a>(&self, supported_types: &[&'a str]) -> Option<&'a str>;
+
     /// Checks whether it has a `content-type: application/json` or similar header.
     fn has_json_content_type(&self) -> bool;
 
@@ -88,26 +92,40 @@ impl HeaderMapExt for HeaderMap {
             })
     }
 
+    #[inline]
     fn get_data_type(&self) -> Option<SharedString> {
-        let content_type = self.get_content_type()?;
-        let data_type = match content_type {
-            "application/json" | "application/problem+json" => "json".into(),
-            "application/jsonlines" | "application/x-ndjson" => "ndjson".into(),
-            "application/msgpack" | "application/x-msgpack" => "msgpack".into(),
-            "application/octet-stream" => "bytes".into(),
-            "application/x-www-form-urlencoded" => "form".into(),
-            "multipart/form-data" => "multipart".into(),
-            "text/csv" => "csv".into(),
-            "text/plain" => "text".into(),
-            _ => {
-                if content_type.starts_with("application/") && content_type.ends_with("+json") {
-                    "json".into()
+        self.get_content_type().map(parse_data_type)
+    }
+
+    fn select_data_type<'a>(&self, supported_types: &[&'a str]) -> Option<&'a str> {
+        let mut data_types = self
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())?
+            .split(',')
+            .filter_map(|s| {
+                let (media_type, quality) = if let Some((media_type, params)) = s.split_once(';') {
+                    let quality = params
+                        .split(';')
+                        .find_map(|param| param.trim().strip_prefix("q="))
+                        .and_then(|q| q.parse::<f32>().ok())
+                        .unwrap_or(1.0);
+                    (media_type.trim(), quality)
                 } else {
-                    content_type.to_owned().into()
+                    (s.trim(), 1.0)
+                };
+                if media_type == "*/*" {
+                    return Some((None, quality));
                 }
-            }
-        };
-        Some(data_type)
+                let data_type = parse_data_type(media_type);
+                supported_types
+                    .iter()
+                    .find(|&&supported_type| supported_type == data_type)
+                    .map(|&supported_type| (Some(supported_type), quality))
+            })
+            .filter(|&(_, quality)| quality > 0.0)
+            .collect::<Vec<_>>();
+        data_types.sort_by(|a, b| b.1.total_cmp(&a.1));
+        data_types.first().and_then(|&(data_type, _)| data_type)
     }
 
     fn has_json_content_type(&self) -> bool {
@@ -147,6 +165,27 @@ impl HeaderMapExt for HeaderMap {
     }
 }
 
+/// Parses the data type of a media type.
+fn parse_data_type(media_type: &str) -> SharedString {
+    match media_type {
+        "application/json" | "application/problem+json" => "json".into(),
+        "application/jsonlines" | "application/x-ndjson" => "ndjson".into(),
+        "application/msgpack" | "application/x-msgpack" => "msgpack".into(),
+        "application/octet-stream" => "bytes".into(),
+        "application/x-www-form-urlencoded" => "form".into(),
+        "multipart/form-data" => "multipart".into(),
+        "text/csv" => "csv".into(),
+        "text/plain" => "text".into(),
+        _ => {
+            if media_type.starts_with("application/") && media_type.ends_with("+json") {
+                "json".into()
+            } else {
+                media_type.to_owned().into()
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::HeaderMapExt;
@@ -176,4 +215,22 @@ mod tests {
         );
         assert_eq!(headers.select_language(&["en-US", "zh-CN"]), Some("en-US"),);
     }
+
+    #[test]
+    fn it_selects_data_type() {
+        let supported_types = ["json", "msgpack", "csv"];
+        let mut headers = HeaderMap::new();
+        assert_eq!(headers.select_data_type(&supported_types), None);
+
+        headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        assert_eq!(headers.select_data_type(&supported_types), None);
+
+        let header_value = "application/msgpack, application/json;q=0.9";
+        headers.insert(header::ACCEPT, HeaderValue::from_static(header_value));
+        assert_eq!(headers.select_data_type(&supported_types), Some("msgpack"));
+
+        let header_value = "text/html, application/json;q=0.8, text/csv;q=0.9, */*;q=0.1";
+        headers.insert(header::ACCEPT, HeaderValue::from_static(header_value));
+        assert_eq!(headers.select_data_type(&supported_types), Some("csv"));
+    }
 }
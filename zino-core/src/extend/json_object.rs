@@ -1,4 +1,4 @@
-use crate::{Map, Record};
+use crate::{Decimal, Map, Record};
 use serde_json::Value;
 
 /// Extension trait for [`Map`](crate::Map).
@@ -29,9 +29,21 @@ pub trait JsonObjectExt {
     /// represents it as `usize` if possible.
     fn get_usize(&self, key: &str) -> Option<usize>;
 
+    /// Extracts the integer value corresponding to the key and represents it as `i128`.
+    /// The integers beyond the range of `i64` and `u64` should be encoded as strings.
+    fn get_i128(&self, key: &str) -> Option<i128>;
+
+    /// Extracts the integer value corresponding to the key and represents it as `u128`.
+    /// The integers beyond the range of `u64` should be encoded as strings.
+    fn get_u128(&self, key: &str) -> Option<u128>;
+
     /// Extracts the float value corresponding to the key.
     fn get_f64(&self, key: &str) -> Option<f64>;
 
+    /// Extracts the number or numeric string corresponding to the key and
+    /// represents it as a decimal without the conversion to a float.
+    fn get_decimal(&self, key: &str) -> Option<Decimal>;
+
     /// Extracts the string corresponding to the key.
     fn get_str(&self, key: &str) -> Option<&str>;
 
@@ -41,11 +53,32 @@ pub trait JsonObjectExt {
     /// Extracts the object value corresponding to the key.
     fn get_object(&self, key: &str) -> Option<&Map>;
 
+    /// Looks up a nested value by the path, in which the keys are separated by `.`
+    /// and the array elements are referred by the indices, such as `items.0.name`.
+    fn get_path(&self, path: &str) -> Option<&Value>;
+
+    /// Extracts the string corresponding to the path.
+    fn get_path_str(&self, path: &str) -> Option<&str>;
+
+    /// Extracts the integer value corresponding to the path.
+    fn get_path_i64(&self, path: &str) -> Option<i64>;
+
     /// Inserts or updates a key/value pair into the map.
     /// If the map did have this key present, the value is updated and the old value is returned,
     /// otherwise `None` is returned.
     fn upsert(&mut self, key: impl Into<String>, value: impl Into<Value>) -> Option<Value>;
 
+    /// Merges another map into `self` recursively. The nested objects are merged and
+    /// the other values take precedence, while the arrays are merged by the strategy.
+    fn deep_merge(&mut self, other: Map, strategy: ArrayMergeStrategy);
+
+    /// Inserts the entries of another map whose keys are absent in `self`.
+    fn merge_if_absent(&mut self, other: Map);
+
+    /// Returns a copy of the map in which the values of the keys are replaced by `***`.
+    /// The keys are matched case-insensitively, including the ones in the nested objects.
+    fn redacted(&self, keys: &[&str]) -> Map;
+
     /// Consumes `self` and constructs an Avro record value.
     fn into_avro_record(self) -> Record;
 
@@ -62,7 +95,7 @@ impl JsonObjectExt for Map {
     #[inline]
     fn get_i32(&self, key: &str) -> Option<i32> {
         self.get(key)
-            .and_then(|v| v.as_u64())
+            .and_then(|v| v.as_i64())
             .and_then(|i| i32::try_from(i).ok())
     }
 
@@ -97,11 +130,53 @@ impl JsonObjectExt for Map {
             .and_then(|i| usize::try_from(i).ok())
     }
 
+    fn get_i128(&self, key: &str) -> Option<i128> {
+        match self.get(key)? {
+            Value::Number(n) => n
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| n.as_u64().map(i128::from)),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn get_u128(&self, key: &str) -> Option<u128> {
+        match self.get(key)? {
+            Value::Number(n) => n.as_u64().map(u128::from),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
     #[inline]
     fn get_f64(&self, key: &str) -> Option<f64> {
         self.get(key).and_then(|v| v.as_f64())
     }
 
+    fn get_decimal(&self, key: &str) -> Option<Decimal> {
+        match self.get(key)? {
+            Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    Some(Decimal::from(i))
+                } else if let Some(u) = n.as_u64() {
+                    Some(Decimal::from(u))
+                } else {
+                    // The shortest representation of the float is parsed
+                    // so that `0.1` is not expanded to its binary approximation.
+                    let s = n.to_string();
+                    Decimal::from_str_exact(&s)
+                        .or_else(|_| Decimal::from_scientific(&s))
+                        .ok()
+                }
+            }
+            Value::String(s) => Decimal::from_str_exact(s)
+                .or_else(|_| Decimal::from_scientific(s))
+                .ok(),
+            _ => None,
+        }
+    }
+
     #[inline]
     fn get_str(&self, key: &str) -> Option<&str> {
         self.get(key).and_then(|v| v.as_str())
@@ -117,11 +192,71 @@ impl JsonObjectExt for Map {
         self.get(key).and_then(|v| v.as_object())
     }
 
+    fn get_path(&self, path: &str) -> Option<&Value> {
+        let mut segments = path.split('.');
+        let mut value = self.get(segments.next()?)?;
+        for segment in segments {
+            value = match value {
+                Value::Object(map) => map.get(segment)?,
+                Value::Array(vec) => vec.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    #[inline]
+    fn get_path_str(&self, path: &str) -> Option<&str> {
+        self.get_path(path).and_then(|v| v.as_str())
+    }
+
+    #[inline]
+    fn get_path_i64(&self, path: &str) -> Option<i64> {
+        self.get_path(path).and_then(|v| v.as_i64())
+    }
+
     #[inline]
     fn upsert(&mut self, key: impl Into<String>, value: impl Into<Value>) -> Option<Value> {
         self.insert(key.into(), value.into())
     }
 
+    fn deep_merge(&mut self, other: Map, strategy: ArrayMergeStrategy) {
+        for (key, value) in other {
+            if let Some(current) = self.get_mut(&key) {
+                match (current, value) {
+                    (Value::Object(map), Value::Object(other)) => map.deep_merge(other, strategy),
+                    (Value::Array(vec), Value::Array(mut other))
+                        if strategy == ArrayMergeStrategy::Concat =>
+                    {
+                        vec.append(&mut other)
+                    }
+                    (current, value) => *current = value,
+                }
+            } else {
+                self.insert(key, value);
+            }
+        }
+    }
+
+    fn merge_if_absent(&mut self, other: Map) {
+        for (key, value) in other {
+            self.entry(key).or_insert(value);
+        }
+    }
+
+    fn redacted(&self, keys: &[&str]) -> Map {
+        self.iter()
+            .map(|(key, value)| {
+                let value = if keys.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                    Value::String("***".to_owned())
+                } else {
+                    redact_value(value, keys)
+                };
+                (key.to_owned(), value)
+            })
+            .collect()
+    }
+
     fn into_avro_record(self) -> Record {
         let mut record = Record::with_capacity(self.len());
         for (field, value) in self.into_iter() {
@@ -137,3 +272,172 @@ impl JsonObjectExt for Map {
         map
     }
 }
+
+/// Redacts the values of the keys in the nested objects.
+fn redact_value(value: &Value, keys: &[&str]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(map.redacted(keys)),
+        Value::Array(vec) => Value::Array(vec.iter().map(|v| redact_value(v, keys)).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Strategies to merge the arrays in [`deep_merge`](JsonObjectExt::deep_merge).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMergeStrategy {
+    /// The array is replaced by the other one.
+    #[default]
+    Replace,
+    /// The other array is appended to the array.
+    Concat,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArrayMergeStrategy, JsonObjectExt};
+    use crate::{Decimal, Map};
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_round_trips_boundary_integers() {
+        let json = format!(
+            r#"{{"i128_min":"{}","i32_min":{},"i64_max":{},"i64_min":{},"u128_max":"{}","u64_max":{}}}"#,
+            i128::MIN,
+            i32::MIN,
+            i64::MAX,
+            i64::MIN,
+            u128::MAX,
+            u64::MAX,
+        );
+        let map = serde_json::from_str::<Map>(&json).unwrap();
+        assert_eq!(map.get_u64("u64_max"), Some(u64::MAX));
+        assert_eq!(map.get_i64("u64_max"), None);
+        assert_eq!(map.get_i64("i64_min"), Some(i64::MIN));
+        assert_eq!(map.get_i64("i64_max"), Some(i64::MAX));
+        assert_eq!(map.get_i32("i32_min"), Some(i32::MIN));
+        let serialized = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<Map>(&serialized).unwrap(), map);
+        assert_eq!(map["u64_max"].to_string(), u64::MAX.to_string());
+        assert_eq!(map["i64_min"].to_string(), i64::MIN.to_string());
+
+        assert_eq!(map.get_u128("u64_max"), Some(u128::from(u64::MAX)));
+        assert_eq!(map.get_i128("u64_max"), Some(i128::from(u64::MAX)));
+        assert_eq!(map.get_i128("i64_min"), Some(i128::from(i64::MIN)));
+        assert_eq!(map.get_u128("i64_min"), None);
+        assert_eq!(map.get_u128("u128_max"), Some(u128::MAX));
+        assert_eq!(map.get_i128("i128_min"), Some(i128::MIN));
+
+        let map = serde_json::from_str::<Map>(
+            r#"{"price":19.99,"rate":"0.0000001","total":18446744073709551615,"name":"x"}"#,
+        )
+        .unwrap();
+        assert_eq!(map.get_decimal("price"), Decimal::from_str("19.99").ok());
+        assert_eq!(map.get_decimal("rate"), Decimal::from_str("0.0000001").ok());
+        assert_eq!(map.get_decimal("total"), Some(Decimal::from(u64::MAX)));
+        assert_eq!(map.get_decimal("name"), None);
+    }
+
+    #[test]
+    fn it_gets_nested_values() {
+        let map = json!({
+            "profile": {
+                "address": {
+                    "city": "Shanghai",
+                    "zip": 200000,
+                },
+            },
+            "items": [
+                { "name": "book", "tags": ["new", "sale"] },
+                { "name": "pen" },
+            ],
+        });
+        let map = map.as_object().unwrap();
+        assert_eq!(map.get_path_str("profile.address.city"), Some("Shanghai"));
+        assert_eq!(map.get_path_i64("profile.address.zip"), Some(200000));
+        assert!(map.get_path("profile.address").unwrap().is_object());
+        assert_eq!(map.get_path_str("items.0.name"), Some("book"));
+        assert_eq!(map.get_path_str("items.1.name"), Some("pen"));
+        assert_eq!(map.get_path_str("items.0.tags.1"), Some("sale"));
+
+        assert_eq!(map.get_path("profile.phone"), None);
+        assert_eq!(map.get_path("profile.address.city.name"), None);
+        assert_eq!(map.get_path("items.2.name"), None);
+        assert_eq!(map.get_path("items.first.name"), None);
+        assert_eq!(map.get_path_i64("profile.address.city"), None);
+        assert_eq!(map.get_path(""), None);
+    }
+
+    #[test]
+    fn it_merges_nested_objects() {
+        let mut config = json!({
+            "name": "data-cube",
+            "database": {
+                "host": "127.0.0.1",
+                "port": 5432,
+                "options": { "ssl": false },
+            },
+            "tags": ["core"],
+        });
+        let overrides = json!({
+            "database": {
+                "host": "10.0.0.1",
+                "options": { "timeout": 30 },
+            },
+            "tags": ["prod"],
+        });
+        let config = config.as_object_mut().unwrap();
+        let mut replaced = config.clone();
+        replaced.deep_merge(
+            overrides.as_object().cloned().unwrap(),
+            ArrayMergeStrategy::Replace,
+        );
+        assert_eq!(replaced.get_path_str("name"), Some("data-cube"));
+        assert_eq!(replaced.get_path_str("database.host"), Some("10.0.0.1"));
+        assert_eq!(replaced.get_path_i64("database.port"), Some(5432));
+        assert_eq!(
+            replaced.get_path("database.options.ssl"),
+            Some(&json!(false))
+        );
+        assert_eq!(replaced.get_path_i64("database.options.timeout"), Some(30));
+        assert_eq!(replaced["tags"], json!(["prod"]));
+
+        config.deep_merge(
+            overrides.as_object().cloned().unwrap(),
+            ArrayMergeStrategy::Concat,
+        );
+        assert_eq!(config["tags"], json!(["core", "prod"]));
+        assert_eq!(config["database"], replaced["database"]);
+    }
+
+    #[test]
+    fn it_merges_absent_keys() {
+        let mut map = json!({ "counter": 1, "status": "running" });
+        let defaults = json!({ "counter": 0, "status": "pending", "retries": 3 });
+        let map = map.as_object_mut().unwrap();
+        map.merge_if_absent(defaults.as_object().cloned().unwrap());
+        assert_eq!(map.get_i64("counter"), Some(1));
+        assert_eq!(map.get_str("status"), Some("running"));
+        assert_eq!(map.get_i64("retries"), Some(3));
+    }
+
+    #[test]
+    fn it_redacts_sensitive_keys() {
+        let map = json!({
+            "username": "alice",
+            "Password": "p@ssw0rd",
+            "profile": { "token": "zk_live_2023", "city": "Shanghai" },
+            "accounts": [{ "name": "github", "secret": "ghp_123" }],
+        });
+        let map = map.as_object().unwrap();
+        let redacted = map.redacted(&["password", "token", "secret"]);
+        assert_eq!(redacted.get_str("Password"), Some("***"));
+        assert_eq!(redacted.get_str("username"), Some("alice"));
+        assert_eq!(redacted.get_path_str("profile.token"), Some("***"));
+        assert_eq!(redacted.get_path_str("profile.city"), Some("Shanghai"));
+        assert_eq!(redacted.get_path_str("accounts.0.secret"), Some("***"));
+        assert_eq!(redacted.get_path_str("accounts.0.name"), Some("github"));
+        assert!(!format!("{redacted:?}").contains("p@ssw0rd"));
+        assert_eq!(map.get_str("Password"), Some("p@ssw0rd"));
+    }
+}
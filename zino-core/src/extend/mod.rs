@@ -7,5 +7,5 @@ mod toml_table;
 
 pub use avro_record::AvroRecordExt;
 pub use header_map::HeaderMapExt;
-pub use json_object::JsonObjectExt;
+pub use json_object::{ArrayMergeStrategy, JsonObjectExt};
 pub use toml_table::TomlTableExt;
@@ -0,0 +1,58 @@
+use crate::Map;
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// Appends a CSV record terminated by CRLF as specified in RFC 4180.
+/// A field is quoted if it contains commas, quotes or line breaks.
+pub(crate) fn write_record<T: AsRef<str>>(
+    buffer: &mut String,
+    fields: impl IntoIterator<Item = T>,
+) {
+    for (index, field) in fields.into_iter().enumerate() {
+        if index > 0 {
+            buffer.push(',');
+        }
+        let field = field.as_ref();
+        if field.contains([',', '"', '\r', '\n']) {
+            buffer.push('"');
+            buffer.push_str(&field.replace('"', "\"\""));
+            buffer.push('"');
+        } else {
+            buffer.push_str(field);
+        }
+    }
+    buffer.push_str("\r\n");
+}
+
+/// Formats a json value as a CSV field. Missing values and nulls are formatted as empty fields.
+pub(crate) fn format_field(value: Option<&Value>) -> Cow<'_, str> {
+    match value {
+        None | Some(Value::Null) => "".into(),
+        Some(Value::String(s)) => s.into(),
+        Some(value) => value.to_string().into(),
+    }
+}
+
+/// Encodes the json objects as CSV with the specified columns as the header.
+pub(crate) fn encode_rows(rows: &[&Map], columns: &[&str]) -> String {
+    let mut buffer = String::new();
+    write_record(&mut buffer, columns);
+    for row in rows {
+        let fields = columns.iter().map(|&col| format_field(row.get(col)));
+        write_record(&mut buffer, fields);
+    }
+    buffer
+}
+
+/// Encodes the json objects as CSV with the union of keys as the header.
+pub(crate) fn encode_objects(rows: &[&Map]) -> String {
+    let mut columns = Vec::new();
+    for row in rows {
+        for key in row.keys() {
+            if !columns.contains(&key.as_str()) {
+                columns.push(key.as_str());
+            }
+        }
+    }
+    encode_rows(rows, &columns)
+}
@@ -1,6 +1,9 @@
 //! Utilities for formatting and parsing.
 
 pub(crate) mod base64;
+pub(crate) mod csv;
+pub(crate) mod query_string;
+pub(crate) mod request_body;
 pub(crate) mod string_array;
 
 #[cfg(any(feature = "connector", feature = "orm"))]
@@ -0,0 +1,72 @@
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// Parses the query string as an instance of type `T`.
+///
+/// Repeated keys such as `tag=a&tag=b` are parsed as a sequence,
+/// and a key without the value such as `?flag` is parsed as `true`.
+pub(crate) fn parse_query<T: DeserializeOwned>(query: &str) -> Result<T, serde_qs::Error> {
+    let pairs = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "true")))
+        .collect::<Vec<_>>();
+    let mut counts = HashMap::new();
+    for (key, _) in pairs.iter() {
+        *counts.entry(*key).or_insert(0) += 1;
+    }
+
+    let mut indices = HashMap::new();
+    let mut query = String::with_capacity(query.len());
+    for (key, value) in pairs {
+        if !query.is_empty() {
+            query.push('&');
+        }
+        query.push_str(key);
+        if counts[key] > 1 && !key.ends_with(']') {
+            let index = indices.entry(key).or_insert(0);
+            query.push_str(&format!("[{index}]"));
+            *index += 1;
+        }
+        query.push('=');
+        query.push_str(value);
+    }
+    serde_qs::from_str(&query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_query;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, PartialEq, Deserialize)]
+    struct TaskQuery {
+        status: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+        #[serde(default)]
+        archived: bool,
+        limit: Option<u64>,
+    }
+
+    #[test]
+    fn it_parses_typed_query() {
+        let query = parse_query::<TaskQuery>("tags=urgent&tags=backend&archived&limit=20").unwrap();
+        assert_eq!(
+            query,
+            TaskQuery {
+                status: None,
+                tags: vec!["urgent".to_owned(), "backend".to_owned()],
+                archived: true,
+                limit: Some(20),
+            }
+        );
+
+        let query = parse_query::<TaskQuery>("status=done&tags=urgent").unwrap();
+        assert_eq!(query.status.as_deref(), Some("done"));
+        assert_eq!(query.tags, ["urgent"]);
+        assert!(!query.archived);
+
+        assert!(parse_query::<TaskQuery>("limit=all").is_err());
+    }
+}
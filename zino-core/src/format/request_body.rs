@@ -0,0 +1,134 @@
+use crate::{error::Error, request::BodyLimitError};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde::de::DeserializeOwned;
+use std::io::Read;
+
+/// Data types of the request body which can be deserialized.
+pub(crate) const SUPPORTED_DATA_TYPES: [&str; 3] = ["form", "json", "msgpack"];
+
+/// Content encodings of the request body which can be decoded.
+pub(crate) const SUPPORTED_CONTENT_ENCODINGS: [&str; 5] =
+    ["br", "deflate", "gzip", "identity", "x-gzip"];
+
+/// Returns `true` if the request body of the data type can be deserialized.
+#[inline]
+pub(crate) fn is_supported(data_type: &str) -> bool {
+    SUPPORTED_DATA_TYPES.contains(&data_type)
+}
+
+/// Returns `true` if all the content encodings of the request body can be decoded.
+pub(crate) fn is_supported_encoding(content_encoding: &str) -> bool {
+    content_encoding.split(',').all(|encoding| {
+        let encoding = encoding.trim().to_ascii_lowercase();
+        SUPPORTED_CONTENT_ENCODINGS.contains(&encoding.as_str())
+    })
+}
+
+/// Decodes the request body with the content encodings in the reverse order they were applied.
+/// The decoded body is capped by `max_size` in bytes to prevent decompression bombs.
+pub(crate) fn decode_body(
+    content_encoding: &str,
+    mut bytes: Vec<u8>,
+    max_size: u64,
+) -> Result<Vec<u8>, Error> {
+    for encoding in content_encoding.rsplit(',') {
+        let encoding = encoding.trim().to_ascii_lowercase();
+        let decoder: Box<dyn Read + '_> = match encoding.as_str() {
+            "br" => Box::new(brotli::Decompressor::new(bytes.as_slice(), 4096)),
+            "deflate" => Box::new(ZlibDecoder::new(bytes.as_slice())),
+            "gzip" | "x-gzip" => Box::new(GzDecoder::new(bytes.as_slice())),
+            "identity" => continue,
+            _ => {
+                let message =
+                    format!("decoding of the content encoding `{encoding}` is unsupported");
+                return Err(Error::new(message));
+            }
+        };
+        let mut decoded_bytes = Vec::new();
+        decoder
+            .take(max_size.saturating_add(1))
+            .read_to_end(&mut decoded_bytes)
+            .map_err(|err| {
+                let message = format!("fail to decode the request body with `{encoding}`");
+                Error::with_source(message, err)
+            })?;
+        if decoded_bytes.len() as u64 > max_size {
+            return Err(Error::from_typed(BodyLimitError::TooLarge(max_size)));
+        }
+        bytes = decoded_bytes;
+    }
+    Ok(bytes)
+}
+
+/// Deserializes the request body of the data type as an instance of type `T`.
+pub(crate) fn parse_body<T: DeserializeOwned>(data_type: &str, bytes: &[u8]) -> Result<T, Error> {
+    match data_type {
+        "form" => serde_urlencoded::from_bytes(bytes).map_err(Error::from),
+        "json" => serde_json::from_slice(bytes).map_err(Error::from),
+        "msgpack" => rmp_serde::from_slice(bytes).map_err(Error::from),
+        _ => {
+            let message = format!("deserialization of the data type `{data_type}` is unsupported");
+            Err(Error::new(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_body, is_supported, is_supported_encoding, parse_body};
+    use crate::{extend::JsonObjectExt, request::BodyLimitError, Map};
+    use flate2::{write::GzEncoder, Compression};
+    use serde_json::json;
+    use std::io::Write;
+
+    #[test]
+    fn it_parses_json_and_msgpack_bodies() {
+        let payload = json!({
+            "name": "union-query",
+            "limit": 10,
+            "archived": false,
+            "tags": ["zino", "sql"],
+            "filters": { "status": "active", "score": 3.5 },
+        });
+        let json_bytes = serde_json::to_vec(&payload).unwrap();
+        let msgpack_bytes = rmp_serde::to_vec_named(&payload).unwrap();
+
+        let json_body = parse_body::<Map>("json", &json_bytes).unwrap();
+        let msgpack_body = parse_body::<Map>("msgpack", &msgpack_bytes).unwrap();
+        assert_eq!(json_body, msgpack_body);
+        assert_eq!(msgpack_body.get_str("name"), Some("union-query"));
+        assert_eq!(msgpack_body.get_u64("limit"), Some(10));
+
+        assert!(is_supported("form"));
+        assert!(!is_supported("csv"));
+        assert!(parse_body::<Map>("text/xml", b"<task/>").is_err());
+    }
+
+    #[test]
+    fn it_decodes_compressed_bodies() {
+        let payload = json!({
+            "name": "union-query",
+            "tags": ["zino", "sql"],
+        });
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(payload.to_string().as_bytes()).unwrap();
+        let gzip_bytes = encoder.finish().unwrap();
+        let bytes = decode_body("gzip", gzip_bytes, 1024).unwrap();
+        let body = parse_body::<Map>("json", &bytes).unwrap();
+        assert_eq!(Some(&body), payload.as_object());
+        assert_eq!(body.get_str("name"), Some("union-query"));
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&vec![b' '; 1024 * 1024]).unwrap();
+        let bomb_bytes = encoder.finish().unwrap();
+        assert!(bomb_bytes.len() < 1024 * 8);
+        let err = decode_body("gzip", bomb_bytes, 1024 * 8).unwrap_err();
+        assert!(BodyLimitError::is_too_large(&err));
+
+        assert!(is_supported_encoding("gzip"));
+        assert!(is_supported_encoding("deflate, br"));
+        assert!(!is_supported_encoding("zstd"));
+        assert!(decode_body("identity", b"{}".to_vec(), 2).is_ok());
+        assert!(decode_body("gzip", b"{}".to_vec(), 1024).is_err());
+    }
+}
@@ -56,6 +56,9 @@ pub type Map = serde_json::Map<String, serde_json::Value>;
 /// A schema-less Avro record value.
 pub type Record = Vec<(String, apache_avro::types::Value)>;
 
+/// A fixed-precision decimal number.
+pub type Decimal = rust_decimal::Decimal;
+
 /// A Universally Unique Identifier (UUID).
 pub type Uuid = uuid::Uuid;
 
@@ -11,7 +11,7 @@ mod row;
 
 pub use column::{Column, EncodeColumn};
 pub use mutation::Mutation;
-pub use query::Query;
+pub use query::{LikeMode, Query};
 pub use row::DecodeRow;
 
 /// General data model.
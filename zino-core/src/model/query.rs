@@ -6,6 +6,14 @@ use serde_json::Value;
 pub struct Query {
     // Projection fields.
     fields: Vec<String>,
+    // A flag to indicate whether the duplicate rows are removed.
+    distinct: bool,
+    // A flag to indicate whether the soft-deleted rows are included.
+    with_trashed: bool,
+    // A flag to indicate whether the query is routed to the primary database.
+    primary: bool,
+    // Relations to be eager-loaded.
+    relations: Vec<String>,
     // Filters.
     filters: Map,
     // Sort order.
@@ -14,6 +22,8 @@ pub struct Query {
     limit: u64,
     // Offset.
     offset: u64,
+    // Union queries with a flag to indicate whether the duplicate rows are kept.
+    unions: Vec<(Query, bool)>,
 }
 
 impl Query {
@@ -22,10 +32,15 @@ impl Query {
     pub fn new(filters: Map) -> Self {
         Self {
             fields: Vec::new(),
+            distinct: false,
+            with_trashed: false,
+            primary: false,
+            relations: Vec::new(),
             filters,
             sort_order: (None, false),
             limit: 10,
             offset: 0,
+            unions: Vec::new(),
         }
     }
 
@@ -33,6 +48,7 @@ impl Query {
     #[must_use]
     pub fn read_map(&mut self, data: &Map) -> Validation {
         let mut validation = Validation::new();
+        let mut page = None;
         let filters = &mut self.filters;
         for (key, value) in data {
             match key.as_str() {
@@ -51,11 +67,19 @@ impl Query {
                         self.sort_order.1 = ascending;
                     }
                 }
-                "limit" => {
+                "limit" | "per_page" => {
                     if let Some(result) = Validation::parse_u64(value) {
                         match result {
                             Ok(limit) => self.limit = limit,
-                            Err(err) => validation.record_fail("limit", err),
+                            Err(err) => validation.record_fail(key.to_owned(), err),
+                        }
+                    }
+                }
+                "page" => {
+                    if let Some(result) = Validation::parse_u64(value) {
+                        match result {
+                            Ok(value) => page = Some(value.max(1)),
+                            Err(err) => validation.record_fail("page", err),
                         }
                     }
                 }
@@ -101,6 +125,9 @@ impl Query {
                 }
             }
         }
+        if let Some(page) = page {
+            self.offset = (page - 1).saturating_mul(self.limit);
+        }
         validation
     }
 
@@ -129,12 +156,249 @@ impl Query {
         })
     }
 
+    /// Selects the distinct rows for the columns. The sort column should be in the list,
+    /// since it is required by `SELECT DISTINCT`, or the query is rejected with an error.
+    #[inline]
+    pub fn distinct(&mut self, columns: &[&str]) {
+        self.fields = columns.iter().map(|&key| key.to_owned()).collect();
+        self.distinct = true;
+    }
+
+    /// Includes the soft-deleted rows, which are excluded by default.
+    #[inline]
+    pub fn with_trashed(&mut self) {
+        self.with_trashed = true;
+    }
+
+    /// Routes the query to the primary database instead of the read replicas,
+    /// which is required to read your own writes.
+    #[inline]
+    pub fn primary(&mut self) {
+        self.primary = true;
+    }
+
+    /// Eager-loads the relation declared for the model,
+    /// which avoids the `N+1` problem when the related models are loaded for each row.
+    #[inline]
+    pub fn with(&mut self, relation: impl Into<String>) {
+        let relation = relation.into();
+        if !self.relations.contains(&relation) {
+            self.relations.push(relation);
+        }
+    }
+
     /// Adds a key-value pair to the query filters.
     #[inline]
     pub fn add_filter(&mut self, key: impl Into<String>, value: impl Into<Value>) {
         self.filters.upsert(key, value);
     }
 
+    /// Adds a filter with the operator for the column. Multiple operators
+    /// for the same column are combined with `AND`.
+    pub fn add_filter_operator(
+        &mut self,
+        column: impl Into<String>,
+        operator: &str,
+        value: impl Into<Value>,
+    ) {
+        let column = column.into();
+        if let Some(Value::Object(filter)) = self.filters.get_mut(&column) {
+            filter.upsert(operator, value);
+        } else {
+            self.filters
+                .upsert(column, Map::from_entry(operator, value));
+        }
+    }
+
+    /// Adds a filter which matches the rows whose column value is in the list.
+    /// The values are bound as params, and an empty list matches nothing.
+    #[inline]
+    pub fn add_filter_in(&mut self, column: impl Into<String>, values: &[Value]) {
+        self.add_filter_operator(column, "$in", values);
+    }
+
+    /// Adds a filter which matches the rows whose column value is not in the list.
+    /// The values are bound as params, and an empty list matches everything.
+    #[inline]
+    pub fn add_filter_not_in(&mut self, column: impl Into<String>, values: &[Value]) {
+        self.add_filter_operator(column, "$nin", values);
+    }
+
+    /// Adds a filter which matches the rows whose column value is within the range,
+    /// including both bounds.
+    #[inline]
+    pub fn add_filter_between(
+        &mut self,
+        column: impl Into<String>,
+        low: impl Into<Value>,
+        high: impl Into<Value>,
+    ) {
+        let column = column.into();
+        self.add_filter_operator(column.as_str(), "$gte", low);
+        self.add_filter_operator(column, "$lte", high);
+    }
+
+    /// Adds a filter which matches the rows whose column value is greater than the value.
+    #[inline]
+    pub fn add_filter_gt(&mut self, column: impl Into<String>, value: impl Into<Value>) {
+        self.add_filter_operator(column, "$gt", value);
+    }
+
+    /// Adds a filter which matches the rows whose column value is greater than
+    /// or equal to the value.
+    #[inline]
+    pub fn add_filter_gte(&mut self, column: impl Into<String>, value: impl Into<Value>) {
+        self.add_filter_operator(column, "$gte", value);
+    }
+
+    /// Adds a filter which matches the rows whose column value is less than the value.
+    #[inline]
+    pub fn add_filter_lt(&mut self, column: impl Into<String>, value: impl Into<Value>) {
+        self.add_filter_operator(column, "$lt", value);
+    }
+
+    /// Adds a filter which matches the rows whose column value is less than
+    /// or equal to the value.
+    #[inline]
+    pub fn add_filter_lte(&mut self, column: impl Into<String>, value: impl Into<Value>) {
+        self.add_filter_operator(column, "$lte", value);
+    }
+
+    /// Adds a filter for the nested field of a JSON column. The path is specified
+    /// in the dot notation such as `profile.address.country`, where the first segment
+    /// is the column name, and a numeric segment refers to an array index.
+    pub fn add_json_filter(&mut self, path: impl Into<String>, value: impl Into<Value>) {
+        if let Some(Value::Object(filter)) = self.filters.get_mut("$json") {
+            filter.upsert(path, value);
+        } else {
+            self.filters.upsert("$json", Map::from_entry(path, value));
+        }
+    }
+
+    /// Adds a filter which matches the rows whose column value contains the value.
+    /// It supports both JSON columns and array columns.
+    pub fn add_filter_contains(&mut self, column: impl Into<String>, value: impl Into<Value>) {
+        if let Some(Value::Object(filter)) = self.filters.get_mut("$contains") {
+            filter.upsert(column, value);
+        } else {
+            self.filters
+                .upsert("$contains", Map::from_entry(column, value));
+        }
+    }
+
+    /// Adds a filter which matches the column value against the term with the `LIKE` operator.
+    /// The wildcards `%` and `_` in the term are escaped, so it is always matched literally.
+    #[inline]
+    pub fn add_filter_like(&mut self, column: impl Into<String>, term: &str, mode: LikeMode) {
+        self.add_pattern_filter("$like", column.into(), term, mode);
+    }
+
+    /// Adds a filter which matches the column value against the term case-insensitively.
+    /// The wildcards `%` and `_` in the term are escaped, so it is always matched literally.
+    #[inline]
+    pub fn add_filter_ilike(&mut self, column: impl Into<String>, term: &str, mode: LikeMode) {
+        self.add_pattern_filter("$ilike", column.into(), term, mode);
+    }
+
+    /// Adds a pattern filter for the `LIKE` or `ILIKE` operator.
+    fn add_pattern_filter(&mut self, key: &str, column: String, term: &str, mode: LikeMode) {
+        let pattern = mode.pattern(term);
+        if let Some(Value::Object(filter)) = self.filters.get_mut(key) {
+            filter.upsert(column, pattern);
+        } else {
+            self.filters.upsert(key, Map::from_entry(column, pattern));
+        }
+    }
+
+    /// Adds a raw SQL fragment as a filter. The placeholders `$1`, `$2`, ... in the fragment
+    /// refer to the params in order, and they are renumbered when the filters are combined.
+    ///
+    /// # Security
+    ///
+    /// The fragment is trusted and inserted into the SQL as it is, so it must never be built
+    /// from user input. The params are untrusted and always bound to the statement
+    /// instead of being interpolated.
+    pub fn add_raw_filter(&mut self, fragment: impl Into<String>, params: &[Value]) {
+        let mut filter = Map::new();
+        filter.upsert("$fragment", fragment.into());
+        filter.upsert("$params", params);
+        if let Some(Value::Array(filters)) = self.filters.get_mut("$raw") {
+            filters.push(filter.into());
+        } else {
+            self.filters.upsert("$raw", vec![Value::from(filter)]);
+        }
+    }
+
+    /// Adds a full-text search filter for the columns.
+    /// The search term is escaped when the SQL expression is generated.
+    #[inline]
+    pub fn search(&mut self, columns: &[&str], term: &str) {
+        let mut text_search = Map::new();
+        text_search.upsert("$fields", columns);
+        text_search.upsert("$search", term);
+        self.filters.upsert("$text", text_search);
+    }
+
+    /// Groups the rows by the columns.
+    #[inline]
+    pub fn group_by(&mut self, columns: &[&str]) {
+        self.filters.upsert("$group", columns);
+    }
+
+    /// Adds a filter for the groups. Aggregate expressions such as `count(*)` or `max(priority)`
+    /// can be used as the keys and compared with numeric values. Only `avg`, `count`, `max`,
+    /// `min` and `sum` of a column are supported, and the other expressions are ignored.
+    #[inline]
+    pub fn having(&mut self, filter: Map) {
+        self.filters.upsert("$match", filter);
+    }
+
+    /// Sets the cursor for the keyset pagination to fetch the rows after the value.
+    #[inline]
+    pub fn cursor_after(&mut self, column: &str, value: impl Into<Value>) {
+        self.set_cursor(&[column], vec![value.into()], true);
+    }
+
+    /// Sets the cursor for the keyset pagination to fetch the rows before the value.
+    #[inline]
+    pub fn cursor_before(&mut self, column: &str, value: impl Into<Value>) {
+        self.set_cursor(&[column], vec![value.into()], false);
+    }
+
+    /// Sets a composite cursor for the keyset pagination. If the sort order is not specified,
+    /// the rows are ordered by the columns ascendingly for `after`, and descendingly otherwise.
+    pub fn set_cursor(&mut self, columns: &[&str], values: Vec<Value>, after: bool) {
+        let mut cursor = Map::new();
+        cursor.upsert("$columns", columns);
+        cursor.upsert("$values", values);
+        cursor.upsert("$after", after);
+        self.filters.upsert("$cursor", cursor);
+    }
+
+    /// Extracts the cursor values for the next page from the last row of the current page.
+    pub fn next_cursor(columns: &[&str], page: &[Map]) -> Option<Vec<Value>> {
+        let row = page.last()?;
+        columns
+            .iter()
+            .map(|&column| row.get(column).cloned())
+            .collect()
+    }
+
+    /// Combines the rows selected by the other query with `UNION`, which removes duplicate rows.
+    /// The projection should match in count and column types. The sort order, limit and offset
+    /// of `self` are applied to the combined result, while those of the other query are ignored.
+    #[inline]
+    pub fn union(&mut self, other: Query) {
+        self.unions.push((other, false));
+    }
+
+    /// Combines the rows selected by the other query with `UNION ALL`,
+    /// which keeps duplicate rows. See [`union()`](Self::union) for the details.
+    #[inline]
+    pub fn union_all(&mut self, other: Query) {
+        self.unions.push((other, true));
+    }
+
     /// Moves all elements from the `filters` into `self`.
     #[inline]
     pub fn append_filters(&mut self, filters: &mut Map) {
@@ -165,6 +429,36 @@ impl Query {
         self.fields.as_slice()
     }
 
+    /// Returns `true` if the duplicate rows are removed.
+    #[inline]
+    pub fn is_distinct(&self) -> bool {
+        self.distinct
+    }
+
+    /// Returns `true` if the soft-deleted rows are included.
+    #[inline]
+    pub fn includes_trashed(&self) -> bool {
+        self.with_trashed
+    }
+
+    /// Returns `true` if the query is routed to the primary database.
+    #[inline]
+    pub fn requires_primary(&self) -> bool {
+        self.primary
+    }
+
+    /// Returns the relations to be eager-loaded.
+    #[inline]
+    pub fn relations(&self) -> &[String] {
+        self.relations.as_slice()
+    }
+
+    /// Returns the union queries with a flag to indicate whether the duplicate rows are kept.
+    #[inline]
+    pub fn unions(&self) -> &[(Query, bool)] {
+        self.unions.as_slice()
+    }
+
     /// Returns a reference to the filters.
     #[inline]
     pub fn filters(&self) -> &Map {
@@ -189,6 +483,41 @@ impl Query {
     pub fn offset(&self) -> u64 {
         self.offset
     }
+
+    /// Returns the page number starting from 1, which is derived from the limit and offset.
+    #[inline]
+    pub fn page(&self) -> u64 {
+        self.offset.checked_div(self.limit).unwrap_or_default() + 1
+    }
+}
+
+/// Matching modes for the `LIKE` operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LikeMode {
+    /// Matches the values starting with the term.
+    StartsWith,
+    /// Matches the values ending with the term.
+    EndsWith,
+    /// Matches the values containing the term.
+    Contains,
+    /// Matches the values equal to the term.
+    Exact,
+}
+
+impl LikeMode {
+    /// Escapes the wildcards in the term and returns the pattern for the mode.
+    pub fn pattern(self, term: &str) -> String {
+        let term = term
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        match self {
+            LikeMode::StartsWith => term + "%",
+            LikeMode::EndsWith => "%".to_owned() + &term,
+            LikeMode::Contains => format!("%{term}%"),
+            LikeMode::Exact => term,
+        }
+    }
 }
 
 impl Default for Query {
@@ -196,10 +525,15 @@ impl Default for Query {
     fn default() -> Self {
         Self {
             fields: Vec::new(),
+            distinct: false,
+            with_trashed: false,
+            primary: false,
+            relations: Vec::new(),
             filters: Map::new(),
             sort_order: (None, false),
             limit: 10,
             offset: 0,
+            unions: Vec::new(),
         }
     }
 }
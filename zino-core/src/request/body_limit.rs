@@ -0,0 +1,198 @@
+use crate::{error::Error, extend::TomlTableExt, state::State};
+use bytes::Bytes;
+use futures::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use std::{error, fmt, sync::LazyLock, time::Duration};
+use toml::value::Table;
+
+/// Limits on reading the request body.
+///
+/// The shared limits are configured by `server.max-body-size` in bytes and
+/// `server.body-read-timeout`, which is the max idle duration waiting for the next chunk
+/// of the body. They can be overridden per route by inserting an instance
+/// into the request extensions.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit {
+    /// Max size of the body in bytes.
+    max_size: u64,
+    /// Max idle duration waiting for the next chunk of the body.
+    read_timeout: Duration,
+}
+
+impl BodyLimit {
+    /// Creates a new instance with the max size in bytes and the read timeout.
+    #[inline]
+    pub fn new(max_size: u64, read_timeout: Duration) -> Self {
+        Self {
+            max_size,
+            read_timeout,
+        }
+    }
+
+    /// Creates a new instance with the config.
+    pub fn with_config(config: &Table) -> Self {
+        let max_size = config.get_u64("max-body-size").unwrap_or(100 * 1024 * 1024);
+        let read_timeout = config
+            .get_duration("body-read-timeout")
+            .unwrap_or_else(|| Duration::from_secs(10));
+        Self::new(max_size, read_timeout)
+    }
+
+    /// Returns a reference to the shared limits.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        LazyLock::force(&SHARED_BODY_LIMIT)
+    }
+
+    /// Returns the max size of the body in bytes.
+    #[inline]
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Returns the max idle duration waiting for the next chunk of the body.
+    #[inline]
+    pub fn read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
+    /// Checks the `content-length` of the body before reading it.
+    pub fn check_content_length(&self, content_length: Option<u64>) -> Result<(), Error> {
+        match content_length {
+            Some(length) if length > self.max_size => {
+                Err(Error::from_typed(BodyLimitError::TooLarge(self.max_size)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Limits the size of the body stream. Once the total size exceeds the max size,
+    /// it yields an error and stops polling the inner stream.
+    pub fn limit_stream(
+        &self,
+        stream: impl Stream<Item = Result<Bytes, Error>> + Send + 'static,
+    ) -> BoxStream<'static, Result<Bytes, Error>> {
+        let max_size = self.max_size;
+        let state = Some((stream.boxed(), 0));
+        stream::unfold(state, move |state| async move {
+            let (mut stream, total_size) = state?;
+            let bytes = match stream.next().await? {
+                Ok(bytes) => bytes,
+                Err(err) => return Some((Err(err), None)),
+            };
+            let total_size = total_size + bytes.len() as u64;
+            if total_size > max_size {
+                let err = Error::from_typed(BodyLimitError::TooLarge(max_size));
+                Some((Err(err), None))
+            } else {
+                Some((Ok(bytes), Some((stream, total_size))))
+            }
+        })
+        .boxed()
+    }
+}
+
+/// An error which occurs when the request body exceeds the limits.
+/// It should be converted by [`Error::from_typed()`] so that it can be detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyLimitError {
+    /// The body is larger than the max size in bytes.
+    TooLarge(u64),
+    /// The next chunk of the body is not received within the timeout.
+    Timeout(Duration),
+}
+
+impl BodyLimitError {
+    /// Returns `true` if the error is caused by an oversize body.
+    /// It can be used to respond with `413 Payload Too Large`.
+    #[inline]
+    pub fn is_too_large(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::TooLarge(_)))
+    }
+
+    /// Returns `true` if the error is caused by a slow body.
+    /// It can be used to respond with `408 Request Timeout`.
+    #[inline]
+    pub fn is_timeout(err: &Error) -> bool {
+        matches!(err.downcast_ref::<Self>(), Some(Self::Timeout(_)))
+    }
+}
+
+impl fmt::Display for BodyLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(max_size) => {
+                write!(
+                    f,
+                    "request body is too large: the max size is {max_size} bytes"
+                )
+            }
+            Self::Timeout(read_timeout) => {
+                write!(
+                    f,
+                    "request body is not read in time: the idle timeout is {read_timeout:?}"
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for BodyLimitError {}
+
+/// Shared limits on reading the request body.
+static SHARED_BODY_LIMIT: LazyLock<BodyLimit> = LazyLock::new(|| {
+    State::shared()
+        .config()
+        .get_table("server")
+        .map(BodyLimit::with_config)
+        .unwrap_or_else(|| BodyLimit::with_config(&Table::new()))
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{BodyLimit, BodyLimitError};
+    use crate::error::Error;
+    use bytes::Bytes;
+    use futures::{executor::block_on, stream, StreamExt};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering::Relaxed},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[test]
+    fn it_aborts_oversize_bodies() {
+        let body_limit = BodyLimit::new(10, Duration::from_secs(1));
+        assert!(body_limit.check_content_length(Some(10)).is_ok());
+        assert!(body_limit.check_content_length(None).is_ok());
+        let err = body_limit.check_content_length(Some(11)).unwrap_err();
+        assert!(BodyLimitError::is_too_large(&err));
+
+        let polled = Arc::new(AtomicUsize::new(0));
+        let counter = polled.clone();
+        let chunks = stream::iter(0..5).map(move |_| {
+            counter.fetch_add(1, Relaxed);
+            Ok(Bytes::from_static(b"zino!"))
+        });
+        let results = block_on(body_limit.limit_stream(chunks).collect::<Vec<_>>());
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok() && results[1].is_ok());
+        let err = results[2].as_ref().unwrap_err();
+        assert!(BodyLimitError::is_too_large(err));
+        assert!(!BodyLimitError::is_timeout(err));
+        assert_eq!(polled.load(Relaxed), 3);
+
+        let err = Error::from_typed(BodyLimitError::Timeout(body_limit.read_timeout()));
+        assert!(BodyLimitError::is_timeout(&err));
+        assert!(!BodyLimitError::is_too_large(&err));
+
+        let err = err.context("fail to read the request body");
+        assert!(BodyLimitError::is_timeout(&err));
+        let err = Error::new("request body is too large: the max size is 10 bytes");
+        assert!(!BodyLimitError::is_too_large(&err));
+    }
+}
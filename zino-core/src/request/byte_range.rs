@@ -0,0 +1,119 @@
+use crate::error::Error;
+
+/// A single range of bytes requested by the `range` header,
+/// in which the first and last byte positions are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    /// First byte position.
+    start: u64,
+    /// Last byte position.
+    end: u64,
+}
+
+impl ByteRange {
+    /// Parses the `range` header against the size of the content.
+    ///
+    /// It returns `Ok(None)` if the header is malformed, so that the full content is served.
+    /// An error is returned if the range is not satisfiable or multiple ranges are requested,
+    /// which should be responded with `416 Range Not Satisfiable`.
+    pub fn parse(range: &str, size: u64) -> Result<Option<Self>, Error> {
+        let Some(ranges) = range.trim().strip_prefix("bytes=") else {
+            return Ok(None);
+        };
+        if ranges.contains(',') {
+            return Err(Error::new("multiple ranges are unsupported"));
+        }
+        let Some((start, end)) = ranges.split_once('-') else {
+            return Ok(None);
+        };
+
+        let not_satisfiable = || Error::new(format!("range `{ranges}` is not satisfiable"));
+        let (start, end) = match (start.trim(), end.trim()) {
+            ("", suffix) => {
+                let Ok(suffix) = suffix.parse::<u64>() else {
+                    return Ok(None);
+                };
+                if suffix == 0 || size == 0 {
+                    return Err(not_satisfiable());
+                }
+                (size - suffix.min(size), size - 1)
+            }
+            (start, end) => {
+                let Ok(start) = start.parse::<u64>() else {
+                    return Ok(None);
+                };
+                let end = if end.is_empty() {
+                    u64::MAX
+                } else if let Ok(end) = end.parse::<u64>() && end >= start {
+                    end
+                } else {
+                    return Ok(None);
+                };
+                if start >= size {
+                    return Err(not_satisfiable());
+                }
+                (start, end.min(size - 1))
+            }
+        };
+        Ok(Some(Self { start, end }))
+    }
+
+    /// Returns the first byte position.
+    #[inline]
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the last byte position.
+    #[inline]
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Returns the number of bytes in the range, which is the `content-length`.
+    #[inline]
+    pub fn content_length(&self) -> u64 {
+        self.end + 1 - self.start
+    }
+
+    /// Returns the value of the `content-range` header for the content with the size.
+    #[inline]
+    pub fn content_range(&self, size: u64) -> String {
+        format!("bytes {}-{}/{size}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ByteRange;
+
+    #[test]
+    fn it_parses_byte_ranges() {
+        let range = ByteRange::parse("bytes=100-199", 1000).unwrap().unwrap();
+        assert_eq!(
+            (range.start(), range.end(), range.content_length()),
+            (100, 199, 100)
+        );
+        assert_eq!(range.content_range(1000), "bytes 100-199/1000");
+
+        let range = ByteRange::parse("bytes=900-", 1000).unwrap().unwrap();
+        assert_eq!((range.start(), range.end()), (900, 999));
+        let range = ByteRange::parse("bytes=-100", 1000).unwrap().unwrap();
+        assert_eq!((range.start(), range.end()), (900, 999));
+        let range = ByteRange::parse("bytes=-2000", 1000).unwrap().unwrap();
+        assert_eq!((range.start(), range.end()), (0, 999));
+        let range = ByteRange::parse("bytes=990-1999", 1000).unwrap().unwrap();
+        assert_eq!(range.content_range(1000), "bytes 990-999/1000");
+
+        assert!(ByteRange::parse("bytes=1000-1999", 1000).is_err());
+        assert!(ByteRange::parse("bytes=-0", 1000).is_err());
+        assert!(ByteRange::parse("bytes=0-", 0).is_err());
+        assert!(ByteRange::parse("bytes=0-99,200-299", 1000).is_err());
+
+        assert_eq!(ByteRange::parse("items=0-99", 1000).unwrap(), None);
+        assert_eq!(ByteRange::parse("bytes=abc", 1000).unwrap(), None);
+        assert_eq!(ByteRange::parse("bytes=a-99", 1000).unwrap(), None);
+        assert_eq!(ByteRange::parse("bytes=199-100", 1000).unwrap(), None);
+        assert_eq!(ByteRange::parse("bytes=-", 1000).unwrap(), None);
+    }
+}
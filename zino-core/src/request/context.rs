@@ -1,3 +1,4 @@
+use crate::authentication::Principal;
 use std::time::Instant;
 use unic_langid::LanguageIdentifier;
 use uuid::Uuid;
@@ -17,6 +18,8 @@ pub struct Context {
     session_id: Option<String>,
     /// Locale.
     locale: Option<LanguageIdentifier>,
+    /// Authenticated principal.
+    principal: Option<Principal>,
 }
 
 impl Context {
@@ -29,6 +32,7 @@ impl Context {
             trace_id: Uuid::nil(),
             session_id: None,
             locale: None,
+            principal: None,
         }
     }
 
@@ -59,6 +63,12 @@ impl Context {
         }
     }
 
+    /// Sets the authenticated principal.
+    #[inline]
+    pub fn set_principal(&mut self, principal: Option<Principal>) {
+        self.principal = principal;
+    }
+
     /// Returns the start time.
     #[inline]
     pub fn start_time(&self) -> Instant {
@@ -93,4 +103,10 @@ impl Context {
     pub fn locale(&self) -> Option<&LanguageIdentifier> {
         self.locale.as_ref()
     }
+
+    /// Returns the authenticated principal.
+    #[inline]
+    pub fn principal(&self) -> Option<&Principal> {
+        self.principal.as_ref()
+    }
 }
@@ -0,0 +1,411 @@
+use crate::{datetime::DateTime, extend::TomlTableExt};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bytes::Bytes;
+use futures::lock::{Mutex as AsyncMutex, OwnedMutexGuard};
+use http::header::{self, HeaderMap};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use toml::value::Table;
+
+/// Max number of in-memory responses before the expired ones are evicted.
+const MAX_MEMORY_RESPONSES: usize = 10_000;
+
+/// A cache of the responses keyed by the idempotency key.
+///
+/// The responses are kept in memory by default. If a store is specified,
+/// they are persisted by the accessor so that they can be replayed across replicas,
+/// and the in-memory responses are used as a fallback when the store is unavailable.
+/// The concurrent requests with the same key are serialized within the process.
+///
+/// The keys are scoped to the caller by the [`scope`](Self::scope) of the request headers,
+/// which are configured by `scope-headers` and default to the `authorization`, `x-api-key`
+/// and `cookie` headers, so that a response is never replayed to another principal.
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    /// Time-to-live of the cached responses.
+    ttl: Duration,
+    /// Names of the headers identifying the caller.
+    scope_headers: Vec<String>,
+    /// Name of the accessor used to store the responses.
+    store: Option<String>,
+    /// In-memory responses.
+    responses: Mutex<HashMap<String, CachedResponse>>,
+    /// Locks of the keys which are in flight.
+    locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl IdempotencyCache {
+    /// Creates a new instance with the time-to-live of the cached responses.
+    #[inline]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            scope_headers: vec![
+                "authorization".to_owned(),
+                "x-api-key".to_owned(),
+                "cookie".to_owned(),
+            ],
+            store: None,
+            responses: Mutex::new(HashMap::new()),
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a new instance with the configuration.
+    pub fn with_config(config: &Table) -> Self {
+        let ttl = config
+            .get_duration("ttl")
+            .unwrap_or_else(|| Duration::from_secs(24 * 60 * 60));
+        let mut cache = Self::new(ttl);
+        if let Some(store) = config.get_str("store") {
+            cache.set_store(store);
+        }
+        if let Some(headers) = config.get_array("scope-headers") {
+            let headers = headers
+                .iter()
+                .filter_map(|v| v.as_str())
+                .collect::<Vec<_>>();
+            cache.set_scope_headers(&headers);
+        }
+        cache
+    }
+
+    /// Sets the names of the headers identifying the caller.
+    #[inline]
+    pub fn set_scope_headers(&mut self, headers: &[&str]) {
+        self.scope_headers = headers.iter().map(|s| s.to_ascii_lowercase()).collect();
+    }
+
+    /// Returns the names of the headers identifying the caller.
+    #[inline]
+    pub fn scope_headers(&self) -> &[String] {
+        &self.scope_headers
+    }
+
+    /// Returns the scope of the caller as a digest of the scope headers,
+    /// which should be a part of the idempotency key.
+    pub fn scope(&self, headers: &HeaderMap) -> String {
+        let mut hasher = Sha256::new();
+        for name in self.scope_headers.iter() {
+            for value in headers.get_all(name.as_str()) {
+                hasher.update(name.as_bytes());
+                hasher.update(b":");
+                hasher.update(value.as_bytes());
+                hasher.update(b"\n");
+            }
+        }
+        URL_SAFE_NO_PAD.encode(hasher.finalize())
+    }
+
+    /// Sets the name of the accessor used to store the responses.
+    #[inline]
+    pub fn set_store(&mut self, store: impl Into<String>) {
+        self.store = Some(store.into());
+    }
+
+    /// Returns the name of the accessor used to store the responses.
+    #[inline]
+    pub fn store(&self) -> Option<&str> {
+        self.store.as_deref()
+    }
+
+    /// Returns the time-to-live of the cached responses.
+    #[inline]
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Acquires the lock of the key. It waits until the request
+    /// with the same key in flight completes and drops its guard.
+    pub async fn lock(&self, key: &str) -> IdempotencyGuard<'_> {
+        let lock = self.locks.lock().entry(key.to_owned()).or_default().clone();
+        let guard = lock.clone().lock_owned().await;
+        IdempotencyGuard {
+            key: key.to_owned(),
+            lock,
+            guard: Some(guard),
+            cache: self,
+        }
+    }
+
+    /// Gets the cached response for the key if it has not expired.
+    pub async fn get(&self, key: &str) -> Option<CachedResponse> {
+        let now = DateTime::now().timestamp_millis();
+        #[cfg(feature = "accessor")]
+        if let Some(store) = &self.store {
+            match self.get_store(store, key).await {
+                Ok(response) => return response.filter(|res| !res.is_expired(now)),
+                Err(err) => tracing::warn!("fail to read the idempotent response: {err}"),
+            }
+        }
+        self.responses
+            .lock()
+            .get(key)
+            .filter(|res| !res.is_expired(now))
+            .cloned()
+    }
+
+    /// Caches the response for the key. The headers are cached except for
+    /// the ones describing the framing of the body, such as `content-length`.
+    pub async fn insert(&self, key: &str, status_code: u16, headers: &HeaderMap, body: Bytes) {
+        let now = DateTime::now().timestamp_millis();
+        let ttl = i64::try_from(self.ttl.as_millis()).unwrap_or(i64::MAX);
+        let framing_headers = [
+            header::CONTENT_LENGTH,
+            header::TRANSFER_ENCODING,
+            header::CONNECTION,
+        ];
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| !framing_headers.contains(name))
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_owned(), value.to_str().ok()?.to_owned()))
+            })
+            .collect();
+        let response = CachedResponse {
+            status_code,
+            headers,
+            body: body.to_vec(),
+            expires_at: now.saturating_add(ttl),
+        };
+        #[cfg(feature = "accessor")]
+        if let Some(store) = &self.store {
+            match self.insert_store(store, key, &response).await {
+                Ok(_) => return,
+                Err(err) => tracing::warn!("fail to write the idempotent response: {err}"),
+            }
+        }
+
+        let mut responses = self.responses.lock();
+        if responses.len() >= MAX_MEMORY_RESPONSES {
+            responses.retain(|_, res| !res.is_expired(now));
+        }
+        responses.insert(key.to_owned(), response);
+    }
+
+    /// Returns the path of the object which stores the response for the key.
+    #[cfg(feature = "accessor")]
+    fn store_path(key: &str) -> String {
+        let digest = Sha256::digest(key.as_bytes());
+        format!("idempotency/{}", URL_SAFE_NO_PAD.encode(digest))
+    }
+
+    /// Gets the response for the key in the store.
+    #[cfg(feature = "accessor")]
+    async fn get_store(
+        &self,
+        store: &str,
+        key: &str,
+    ) -> Result<Option<CachedResponse>, crate::error::Error> {
+        use crate::{accessor::GlobalAccessor, error::Error};
+        use opendal::ErrorKind::NotFound;
+
+        let operator = GlobalAccessor::try_get(store)
+            .map_err(|err| Error::with_source("fail to get the idempotency store", err))?;
+        match operator.read(&Self::store_path(key)).await {
+            Ok(bytes) => rmp_serde::from_slice(&bytes)
+                .map(Some)
+                .map_err(|err| Error::with_source("fail to decode the idempotent response", err)),
+            Err(err) if err.kind() == NotFound => Ok(None),
+            Err(err) => Err(Error::with_source(
+                "fail to read the idempotent response",
+                err,
+            )),
+        }
+    }
+
+    /// Writes the response for the key into the store.
+    #[cfg(feature = "accessor")]
+    async fn insert_store(
+        &self,
+        store: &str,
+        key: &str,
+        response: &CachedResponse,
+    ) -> Result<(), crate::error::Error> {
+        use crate::{accessor::GlobalAccessor, error::Error};
+
+        let operator = GlobalAccessor::try_get(store)
+            .map_err(|err| Error::with_source("fail to get the idempotency store", err))?;
+        let bytes = rmp_serde::to_vec(response)
+            .map_err(|err| Error::with_source("fail to encode the idempotent response", err))?;
+        operator
+            .write(&Self::store_path(key), bytes)
+            .await
+            .map_err(|err| Error::with_source("fail to write the idempotent response", err))
+    }
+}
+
+/// A response cached for the idempotency key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    /// Status code.
+    status_code: u16,
+    /// Response headers.
+    headers: Vec<(String, String)>,
+    /// Response body.
+    body: Vec<u8>,
+    /// Timestamp in milliseconds when the response expires.
+    expires_at: i64,
+}
+
+impl CachedResponse {
+    /// Returns the status code.
+    #[inline]
+    pub fn status_code(&self) -> u16 {
+        self.status_code
+    }
+
+    /// Returns the response headers.
+    #[inline]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the content type.
+    #[inline]
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers
+            .iter()
+            .find_map(|(name, value)| (name == "content-type").then_some(value.as_str()))
+    }
+
+    /// Returns the response body.
+    #[inline]
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Consumes `self` and returns the response body.
+    #[inline]
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Returns `true` if the response has expired at the time.
+    #[inline]
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// A guard which releases the lock of the idempotency key when it is dropped.
+#[derive(Debug)]
+pub struct IdempotencyGuard<'a> {
+    /// Idempotency key.
+    key: String,
+    /// Lock of the key.
+    lock: Arc<AsyncMutex<()>>,
+    /// Guard of the lock.
+    guard: Option<OwnedMutexGuard<()>>,
+    /// Idempotency cache.
+    cache: &'a IdempotencyCache,
+}
+
+impl Drop for IdempotencyGuard<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.cache.locks.lock();
+        self.guard.take();
+        if Arc::strong_count(&self.lock) == 2 {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IdempotencyCache;
+    use bytes::Bytes;
+    use futures::{executor::block_on, FutureExt};
+    use http::header::{self, HeaderMap, HeaderValue};
+    use std::time::Duration;
+
+    #[test]
+    fn it_replays_cached_responses() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        block_on(async {
+            assert!(cache.get("POST:/task/execute:abc").await.is_none());
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            headers.insert(header::LOCATION, HeaderValue::from_static("/task/1"));
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from_static("14"));
+            cache
+                .insert(
+                    "POST:/task/execute:abc",
+                    202,
+                    &headers,
+                    Bytes::from_static(br#"{"status":202}"#),
+                )
+                .await;
+            let res = cache.get("POST:/task/execute:abc").await.unwrap();
+            assert_eq!(res.status_code(), 202);
+            assert_eq!(res.content_type(), Some("application/json"));
+            assert!(res
+                .headers()
+                .contains(&("location".to_owned(), "/task/1".to_owned())));
+            assert!(res
+                .headers()
+                .iter()
+                .all(|(name, _)| name != "content-length"));
+            assert_eq!(res.body(), br#"{"status":202}"#);
+            assert!(cache.get("POST:/task/execute:xyz").await.is_none());
+
+            let cache = IdempotencyCache::new(Duration::ZERO);
+            cache
+                .insert(
+                    "POST:/task/execute:abc",
+                    202,
+                    &HeaderMap::new(),
+                    Bytes::new(),
+                )
+                .await;
+            assert!(cache.get("POST:/task/execute:abc").await.is_none());
+        });
+    }
+
+    #[test]
+    fn it_scopes_keys_to_callers() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let mut alice = HeaderMap::new();
+        alice.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer alice"),
+        );
+        alice.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        let mut bob = HeaderMap::new();
+        bob.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer bob"),
+        );
+
+        let scope = cache.scope(&alice);
+        assert_ne!(scope, cache.scope(&bob));
+        assert_ne!(scope, cache.scope(&HeaderMap::new()));
+
+        alice.remove(header::ACCEPT);
+        assert_eq!(cache.scope(&alice), scope);
+    }
+
+    #[test]
+    fn it_blocks_concurrent_duplicates() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        block_on(async {
+            let guard = cache.lock("POST:/task/execute:abc").await;
+            let other_guard = cache.lock("POST:/task/execute:xyz").now_or_never();
+            assert!(other_guard.is_some());
+
+            let mut duplicate = Box::pin(cache.lock("POST:/task/execute:abc"));
+            assert!((&mut duplicate).now_or_never().is_none());
+            drop(guard);
+            let duplicate_guard = duplicate.await;
+            assert_eq!(cache.locks.lock().len(), 2);
+            drop(other_guard);
+            drop(duplicate_guard);
+            assert!(cache.locks.lock().is_empty());
+        });
+    }
+}
@@ -2,34 +2,54 @@
 
 use crate::{
     application::{self, http_client},
-    authentication::{Authentication, ParseSecurityTokenError, SecurityToken, SessionId},
+    authentication::{
+        ApiKeyStore, Authentication, ParseSecurityTokenError, Principal, SecurityToken,
+        SessionCookie, SessionId,
+    },
     channel::{CloudEvent, Subscription},
     datetime::DateTime,
     error::Error,
-    extend::HeaderMapExt,
+    extend::{HeaderMapExt, JsonObjectExt, TomlTableExt},
+    format::{query_string, request_body},
     i18n,
     model::{Model, Query},
     response::{Rejection, Response, ResponseCode},
     trace::{TraceContext, TraceState},
     Map, SharedString, Uuid,
 };
+use bytes::Bytes;
 use cookie::{Cookie, SameSite};
 use fluent::FluentArgs;
-use multer::Multipart;
+use futures::stream::BoxStream;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::{
+    fmt,
     net::IpAddr,
+    str::FromStr,
     time::{Duration, Instant},
 };
 use toml::value::Table;
 use unic_langid::LanguageIdentifier;
 
+mod body_limit;
+mod byte_range;
 mod context;
+mod idempotency;
+mod multipart;
+mod rate_limiter;
+mod single_flight;
 mod validation;
+mod websocket;
 
+pub use body_limit::{BodyLimit, BodyLimitError};
+pub use byte_range::ByteRange;
 pub use context::Context;
-pub use validation::Validation;
+pub use idempotency::{CachedResponse, IdempotencyCache, IdempotencyGuard};
+pub use multipart::{FileField, MultipartField, MultipartStream};
+pub use rate_limiter::RateLimiter;
+pub use single_flight::SingleFlight;
+pub use validation::{Validation, ValidationError};
 
 /// Request context.
 pub trait RequestContext {
@@ -62,6 +82,9 @@ pub trait RequestContext {
     /// Gets a reference to the request context.
     fn get_context(&self) -> Option<&Context>;
 
+    /// Gets a mutable reference to the request context.
+    fn get_context_mut(&mut self) -> Option<&mut Context>;
+
     /// Gets a cookie with the given name.
     fn get_cookie(&self, name: &str) -> Option<Cookie<'static>>;
 
@@ -84,8 +107,19 @@ pub trait RequestContext {
     fn try_send(&self, message: CloudEvent) -> Result<(), Rejection>;
 
     /// Reads the entire request body into a byte buffer.
+    /// The body is read with the limits returned by [`body_limit()`](RequestContext::body_limit).
     async fn read_body_bytes(&mut self) -> Result<Vec<u8>, Error>;
 
+    /// Takes the request body as a stream of bytes without buffering.
+    /// The stream yields an error and stops reading the body once the limits are exceeded.
+    fn take_body_stream(&mut self) -> BoxStream<'static, Result<Bytes, Error>>;
+
+    /// Returns the limits on reading the request body.
+    #[inline]
+    fn body_limit(&self) -> BodyLimit {
+        *BodyLimit::shared()
+    }
+
     /// Creates a new request context.
     fn new_context(&self) -> Context {
         // Emit metrics.
@@ -178,6 +212,17 @@ pub trait RequestContext {
         cookie_builder.finish()
     }
 
+    /// Returns the session data stored in the signed session cookie.
+    /// Returns `None` if the cookie is missing, tampered or expired.
+    fn session(&self) -> Option<Map> {
+        let session_cookie = SessionCookie::shared();
+        let value = self.get_header("cookie")?.split(';').find_map(|cookie| {
+            let (name, value) = cookie.trim().split_once('=')?;
+            (name == session_cookie.name()).then_some(value)
+        })?;
+        session_cookie.decode(value, DateTime::now().timestamp())
+    }
+
     /// Returns the start time.
     #[inline]
     fn start_time(&self) -> Instant {
@@ -222,48 +267,86 @@ pub trait RequestContext {
         self.get_context().and_then(|ctx| ctx.locale())
     }
 
-    /// Parses the route parameter by name as an instance of type `T`.
-    /// The name should not include `:`, `*`, `{` or `}`.
-    fn parse_param<T>(&self, name: &str) -> Result<T, Rejection>
-    where
-        T: DeserializeOwned + Send + 'static,
-    {
+    /// Returns the principal authenticated by
+    /// [`parse_api_key()`](RequestContext::parse_api_key).
+    #[inline]
+    fn principal(&self) -> Option<&Principal> {
+        self.get_context().and_then(|ctx| ctx.principal())
+    }
+
+    /// Returns the route parameters as a map of raw string values.
+    /// The keys do not include `:`, `*`, `{` or `}`.
+    fn params(&self) -> Map {
         const CAPTURES: [char; 4] = [':', '*', '{', '}'];
+        let mut params = Map::new();
         let route = self.matched_route();
         if route.contains(CAPTURES) {
             let segments = route.split('/').collect::<Vec<_>>();
-            if let Some(index) = segments
-                .iter()
-                .position(|segment| segment.trim_matches(CAPTURES.as_slice()) == name)
-            {
-                let path = self.request_path();
-                if let Some(&param) = path
-                    .splitn(segments.len(), '/')
-                    .collect::<Vec<_>>()
-                    .get(index)
-                {
-                    return serde_json::from_value::<T>(param.into()).map_err(|err| {
-                        Rejection::from_validation_entry(name.to_owned(), err).provide_context(self)
-                    });
+            let path = self.request_path();
+            for (segment, param) in segments.iter().zip(path.splitn(segments.len(), '/')) {
+                if segment.contains(CAPTURES) {
+                    let name = segment.trim_matches(CAPTURES.as_slice());
+                    params.upsert(name, param);
                 }
             }
         }
+        params
+    }
 
-        Err(Rejection::from_validation_entry(
-            name.to_owned(),
-            Error::new(format!("the param `{name}` does not exist")),
-        )
-        .provide_context(self))
+    /// Parses the route parameter by name as an instance of type `T` via [`FromStr`].
+    /// The name should not include `:`, `*`, `{` or `}`.
+    ///
+    /// It is a `BadRequest` rejection if the param is missing or fails to parse.
+    fn param<T>(&self, name: &str) -> Result<T, Rejection>
+    where
+        T: FromStr,
+        <T as FromStr>::Err: fmt::Display,
+    {
+        let params = self.params();
+        let Some(param) = params.get_str(name) else {
+            return Err(Rejection::from_validation_entry(
+                name.to_owned(),
+                Error::new(format!("the param `{name}` does not exist")),
+            )
+            .provide_context(self));
+        };
+        param.parse::<T>().map_err(|err| {
+            let message = format!("invalid value `{param}` for the param `{name}`: {err}");
+            Rejection::from_validation_entry(name.to_owned(), Error::new(message))
+                .provide_context(self)
+        })
+    }
+
+    /// Parses the route parameter by name as an instance of type `T`.
+    /// The name should not include `:`, `*`, `{` or `}`.
+    fn parse_param<T>(&self, name: &str) -> Result<T, Rejection>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        if let Some(param) = self.params().remove(name) {
+            serde_json::from_value::<T>(param).map_err(|err| {
+                Rejection::from_validation_entry(name.to_owned(), err).provide_context(self)
+            })
+        } else {
+            Err(Rejection::from_validation_entry(
+                name.to_owned(),
+                Error::new(format!("the param `{name}` does not exist")),
+            )
+            .provide_context(self))
+        }
     }
 
     /// Parses the query as an instance of type `T`.
     /// Returns a default value of `T` when the query is empty.
+    ///
+    /// Repeated keys such as `tag=a&tag=b` are parsed as a sequence,
+    /// and a key without the value such as `?flag` is parsed as `true`.
     fn parse_query<T>(&self) -> Result<T, Rejection>
     where
         T: Default + DeserializeOwned + Send + 'static,
     {
         if let Some(query) = self.query_string() {
-            serde_qs::from_str::<T>(query)
+            query_string::parse_query::<T>(query)
                 .map_err(|err| Rejection::from_validation_entry("query", err).provide_context(self))
         } else {
             Ok(T::default())
@@ -278,46 +361,85 @@ pub trait RequestContext {
     /// - `application/msgpack`
     /// - `application/problem+json`
     /// - `application/x-www-form-urlencoded`
+    ///
+    /// Other content types are rejected with `415 Unsupported Media Type`.
+    /// The body exceeding the max size is rejected with `413 Payload Too Large`,
+    /// and a body which is not read in time is rejected with `408 Request Timeout`.
+    ///
+    /// The body compressed with the `content-encoding` of `gzip`, `br` or `deflate`
+    /// is decoded transparently, and the decoded size is also capped by the max size.
+    /// Other content encodings are rejected with `415 Unsupported Media Type`.
     async fn parse_body<T>(&mut self) -> Result<T, Rejection>
     where
         T: DeserializeOwned + Send + 'static,
     {
         let data_type = self.header_map().get_data_type().unwrap_or("form".into());
-        if data_type.contains('/') {
+        if !request_body::is_supported(&data_type) {
             let message = format!("deserialization of the data type `{data_type}` is unsupported");
-            let rejection = Rejection::from_validation_entry("data_type", Error::new(message))
-                .provide_context(self);
+            let rejection =
+                Rejection::unsupported_media_type(Error::new(message)).provide_context(self);
+            return Err(rejection);
+        }
+        let content_encoding = self.get_header("content-encoding").map(|s| s.to_owned());
+        if let Some(content_encoding) = &content_encoding &&
+            !request_body::is_supported_encoding(content_encoding)
+        {
+            let message =
+                format!("decoding of the content encoding `{content_encoding}` is unsupported");
+            let rejection =
+                Rejection::unsupported_media_type(Error::new(message)).provide_context(self);
             return Err(rejection);
         }
-        let bytes = self
+        let mut bytes = self
             .read_body_bytes()
             .await
-            .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))?;
-        if data_type == "form" {
-            serde_urlencoded::from_bytes(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
-        } else if data_type == "msgpack" {
-            rmp_serde::from_slice(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
-        } else {
-            serde_json::from_slice(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
+            .map_err(|err| body_rejection(err).provide_context(self))?;
+        if let Some(content_encoding) = content_encoding {
+            let max_size = self.body_limit().max_size();
+            bytes = request_body::decode_body(&content_encoding, bytes, max_size)
+                .map_err(|err| body_rejection(err).provide_context(self))?;
         }
+        request_body::parse_body(&data_type, &bytes)
+            .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
     }
 
     /// Parses the request body as a multipart, which is commonly used with file uploads.
-    async fn parse_multipart(&mut self) -> Result<Multipart, Rejection> {
+    /// The body is read lazily, so the content of a file field can be piped into a writer
+    /// without buffering in memory.
+    ///
+    /// The max size of a part and the whole body can be configured by
+    /// `multipart.max-part-size` and `multipart.max-total-size` in bytes.
+    /// The limits of [`body_limit()`](RequestContext::body_limit) are also enforced, and
+    /// the errors of the fields can be converted by [`Rejection::from_error()`].
+    fn parse_multipart(&mut self) -> Result<MultipartStream, Rejection> {
         let Some(content_type) = self.get_header("content-type") else {
             return Err(Rejection::from_validation_entry(
                 "content_type",
                 Error::new("invalid `content-type` header"),
             ).provide_context(self));
         };
+        let content_length = self
+            .get_header("content-length")
+            .and_then(|s| s.parse().ok());
+        if let Err(err) = self.body_limit().check_content_length(content_length) {
+            return Err(Rejection::from_error(err).provide_context(self));
+        }
         match multer::parse_boundary(content_type) {
             Ok(boundary) => {
-                let result = self.read_body_bytes().await.map_err(|err| err.to_string());
-                let stream = futures::stream::once(async { result });
-                Ok(Multipart::new(stream, boundary))
+                let config = self.config().get_table("multipart");
+                let max_part_size = config
+                    .and_then(|t| t.get_u64("max-part-size"))
+                    .unwrap_or(multipart::DEFAULT_MAX_PART_SIZE);
+                let max_total_size = config
+                    .and_then(|t| t.get_u64("max-total-size"))
+                    .unwrap_or(multipart::DEFAULT_MAX_TOTAL_SIZE);
+                let stream = self.take_body_stream();
+                Ok(MultipartStream::new(
+                    stream,
+                    boundary,
+                    max_part_size,
+                    max_total_size,
+                ))
             }
             Err(err) => {
                 Err(Rejection::from_validation_entry("boundary", err).provide_context(self))
@@ -453,6 +575,83 @@ pub trait RequestContext {
             })
     }
 
+    /// Authenticates the request with an API key, and attaches the resolved principal
+    /// to the request context. The key is extracted from the `x-api-key` header
+    /// or the `authorization` header with the `Bearer` scheme, and validated
+    /// against the shared [`ApiKeyStore`]. It is rejected with a `401 Unauthorized`
+    /// if the key is missing, invalid or revoked.
+    async fn parse_api_key(&mut self) -> Result<Principal, Rejection> {
+        let api_key = self.get_header("x-api-key").or_else(|| {
+            self.get_header("authorization")
+                .and_then(|s| s.split_once(' '))
+                .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+                .map(|(_, token)| token.trim())
+        });
+        let Some(api_key) = api_key.filter(|s| !s.is_empty()).map(|s| s.to_owned()) else {
+            let rejection = Rejection::unauthorized(Error::new("the API key is missing"));
+            return Err(rejection.provide_context(self));
+        };
+        match ApiKeyStore::shared().resolve(&api_key).await {
+            Ok(principal) => {
+                if let Some(ctx) = self.get_context_mut() {
+                    ctx.set_principal(Some(principal.clone()));
+                }
+                Ok(principal)
+            }
+            Err(err) => Err(Rejection::unauthorized(err).provide_context(self)),
+        }
+    }
+
+    /// Requires the authenticated principal to have all of the roles.
+    /// It is rejected with a `401 Unauthorized` if there is no principal,
+    /// and a `403 Forbidden` if the principal lacks any of the roles.
+    fn require_roles(&self, roles: &[&str]) -> Result<&Principal, Rejection> {
+        let principal = self.principal().ok_or_else(|| {
+            Rejection::unauthorized(Error::new("the principal is unauthenticated"))
+                .provide_context(self)
+        })?;
+        if principal.has_all_roles(roles) {
+            Ok(principal)
+        } else {
+            let roles = roles.join(", ");
+            let message = format!("the principal should have all of the roles: {roles}");
+            Err(Rejection::forbidden(Error::new(message)).provide_context(self))
+        }
+    }
+
+    /// Requires the authenticated principal to have any of the roles.
+    /// It is rejected with a `401 Unauthorized` if there is no principal,
+    /// and a `403 Forbidden` if the principal has none of the roles.
+    fn require_any_role(&self, roles: &[&str]) -> Result<&Principal, Rejection> {
+        let principal = self.principal().ok_or_else(|| {
+            Rejection::unauthorized(Error::new("the principal is unauthenticated"))
+                .provide_context(self)
+        })?;
+        if principal.has_any_role(roles) {
+            Ok(principal)
+        } else {
+            let roles = roles.join(", ");
+            let message = format!("the principal should have any of the roles: {roles}");
+            Err(Rejection::forbidden(Error::new(message)).provide_context(self))
+        }
+    }
+
+    /// Validates the WebSocket opening handshake and returns the value of `sec-websocket-accept`.
+    /// It is rejected with a `400 Bad Request` if the request is not a valid upgrade request.
+    fn websocket_accept_key(&self) -> Result<String, Rejection> {
+        websocket::validate_handshake(self.request_method().as_ref(), self.header_map())
+            .map_err(|validation| Rejection::bad_request(validation).provide_context(self))
+    }
+
+    /// Parses the `range` header against the size of the content.
+    /// See [`ByteRange::parse()`] for the handling of malformed and unsatisfiable ranges.
+    fn parse_byte_range(&self, size: u64) -> Result<Option<ByteRange>, Error> {
+        match self.get_header("range") {
+            Some(range) => ByteRange::parse(range, size),
+            None => Ok(None),
+        }
+    }
+
     /// Returns a `Response` or `Rejection` from an SQL query validation.
     /// The data is extracted from [`parse_query()`](RequestContext::parse_query).
     fn query_validation<S: ResponseCode>(&self, query: &mut Query) -> Result<Response<S>, Rejection>
@@ -482,50 +681,41 @@ pub trait RequestContext {
         Self: Sized,
     {
         let data_type = self.header_map().get_data_type().unwrap_or("form".into());
-        if data_type.contains('/') {
+        if !request_body::is_supported(&data_type) {
             let message = format!("deserialization of the data type `{data_type}` is unsupported");
-            let rejection = Rejection::from_validation_entry("data_type", Error::new(message))
-                .provide_context(self);
+            let rejection =
+                Rejection::unsupported_media_type(Error::new(message)).provide_context(self);
             return Err(rejection);
         }
-        let bytes = self
+        let content_encoding = self.get_header("content-encoding").map(|s| s.to_owned());
+        if let Some(content_encoding) = &content_encoding &&
+            !request_body::is_supported_encoding(content_encoding)
+        {
+            let message =
+                format!("decoding of the content encoding `{content_encoding}` is unsupported");
+            let rejection =
+                Rejection::unsupported_media_type(Error::new(message)).provide_context(self);
+            return Err(rejection);
+        }
+        let mut bytes = self
             .read_body_bytes()
             .await
-            .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))?;
-        if data_type == "form" {
-            serde_urlencoded::from_bytes(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
-                .and_then(|data: Map| {
-                    let validation = model.read_map(&data);
-                    if validation.is_success() {
-                        Ok(Response::with_context(S::OK, self))
-                    } else {
-                        Err(Rejection::bad_request(validation).provide_context(self))
-                    }
-                })
-        } else if data_type == "msgpack" {
-            rmp_serde::from_slice(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
-                .and_then(|data: Map| {
-                    let validation = model.read_map(&data);
-                    if validation.is_success() {
-                        Ok(Response::with_context(S::OK, self))
-                    } else {
-                        Err(Rejection::bad_request(validation).provide_context(self))
-                    }
-                })
-        } else {
-            serde_json::from_slice(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
-                .and_then(|data: Map| {
-                    let validation = model.read_map(&data);
-                    if validation.is_success() {
-                        Ok(Response::with_context(S::OK, self))
-                    } else {
-                        Err(Rejection::bad_request(validation).provide_context(self))
-                    }
-                })
+            .map_err(|err| body_rejection(err).provide_context(self))?;
+        if let Some(content_encoding) = content_encoding {
+            let max_size = self.body_limit().max_size();
+            bytes = request_body::decode_body(&content_encoding, bytes, max_size)
+                .map_err(|err| body_rejection(err).provide_context(self))?;
         }
+        request_body::parse_body(&data_type, &bytes)
+            .map_err(|err| Rejection::from_validation_entry("body", err).provide_context(self))
+            .and_then(|data: Map| {
+                let validation = model.read_map(&data);
+                if validation.is_success() {
+                    Ok(Response::with_context(S::OK, self))
+                } else {
+                    Err(Rejection::bad_request(validation).provide_context(self))
+                }
+            })
     }
 
     /// Makes an HTTP request to the provided resource
@@ -591,3 +781,12 @@ pub trait RequestContext {
         event
     }
 }
+
+/// Converts the error of reading the request body into a rejection.
+fn body_rejection<'a>(err: Error) -> Rejection<'a> {
+    if BodyLimitError::is_too_large(&err) || BodyLimitError::is_timeout(&err) {
+        Rejection::from_error(err)
+    } else {
+        Rejection::from_validation_entry("body", err)
+    }
+}
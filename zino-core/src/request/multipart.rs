@@ -0,0 +1,222 @@
+use crate::error::Error;
+use bytes::Bytes;
+use futures::{
+    io::IntoAsyncRead,
+    stream::{self, BoxStream},
+    Stream, StreamExt, TryStreamExt,
+};
+use multer::{Constraints, Multipart, SizeLimit};
+use std::io;
+
+/// Default max size of a part in bytes.
+pub(super) const DEFAULT_MAX_PART_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Default max size of the whole multipart body in bytes.
+pub(super) const DEFAULT_MAX_TOTAL_SIZE: u64 = 256 * 1024 * 1024;
+
+/// A stream of multipart fields which reads the request body lazily.
+pub struct MultipartStream {
+    /// Inner multipart parser.
+    multipart: Multipart<'static>,
+}
+
+impl MultipartStream {
+    /// Creates a new instance with the body stream, the boundary and the size limits.
+    pub fn new(
+        stream: impl Stream<Item = Result<Bytes, Error>> + Send + 'static,
+        boundary: impl Into<String>,
+        max_part_size: u64,
+        max_total_size: u64,
+    ) -> Self {
+        let stream = stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()));
+        let size_limit = SizeLimit::new()
+            .per_field(max_part_size)
+            .whole_stream(max_total_size);
+        let constraints = Constraints::new().size_limit(size_limit);
+        Self {
+            multipart: Multipart::with_constraints(stream, boundary, constraints),
+        }
+    }
+
+    /// Reads the next field. A field without the filename is read as a value,
+    /// while the content of a file field is left in the body until it is read.
+    ///
+    /// The reader of a file field should be consumed or dropped before reading the next field.
+    pub async fn next_field(&mut self) -> Result<Option<MultipartField>, Error> {
+        let Some(field) = self.multipart.next_field().await? else {
+            return Ok(None);
+        };
+        let name = field.name().unwrap_or_default().to_owned();
+        if let Some(file_name) = field.file_name() {
+            let file_name = file_name.to_owned();
+            let content_type = field.content_type().map(|mime| mime.to_string());
+            let stream = field
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+                .boxed();
+            let file = FileField {
+                name,
+                file_name,
+                content_type,
+                reader: stream.into_async_read(),
+            };
+            Ok(Some(MultipartField::File(file)))
+        } else {
+            let value = field.text().await?;
+            Ok(Some(MultipartField::Value { name, value }))
+        }
+    }
+
+    /// Converts `self` into a stream of fields.
+    pub fn into_stream(self) -> impl Stream<Item = Result<MultipartField, Error>> {
+        stream::try_unfold(self, |mut multipart| async move {
+            let field = multipart.next_field().await?;
+            Ok(field.map(|field| (field, multipart)))
+        })
+    }
+}
+
+/// A field in the multipart body.
+pub enum MultipartField {
+    /// A plain form field without the filename.
+    Value {
+        /// Field name.
+        name: String,
+        /// Field value.
+        value: String,
+    },
+    /// A file field.
+    File(FileField),
+}
+
+/// A file field whose content is streamed from the request body.
+pub struct FileField {
+    /// Field name.
+    name: String,
+    /// File name.
+    file_name: String,
+    /// Content type.
+    content_type: Option<String>,
+    /// Content reader.
+    reader: IntoAsyncRead<BoxStream<'static, Result<Bytes, io::Error>>>,
+}
+
+impl FileField {
+    /// Returns the field name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the file name.
+    #[inline]
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Returns the content type.
+    #[inline]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Returns a mutable reference to the content reader.
+    /// It can be piped into a writer with [`futures::io::copy()`].
+    #[inline]
+    pub fn reader(&mut self) -> &mut (impl futures::AsyncRead + Unpin + Send) {
+        &mut self.reader
+    }
+
+    /// Consumes `self` and returns the content reader.
+    #[inline]
+    pub fn into_reader(self) -> impl futures::AsyncRead + Unpin + Send {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MultipartField, MultipartStream};
+    use bytes::Bytes;
+    use futures::{executor::block_on, stream, AsyncReadExt, StreamExt};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    };
+
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    fn multipart_body(
+        num_chunks: usize,
+        polled_chunks: Arc<AtomicUsize>,
+    ) -> impl futures::Stream<Item = Result<Bytes, crate::error::Error>> {
+        let head = Bytes::from(
+            "--boundary\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nreport\r\n\
+                --boundary\r\nContent-Disposition: form-data; name=\"file\"; \
+                filename=\"report.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        );
+        let tail = Bytes::from("\r\n--boundary--\r\n");
+        let chunks = stream::iter(0..num_chunks).map(move |_| {
+            polled_chunks.fetch_add(1, Relaxed);
+            Ok(Bytes::from(vec![b'a'; CHUNK_SIZE]))
+        });
+        stream::iter([Ok(head)])
+            .chain(chunks)
+            .chain(stream::iter([Ok(tail)]))
+    }
+
+    #[test]
+    fn it_streams_large_file_fields() {
+        let num_chunks = 128;
+        let polled_chunks = Arc::new(AtomicUsize::new(0));
+        let body = multipart_body(num_chunks, polled_chunks.clone());
+        let mut multipart = MultipartStream::new(body, "boundary", u64::MAX, u64::MAX);
+        block_on(async {
+            let Some(MultipartField::Value { name, value }) = multipart.next_field().await.unwrap()
+            else {
+                panic!("the first field should be a value");
+            };
+            assert_eq!(name, "title");
+            assert_eq!(value, "report");
+
+            let Some(MultipartField::File(mut file)) = multipart.next_field().await.unwrap() else {
+                panic!("the second field should be a file");
+            };
+            assert_eq!(file.name(), "file");
+            assert_eq!(file.file_name(), "report.bin");
+            assert_eq!(file.content_type(), Some("application/octet-stream"));
+
+            let mut buffer = vec![0; CHUNK_SIZE];
+            let mut total_size = 0;
+            loop {
+                let size = file.reader().read(&mut buffer).await.unwrap();
+                if size == 0 {
+                    break;
+                }
+                total_size += size;
+                assert!(polled_chunks.load(Relaxed) <= total_size / CHUNK_SIZE + 2);
+            }
+            assert_eq!(total_size, num_chunks * CHUNK_SIZE);
+
+            drop(file);
+            assert!(multipart.next_field().await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn it_limits_multipart_sizes() {
+        let polled_chunks = Arc::new(AtomicUsize::new(0));
+        let body = multipart_body(32, polled_chunks.clone());
+        let max_part_size = 4 * CHUNK_SIZE as u64;
+        let mut multipart = MultipartStream::new(body, "boundary", max_part_size, u64::MAX);
+        block_on(async {
+            assert!(multipart.next_field().await.is_ok());
+
+            let Some(MultipartField::File(file)) = multipart.next_field().await.unwrap() else {
+                panic!("the second field should be a file");
+            };
+            let mut bytes = Vec::new();
+            assert!(file.into_reader().read_to_end(&mut bytes).await.is_err());
+            assert!(polled_chunks.load(Relaxed) < 32);
+        });
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{datetime::DateTime, extend::TomlTableExt};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering::Relaxed},
+    time::Duration,
+};
+use toml::value::Table;
+
+/// Max number of in-memory buckets before the full buckets are evicted.
+const MAX_MEMORY_BUCKETS: usize = 10_000;
+
+/// A rate limiter with the token bucket algorithm, which is keyed by the client.
+///
+/// The buckets are kept in memory by default. If a store is specified,
+/// they are persisted by the accessor so that the limits are shared across replicas,
+/// and the in-memory buckets are used as a fallback when the store is unavailable.
+/// Since the storage services do not support atomic updates,
+/// the shared limits are approximate under concurrent requests.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Number of tokens refilled per second, stored as the bits of `f64`.
+    rate: AtomicU64,
+    /// Max number of tokens in a bucket.
+    burst: AtomicU32,
+    /// Name of the accessor used to store the buckets.
+    store: Option<String>,
+    /// In-memory buckets.
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// Creates a new instance with the rate per second and the burst size.
+    #[inline]
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate: AtomicU64::new(rate.to_bits()),
+            burst: AtomicU32::new(burst.max(1)),
+            store: None,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a new instance with the configuration.
+    pub fn with_config(config: &Table) -> Self {
+        let (rate, burst) = Self::parse_limits(config);
+        let mut rate_limiter = Self::new(rate, burst);
+        if let Some(store) = config.get_str("store") {
+            rate_limiter.set_store(store);
+        }
+        rate_limiter
+    }
+
+    /// Updates the rate and the burst size with the configuration.
+    /// The existing buckets are kept and refilled with the new limits.
+    pub fn reconfigure(&self, config: &Table) {
+        let (rate, burst) = Self::parse_limits(config);
+        self.rate.store(rate.to_bits(), Relaxed);
+        self.burst.store(burst.max(1), Relaxed);
+    }
+
+    /// Parses the rate and the burst size from the configuration.
+    fn parse_limits(config: &Table) -> (f64, u32) {
+        let rate = config.get_f64("rate").unwrap_or(10.0);
+        let burst = config.get_u32("burst").unwrap_or(rate.ceil() as u32);
+        (rate, burst)
+    }
+
+    /// Sets the name of the accessor used to store the buckets.
+    #[inline]
+    pub fn set_store(&mut self, store: impl Into<String>) {
+        self.store = Some(store.into());
+    }
+
+    /// Returns the name of the accessor used to store the buckets.
+    #[inline]
+    pub fn store(&self) -> Option<&str> {
+        self.store.as_deref()
+    }
+
+    /// Returns the number of tokens refilled per second.
+    #[inline]
+    pub fn rate(&self) -> f64 {
+        f64::from_bits(self.rate.load(Relaxed))
+    }
+
+    /// Returns the max number of tokens in a bucket.
+    #[inline]
+    pub fn burst(&self) -> u32 {
+        self.burst.load(Relaxed)
+    }
+
+    /// Attempts to take a token for the key.
+    /// If the limit is exceeded, it returns the duration to wait before retrying.
+    pub async fn check(&self, key: &str) -> Result<(), Duration> {
+        let now = DateTime::now().timestamp_millis();
+        #[cfg(feature = "accessor")]
+        if let Some(store) = &self.store {
+            match self.check_store(store, key, now).await {
+                Ok(result) => return result,
+                Err(err) => tracing::warn!("fail to check the rate limit in the store: {err}"),
+            }
+        }
+        self.check_memory(key, now)
+    }
+
+    /// Attempts to take a token from the in-memory bucket.
+    fn check_memory(&self, key: &str, now: i64) -> Result<(), Duration> {
+        let (rate, burst) = (self.rate(), self.burst());
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_MEMORY_BUCKETS {
+            buckets.retain(|_, bucket| !bucket.is_full(rate, burst, now));
+        }
+        buckets
+            .entry(key.to_owned())
+            .or_insert_with(|| TokenBucket::new(burst, now))
+            .try_take(rate, burst, now)
+    }
+
+    /// Attempts to take a token from the bucket in the store.
+    #[cfg(feature = "accessor")]
+    async fn check_store(
+        &self,
+        store: &str,
+        key: &str,
+        now: i64,
+    ) -> Result<Result<(), Duration>, crate::error::Error> {
+        use crate::{accessor::GlobalAccessor, error::Error};
+        use opendal::ErrorKind::NotFound;
+
+        let operator = GlobalAccessor::try_get(store)
+            .map_err(|err| Error::with_source("fail to get the rate limit store", err))?;
+        let (rate, burst) = (self.rate(), self.burst());
+        let path = format!("rate-limit/{key}");
+        let mut bucket = match operator.read(&path).await {
+            Ok(bytes) => TokenBucket::decode(&bytes),
+            Err(err) if err.kind() == NotFound => None,
+            Err(err) => return Err(Error::with_source("fail to read the token bucket", err)),
+        }
+        .unwrap_or_else(|| TokenBucket::new(burst, now));
+        let result = bucket.try_take(rate, burst, now);
+        operator
+            .write(&path, bucket.encode())
+            .await
+            .map_err(|err| Error::with_source("fail to write the token bucket", err))?;
+        Ok(result)
+    }
+}
+
+/// A bucket of tokens.
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    /// Number of available tokens.
+    tokens: f64,
+    /// Timestamp in milliseconds when the tokens are refilled.
+    updated_at: i64,
+}
+
+impl TokenBucket {
+    /// Creates a new instance with the full tokens.
+    #[inline]
+    fn new(burst: u32, now: i64) -> Self {
+        Self {
+            tokens: burst.into(),
+            updated_at: now,
+        }
+    }
+
+    /// Refills the tokens and attempts to take one.
+    /// If no token is available, it returns the duration to wait for the next token.
+    fn try_take(&mut self, rate: f64, burst: u32, now: i64) -> Result<(), Duration> {
+        let elapsed = now.saturating_sub(self.updated_at).max(0) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed * rate).min(burst.into());
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else if rate > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+
+    /// Returns `true` if the bucket will be full at the time.
+    fn is_full(&self, rate: f64, burst: u32, now: i64) -> bool {
+        let elapsed = now.saturating_sub(self.updated_at).max(0) as f64 / 1000.0;
+        self.tokens + elapsed * rate >= f64::from(burst)
+    }
+
+    /// Encodes the bucket as bytes.
+    #[cfg(feature = "accessor")]
+    fn encode(&self) -> String {
+        format!("{}:{}", self.tokens, self.updated_at)
+    }
+
+    /// Decodes the bucket from bytes.
+    #[cfg(feature = "accessor")]
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let value = std::str::from_utf8(bytes).ok()?;
+        let (tokens, updated_at) = value.split_once(':')?;
+        Some(Self {
+            tokens: tokens.parse().ok()?,
+            updated_at: updated_at.parse().ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::Duration;
+
+    #[test]
+    fn it_limits_requests_by_key() {
+        let rate_limiter = RateLimiter::new(2.0, 3);
+        let now = 1_000_000;
+        for _ in 0..3 {
+            assert!(rate_limiter.check_memory("127.0.0.1", now).is_ok());
+        }
+        assert_eq!(
+            rate_limiter.check_memory("127.0.0.1", now),
+            Err(Duration::from_millis(500))
+        );
+        assert!(rate_limiter.check_memory("127.0.0.2", now).is_ok());
+
+        assert!(rate_limiter.check_memory("127.0.0.1", now + 500).is_ok());
+        assert!(rate_limiter.check_memory("127.0.0.1", now + 500).is_err());
+        for _ in 0..3 {
+            assert!(rate_limiter.check_memory("127.0.0.1", now + 2000).is_ok());
+        }
+        assert!(rate_limiter.check_memory("127.0.0.1", now + 2000).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+
+/// A primitive to de-duplicate the concurrent calls with the same key,
+/// which is similar to the `singleflight` package in Go.
+///
+/// The first caller of a key runs the work, and the callers arriving while it is
+/// in flight wait for it and share its result. The results are not kept after the work
+/// completes. If the work fails or is cancelled, the error is only returned to the first caller,
+/// and one of the waiting callers runs its own work instead.
+#[derive(Debug)]
+pub struct SingleFlight<T> {
+    /// Calls in flight.
+    calls: Mutex<HashMap<String, Flight<T>>>,
+    /// ID of the next call.
+    next_id: AtomicU64,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Runs the work for the key unless a call with the same key is in flight,
+    /// in which case it waits for the call and returns a clone of its result.
+    pub async fn run<E, F, Fut>(&self, key: &str, work: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let id = loop {
+            let call = {
+                let mut calls = self.calls.lock();
+                if let Some(flight) = calls.get_mut(key) {
+                    let (sender, receiver) = oneshot::channel();
+                    flight.waiters.push(sender);
+                    Ok(receiver)
+                } else {
+                    let id = self.next_id.fetch_add(1, Relaxed);
+                    let flight = Flight {
+                        id,
+                        waiters: Vec::new(),
+                    };
+                    calls.insert(key.to_owned(), flight);
+                    Err(id)
+                }
+            };
+            match call {
+                Ok(receiver) => {
+                    if let Ok(value) = receiver.await {
+                        return Ok(value);
+                    }
+                }
+                Err(id) => break id,
+            }
+        };
+
+        let guard = InFlightGuard {
+            key,
+            id,
+            calls: &self.calls,
+        };
+        let result = work().await;
+        if let Ok(value) = &result &&
+            let Some(flight) = guard.take()
+        {
+            for sender in flight.waiters {
+                sender.send(value.clone()).ok();
+            }
+        }
+        result
+    }
+
+    /// Returns `true` if a call with the key is in flight.
+    #[inline]
+    pub fn is_in_flight(&self, key: &str) -> bool {
+        self.calls.lock().contains_key(key)
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A call in flight.
+#[derive(Debug)]
+struct Flight<T> {
+    /// ID of the call.
+    id: u64,
+    /// Waiters of the call.
+    waiters: Vec<oneshot::Sender<T>>,
+}
+
+/// A guard which removes the call in flight when it is dropped,
+/// so that the waiters are woken up if the work fails or is cancelled.
+/// A newer call with the same key is never removed, since the call is matched by its ID.
+struct InFlightGuard<'a, T> {
+    /// Key of the call.
+    key: &'a str,
+    /// ID of the call.
+    id: u64,
+    /// Calls in flight.
+    calls: &'a Mutex<HashMap<String, Flight<T>>>,
+}
+
+impl<T> InFlightGuard<'_, T> {
+    /// Removes the call in flight and returns it if it has not been removed.
+    fn take(&self) -> Option<Flight<T>> {
+        let mut calls = self.calls.lock();
+        if calls.get(self.key)?.id == self.id {
+            calls.remove(self.key)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Drop for InFlightGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Flight, InFlightGuard, SingleFlight};
+    use crate::error::Error;
+    use futures::{executor::block_on, future};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering::Relaxed},
+        task::Poll,
+    };
+
+    /// Yields to the other futures once.
+    async fn yield_now() {
+        let mut yielded = false;
+        future::poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn it_shares_concurrent_computations() {
+        let single_flight = SingleFlight::new();
+        let executions = &AtomicUsize::new(0);
+        let callers = (0..100).map(|_| {
+            single_flight.run("report:daily", move || async move {
+                let execution = executions.fetch_add(1, Relaxed) + 1;
+                yield_now().await;
+                Ok::<_, Error>(format!("execution {execution}"))
+            })
+        });
+        let results = block_on(future::join_all(callers));
+        assert_eq!(executions.load(Relaxed), 1);
+        assert!(results
+            .iter()
+            .all(|r| r.as_deref().ok() == Some("execution 1")));
+        assert!(!single_flight.is_in_flight("report:daily"));
+
+        let result = block_on(single_flight.run("report:daily", move || async move {
+            let execution = executions.fetch_add(1, Relaxed) + 1;
+            Ok::<_, Error>(format!("execution {execution}"))
+        }));
+        assert_eq!(result.unwrap(), "execution 2");
+    }
+
+    #[test]
+    fn it_does_not_share_errors() {
+        let single_flight = SingleFlight::new();
+        let executions = &AtomicUsize::new(0);
+        let callers = (0..3).map(|_| {
+            single_flight.run("report:daily", move || async move {
+                let execution = executions.fetch_add(1, Relaxed) + 1;
+                yield_now().await;
+                if execution == 1 {
+                    Err(Error::new("the report is not ready"))
+                } else {
+                    Ok(execution)
+                }
+            })
+        });
+        let results = block_on(future::join_all(callers));
+        assert_eq!(executions.load(Relaxed), 2);
+        assert!(results[0].is_err());
+        assert!(results[1..].iter().all(|r| r.as_ref().ok() == Some(&2)));
+        assert!(!single_flight.is_in_flight("report:daily"));
+    }
+
+    #[test]
+    fn it_keeps_newer_calls_in_flight() {
+        let single_flight = SingleFlight::<usize>::new();
+        let flight = Flight {
+            id: 0,
+            waiters: Vec::new(),
+        };
+        single_flight
+            .calls
+            .lock()
+            .insert("report:daily".to_owned(), flight);
+        let guard = InFlightGuard {
+            key: "report:daily",
+            id: 0,
+            calls: &single_flight.calls,
+        };
+        assert!(guard.take().is_some());
+
+        // A newer call starts before the guard of the previous one is dropped.
+        let flight = Flight {
+            id: 1,
+            waiters: Vec::new(),
+        };
+        single_flight
+            .calls
+            .lock()
+            .insert("report:daily".to_owned(), flight);
+        drop(guard);
+        assert!(single_flight.is_in_flight("report:daily"));
+    }
+}
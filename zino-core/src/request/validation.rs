@@ -5,12 +5,15 @@ use crate::{
     format::string_array,
     Map, SharedString,
 };
+use regex::Regex;
 use serde_json::Value;
 use std::{
     borrow::Cow,
+    error, fmt,
     net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr},
     num::{ParseFloatError, ParseIntError},
     str::{FromStr, ParseBoolError},
+    sync::LazyLock,
     time::Duration,
 };
 use url::{self, Url};
@@ -73,6 +76,58 @@ impl Validation {
         map
     }
 
+    /// Consumes the validation and converts it into an error with a [`ValidationError`].
+    /// The failed entries can be recovered by [`from_error()`](Validation::from_error).
+    #[inline]
+    pub fn into_error(self) -> Error {
+        Error::from_typed(ValidationError {
+            entries: self.into_map(),
+        })
+    }
+
+    /// Recovers the validation from an error created by [`into_error()`](Validation::into_error).
+    /// It returns `None` if the error is not caused by a failed validation.
+    #[inline]
+    pub fn from_error(err: &Error) -> Option<Self> {
+        err.downcast_ref::<ValidationError>()
+            .map(ValidationError::to_validation)
+    }
+
+    /// Validates a str with the specified format.
+    /// Supported formats: `email`, `uri`, `uuid`, `ip`, `ipv4`, `ipv6` and `date-time`.
+    pub fn validate_format(format: &str, value: &str) -> Result<(), Error> {
+        match format {
+            "email" => {
+                if !EMAIL_PATTERN.is_match(value) {
+                    return Err(Error::new("should be a valid email address"));
+                }
+            }
+            "uri" => {
+                value.parse::<Url>()?;
+            }
+            "uuid" => {
+                value.parse::<Uuid>()?;
+            }
+            "ip" => {
+                value.parse::<IpAddr>()?;
+            }
+            "ipv4" => {
+                value.parse::<Ipv4Addr>()?;
+            }
+            "ipv6" => {
+                value.parse::<Ipv6Addr>()?;
+            }
+            "date-time" => {
+                value.parse::<DateTime>()?;
+            }
+            _ => {
+                let message = format!("the format `{format}` is not supported");
+                return Err(Error::new(message));
+            }
+        }
+        Ok(())
+    }
+
     /// Parses a json value as `i64`.
     pub fn parse_i64<'a>(
         value: impl Into<Option<&'a Value>>,
@@ -231,3 +286,45 @@ impl Validation {
         value.into().and_then(|v| v.as_str()).map(|s| s.parse())
     }
 }
+
+/// An error which occurs when the validation fails.
+/// It is created by [`Validation::into_error()`] so that it can be detected.
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    /// Messages of the failed entries.
+    entries: Map,
+}
+
+impl ValidationError {
+    /// Returns a reference to the messages of the failed entries.
+    #[inline]
+    pub fn entries(&self) -> &Map {
+        &self.entries
+    }
+
+    /// Converts the error into a validation with the failed entries.
+    pub fn to_validation(&self) -> Validation {
+        let mut validation = Validation::new();
+        for (key, value) in &self.entries {
+            if let Value::String(message) = value {
+                validation.record(key.to_owned(), message.to_owned());
+            }
+        }
+        validation
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = serde_json::to_string(&self.entries).map_err(|_| fmt::Error)?;
+        write!(f, "validation failed: {entries}")
+    }
+}
+
+impl error::Error for ValidationError {}
+
+/// Email address pattern.
+static EMAIL_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[\w.%+-]+@[a-zA-Z0-9-]+(\.[a-zA-Z0-9-]+)*\.[a-zA-Z]{2,}$")
+        .expect("fail to create the email pattern")
+});
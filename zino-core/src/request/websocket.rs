@@ -0,0 +1,85 @@
+use super::Validation;
+use crate::extend::HeaderMapExt;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha1::{Digest, Sha1};
+
+/// GUID appended to the key to compute the accept value as specified in RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Validates the WebSocket opening handshake and returns the value of `sec-websocket-accept`.
+pub(super) fn validate_handshake(
+    method: &str,
+    headers: &impl HeaderMapExt,
+) -> Result<String, Validation> {
+    let mut validation = Validation::new();
+    if method != "GET" {
+        validation.record("method", "should be `GET`");
+    }
+
+    let connection = headers.get_str("connection").unwrap_or_default();
+    if !connection
+        .split(',')
+        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+    {
+        validation.record("connection", "should contain the `upgrade` token");
+    }
+    if !headers
+        .get_str("upgrade")
+        .is_some_and(|upgrade| upgrade.trim().eq_ignore_ascii_case("websocket"))
+    {
+        validation.record("upgrade", "should be `websocket`");
+    }
+    if headers.get_str("sec-websocket-version") != Some("13") {
+        validation.record("sec-websocket-version", "should be `13`");
+    }
+
+    let key = headers
+        .get_str("sec-websocket-key")
+        .unwrap_or_default()
+        .trim();
+    match STANDARD.decode(key) {
+        Ok(bytes) if bytes.len() == 16 => (),
+        Ok(_) => validation.record("sec-websocket-key", "should be a 16-byte nonce"),
+        Err(err) => validation.record_fail("sec-websocket-key", err),
+    }
+    if validation.is_success() {
+        Ok(derive_accept_key(key))
+    } else {
+        Err(validation)
+    }
+}
+
+/// Derives the value of `sec-websocket-accept` from the value of `sec-websocket-key`.
+fn derive_accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_handshake;
+    use http::HeaderMap;
+
+    #[test]
+    fn it_validates_websocket_handshake() {
+        let mut headers = HeaderMap::new();
+        headers.insert("connection", "keep-alive, Upgrade".parse().unwrap());
+        headers.insert("upgrade", "websocket".parse().unwrap());
+        headers.insert("sec-websocket-version", "13".parse().unwrap());
+        headers.insert(
+            "sec-websocket-key",
+            "dGhlIHNhbXBsZSBub25jZQ==".parse().unwrap(),
+        );
+        assert_eq!(
+            validate_handshake("GET", &headers).unwrap(),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert!(validate_handshake("POST", &headers).is_err());
+
+        headers.remove("upgrade");
+        let validation = validate_handshake("GET", &headers).unwrap_err();
+        assert!(validation.into_map().contains_key("upgrade"));
+    }
+}
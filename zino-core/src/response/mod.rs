@@ -1,26 +1,48 @@
 //! Constructing responses and rejections.
 
 use crate::{
+    authentication::SessionCookie,
+    datetime::DateTime,
     error::Error,
-    request::{RequestContext, Validation},
+    extend::{HeaderMapExt, JsonObjectExt, TomlTableExt},
+    format::csv,
+    request::{ByteRange, RequestContext, Validation},
+    state::State,
     trace::{ServerTiming, TimingMetric, TraceContext},
-    SharedString, Uuid,
+    Map, SharedString, Uuid,
 };
 use bytes::Bytes;
-use http::header::{self, HeaderValue};
-use http_body::Full;
+use futures::{
+    future,
+    stream::{self, Stream},
+    StreamExt,
+};
+use http::header::{self, HeaderName, HeaderValue};
 use serde::Serialize;
 use serde_json::value::{RawValue, Value};
+use sha2::{Digest, Sha256};
 use std::{
     marker::PhantomData,
     time::{Duration, Instant},
 };
+use url::form_urlencoded;
 
 mod rejection;
+mod response_body;
 mod response_code;
+mod sse_event;
 
 pub use rejection::{ExtractRejection, Rejection};
+pub use response_body::{BoxError, ResponseBody};
 pub use response_code::ResponseCode;
+pub use sse_event::SseEvent;
+
+/// Size of the chunks streamed from the storage accessor.
+#[cfg(feature = "accessor")]
+const ACCESSOR_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Data types supported in the content negotiation.
+const SUPPORTED_DATA_TYPES: [&str; 3] = ["json", "msgpack", "csv"];
 
 /// An HTTP response.
 #[derive(Debug, Serialize)]
@@ -46,6 +68,9 @@ pub struct Response<S> {
     /// A URI reference that identifies the specific occurrence of the problem.
     #[serde(skip_serializing_if = "Option::is_none")]
     instance: Option<SharedString>,
+    /// Validation errors which map the fields to the messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<Vec<Map>>,
     /// Indicates the response is successful or not.
     success: bool,
     /// A context-specific descriptive message for successful response.
@@ -60,9 +85,42 @@ pub struct Response<S> {
     /// Response data.
     #[serde(skip_serializing_if = "Option::is_none")]
     data: Option<Box<RawValue>>,
+    /// Pagination metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pagination: Option<Map>,
     /// Content type.
     #[serde(skip)]
     content_type: Option<SharedString>,
+    /// Data type negotiated by the `accept` header or specified explicitly.
+    #[serde(skip)]
+    data_type: Option<SharedString>,
+    /// Streaming body which takes precedence over the response data.
+    #[serde(skip)]
+    body: Option<ResponseBody>,
+    /// Custom headers.
+    #[serde(skip)]
+    headers: Vec<(SharedString, String)>,
+    /// A flag indicating whether the JSON data is indented.
+    #[serde(skip)]
+    pretty: Option<bool>,
+    /// Entity tag of the response data.
+    #[serde(skip)]
+    etag: Option<SharedString>,
+    /// Last modified time of the response data.
+    #[serde(skip)]
+    last_modified: Option<DateTime>,
+    /// Request path with the query string.
+    #[serde(skip)]
+    request_uri: Option<String>,
+    /// The `if-none-match` header of the request.
+    #[serde(skip)]
+    if_none_match: Option<String>,
+    /// The `if-modified-since` header of the request.
+    #[serde(skip)]
+    if_modified_since: Option<DateTime>,
+    /// The `range` header of the request.
+    #[serde(skip)]
+    range: Option<String>,
     /// Trace context.
     #[serde(skip)]
     trace_context: Option<TraceContext>,
@@ -86,12 +144,24 @@ impl<S: ResponseCode> Response<S> {
             error_code: code.error_code(),
             detail: None,
             instance: None,
+            errors: None,
             success,
             message: None,
             start_time: Instant::now(),
             request_id: Uuid::nil(),
             data: None,
+            pagination: None,
             content_type: None,
+            data_type: None,
+            body: None,
+            headers: Vec::new(),
+            pretty: None,
+            etag: None,
+            last_modified: None,
+            request_uri: None,
+            if_none_match: None,
+            if_modified_since: None,
+            range: None,
             trace_context: None,
             server_timing: ServerTiming::new(),
             phantom: PhantomData,
@@ -114,13 +184,25 @@ impl<S: ResponseCode> Response<S> {
             status_code: code.status_code(),
             error_code: code.error_code(),
             detail: None,
-            instance: (!success).then(|| ctx.instance().to_owned().into()),
+            instance: None,
+            errors: None,
             success,
             message: None,
             start_time: ctx.start_time(),
             request_id: ctx.request_id(),
             data: None,
+            pagination: None,
             content_type: None,
+            data_type: select_data_type(ctx),
+            body: None,
+            headers: Vec::new(),
+            pretty: None,
+            etag: None,
+            last_modified: None,
+            request_uri: None,
+            if_none_match: None,
+            if_modified_since: None,
+            range: None,
             trace_context: None,
             server_timing: ServerTiming::new(),
             phantom: PhantomData,
@@ -131,15 +213,159 @@ impl<S: ResponseCode> Response<S> {
             res.detail = message;
         }
         res.trace_context = Some(ctx.new_trace_context());
+        res.read_request(ctx);
+        res
+    }
+
+    /// Creates a new instance of Server-Sent Events with the stream of events.
+    /// Each event is flushed to the client as soon as it is produced.
+    pub fn sse(stream: impl Stream<Item = SseEvent> + Send + 'static) -> Self {
+        let mut res = Self::new(S::OK);
+        res.set_content_type("text/event-stream");
+        res.set_body_stream(stream.map(|event| event.to_bytes()));
+        res
+    }
+
+    /// Creates a new instance of Server-Sent Events with the stream of events,
+    /// and sends a heartbeat comment on each tick of the `heartbeat` stream
+    /// to keep the connection alive. The response ends when the stream of events ends.
+    pub fn sse_with_heartbeat(
+        stream: impl Stream<Item = SseEvent> + Send + 'static,
+        heartbeat: impl Stream + Send + 'static,
+    ) -> Self {
+        let events = stream
+            .map(|event| Some(event.to_bytes()))
+            .chain(stream::once(future::ready(None)));
+        let comments = heartbeat.map(|_| Some(SseEvent::with_comment("heartbeat").to_bytes()));
+        let stream = stream::select(events, comments)
+            .take_while(|bytes| future::ready(bytes.is_some()))
+            .filter_map(future::ready);
+        let mut res = Self::new(S::OK);
+        res.set_content_type("text/event-stream");
+        res.set_body_stream(stream);
+        res
+    }
+
+    /// Creates a new instance of the CSV attachment with the rows and columns.
+    /// Each row is encoded as an RFC 4180 record, and missing values are empty fields.
+    /// The default filename is `data.csv`.
+    pub fn csv(rows: &[Map], columns: &[&str]) -> Self {
+        let mut header = String::new();
+        csv::write_record(&mut header, columns);
+        let mut chunks = Vec::with_capacity(rows.len() + 1);
+        chunks.push(Bytes::from(header));
+        for row in rows {
+            let mut record = String::new();
+            let fields = columns.iter().map(|&col| csv::format_field(row.get(col)));
+            csv::write_record(&mut record, fields);
+            chunks.push(Bytes::from(record));
+        }
+        let mut res = Self::new(S::OK);
+        res.set_content_type("text/csv");
+        res.set_attachment_filename("data.csv");
+        res.set_body_stream(stream::iter(chunks));
         res
     }
 
+    /// Streams the object at `path` for the storage accessor as the response body,
+    /// with the `content-length` from its metadata and the `content-type` guessed
+    /// from the extension. If the request has a `range` header for a single range of bytes,
+    /// only the bytes in the range are read and the status code is `206 Partial Content`.
+    /// The status code is `416 Range Not Satisfiable` if the range is out of bounds
+    /// or multiple ranges are requested, and the malformed ranges are ignored.
+    /// The `content-disposition` can be set by [`set_attachment_filename()`].
+    ///
+    /// It should be called after the request context has been provided.
+    ///
+    /// [`set_attachment_filename()`]: Response::set_attachment_filename
+    #[cfg(feature = "accessor")]
+    pub async fn stream_from_accessor(
+        self,
+        name: &str,
+        path: &str,
+    ) -> Result<Self, opendal::Error> {
+        use crate::accessor::GlobalAccessor;
+
+        let operator = GlobalAccessor::try_get(name)?;
+        let path = GlobalAccessor::anchor_path(&operator.info().root(), path)?;
+        self.stream_from_operator(&operator, &path).await
+    }
+
+    /// Streams the object at `path` for the operator as the response body.
+    #[cfg(feature = "accessor")]
+    async fn stream_from_operator(
+        mut self,
+        operator: &opendal::Operator,
+        path: &str,
+    ) -> Result<Self, opendal::Error> {
+        use crate::accessor::GlobalAccessor;
+        use futures::AsyncReadExt;
+
+        let metadata = operator.stat(path).await?;
+        let size = metadata.content_length();
+        let range = self
+            .range
+            .as_deref()
+            .map(|range| ByteRange::parse(range, size));
+        let range = match range {
+            Some(Ok(range)) => range,
+            Some(Err(err)) => {
+                self.status_code = http::StatusCode::RANGE_NOT_SATISFIABLE.as_u16();
+                self.success = false;
+                self.message = None;
+                self.detail = Some(err.to_string().into());
+                self.insert_header("content-range", format!("bytes */{size}"));
+                return Ok(self);
+            }
+            None => None,
+        };
+        let reader = if let Some(range) = range {
+            self.status_code = http::StatusCode::PARTIAL_CONTENT.as_u16();
+            self.insert_header("content-range", range.content_range(size));
+            self.insert_header("content-length", range.content_length());
+            operator
+                .reader_with(path)
+                .range(range.start()..=range.end())
+                .await?
+        } else {
+            self.insert_header("content-length", size);
+            operator.reader(path).await?
+        };
+        let content_type = metadata
+            .content_type()
+            .unwrap_or_else(|| GlobalAccessor::guess_content_type(path));
+        self.set_content_type(content_type.to_owned());
+        self.insert_header("accept-ranges", "bytes");
+
+        // The stream yields the read error and ends, so that the response body is aborted.
+        let stream = stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+            let mut buf = vec![0; ACCESSOR_CHUNK_SIZE];
+            match reader.read(&mut buf).await {
+                Ok(0) => None,
+                Ok(size) => {
+                    buf.truncate(size);
+                    Some((Ok(Bytes::from(buf)), Some(reader)))
+                }
+                Err(err) => {
+                    tracing::error!("fail to read the object from the accessor: {err}");
+                    Some((Err(err), None))
+                }
+            }
+        });
+        self.body = Some(ResponseBody::try_from_stream(stream));
+        Ok(self)
+    }
+
     /// Provides the request context for the response.
     pub fn provide_context<Ctx: RequestContext>(mut self, ctx: &Ctx) -> Self {
-        self.instance = (!self.is_success()).then(|| ctx.instance().to_owned().into());
         self.start_time = ctx.start_time();
         self.request_id = ctx.request_id();
         self.trace_context = Some(ctx.new_trace_context());
+        if self.data_type.is_none() {
+            self.data_type = select_data_type(ctx);
+        }
+        self.read_request(ctx);
         self
     }
 
@@ -198,6 +424,7 @@ impl<S: ResponseCode> Response<S> {
     }
 
     /// Sets a URI reference that identifies the specific occurrence of the problem.
+    /// If it is not set, the request ID in the form of `urn:uuid:{request_id}` is used.
     #[inline]
     pub fn set_instance(&mut self, instance: Option<SharedString>) {
         self.instance = instance;
@@ -228,7 +455,8 @@ impl<S: ResponseCode> Response<S> {
         }
     }
 
-    /// Sets the response data.
+    /// Sets the response data. It is encoded as JSON, MessagePack or CSV
+    /// according to the `accept` header of the request context.
     #[inline]
     pub fn set_data<T: ?Sized + Serialize>(&mut self, data: &T) {
         match serde_json::value::to_raw_value(data) {
@@ -237,13 +465,34 @@ impl<S: ResponseCode> Response<S> {
         }
     }
 
-    /// Sets the response data for the validation.
+    /// Sets the response data with the data type, which overrides the content negotiation.
+    /// Supported data types: `json`, `msgpack` and `csv`.
+    ///
+    /// For `csv`, an array of objects is flattened into rows with the union of keys as the header.
     #[inline]
+    pub fn set_data_as<T: ?Sized + Serialize>(
+        &mut self,
+        data: &T,
+        data_type: impl Into<SharedString>,
+    ) {
+        self.set_data(data);
+        self.data_type = Some(data_type.into());
+    }
+
+    /// Sets the validation errors, which are encoded as an `errors` array
+    /// of objects with the `field` and `message`.
     pub fn set_validation_data(&mut self, validation: Validation) {
-        match serde_json::value::to_raw_value(&validation.into_map()) {
-            Ok(raw_value) => self.data = Some(raw_value),
-            Err(err) => self.set_error_message(err),
-        }
+        let errors = validation
+            .into_map()
+            .into_iter()
+            .map(|(field, message)| {
+                let mut error = Map::new();
+                error.upsert("field", field);
+                error.upsert("message", message);
+                error
+            })
+            .collect();
+        self.errors = Some(errors);
     }
 
     /// Sets the content type.
@@ -254,6 +503,7 @@ impl<S: ResponseCode> Response<S> {
     /// - `application/jsonlines`
     /// - `application/msgpack`
     /// - `application/problem+json`
+    /// - `text/csv`
     /// - `text/html`
     /// - `text/plain`
     #[inline]
@@ -261,6 +511,201 @@ impl<S: ResponseCode> Response<S> {
         self.content_type = Some(content_type.into());
     }
 
+    /// Sets whether the JSON data is indented. It takes precedence over the `pretty` param
+    /// and the `response.pretty` config, which defaults to `false`.
+    #[inline]
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = Some(pretty);
+        self
+    }
+
+    /// Inserts a custom header. The existing value with the same name will be replaced.
+    pub fn insert_header(&mut self, name: impl Into<SharedString>, value: impl ToString) {
+        let name = name.into();
+        let value = value.to_string();
+        if let Some(header) = self
+            .headers
+            .iter_mut()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&name))
+        {
+            header.1 = value;
+        } else {
+            self.headers.push((name, value));
+        }
+    }
+
+    /// Sets the filename of the attachment with the `content-disposition` header.
+    #[inline]
+    pub fn set_attachment_filename(&mut self, filename: &str) {
+        let filename = filename.replace('\\', "\\\\").replace('"', "\\\"");
+        let content_disposition = format!("attachment; filename=\"{filename}\"");
+        self.insert_header("content-disposition", content_disposition);
+    }
+
+    /// Sets the entity tag, which should be a quoted string optionally prefixed with `W/`.
+    #[inline]
+    pub fn set_etag(&mut self, etag: impl Into<SharedString>) {
+        self.etag = Some(etag.into());
+    }
+
+    /// Computes the entity tag from the response data and the data type.
+    /// Since the request ID and timing are excluded, it is stable for identical data.
+    pub fn compute_etag(&mut self, weak: bool) {
+        let mut hasher = Sha256::new();
+        if let Some(data) = &self.data {
+            hasher.update(data.get().as_bytes());
+        }
+        if let Some(data_type) = &self.data_type {
+            hasher.update(b"\n");
+            hasher.update(data_type.as_bytes());
+        }
+        let digest = format!("{:x}", hasher.finalize());
+        let tag = &digest[..32];
+        let etag = if weak {
+            format!("W/\"{tag}\"")
+        } else {
+            format!("\"{tag}\"")
+        };
+        self.etag = Some(etag.into());
+    }
+
+    /// Sets the last modified time of the response data.
+    #[inline]
+    pub fn set_last_modified(&mut self, last_modified: DateTime) {
+        self.last_modified = Some(last_modified);
+    }
+
+    /// Stores the session data in a cookie signed with the key from the `session` config.
+    /// The cookie expires after the `max-age` of the session.
+    pub fn set_session(&mut self, session: &Map) {
+        let session_cookie = SessionCookie::shared();
+        let value = session_cookie.encode(session, DateTime::now().timestamp());
+        let cookie = session_cookie.new_cookie(value);
+        self.insert_header("set-cookie", cookie);
+    }
+
+    /// Removes the session cookie.
+    pub fn clear_session(&mut self) {
+        let mut cookie = SessionCookie::shared().new_cookie(String::new());
+        cookie.make_removal();
+        self.insert_header("set-cookie", cookie);
+    }
+
+    /// Sets the pagination metadata with the total number of items, the page number
+    /// starting from 1 and the number of items per page. It emits the `x-total-count`,
+    /// `x-page` and `link` headers, and embeds a `pagination` object in the JSON body.
+    ///
+    /// The page number and size are consistent with [`Query::page()`](crate::model::Query::page)
+    /// and [`Query::limit()`](crate::model::Query::limit).
+    pub fn set_pagination(&mut self, total: u64, page: u64, per_page: u64) {
+        let page = page.max(1);
+        let per_page = per_page.max(1);
+        let total_pages = (total / per_page + u64::from(total % per_page != 0)).max(1);
+        let (path, query) = self
+            .request_uri
+            .as_deref()
+            .and_then(|uri| uri.split_once('?'))
+            .unwrap_or((self.request_uri.as_deref().unwrap_or_default(), ""));
+        let params = form_urlencoded::parse(query.as_bytes())
+            .filter(|(key, _)| {
+                !matches!(
+                    key.as_ref(),
+                    "page" | "per_page" | "limit" | "offset" | "skip"
+                )
+            })
+            .collect::<Vec<_>>();
+        let page_link = |page: u64, rel: &str| {
+            let query = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(params.iter())
+                .append_pair("page", &page.to_string())
+                .append_pair("per_page", &per_page.to_string())
+                .finish();
+            format!("<{path}?{query}>; rel=\"{rel}\"")
+        };
+        let mut links = vec![page_link(1, "first")];
+        if page > 1 {
+            links.push(page_link(page.min(total_pages + 1) - 1, "prev"));
+        }
+        if page < total_pages {
+            links.push(page_link(page + 1, "next"));
+        }
+        links.push(page_link(total_pages, "last"));
+        self.insert_header("x-total-count", total);
+        self.insert_header("x-page", page);
+        self.insert_header("link", links.join(", "));
+
+        let mut pagination = Map::new();
+        pagination.upsert("total", total);
+        pagination.upsert("page", page);
+        pagination.upsert("per_page", per_page);
+        pagination.upsert("total_pages", total_pages);
+        self.pagination = Some(pagination);
+    }
+
+    /// Sets a stream of chunks as the response body, which takes precedence over the data.
+    #[inline]
+    pub fn set_body_stream(&mut self, stream: impl Stream<Item = Bytes> + Send + 'static) {
+        self.body = Some(ResponseBody::from_stream(stream));
+    }
+
+    /// Reads the request URI and the conditional request headers from the request context.
+    fn read_request<Ctx: RequestContext>(&mut self, ctx: &Ctx) {
+        let request_path = ctx.request_path();
+        self.request_uri = Some(match ctx.query_string() {
+            Some(query) if !query.is_empty() => format!("{request_path}?{query}"),
+            _ => request_path.to_owned(),
+        });
+        if self.pretty.is_none() {
+            // The `pretty` param is only honored in non-production envs.
+            let pretty = ctx
+                .query_string()
+                .and_then(|query| {
+                    form_urlencoded::parse(query.as_bytes()).find_map(|(key, value)| {
+                        (key == "pretty").then_some(value == "1" || value == "true")
+                    })
+                })
+                .filter(|_| State::shared().env() != "prod")
+                .or_else(|| {
+                    ctx.config()
+                        .get_table("response")
+                        .and_then(|config| config.get_bool("pretty"))
+                });
+            self.pretty = pretty;
+        }
+
+        let headers = ctx.header_map();
+        self.if_none_match = headers.get_str("if-none-match").map(|s| s.to_owned());
+        self.if_modified_since = headers
+            .get_str("if-modified-since")
+            .and_then(|s| DateTime::parse_utc_str(s).ok());
+        self.range = headers.get_str("range").map(|s| s.to_owned());
+    }
+
+    /// Returns `true` if the preconditions of the request indicate that
+    /// the client has an up-to-date copy of the response data.
+    /// The `if-modified-since` header is ignored when `if-none-match` is present.
+    fn is_not_modified(&self) -> bool {
+        if !self.is_success() || self.body.is_some() {
+            return false;
+        }
+        if let Some(if_none_match) = &self.if_none_match {
+            let Some(etag) = self.etag.as_deref() else {
+                return false;
+            };
+            let etag = etag.trim_start_matches("W/");
+            return if_none_match.trim() == "*"
+                || if_none_match
+                    .split(',')
+                    .any(|tag| tag.trim().trim_start_matches("W/") == etag);
+        }
+        if let Some(if_modified_since) = self.if_modified_since &&
+            let Some(last_modified) = self.last_modified
+        {
+            return last_modified.timestamp() <= if_modified_since.timestamp();
+        }
+        false
+    }
+
     /// Sets the request ID.
     #[inline]
     pub(crate) fn set_request_id(&mut self, request_id: Uuid) {
@@ -331,10 +776,40 @@ impl<S: ResponseCode> From<Validation> for Response<S> {
     }
 }
 
-impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
+impl<S: ResponseCode> From<Response<S>> for http::Response<ResponseBody> {
     fn from(mut response: Response<S>) -> Self {
-        let status_code = response.status_code;
-        let mut res = if let Some(ref content_type) = response.content_type {
+        let not_modified = response.is_not_modified();
+        let status_code = if not_modified {
+            http::StatusCode::NOT_MODIFIED.as_u16()
+        } else {
+            response.status_code
+        };
+        if response.content_type.is_none() && response.data.is_some() && response.is_success() {
+            response.content_type = match response.data_type.as_deref() {
+                Some("msgpack") => Some("application/msgpack".into()),
+                Some("csv") => Some("text/csv".into()),
+                _ => None,
+            };
+        }
+        let pretty = response.pretty.unwrap_or(false);
+        let mut res = if not_modified {
+            http::Response::builder()
+                .status(status_code)
+                .body(ResponseBody::default())
+                .unwrap_or_default()
+        } else if let Some(body) = response.body.take() {
+            let content_type = response
+                .content_type
+                .as_deref()
+                .unwrap_or("application/octet-stream");
+            let mut builder = http::Response::builder()
+                .status(status_code)
+                .header(header::CONTENT_TYPE, content_type);
+            if content_type.starts_with("text/event-stream") {
+                builder = builder.header(header::CACHE_CONTROL, "no-cache");
+            }
+            builder.body(body).unwrap_or_default()
+        } else if let Some(ref content_type) = response.content_type {
             if let Some(data) = &response.data {
                 let capacity = data.get().len();
                 let result = serde_json::to_value(data)
@@ -347,6 +822,10 @@ impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
                                 rmp_serde::encode::write(&mut bytes, &vec)
                                     .map_err(|err| err.to_string())?;
                                 Ok(bytes)
+                            } else if content_type.starts_with("text/csv") {
+                                let rows =
+                                    vec.iter().filter_map(|v| v.as_object()).collect::<Vec<_>>();
+                                Ok(csv::encode_objects(&rows).into_bytes())
                             } else if content_type.starts_with("application/jsonlines") {
                                 let mut bytes = Vec::with_capacity(capacity);
                                 for value in vec {
@@ -357,7 +836,7 @@ impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
                                 }
                                 Ok(bytes)
                             } else {
-                                Ok(Value::Array(vec).to_string().into_bytes())
+                                encode_json(&Value::Array(vec), pretty)
                             }
                         }
                         Value::Object(map) => {
@@ -366,8 +845,10 @@ impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
                                 rmp_serde::encode::write(&mut bytes, &map)
                                     .map_err(|err| err.to_string())?;
                                 Ok(bytes)
+                            } else if content_type.starts_with("text/csv") {
+                                Ok(csv::encode_objects(&[&map]).into_bytes())
                             } else {
-                                Ok(Value::Object(map).to_string().into_bytes())
+                                encode_json(&Value::Object(map), pretty)
                             }
                         }
                         _ => Err(data.to_string()),
@@ -376,19 +857,19 @@ impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
                     Ok(data) => http::Response::builder()
                         .status(status_code)
                         .header(header::CONTENT_TYPE, content_type.as_ref())
-                        .body(Full::from(data))
+                        .body(data.into())
                         .unwrap_or_default(),
                     Err(err) => http::Response::builder()
                         .status(S::INTERNAL_SERVER_ERROR.status_code())
                         .header(header::CONTENT_TYPE, content_type.as_ref())
-                        .body(Full::from(err))
+                        .body(err.into())
                         .unwrap_or_default(),
                 }
             } else {
                 http::Response::builder()
                     .status(status_code)
                     .header(header::CONTENT_TYPE, content_type.as_ref())
-                    .body(Full::default())
+                    .body(ResponseBody::default())
                     .unwrap_or_default()
             }
         } else {
@@ -397,12 +878,30 @@ impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
             } else {
                 128
             };
+            if !response.is_success() {
+                // Fill in the problem details defined in RFC 7807.
+                if response.type_uri.is_none() {
+                    response.type_uri = Some("about:blank".into());
+                }
+                if response.instance.is_none() && !response.request_id.is_nil() {
+                    let request_id = response.request_id;
+                    response.instance = Some(format!("urn:uuid:{request_id}").into());
+                }
+            }
+
             let mut bytes = Vec::with_capacity(capacity);
-            if let Err(err) = serde_json::to_writer(&mut bytes, &response) {
+            let result = if pretty {
+                // The raw data is converted to a value so that it is indented as well.
+                serde_json::to_value(&response)
+                    .and_then(|value| serde_json::to_writer_pretty(&mut bytes, &value))
+            } else {
+                serde_json::to_writer(&mut bytes, &response)
+            };
+            if let Err(err) = result {
                 http::Response::builder()
                     .status(S::INTERNAL_SERVER_ERROR.status_code())
                     .header(header::CONTENT_TYPE, "text/plain")
-                    .body(Full::from(err.to_string()))
+                    .body(err.to_string().into())
                     .unwrap_or_default()
             } else {
                 let content_type = if response.is_success() {
@@ -413,10 +912,28 @@ impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
                 http::Response::builder()
                     .status(status_code)
                     .header(header::CONTENT_TYPE, content_type)
-                    .body(Full::from(bytes))
+                    .body(bytes.into())
                     .unwrap_or_default()
             }
         };
+        for (name, value) in response.headers.iter() {
+            if let Ok(header_name) = HeaderName::from_bytes(name.as_bytes()) &&
+                let Ok(header_value) = HeaderValue::try_from(value)
+            {
+                res.headers_mut().insert(header_name, header_value);
+            }
+        }
+        if let Some(etag) = response.etag.as_deref() &&
+            let Ok(header_value) = HeaderValue::try_from(etag)
+        {
+            res.headers_mut().insert(header::ETAG, header_value);
+        }
+        if let Some(last_modified) = response.last_modified &&
+            let Ok(header_value) = HeaderValue::try_from(last_modified.to_utc_string())
+        {
+            res.headers_mut().insert(header::LAST_MODIFIED, header_value);
+        }
+
         let (traceparent, tracestate) = if let Some(ref trace_context) = response.trace_context {
             (trace_context.traceparent(), trace_context.tracestate())
         } else {
@@ -460,3 +977,342 @@ impl<S: ResponseCode> From<Response<S>> for http::Response<Full<Bytes>> {
         res
     }
 }
+
+/// Encodes the JSON value as bytes, which are indented if `pretty` is `true`.
+fn encode_json(value: &Value, pretty: bool) -> Result<Vec<u8>, String> {
+    let result = if pretty {
+        serde_json::to_vec_pretty(value)
+    } else {
+        serde_json::to_vec(value)
+    };
+    result.map_err(|err| err.to_string())
+}
+
+/// Selects the data type by the `accept` header of the request context.
+fn select_data_type<Ctx: RequestContext>(ctx: &Ctx) -> Option<SharedString> {
+    ctx.header_map()
+        .select_data_type(&SUPPORTED_DATA_TYPES)
+        .map(|data_type| data_type.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Response, ResponseBody};
+    use crate::{
+        datetime::DateTime, error::Error, extend::JsonObjectExt, request::Validation,
+        response::Rejection, Map, Uuid,
+    };
+    use futures::executor::block_on;
+    use http::StatusCode;
+    use http_body::Body;
+    use serde_json::{json, Value};
+
+    fn encode_response(res: Response<StatusCode>) -> (String, Vec<u8>) {
+        let mut res = http::Response::<ResponseBody>::from(res);
+        let content_type = res.headers()["content-type"].to_str().unwrap().to_owned();
+        let bytes = block_on(res.body_mut().data()).unwrap().unwrap();
+        (content_type, bytes.to_vec())
+    }
+
+    #[test]
+    fn it_encodes_data_by_data_types() {
+        let data = json!([
+            { "age": 18, "name": "alice" },
+            { "email": "bob@example.com", "name": "bob, \"jr\"" },
+        ]);
+
+        let mut res = Response::new(StatusCode::OK);
+        res.set_data(&data);
+        let (content_type, bytes) = encode_response(res);
+        assert_eq!(content_type, "application/json");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&bytes).unwrap()["data"],
+            data
+        );
+
+        let mut res = Response::new(StatusCode::OK);
+        res.set_data_as(&data, "msgpack");
+        let (content_type, bytes) = encode_response(res);
+        assert_eq!(content_type, "application/msgpack");
+        assert_eq!(rmp_serde::from_slice::<Value>(&bytes).unwrap(), data);
+
+        let mut res = Response::new(StatusCode::OK);
+        res.set_data_as(&data, "csv");
+        let (content_type, bytes) = encode_response(res);
+        assert_eq!(content_type, "text/csv");
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "age,name,email\r\n18,alice,\r\n,\"bob, \"\"jr\"\"\",bob@example.com\r\n"
+        );
+    }
+
+    #[test]
+    fn it_encodes_pretty_json() {
+        let data = json!({ "tasks": [{ "id": 1, "status": "done" }] });
+        let encode = |pretty: Option<bool>, content_type: Option<&'static str>| {
+            let mut res = Response::new(StatusCode::OK);
+            if let Some(pretty) = pretty {
+                res = res.pretty(pretty);
+            }
+            if let Some(content_type) = content_type {
+                res.set_content_type(content_type);
+            }
+            res.set_data(&data);
+            let mut res = http::Response::<ResponseBody>::from(res);
+            let size = res.body().size_hint().exact().unwrap();
+            let bytes = block_on(res.body_mut().data()).unwrap().unwrap();
+            assert_eq!(u64::try_from(bytes.len()).unwrap(), size);
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let compact = encode(None, None);
+        assert!(!compact.contains('\n'));
+        assert!(compact.contains(r#""data":{"tasks":[{"id":1,"status":"done"}]}"#));
+        assert_eq!(encode(Some(false), None), compact);
+
+        let pretty = encode(Some(true), None);
+        assert!(pretty.contains("\n  \"data\": {\n    \"tasks\": [\n"));
+        assert_eq!(
+            serde_json::from_str::<Value>(&pretty).unwrap(),
+            serde_json::from_str::<Value>(&compact).unwrap()
+        );
+
+        let pretty = encode(Some(true), Some("application/json"));
+        assert_eq!(pretty, serde_json::to_string_pretty(&data).unwrap());
+    }
+
+    #[cfg(feature = "accessor")]
+    #[test]
+    fn it_streams_objects_from_accessors() {
+        use crate::accessor::GlobalAccessor;
+        use toml::Table;
+
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let content = (0..200_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        block_on(operator.write("videos/intro.mp4", content.clone())).unwrap();
+        let read_body = |mut res: http::Response<ResponseBody>| {
+            let mut bytes = Vec::new();
+            while let Some(chunk) = block_on(res.body_mut().data()) {
+                bytes.extend_from_slice(&chunk.unwrap());
+            }
+            bytes
+        };
+
+        let res = Response::<StatusCode>::new(StatusCode::OK);
+        let res = block_on(res.stream_from_operator(&operator, "videos/intro.mp4")).unwrap();
+        let res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "video/mp4");
+        assert_eq!(res.headers()["content-length"], "200000");
+        assert_eq!(res.headers()["accept-ranges"], "bytes");
+        assert!(res.body().is_stream());
+        assert_eq!(read_body(res), content);
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.range = Some("bytes=100-199".to_owned());
+        let mut res = block_on(res.stream_from_operator(&operator, "videos/intro.mp4")).unwrap();
+        res.set_attachment_filename("intro.mp4");
+        let res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(res.headers()["content-range"], "bytes 100-199/200000");
+        assert_eq!(res.headers()["content-length"], "100");
+        assert_eq!(
+            res.headers()["content-disposition"],
+            "attachment; filename=\"intro.mp4\""
+        );
+        assert_eq!(read_body(res), &content[100..200]);
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.range = Some("bytes=200000-".to_owned());
+        let res = block_on(res.stream_from_operator(&operator, "videos/intro.mp4")).unwrap();
+        let res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(res.headers()["content-range"], "bytes */200000");
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.range = Some("bytes=0-99,200-299".to_owned());
+        let res = block_on(res.stream_from_operator(&operator, "videos/intro.mp4")).unwrap();
+        let res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.range = Some("bytes=abc".to_owned());
+        let res = block_on(res.stream_from_operator(&operator, "videos/intro.mp4")).unwrap();
+        let res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key("content-range"));
+        assert_eq!(read_body(res), content);
+
+        let res = Response::<StatusCode>::new(StatusCode::OK);
+        let result = block_on(res.stream_from_operator(&operator, "videos/missing.mp4"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn it_aborts_streams_on_errors() {
+        use bytes::Bytes;
+        use futures::stream;
+        use std::io;
+
+        let chunks = [
+            Ok(Bytes::from_static(b"partial")),
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection reset")),
+        ];
+        let mut body = ResponseBody::try_from_stream(stream::iter(chunks));
+        block_on(async {
+            assert_eq!(body.data().await.unwrap().unwrap(), "partial");
+            let err = body.data().await.unwrap().unwrap_err();
+            assert_eq!(err.to_string(), "connection reset");
+            assert!(body.data().await.is_none());
+        });
+    }
+
+    #[test]
+    fn it_honors_conditional_requests() {
+        let data = json!({ "tasks": [{ "id": 1, "status": "done" }] });
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.set_data(&data);
+        res.compute_etag(true);
+        let etag = res.etag.clone().unwrap();
+        assert!(etag.starts_with("W/\""));
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.set_data(&data);
+        res.compute_etag(true);
+        res.if_none_match = Some(format!("\"other\", {etag}"));
+        let mut res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()["etag"], &*etag);
+        assert!(block_on(res.body_mut().data()).is_none());
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.set_data(&json!({ "tasks": [] }));
+        res.compute_etag(true);
+        res.if_none_match = Some(etag.into_owned());
+        let res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let last_modified = DateTime::from_timestamp(1_680_000_000);
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.set_data(&data);
+        res.set_last_modified(last_modified);
+        res.if_modified_since = DateTime::parse_utc_str(&last_modified.to_utc_string()).ok();
+        let res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            res.headers()["last-modified"],
+            last_modified.to_utc_string().as_str()
+        );
+    }
+
+    #[test]
+    fn it_sets_pagination_links() {
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.request_uri = Some("/task/list?status=done&page=3&per_page=10".to_owned());
+        res.set_data(&json!([]));
+        res.set_pagination(45, 3, 10);
+        let mut res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.headers()["x-total-count"], "45");
+        assert_eq!(res.headers()["x-page"], "3");
+        assert_eq!(
+            res.headers()["link"],
+            "</task/list?status=done&page=1&per_page=10>; rel=\"first\", \
+                </task/list?status=done&page=2&per_page=10>; rel=\"prev\", \
+                </task/list?status=done&page=4&per_page=10>; rel=\"next\", \
+                </task/list?status=done&page=5&per_page=10>; rel=\"last\""
+        );
+        let bytes = block_on(res.body_mut().data()).unwrap().unwrap();
+        let body = serde_json::from_slice::<Value>(&bytes).unwrap();
+        assert_eq!(
+            body["pagination"],
+            json!({ "total": 45, "page": 3, "per_page": 10, "total_pages": 5 })
+        );
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.set_pagination(45, 5, 10);
+        let link = res.headers.iter().find(|(name, _)| name == "link").unwrap();
+        assert!(link.1.contains("rel=\"prev\""));
+        assert!(!link.1.contains("rel=\"next\""));
+
+        let mut res = Response::<StatusCode>::new(StatusCode::OK);
+        res.set_pagination(45, 1, 10);
+        let link = res.headers.iter().find(|(name, _)| name == "link").unwrap();
+        assert!(!link.1.contains("rel=\"prev\""));
+        assert!(link.1.contains("rel=\"next\""));
+    }
+
+    #[test]
+    fn it_encodes_csv_attachments() {
+        let mut alice = Map::new();
+        alice.upsert("name", "alice");
+        alice.upsert("age", 18);
+        alice.upsert("verified", true);
+        let mut bob = Map::new();
+        bob.upsert("name", "Robert \"Bob\", Jr.");
+        bob.upsert("note", "line1\nline2");
+
+        let mut res =
+            Response::<StatusCode>::csv(&[alice, bob], &["name", "age", "verified", "note"]);
+        res.set_attachment_filename("users.csv");
+        let mut res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.headers()["content-type"], "text/csv");
+        assert_eq!(
+            res.headers()["content-disposition"],
+            "attachment; filename=\"users.csv\""
+        );
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = block_on(res.body_mut().data()) {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "name,age,verified,note\r\nalice,18,true,\r\n\
+                \"Robert \"\"Bob\"\", Jr.\",,,\"line1\nline2\"\r\n"
+        );
+    }
+
+    #[test]
+    fn it_encodes_problem_details() {
+        let request_id = Uuid::new_v4();
+        let mut validation = Validation::new();
+        validation.record("id", "invalid value `42` for the param `id`");
+        validation.record("limit", "should be a positive integer");
+        let mut res = Response::new(StatusCode::BAD_REQUEST);
+        res.set_validation_data(validation);
+        res.set_request_id(request_id);
+        let (content_type, bytes) = encode_response(res);
+        assert_eq!(content_type, "application/problem+json");
+        let problem = serde_json::from_slice::<Map>(&bytes).unwrap();
+        assert_eq!(problem.get_str("type"), Some("about:blank"));
+        assert_eq!(problem.get_str("title"), Some("Bad Request"));
+        assert_eq!(problem.get_u64("status"), Some(400));
+        assert_eq!(
+            problem.get_str("instance"),
+            Some(format!("urn:uuid:{request_id}").as_str())
+        );
+        assert_eq!(
+            problem["errors"],
+            json!([
+                { "field": "id", "message": "invalid value `42` for the param `id`" },
+                { "field": "limit", "message": "should be a positive integer" },
+            ])
+        );
+
+        let rejection =
+            Rejection::internal_server_error(Error::new("fail to connect to the database"));
+        let mut res = http::Response::<ResponseBody>::from(rejection);
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(res.headers()["content-type"], "application/problem+json");
+        let bytes = block_on(res.body_mut().data()).unwrap().unwrap();
+        let problem = serde_json::from_slice::<Map>(&bytes).unwrap();
+        assert_eq!(problem.get_str("type"), Some("about:blank"));
+        assert_eq!(problem.get_str("title"), Some("Internal Server Error"));
+        assert_eq!(problem.get_u64("status"), Some(500));
+        assert_eq!(
+            problem.get_str("detail"),
+            Some("fail to connect to the database")
+        );
+        assert!(!problem.contains_key("errors"));
+    }
+}
@@ -1,14 +1,12 @@
 use self::RejectionKind::*;
-use super::Response;
+use super::{Response, ResponseBody};
 use crate::{
     error::Error,
-    request::{Context, RequestContext, Validation},
+    request::{BodyLimitError, Context, RequestContext, Validation, ValidationError},
     trace::TraceContext,
     SharedString,
 };
-use bytes::Bytes;
 use http::StatusCode;
-use http_body::Full;
 
 /// A rejection response type.
 #[derive(Debug)]
@@ -35,8 +33,14 @@ enum RejectionKind {
     NotFound(Error),
     /// 405 Method Not Allowed
     MethodNotAllowed(Error),
+    /// 408 Request Timeout
+    RequestTimeout(Error),
     /// 409 Conflict
     Conflict(Error),
+    /// 413 Payload Too Large
+    PayloadTooLarge(Error),
+    /// 415 Unsupported Media Type
+    UnsupportedMediaType(Error),
     /// 500 Internal Server Error
     InternalServerError(Error),
 }
@@ -92,6 +96,16 @@ impl<'a> Rejection<'a> {
         }
     }
 
+    /// Creates a `RequestTimeout` rejection.
+    #[inline]
+    pub fn request_timeout(err: impl Into<Error>) -> Self {
+        Self {
+            kind: RequestTimeout(err.into()),
+            context: None,
+            trace_context: None,
+        }
+    }
+
     /// Creates a `Conflict` rejection.
     #[inline]
     pub fn conflict(err: impl Into<Error>) -> Self {
@@ -102,6 +116,26 @@ impl<'a> Rejection<'a> {
         }
     }
 
+    /// Creates a `PayloadTooLarge` rejection.
+    #[inline]
+    pub fn payload_too_large(err: impl Into<Error>) -> Self {
+        Self {
+            kind: PayloadTooLarge(err.into()),
+            context: None,
+            trace_context: None,
+        }
+    }
+
+    /// Creates an `UnsupportedMediaType` rejection.
+    #[inline]
+    pub fn unsupported_media_type(err: impl Into<Error>) -> Self {
+        Self {
+            kind: UnsupportedMediaType(err.into()),
+            context: None,
+            trace_context: None,
+        }
+    }
+
     /// Creates an `InternalServerError` rejection.
     #[inline]
     pub fn internal_server_error(err: impl Into<Error>) -> Self {
@@ -119,6 +153,23 @@ impl<'a> Rejection<'a> {
         Self::bad_request(validation)
     }
 
+    /// Creates a new instance from the error.
+    /// It is a `BadRequest` rejection if the error is caused by a failed validation,
+    /// a `PayloadTooLarge` or `RequestTimeout` rejection if the request body exceeds the limits,
+    /// and an `InternalServerError` rejection otherwise.
+    pub fn from_error(err: impl Into<Error>) -> Self {
+        let err = err.into();
+        if let Some(validation_error) = err.downcast_ref::<ValidationError>() {
+            Self::bad_request(validation_error.to_validation())
+        } else if BodyLimitError::is_too_large(&err) {
+            Self::payload_too_large(err)
+        } else if BodyLimitError::is_timeout(&err) {
+            Self::request_timeout(err)
+        } else {
+            Self::internal_server_error(err)
+        }
+    }
+
     /// Provides the request context for the rejection.
     #[inline]
     pub fn provide_context<T: RequestContext + ?Sized>(mut self, ctx: &'a T) -> Self {
@@ -128,7 +179,7 @@ impl<'a> Rejection<'a> {
     }
 }
 
-impl<'a> From<Rejection<'a>> for http::Response<Full<Bytes>> {
+impl<'a> From<Rejection<'a>> for http::Response<ResponseBody> {
     fn from(rejection: Rejection<'a>) -> Self {
         let mut res = match rejection.kind {
             BadRequest(validation) => {
@@ -156,11 +207,26 @@ impl<'a> From<Rejection<'a>> for http::Response<Full<Bytes>> {
                 res.set_error_message(err);
                 res
             }
+            RequestTimeout(err) => {
+                let mut res = Response::new(StatusCode::REQUEST_TIMEOUT);
+                res.set_error_message(err);
+                res
+            }
             Conflict(err) => {
                 let mut res = Response::new(StatusCode::CONFLICT);
                 res.set_error_message(err);
                 res
             }
+            PayloadTooLarge(err) => {
+                let mut res = Response::new(StatusCode::PAYLOAD_TOO_LARGE);
+                res.set_error_message(err);
+                res
+            }
+            UnsupportedMediaType(err) => {
+                let mut res = Response::new(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+                res.set_error_message(err);
+                res
+            }
             InternalServerError(err) => {
                 let mut res = Response::new(StatusCode::INTERNAL_SERVER_ERROR);
                 res.set_error_message(err);
@@ -168,7 +234,6 @@ impl<'a> From<Rejection<'a>> for http::Response<Full<Bytes>> {
             }
         };
         if let Some(ctx) = rejection.context {
-            res.set_instance(Some(ctx.instance().to_owned().into()));
             res.set_start_time(ctx.start_time());
             res.set_request_id(ctx.request_id());
         }
@@ -203,14 +268,14 @@ impl<'a, T> ExtractRejection<'a, T> for Result<T, Validation> {
 impl<'a, T, E: Into<Error>> ExtractRejection<'a, T> for Result<T, E> {
     #[inline]
     fn extract(self) -> Result<T, Rejection<'a>> {
-        self.map_err(Rejection::internal_server_error)
+        self.map_err(Rejection::from_error)
     }
 }
 
 impl<'a, T, E: Into<Error>> ExtractRejection<'a, T> for Result<Option<T>, E> {
     #[inline]
     fn extract(self) -> Result<T, Rejection<'a>> {
-        self.map_err(Rejection::internal_server_error)?
+        self.map_err(Rejection::from_error)?
             .ok_or_else(|| Rejection::not_found(Error::new("resource does not exit")))
     }
 }
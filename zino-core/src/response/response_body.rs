@@ -0,0 +1,144 @@
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt};
+use http::HeaderMap;
+use http_body::{Body, SizeHint};
+use std::{
+    error, fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Type-erased error which aborts the streaming of a response body.
+pub type BoxError = Box<dyn error::Error + Send + Sync>;
+
+/// An HTTP response body which is either a full buffer or a stream of chunks.
+pub struct ResponseBody {
+    /// Inner body.
+    inner: Inner,
+}
+
+/// Inner body.
+enum Inner {
+    /// A body with a single chunk.
+    Full(Option<Bytes>),
+    /// A body with a stream of chunks.
+    Stream(BoxStream<'static, Result<Bytes, BoxError>>),
+}
+
+impl ResponseBody {
+    /// Creates a new instance with the stream of chunks.
+    /// Each chunk is sent to the client as soon as it is produced.
+    #[inline]
+    pub fn from_stream(stream: impl Stream<Item = Bytes> + Send + 'static) -> Self {
+        Self {
+            inner: Inner::Stream(stream.map(Ok).boxed()),
+        }
+    }
+
+    /// Creates a new instance with the stream of fallible chunks.
+    /// The body is aborted once an error is produced, so that the client
+    /// can not mistake a truncated body for a complete one.
+    #[inline]
+    pub fn try_from_stream<E>(stream: impl Stream<Item = Result<Bytes, E>> + Send + 'static) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        Self {
+            inner: Inner::Stream(stream.map(|chunk| chunk.map_err(|err| err.into())).boxed()),
+        }
+    }
+
+    /// Returns `true` if the body is a stream of chunks.
+    #[inline]
+    pub fn is_stream(&self) -> bool {
+        matches!(self.inner, Inner::Stream(_))
+    }
+}
+
+impl Default for ResponseBody {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            inner: Inner::Full(None),
+        }
+    }
+}
+
+impl From<Bytes> for ResponseBody {
+    #[inline]
+    fn from(bytes: Bytes) -> Self {
+        Self {
+            inner: Inner::Full(Some(bytes)),
+        }
+    }
+}
+
+impl From<Vec<u8>> for ResponseBody {
+    #[inline]
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes::from(bytes).into()
+    }
+}
+
+impl From<String> for ResponseBody {
+    #[inline]
+    fn from(s: String) -> Self {
+        Bytes::from(s).into()
+    }
+}
+
+impl From<&'static str> for ResponseBody {
+    #[inline]
+    fn from(s: &'static str) -> Self {
+        Bytes::from_static(s.as_bytes()).into()
+    }
+}
+
+impl fmt::Debug for ResponseBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.inner {
+            Inner::Full(bytes) => f.debug_tuple("Full").field(bytes).finish(),
+            Inner::Stream(_) => f.debug_tuple("Stream").finish(),
+        }
+    }
+}
+
+impl Body for ResponseBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        match &mut self.get_mut().inner {
+            Inner::Full(bytes) => Poll::Ready(bytes.take().filter(|b| !b.is_empty()).map(Ok)),
+            Inner::Stream(stream) => stream.poll_next_unpin(cx),
+        }
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match &self.inner {
+            Inner::Full(bytes) => bytes.as_ref().map_or(true, |b| b.is_empty()),
+            Inner::Stream(_) => false,
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match &self.inner {
+            Inner::Full(bytes) => {
+                let size = bytes.as_ref().map_or(0, |b| b.len());
+                SizeHint::with_exact(size.try_into().unwrap_or_default())
+            }
+            Inner::Stream(_) => SizeHint::default(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::SharedString;
+use bytes::Bytes;
+use std::time::Duration;
+
+/// An event of Server-Sent Events.
+#[derive(Debug, Clone, Default)]
+pub struct SseEvent {
+    /// Event ID.
+    id: Option<SharedString>,
+    /// Event type.
+    event: Option<SharedString>,
+    /// Event data.
+    data: Option<SharedString>,
+    /// Reconnection time.
+    retry: Option<Duration>,
+    /// Comment.
+    comment: Option<SharedString>,
+}
+
+impl SseEvent {
+    /// Creates a new instance with the data.
+    #[inline]
+    pub fn new(data: impl Into<SharedString>) -> Self {
+        Self {
+            data: Some(data.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a new instance with the comment, which is ignored by the client.
+    #[inline]
+    pub fn with_comment(comment: impl Into<SharedString>) -> Self {
+        Self {
+            comment: Some(comment.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the event ID.
+    #[inline]
+    pub fn set_id(&mut self, id: impl Into<SharedString>) {
+        self.id = Some(id.into());
+    }
+
+    /// Sets the event type.
+    #[inline]
+    pub fn set_event(&mut self, event: impl Into<SharedString>) {
+        self.event = Some(event.into());
+    }
+
+    /// Sets the event data.
+    #[inline]
+    pub fn set_data(&mut self, data: impl Into<SharedString>) {
+        self.data = Some(data.into());
+    }
+
+    /// Sets the reconnection time.
+    #[inline]
+    pub fn set_retry(&mut self, retry: Duration) {
+        self.retry = Some(retry);
+    }
+
+    /// Encodes the event in the wire format. Newlines in the data are split into
+    /// multiple `data:` lines, and the event is terminated by a blank line.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut text = String::new();
+        if let Some(comment) = &self.comment {
+            for line in comment.lines() {
+                text.push_str(": ");
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        if let Some(event) = &self.event {
+            text.push_str("event: ");
+            text.push_str(strip_newlines(event));
+            text.push('\n');
+        }
+        if let Some(data) = &self.data {
+            for line in data.split('\n') {
+                text.push_str("data: ");
+                text.push_str(line.strip_suffix('\r').unwrap_or(line));
+                text.push('\n');
+            }
+        }
+        if let Some(id) = &self.id {
+            text.push_str("id: ");
+            text.push_str(strip_newlines(id));
+            text.push('\n');
+        }
+        if let Some(retry) = self.retry {
+            text.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+        text.push('\n');
+        text.into()
+    }
+}
+
+/// Truncates the str at the first newline, which is not allowed in a field value.
+fn strip_newlines(s: &str) -> &str {
+    s.split(['\r', '\n']).next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SseEvent;
+    use crate::response::{Response, ResponseBody};
+    use futures::{executor::block_on, stream};
+    use http::StatusCode;
+    use http_body::Body;
+    use std::time::Duration;
+
+    #[test]
+    fn it_encodes_sse_events() {
+        let mut progress = SseEvent::new("progress: 50%\nstep: 2");
+        progress.set_event("progress");
+        progress.set_id("1");
+        progress.set_retry(Duration::from_secs(3));
+        let done = SseEvent::new("done");
+        let res = Response::<StatusCode>::sse(stream::iter([progress, done]));
+        let mut res = http::Response::<ResponseBody>::from(res);
+        assert_eq!(res.headers()["content-type"], "text/event-stream");
+        assert_eq!(res.headers()["cache-control"], "no-cache");
+        block_on(async {
+            let body = res.body_mut();
+            assert_eq!(
+                body.data().await.unwrap().unwrap(),
+                "event: progress\ndata: progress: 50%\ndata: step: 2\nid: 1\nretry: 3000\n\n"
+            );
+            assert_eq!(body.data().await.unwrap().unwrap(), "data: done\n\n");
+            assert!(body.data().await.is_none());
+        });
+
+        let events = stream::iter([SseEvent::new("done")]);
+        let heartbeat = stream::repeat(());
+        let res = Response::<StatusCode>::sse_with_heartbeat(events, heartbeat);
+        let mut res = http::Response::<ResponseBody>::from(res);
+        block_on(async {
+            let body = res.body_mut();
+            assert_eq!(body.data().await.unwrap().unwrap(), "data: done\n\n");
+            assert_eq!(body.data().await.unwrap().unwrap(), ": heartbeat\n\n");
+            assert!(body.data().await.is_none());
+        });
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+/// Options for running a scheduled job.
+#[derive(Debug, Clone)]
+pub struct JobConfig {
+    /// A flag to indicate whether the job data is persisted.
+    persist: bool,
+    /// Name of the accessor used to persist the job data and acquire the lock.
+    store: Option<String>,
+    /// A flag to indicate whether the job is executed on only one node per tick.
+    exclusive: bool,
+    /// TTL of the distributed lock.
+    lock_ttl: Duration,
+}
+
+impl JobConfig {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the flag to persist the job data.
+    #[inline]
+    pub fn set_persist(&mut self, persist: bool) {
+        self.persist = persist;
+    }
+
+    /// Sets the name of the accessor used to persist the job data and acquire the lock.
+    /// If it is not specified, the job data is persisted by the default accessor,
+    /// and the lock is acquired by the `redis` accessor.
+    #[inline]
+    pub fn set_store(&mut self, store: impl Into<String>) {
+        self.store = Some(store.into());
+    }
+
+    /// Sets the flag to execute the job on only one node per tick.
+    #[inline]
+    pub fn set_exclusive(&mut self, exclusive: bool) {
+        self.exclusive = exclusive;
+    }
+
+    /// Sets the TTL of the distributed lock. Since the lock is keyed by the tick,
+    /// it only needs to be longer than the clock skew between the nodes.
+    #[inline]
+    pub fn set_lock_ttl(&mut self, lock_ttl: Duration) {
+        self.lock_ttl = lock_ttl;
+    }
+
+    /// Returns `true` if the job data is persisted.
+    #[inline]
+    pub fn is_persistent(&self) -> bool {
+        self.persist
+    }
+
+    /// Returns the name of the accessor used to persist the job data and acquire the lock
+    /// if it has been specified.
+    #[inline]
+    pub fn store(&self) -> Option<&str> {
+        self.store.as_deref()
+    }
+
+    /// Returns `true` if the job is executed on only one node per tick.
+    #[inline]
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// Returns the TTL of the distributed lock.
+    #[inline]
+    pub fn lock_ttl(&self) -> Duration {
+        self.lock_ttl
+    }
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            persist: false,
+            store: None,
+            exclusive: false,
+            lock_ttl: Duration::from_secs(10),
+        }
+    }
+}
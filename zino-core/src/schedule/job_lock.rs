@@ -0,0 +1,20 @@
+use crate::{error::Error, BoxFuture};
+use std::time::Duration;
+
+/// A distributed lock which ensures that a job is executed on only one node per tick.
+///
+/// The lock should be acquired by an atomic create-if-absent operation,
+/// such as the `SET NX PX` command of Redis, since the nodes compete for the same key.
+pub trait JobLock: Send + Sync {
+    /// Attempts to acquire the lock for the key with a TTL.
+    /// Returns `true` if the lock is acquired by the current node.
+    fn try_acquire<'a>(&'a self, key: &'a str, ttl: Duration)
+        -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Attempts to acquire the lock for the key with a TTL in a blocking way,
+    /// which is used by the sync jobs.
+    #[inline]
+    fn try_acquire_blocking(&self, key: &str, ttl: Duration) -> Result<bool, Error> {
+        futures::executor::block_on(self.try_acquire(key, ttl))
+    }
+}
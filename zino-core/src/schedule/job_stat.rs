@@ -0,0 +1,84 @@
+use crate::{datetime::DateTime, Uuid};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Execution statistics of a scheduled job.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStat {
+    /// Job ID.
+    job_id: Uuid,
+    /// Start time of the last run.
+    last_run: Option<DateTime>,
+    /// Duration of the last run.
+    last_duration: Option<Duration>,
+    /// Number of successful runs.
+    success_count: u64,
+    /// Number of failed runs.
+    failure_count: u64,
+    /// Number of consecutive failed runs.
+    consecutive_failures: u64,
+}
+
+impl JobStat {
+    /// Creates a new instance for the job.
+    #[inline]
+    pub(super) fn new(job_id: Uuid) -> Self {
+        Self {
+            job_id,
+            last_run: None,
+            last_duration: None,
+            success_count: 0,
+            failure_count: 0,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Records a run of the job.
+    pub(super) fn record(&mut self, start: DateTime, duration: Duration, succeeded: bool) {
+        self.last_run = Some(start);
+        self.last_duration = Some(duration);
+        if succeeded {
+            self.success_count += 1;
+            self.consecutive_failures = 0;
+        } else {
+            self.failure_count += 1;
+            self.consecutive_failures += 1;
+        }
+    }
+
+    /// Returns the job ID.
+    #[inline]
+    pub fn job_id(&self) -> Uuid {
+        self.job_id
+    }
+
+    /// Returns the start time of the last run.
+    #[inline]
+    pub fn last_run(&self) -> Option<DateTime> {
+        self.last_run
+    }
+
+    /// Returns the duration of the last run.
+    #[inline]
+    pub fn last_duration(&self) -> Option<Duration> {
+        self.last_duration
+    }
+
+    /// Returns the number of successful runs.
+    #[inline]
+    pub fn success_count(&self) -> u64 {
+        self.success_count
+    }
+
+    /// Returns the number of failed runs.
+    #[inline]
+    pub fn failure_count(&self) -> u64 {
+        self.failure_count
+    }
+
+    /// Returns the number of consecutive failed runs.
+    #[inline]
+    pub fn consecutive_failures(&self) -> u64 {
+        self.consecutive_failures
+    }
+}
@@ -1,9 +1,46 @@
 //! Scheduler for sync and async cron jobs.
+//!
+//! Both the extended format with seconds (`"0/30 * * * * *"`) and
+//! the standard five-field crontab format (`"0 9 * * 1-5"`) are supported.
+//! For the latter, the days of week are numbered from `0` (Sunday) to `7` (Sunday).
 
-use crate::{datetime::DateTime, BoxFuture, Map, Uuid};
+use crate::{
+    application::REDACTED_KEYS,
+    datetime::{self, DateTime},
+    error::Error,
+    extend::{JsonObjectExt, TomlTableExt},
+    state::State,
+    BoxFuture, Map, Uuid,
+};
 use chrono::Local;
+use chrono_tz::Tz;
 use cron::Schedule;
-use std::{str::FromStr, time::Duration};
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicI64, Ordering::Relaxed},
+        Arc, LazyLock,
+    },
+    time::{Duration, Instant},
+};
+use toml::Table;
+use tracing::{Instrument, Span};
+
+mod job_config;
+mod job_lock;
+mod job_stat;
+mod scheduler_handle;
+
+#[cfg(feature = "accessor-redis")]
+mod redis_lock;
+
+pub use job_config::JobConfig;
+pub use job_lock::JobLock;
+pub use job_stat::JobStat;
+pub use scheduler_handle::SchedulerHandle;
+
+#[cfg(feature = "accessor-redis")]
+pub use redis_lock::RedisLock;
 
 /// A function pointer of the cron job.
 pub type CronJob = fn(id: Uuid, data: &mut Map, last_tick: DateTime);
@@ -12,18 +49,37 @@ pub type CronJob = fn(id: Uuid, data: &mut Map, last_tick: DateTime);
 pub type AsyncCronJob =
     for<'a> fn(id: Uuid, data: &'a mut Map, last_tick: DateTime) -> BoxFuture<'a>;
 
+/// A function pointer of the fallible async cron job.
+pub type FallibleAsyncCronJob = for<'a> fn(
+    id: Uuid,
+    data: &'a mut Map,
+    last_tick: DateTime,
+) -> BoxFuture<'a, Result<(), Error>>;
+
 /// Exectuable job.
 enum ExecutableJob {
     Fn(CronJob),
     AsyncFn(AsyncCronJob),
+    FallibleAsyncFn(FallibleAsyncCronJob),
 }
 
 /// A schedulable `Job`.
 pub struct Job {
     id: Uuid,
+    name: Option<String>,
     data: Map,
-    schedule: Schedule,
+    schedule: Option<Schedule>,
+    fire_at: Option<chrono::DateTime<Local>>,
+    finished: bool,
     run: ExecutableJob,
+    timezone: Option<Tz>,
+    config: JobConfig,
+    lock: Option<Arc<dyn JobLock>>,
+    stat: JobStat,
+    restored: bool,
+    max_retries: u32,
+    retry_delay: Duration,
+    retry_at: Option<chrono::DateTime<Local>>,
     last_tick: Option<chrono::DateTime<Local>>,
 }
 
@@ -31,27 +87,72 @@ impl Job {
     /// Creates a new `Job`.
     #[inline]
     pub fn new(cron_expr: &str, exec: CronJob) -> Self {
-        let schedule = Schedule::from_str(cron_expr)
-            .unwrap_or_else(|err| panic!("invalid cron expression `{cron_expr}`: {err}"));
-        Job {
-            id: Uuid::new_v4(),
-            data: Map::new(),
-            schedule,
-            run: ExecutableJob::Fn(exec),
-            last_tick: None,
-        }
+        Self::with_schedule(cron_expr, ExecutableJob::Fn(exec))
     }
 
     /// Creates a new async `Job`.
     #[inline]
     pub fn new_async(cron_expr: &str, exec: AsyncCronJob) -> Self {
-        let schedule = Schedule::from_str(cron_expr)
+        Self::with_schedule(cron_expr, ExecutableJob::AsyncFn(exec))
+    }
+
+    /// Creates a new fallible async `Job`. Failed runs will be retried
+    /// with an exponential backoff if the max retries is greater than zero.
+    #[inline]
+    pub fn new_fallible_async(cron_expr: &str, exec: FallibleAsyncCronJob) -> Self {
+        Self::with_schedule(cron_expr, ExecutableJob::FallibleAsyncFn(exec))
+    }
+
+    /// Creates a new `Job` which is executed only once at the specified datetime.
+    /// It will be executed immediately if the datetime is in the past.
+    #[inline]
+    pub fn new_once(datetime: DateTime, exec: CronJob) -> Self {
+        Self::with_fire_time(datetime, ExecutableJob::Fn(exec))
+    }
+
+    /// Creates a new async `Job` which is executed only once at the specified datetime.
+    /// It will be executed immediately if the datetime is in the past.
+    #[inline]
+    pub fn new_async_once(datetime: DateTime, exec: AsyncCronJob) -> Self {
+        Self::with_fire_time(datetime, ExecutableJob::AsyncFn(exec))
+    }
+
+    /// Creates a new `Job` with the cron expression.
+    fn with_schedule(cron_expr: &str, run: ExecutableJob) -> Self {
+        let schedule = parse_cron_expr(cron_expr)
             .unwrap_or_else(|err| panic!("invalid cron expression `{cron_expr}`: {err}"));
+        let mut job = Self::with_executable(run);
+        job.schedule = Some(schedule);
+        job
+    }
+
+    /// Creates a new `Job` with the fire time.
+    fn with_fire_time(datetime: DateTime, run: ExecutableJob) -> Self {
+        let mut job = Self::with_executable(run);
+        job.fire_at = Some(datetime.into());
+        job.last_tick = Some(Local::now());
+        job
+    }
+
+    /// Creates a new `Job` with the executable.
+    fn with_executable(run: ExecutableJob) -> Self {
+        let id = Uuid::new_v4();
         Job {
-            id: Uuid::new_v4(),
+            id,
+            name: None,
             data: Map::new(),
-            schedule,
-            run: ExecutableJob::AsyncFn(exec),
+            schedule: None,
+            fire_at: None,
+            finished: false,
+            run,
+            timezone: None,
+            config: JobConfig::default(),
+            lock: None,
+            stat: JobStat::new(id),
+            restored: false,
+            max_retries: 0,
+            retry_delay: Duration::from_secs(1),
+            retry_at: None,
             last_tick: None,
         }
     }
@@ -62,6 +163,12 @@ impl Job {
         self.id
     }
 
+    /// Returns the job name.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     /// Returns a reference to the job data.
     #[inline]
     pub fn data(&self) -> &Map {
@@ -80,18 +187,114 @@ impl Job {
         self.last_tick = last_tick.map(|dt| dt.into());
     }
 
+    /// Sets the time zone in which the cron schedule is evaluated.
+    /// By default, the local time zone is used.
+    #[inline]
+    pub fn set_timezone(&mut self, timezone: Tz) {
+        self.timezone = Some(timezone);
+    }
+
+    /// Sets the job ID.
+    #[inline]
+    pub fn set_id(&mut self, id: Uuid) {
+        self.id = id;
+        self.stat = JobStat::new(id);
+    }
+
+    /// Sets the job name. It should be stable across restarts and unique in the application,
+    /// which is required if the job data is persisted since the persisted data is keyed by it.
+    #[inline]
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = Some(name.into());
+    }
+
+    /// Sets the job config.
+    #[inline]
+    pub fn set_config(&mut self, config: JobConfig) {
+        self.config = config;
+    }
+
+    /// Sets the distributed lock for an exclusive job. If it is not specified,
+    /// a `RedisLock` with the `redis` accessor named by the configured store will be used,
+    /// and the accessor is named `redis` if the store is not configured.
+    #[inline]
+    pub fn set_lock(&mut self, lock: Arc<dyn JobLock>) {
+        self.lock = Some(lock);
+    }
+
+    /// Sets the max number of retries for failed runs.
+    #[inline]
+    pub fn set_max_retries(&mut self, max_retries: u32) {
+        self.max_retries = max_retries;
+    }
+
+    /// Sets the initial delay between retries. The delay is doubled for each retry.
+    #[inline]
+    pub fn set_retry_delay(&mut self, retry_delay: Duration) {
+        self.retry_delay = retry_delay;
+    }
+
+    /// Returns the execution statistics of the job.
+    #[inline]
+    pub fn stat(&self) -> &JobStat {
+        &self.stat
+    }
+
+    /// Returns `true` if the job is a one-shot job which has been executed.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished && self.retry_at.is_none()
+    }
+
+    /// Returns the next fire time after the specified datetime.
+    /// For a one-shot job, the fire time is returned until it has been executed.
+    fn next_event(&self, after: chrono::DateTime<Local>) -> Option<chrono::DateTime<Local>> {
+        if let Some(fire_at) = self.fire_at {
+            return (!self.finished).then_some(fire_at);
+        }
+
+        let schedule = self.schedule.as_ref()?;
+        if let Some(timezone) = self.timezone {
+            schedule
+                .after(&after.with_timezone(&timezone))
+                .next()
+                .map(|dt| dt.with_timezone(&Local))
+        } else {
+            schedule.after(&after).next()
+        }
+    }
+
     /// Executes missed runs.
     pub fn tick(&mut self) {
+        if !self.restored {
+            self.restored = true;
+            self.restore_data();
+        }
+
         let now = Local::now();
         if let Some(last_tick) = self.last_tick {
-            for event in self.schedule.after(&last_tick) {
-                if event > now {
-                    break;
+            let mut tick = last_tick;
+            while let Some(event) = self.next_event(tick) && event <= now {
+                tick = event;
+                if !self.try_acquire_lock_blocking(event) {
+                    // The tick is handled by another node or skipped,
+                    // so a one-shot job should not fire again.
+                    self.finished = self.fire_at.is_some();
+                    continue;
                 }
                 match self.run {
-                    ExecutableJob::Fn(exec) => exec(self.id, &mut self.data, last_tick.into()),
-                    ExecutableJob::AsyncFn(_) => tracing::warn!("job {} is async", self.id),
+                    ExecutableJob::Fn(exec) => {
+                        let (started_at, instant) = (DateTime::now(), Instant::now());
+                        let span = self.new_span();
+                        span.in_scope(|| exec(self.id, &mut self.data, last_tick.into()));
+                        self.stat.record(started_at, instant.elapsed(), true);
+                    }
+                    _ => tracing::warn!("job {} is async", self.id),
                 }
+                self.finished = self.fire_at.is_some();
+            }
+            if tick > last_tick {
+                self.persist_data();
             }
         }
         self.last_tick = Some(now);
@@ -99,44 +302,378 @@ impl Job {
 
     /// Executes missed runs asynchronously.
     pub async fn tick_async(&mut self) {
+        if !self.restored {
+            self.restored = true;
+            self.restore_data_async().await;
+        }
+
         let now = Local::now();
         if let Some(last_tick) = self.last_tick {
-            for event in self.schedule.after(&last_tick) {
-                if event > now {
-                    break;
+            let mut executed = false;
+            if let Some(retry_at) = self.retry_at && retry_at <= now {
+                self.retry_at = None;
+                if let ExecutableJob::FallibleAsyncFn(exec) = self.run {
+                    self.execute_fallible(exec, last_tick).await;
+                    executed = true;
+                }
+            }
+
+            let mut tick = last_tick;
+            while let Some(event) = self.next_event(tick) && event <= now {
+                tick = event;
+                if !self.try_acquire_lock(event).await {
+                    // The tick is handled by another node or skipped,
+                    // so a one-shot job should not fire again.
+                    self.finished = self.fire_at.is_some();
+                    continue;
                 }
                 match self.run {
                     ExecutableJob::Fn(_) => tracing::warn!("job {} is not async", self.id),
                     ExecutableJob::AsyncFn(exec) => {
-                        exec(self.id, &mut self.data, last_tick.into()).await
+                        let (started_at, instant) = (DateTime::now(), Instant::now());
+                        let span = self.new_span();
+                        exec(self.id, &mut self.data, last_tick.into())
+                            .instrument(span)
+                            .await;
+                        self.stat.record(started_at, instant.elapsed(), true);
+                    }
+                    ExecutableJob::FallibleAsyncFn(exec) => {
+                        self.data.upsert("attempts", 0);
+                        self.execute_fallible(exec, last_tick).await;
                     }
                 }
+                self.finished = self.fire_at.is_some();
+                executed = true;
+            }
+            if executed {
+                self.persist_data_async().await;
             }
         }
         self.last_tick = Some(now);
     }
+
+    /// Creates a new span for an execution of the job. A new request ID is attached
+    /// so that the logs of the execution can be correlated.
+    fn new_span(&self) -> Span {
+        tracing::info_span!(
+            "cron job",
+            "job.id" = self.id.to_string(),
+            "context.request_id" = Uuid::new_v4().to_string(),
+        )
+    }
+
+    /// Attempts to acquire the distributed lock for the tick if the job is exclusive.
+    /// Returns `true` if the job should be executed on the current node.
+    async fn try_acquire_lock(&mut self, tick: chrono::DateTime<Local>) -> bool {
+        if !self.config.is_exclusive() {
+            return true;
+        }
+
+        let job_id = self.id;
+        if let Some((lock, key)) = self.exclusive_lock(tick) {
+            lock.try_acquire(&key, self.config.lock_ttl())
+                .await
+                .inspect_err(|err| {
+                    tracing::error!("fail to acquire the lock of job {job_id}: {err}")
+                })
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// Attempts to acquire the distributed lock for the tick in a blocking way
+    /// if the job is exclusive. Returns `true` if the job should be executed on the current node.
+    fn try_acquire_lock_blocking(&mut self, tick: chrono::DateTime<Local>) -> bool {
+        if !self.config.is_exclusive() {
+            return true;
+        }
+
+        let job_id = self.id;
+        if let Some((lock, key)) = self.exclusive_lock(tick) {
+            lock.try_acquire_blocking(&key, self.config.lock_ttl())
+                .inspect_err(|err| {
+                    tracing::error!("fail to acquire the lock of job {job_id}: {err}")
+                })
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// Returns the distributed lock and the lock key for the tick of an exclusive job.
+    /// The key consists of the job name and the tick, so that the nodes compete
+    /// for the same key and only one of them executes the tick.
+    fn exclusive_lock(
+        &mut self,
+        tick: chrono::DateTime<Local>,
+    ) -> Option<(Arc<dyn JobLock>, String)> {
+        let job_id = self.id;
+        let Some(name) = self.name.as_deref() else {
+            tracing::error!("the name of the exclusive job {job_id} should be specified");
+            return None;
+        };
+        let key = format!("jobs/{name}.lock:{}", tick.timestamp_millis());
+
+        #[cfg(feature = "accessor-redis")]
+        if self.lock.is_none() {
+            match RedisLock::with_accessor(self.config.store().unwrap_or("redis")) {
+                Ok(lock) => self.lock = Some(Arc::new(lock)),
+                Err(err) => tracing::error!("fail to create the lock of job {job_id}: {err}"),
+            }
+        }
+        if let Some(lock) = self.lock.clone() {
+            Some((lock, key))
+        } else {
+            tracing::error!("the lock of the exclusive job {job_id} should be specified");
+            None
+        }
+    }
+
+    /// Executes a fallible async job and schedules a retry if it fails.
+    async fn execute_fallible(
+        &mut self,
+        exec: FallibleAsyncCronJob,
+        last_tick: chrono::DateTime<Local>,
+    ) {
+        let job_id = self.id;
+        let attempts = self.data.get_u32("attempts").unwrap_or_default() + 1;
+        self.data.upsert("attempts", attempts);
+
+        let (started_at, instant) = (DateTime::now(), Instant::now());
+        let span = self.new_span();
+        let result = exec(job_id, &mut self.data, last_tick.into())
+            .instrument(span)
+            .await;
+        self.stat.record(started_at, instant.elapsed(), result.is_ok());
+        if let Err(err) = result {
+            if attempts <= self.max_retries {
+                let delay = self.retry_delay.saturating_mul(1 << (attempts - 1).min(16));
+                let retry_at = chrono::Duration::from_std(delay)
+                    .ok()
+                    .and_then(|delay| Local::now().checked_add_signed(delay));
+                self.retry_at = retry_at;
+                tracing::warn!(
+                    job_id = job_id.to_string(),
+                    job_data = format!("{:?}", self.data.redacted(&REDACTED_KEYS)),
+                    attempts,
+                    "job {job_id} failed and will be retried in {delay:?}: {err}"
+                );
+            } else {
+                tracing::error!(
+                    job_id = job_id.to_string(),
+                    job_data = format!("{:?}", self.data.redacted(&REDACTED_KEYS)),
+                    attempts,
+                    "job {job_id} failed after {attempts} attempts: {err}"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "accessor")]
+impl Job {
+    /// Returns the path of the persisted job data, which is keyed by the job name.
+    fn data_path(&self) -> Option<String> {
+        self.name.as_ref().map(|name| format!("jobs/{name}.json"))
+    }
+
+    /// Restores the job data from the persisted bytes.
+    /// The corrupt data will be discarded.
+    fn restore_data_from_slice(&mut self, bytes: &[u8]) {
+        match serde_json::from_slice(bytes) {
+            Ok(data) => self.data = data,
+            Err(err) => {
+                let job_id = self.id;
+                tracing::error!(
+                    job_id = job_id.to_string(),
+                    "fail to restore the data of job {job_id}: {err}"
+                );
+            }
+        }
+    }
+
+    /// Restores the persisted job data.
+    fn restore_data(&mut self) {
+        if let Some(operator) = self.persistence_operator() &&
+            let Some(path) = self.data_path()
+        {
+            match operator.blocking().read(&path) {
+                Ok(bytes) => self.restore_data_from_slice(&bytes),
+                Err(err) => self.handle_persistence_error(err),
+            }
+        }
+    }
+
+    /// Restores the persisted job data asynchronously.
+    async fn restore_data_async(&mut self) {
+        if let Some(operator) = self.persistence_operator() {
+            self.restore_data_with(&operator).await;
+        }
+    }
+
+    /// Restores the job data persisted by the operator.
+    async fn restore_data_with(&mut self, operator: &opendal::Operator) {
+        if let Some(path) = self.data_path() {
+            match operator.read(&path).await {
+                Ok(bytes) => self.restore_data_from_slice(&bytes),
+                Err(err) => self.handle_persistence_error(err),
+            }
+        }
+    }
+
+    /// Persists the job data.
+    fn persist_data(&self) {
+        if let Some(operator) = self.persistence_operator() &&
+            let Some(path) = self.data_path()
+        {
+            match serde_json::to_vec(&self.data) {
+                Ok(bytes) => {
+                    if let Err(err) = operator.blocking().write(&path, bytes) {
+                        self.handle_persistence_error(err);
+                    }
+                }
+                Err(err) => tracing::error!("fail to serialize the data of job {}: {err}", self.id),
+            }
+        }
+    }
+
+    /// Persists the job data asynchronously.
+    async fn persist_data_async(&self) {
+        if let Some(operator) = self.persistence_operator() {
+            self.persist_data_with(&operator).await;
+        }
+    }
+
+    /// Persists the job data by the operator.
+    async fn persist_data_with(&self, operator: &opendal::Operator) {
+        if let Some(path) = self.data_path() {
+            match serde_json::to_vec(&self.data) {
+                Ok(bytes) => {
+                    if let Err(err) = operator.write(&path, bytes).await {
+                        self.handle_persistence_error(err);
+                    }
+                }
+                Err(err) => tracing::error!("fail to serialize the data of job {}: {err}", self.id),
+            }
+        }
+    }
+
+    /// Returns the operator used to persist the job data.
+    fn persistence_operator(&self) -> Option<opendal::Operator> {
+        let config = &self.config;
+        if !config.is_persistent() {
+            return None;
+        }
+        if self.name.is_none() {
+            let job_id = self.id;
+            tracing::error!("the name of the persistent job {job_id} should be specified");
+            return None;
+        }
+
+        let store = config
+            .store()
+            .unwrap_or_else(crate::accessor::GlobalAccessor::default_name);
+        crate::accessor::GlobalAccessor::try_get(store)
+            .inspect_err(|err| tracing::error!("fail to get the job store `{store}`: {err}"))
+            .ok()
+    }
+
+    /// Handles the error of persisting the job data.
+    fn handle_persistence_error(&self, err: opendal::Error) {
+        if err.kind() != opendal::ErrorKind::NotFound {
+            let job_id = self.id;
+            tracing::error!(
+                job_id = job_id.to_string(),
+                "fail to access the persisted data of job {job_id}: {err}"
+            );
+        }
+    }
+}
+
+#[cfg(not(feature = "accessor"))]
+impl Job {
+    /// Restores the persisted job data.
+    fn restore_data(&mut self) {
+        if self.config.is_persistent() {
+            tracing::warn!("the `accessor` feature should be enabled to persist the job data");
+        }
+    }
+
+    /// Restores the persisted job data asynchronously.
+    async fn restore_data_async(&mut self) {
+        self.restore_data();
+    }
+
+    /// Persists the job data.
+    fn persist_data(&self) {}
+
+    /// Persists the job data asynchronously.
+    async fn persist_data_async(&self) {}
 }
 
 /// A type contains and executes the scheduled jobs.
 #[derive(Default)]
 pub struct JobScheduler {
     jobs: Vec<Job>,
+    handle: SchedulerHandle,
 }
 
 impl JobScheduler {
     /// Creates a new `JobScheduler`.
     #[inline]
     pub fn new() -> Self {
-        Self { jobs: Vec::new() }
+        Self::default()
+    }
+
+    /// Creates a new `JobScheduler` with the handle, so that the jobs can be added
+    /// and the statistics can be retrieved by the handle after the scheduler is moved.
+    #[inline]
+    pub fn with_handle(handle: SchedulerHandle) -> Self {
+        Self {
+            jobs: Vec::new(),
+            handle,
+        }
+    }
+
+    /// Returns a reference to the handle of the `JobScheduler`.
+    #[inline]
+    pub fn handle(&self) -> &SchedulerHandle {
+        &self.handle
     }
 
     /// Adds a job to the `JobScheduler` and returns the job ID.
-    pub fn add(&mut self, job: Job) -> Uuid {
+    /// The configured time zone is used if the job does not specify one.
+    pub fn add(&mut self, mut job: Job) -> Uuid {
+        if job.timezone.is_none() {
+            job.timezone = *SCHEDULER_TIMEZONE;
+        }
+
         let job_id = job.id;
         self.jobs.push(job);
         job_id
     }
 
+    /// Adds a one-shot job to the `JobScheduler` which is executed after the delay,
+    /// and returns the job ID. The job will be dropped after it has been executed.
+    #[inline]
+    pub fn run_after(&mut self, delay: Duration, exec: CronJob) -> Uuid {
+        self.run_at(DateTime::now() + delay, exec)
+    }
+
+    /// Adds a one-shot job to the `JobScheduler` which is executed at the datetime,
+    /// and returns the job ID. The job will be dropped after it has been executed.
+    /// If the datetime is in the past, the job will be executed on the next tick.
+    #[inline]
+    pub fn run_at(&mut self, datetime: DateTime, exec: CronJob) -> Uuid {
+        self.add(Job::new_once(datetime, exec))
+    }
+
+    /// Returns the execution statistics of the jobs.
+    pub fn stats(&self) -> Vec<JobStat> {
+        self.jobs.iter().map(|job| job.stat.clone()).collect()
+    }
+
     /// Removes a job by ID from the `JobScheduler`.
     pub fn remove(&mut self, job_id: Uuid) -> bool {
         let position = self.jobs.iter().position(|job| job.id == job_id);
@@ -152,40 +689,430 @@ impl JobScheduler {
     /// any pending jobs. It is recommended to sleep for at least 500
     /// milliseconds between invocations of this method.
     pub fn tick(&mut self) {
+        self.add_pending_jobs();
         for job in &mut self.jobs {
             job.tick();
         }
+        self.handle.update_stats(self.stats());
+        self.jobs.retain(|job| !job.is_finished());
+        self.heartbeat();
     }
 
     /// The `tick_async` method increments time for the `JobScheduler` and executes
     /// any pending jobs asynchronously. It is recommended to sleep for at least 500
     /// milliseconds between invocations of this method.
     pub async fn tick_async(&mut self) {
+        self.add_pending_jobs();
         for job in &mut self.jobs {
             job.tick_async().await;
         }
+        self.handle.update_stats(self.stats());
+        self.jobs.retain(|job| !job.is_finished());
+        self.heartbeat();
     }
 
     /// The `time_till_next_job` method returns the duration till the next job
     /// is supposed to run. This can be used to sleep until then without waking
     /// up at a fixed interval.
     pub fn time_till_next_job(&self) -> Duration {
-        if self.jobs.is_empty() {
-            Duration::from_millis(500)
-        } else {
-            let mut duration = chrono::Duration::zero();
-            let now = Local::now();
-            for job in self.jobs.iter() {
-                for event in job.schedule.after(&now).take(1) {
-                    let interval = event - now;
-                    if duration.is_zero() || interval < duration {
-                        duration = interval;
-                    }
+        let now = Local::now();
+        self.jobs
+            .iter()
+            .filter_map(|job| {
+                let next_event = job.next_event(now);
+                match (job.retry_at, next_event) {
+                    (Some(retry_at), Some(event)) => Some(retry_at.min(event)),
+                    (retry_at, event) => retry_at.or(event),
                 }
+            })
+            .min()
+            .map(|event| (event - now).to_std().unwrap_or_default())
+            .unwrap_or_else(|| Duration::from_millis(500))
+    }
+
+    /// Adds the jobs which have been added by the handle.
+    fn add_pending_jobs(&mut self) {
+        for job in self.handle.take_pending_jobs() {
+            self.add(job);
+        }
+    }
+
+    /// Records the deadline before which the `JobScheduler` is expected to tick again.
+    fn heartbeat(&self) {
+        let timeout = self.time_till_next_job() + HEARTBEAT_GRACE_PERIOD;
+        let deadline = DateTime::now().timestamp_millis() + timeout.as_millis() as i64;
+        SCHEDULER_DEADLINE.store(deadline, Relaxed);
+    }
+}
+
+/// Checks whether the scheduler keeps ticking. It is healthy if no scheduler has ticked yet.
+pub(crate) fn check_heartbeat() -> Result<(), Error> {
+    let deadline = SCHEDULER_DEADLINE.load(Relaxed);
+    if deadline == 0 || DateTime::now().timestamp_millis() <= deadline {
+        Ok(())
+    } else {
+        let deadline = DateTime::from_timestamp_millis(deadline);
+        Err(Error::new(format!(
+            "the job scheduler has not ticked since {deadline}"
+        )))
+    }
+}
+
+/// Grace period for the scheduler to tick after the expected time,
+/// which also allows the long-running jobs.
+const HEARTBEAT_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Timestamp in milliseconds before which the scheduler is expected to tick again.
+static SCHEDULER_DEADLINE: AtomicI64 = AtomicI64::new(0);
+
+/// Time zone for the scheduler.
+static SCHEDULER_TIMEZONE: LazyLock<Option<Tz>> =
+    LazyLock::new(|| scheduler_timezone(State::shared().config()));
+
+/// Returns the time zone specified by the `scheduler.timezone` field in the config,
+/// which falls back to the default time zone specified by the `timezone` field.
+fn scheduler_timezone(config: &Table) -> Option<Tz> {
+    config
+        .get_table("scheduler")
+        .and_then(|config| config.get_str("timezone"))
+        .and_then(|timezone| {
+            timezone
+                .parse()
+                .inspect_err(|err| tracing::error!("invalid time zone `{timezone}`: {err}"))
+                .ok()
+        })
+        .or_else(datetime::configured_timezone)
+}
+
+/// Parses a cron expression. The standard five-field crontab format is converted
+/// to the extended format with seconds.
+fn parse_cron_expr(cron_expr: &str) -> Result<Schedule, cron::error::Error> {
+    let fields = cron_expr.split_whitespace().collect::<Vec<_>>();
+    if let [minutes, hours, days_of_month, months, days_of_week] = fields.as_slice() {
+        let days_of_week = days_of_week
+            .split(',')
+            .map(convert_days_of_week)
+            .collect::<Vec<_>>()
+            .join(",");
+        let cron_expr = format!("0 {minutes} {hours} {days_of_month} {months} {days_of_week}");
+        Schedule::from_str(&cron_expr)
+    } else {
+        Schedule::from_str(cron_expr)
+    }
+}
+
+/// Converts the crontab numbering of the days of week into names,
+/// since the `cron` crate numbers them from `1` (Sunday) to `7` (Saturday).
+fn convert_days_of_week(spec: &str) -> String {
+    const WEEKDAYS: [&str; 8] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    let (range, step) = match spec.split_once('/') {
+        Some((range, step)) => (range, Some(step)),
+        None => (spec, None),
+    };
+    let weekday = |s: &str| {
+        s.parse::<usize>()
+            .ok()
+            .and_then(|n| WEEKDAYS.get(n))
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| s.to_owned())
+    };
+    let range = match range.split_once('-') {
+        Some(("0", "7")) => "Sun-Sat".to_owned(),
+        Some((start, "7")) if step.is_none() => format!("{}-Sat,Sun", weekday(start)),
+        Some((start, end)) => format!("{}-{}", weekday(start), weekday(end)),
+        None => weekday(range),
+    };
+    match step {
+        Some(step) => format!("{range}/{step}"),
+        None => range,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        scheduler_timezone, Job, JobConfig, JobLock, JobScheduler, Map, SchedulerHandle, Uuid,
+    };
+    use crate::{
+        datetime::{self, DateTime},
+        error::Error,
+        extend::JsonObjectExt,
+        BoxFuture,
+    };
+    use chrono::{Local, TimeZone};
+    use chrono_tz::{America::New_York, UTC};
+    use futures::executor;
+    use parking_lot::Mutex;
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering::Relaxed},
+            Arc,
+        },
+        time::Duration,
+    };
+    use toml::Table;
+
+    fn noop(_id: Uuid, _data: &mut Map, _last_tick: DateTime) {}
+
+    fn fail(_id: Uuid, _data: &mut Map, _last_tick: DateTime) -> BoxFuture<Result<(), Error>> {
+        Box::pin(async { Err(Error::new("always fails")) })
+    }
+
+    #[test]
+    fn it_fires_on_weekdays() {
+        let mut job = Job::new("0 9 * * 1-5", noop);
+        job.set_timezone(UTC);
+
+        // Friday, 2023-04-07 10:00 UTC
+        let friday = UTC.with_ymd_and_hms(2023, 4, 7, 10, 0, 0).unwrap();
+        let event = job.next_event(friday.with_timezone(&Local)).unwrap();
+        let monday = UTC.with_ymd_and_hms(2023, 4, 10, 9, 0, 0).unwrap();
+        assert_eq!(event, monday);
+
+        let event = job.next_event(event).unwrap();
+        let tuesday = UTC.with_ymd_and_hms(2023, 4, 11, 9, 0, 0).unwrap();
+        assert_eq!(event, tuesday);
+    }
+
+    #[test]
+    fn it_fires_across_dst_transitions() {
+        let mut job = Job::new("0 9 * * *", noop);
+        job.set_timezone(New_York);
+
+        // DST starts on 2023-03-12 in New York.
+        let saturday = New_York.with_ymd_and_hms(2023, 3, 11, 9, 0, 0).unwrap();
+        let event = job.next_event(saturday.with_timezone(&Local)).unwrap();
+        let sunday = New_York.with_ymd_and_hms(2023, 3, 12, 9, 0, 0).unwrap();
+        assert_eq!(event, sunday);
+        assert_eq!((event - saturday.with_timezone(&Local)).num_hours(), 23);
+    }
+
+    #[test]
+    fn it_falls_back_to_default_timezone() {
+        let config = r#"
+            timezone = "Asia/Shanghai"
+
+            [scheduler]
+            timezone = "America/New_York"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert_eq!(scheduler_timezone(&config), Some(New_York));
+
+        let config = r#"
+            [scheduler]
+            timezone = "Mars/Olympus_Mons"
+        "#
+        .parse::<Table>()
+        .unwrap();
+        assert_eq!(scheduler_timezone(&config), datetime::configured_timezone());
+        assert_eq!(
+            scheduler_timezone(&Table::new()),
+            datetime::configured_timezone()
+        );
+    }
+
+    #[test]
+    fn it_retries_failed_jobs() {
+        let mut job = Job::new_fallible_async("0 0 * * *", fail);
+        job.set_max_retries(2);
+        job.set_retry_delay(Duration::ZERO);
+
+        let last_tick = Local::now();
+        for attempts in 1..=2 {
+            executor::block_on(job.execute_fallible(fail, last_tick));
+            assert_eq!(job.data().get_u32("attempts"), Some(attempts));
+            assert!(job.retry_at.is_some());
+        }
+
+        job.retry_at = None;
+        executor::block_on(job.execute_fallible(fail, last_tick));
+        assert_eq!(job.data().get_u32("attempts"), Some(3));
+        assert!(job.retry_at.is_none());
+    }
+
+    #[test]
+    fn it_records_failed_runs() {
+        let mut job = Job::new_fallible_async("0 0 * * *", fail);
+        let last_tick = Local::now();
+        for count in 1..=3 {
+            executor::block_on(job.execute_fallible(fail, last_tick));
+
+            let stat = job.stat();
+            assert_eq!(stat.failure_count(), count);
+            assert_eq!(stat.consecutive_failures(), count);
+            assert_eq!(stat.success_count(), 0);
+            assert!(stat.last_run().is_some());
+        }
+    }
+
+    #[cfg(feature = "accessor")]
+    #[test]
+    fn it_discards_corrupt_data() {
+        let mut job = Job::new("0 0 * * *", noop);
+        job.restore_data_from_slice(br#"{"counter": 3}"#);
+        assert_eq!(job.data().get_u64("counter"), Some(3));
+
+        job.restore_data_from_slice(b"{\"counter\": ");
+        assert_eq!(job.data().get_u64("counter"), Some(3));
+    }
+
+    #[cfg(feature = "accessor")]
+    #[test]
+    fn it_restores_data_across_restarts() {
+        use crate::accessor::GlobalAccessor;
+
+        let operator = GlobalAccessor::try_new_operator("memory", &Table::new()).unwrap();
+        let mut job = Job::new("0 0 * * *", noop);
+        job.set_name("daily-report");
+        job.data_mut().upsert("counter", 3);
+        executor::block_on(job.persist_data_with(&operator));
+
+        let mut restarted_job = Job::new("0 0 * * *", noop);
+        restarted_job.set_name("daily-report");
+        assert_ne!(restarted_job.id(), job.id());
+        executor::block_on(restarted_job.restore_data_with(&operator));
+        assert_eq!(restarted_job.data().get_u64("counter"), Some(3));
+
+        let mut other_job = Job::new("0 0 * * *", noop);
+        other_job.set_name("weekly-report");
+        executor::block_on(other_job.restore_data_with(&operator));
+        assert!(other_job.data().is_empty());
+    }
+
+    #[test]
+    fn it_executes_exclusive_jobs_once_per_tick() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        fn count(_id: Uuid, _data: &mut Map, _last_tick: DateTime) {
+            COUNTER.fetch_add(1, Relaxed);
+        }
+
+        #[derive(Default)]
+        struct MemoryLock(Mutex<HashSet<String>>);
+
+        impl JobLock for MemoryLock {
+            fn try_acquire<'a>(
+                &'a self,
+                key: &'a str,
+                _ttl: Duration,
+            ) -> BoxFuture<'a, Result<bool, Error>> {
+                Box::pin(async move { Ok(self.0.lock().insert(key.to_owned())) })
+            }
+        }
+
+        let lock = Arc::new(MemoryLock::default());
+        let mut config = JobConfig::new();
+        config.set_exclusive(true);
+
+        let fire_at = DateTime::now() - Duration::from_secs(60);
+        let mut jobs = (0..3)
+            .map(|_| {
+                let mut job = Job::new_once(fire_at, count);
+                job.set_name("send-reminder");
+                job.set_config(config.clone());
+                job.set_lock(lock.clone());
+                job
+            })
+            .collect::<Vec<_>>();
+        for job in &mut jobs {
+            job.tick();
+        }
+        assert_eq!(COUNTER.load(Relaxed), 1);
+
+        let mut job = Job::new_once(fire_at, count);
+        job.set_config(config);
+        job.set_lock(lock);
+        job.tick();
+        assert_eq!(COUNTER.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn it_finishes_one_shot_jobs_losing_locks() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        fn count(_id: Uuid, _data: &mut Map, _last_tick: DateTime) -> BoxFuture {
+            Box::pin(async {
+                COUNTER.fetch_add(1, Relaxed);
+            })
+        }
+
+        struct HeldLock;
+
+        impl JobLock for HeldLock {
+            fn try_acquire<'a>(
+                &'a self,
+                _key: &'a str,
+                _ttl: Duration,
+            ) -> BoxFuture<'a, Result<bool, Error>> {
+                Box::pin(async { Ok(false) })
             }
-            duration
-                .to_std()
-                .unwrap_or_else(|_| Duration::from_millis(500))
         }
+
+        let mut config = JobConfig::new();
+        config.set_exclusive(true);
+
+        let fire_at = DateTime::now() - Duration::from_secs(60);
+        let mut job = Job::new_async_once(fire_at, count);
+        job.set_name("send-reminder");
+        job.set_config(config.clone());
+        job.set_lock(Arc::new(HeldLock));
+        executor::block_on(job.tick_async());
+        assert_eq!(COUNTER.load(Relaxed), 0);
+        assert!(job.is_finished());
+
+        let mut unnamed_job = Job::new_async_once(fire_at, count);
+        unnamed_job.set_config(config);
+        executor::block_on(unnamed_job.tick_async());
+        assert_eq!(COUNTER.load(Relaxed), 0);
+        assert!(unnamed_job.is_finished());
+    }
+
+    #[test]
+    fn it_adds_jobs_by_handle() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        fn count(_id: Uuid, _data: &mut Map, _last_tick: DateTime) -> BoxFuture {
+            Box::pin(async {
+                COUNTER.fetch_add(1, Relaxed);
+            })
+        }
+
+        let handle = SchedulerHandle::new();
+        let mut scheduler = JobScheduler::with_handle(handle.clone());
+        let job_id = handle.run_at(DateTime::now() - Duration::from_secs(60), count);
+        handle.run_after(Duration::from_secs(60 * 60), count);
+        assert!(handle.stats().is_empty());
+
+        executor::block_on(scheduler.tick_async());
+        assert_eq!(COUNTER.load(Relaxed), 1);
+
+        let stats = handle.stats();
+        assert_eq!(stats.len(), 2);
+        let stat = stats.iter().find(|stat| stat.job_id() == job_id).unwrap();
+        assert_eq!(stat.success_count(), 1);
+
+        executor::block_on(scheduler.tick_async());
+        assert_eq!(COUNTER.load(Relaxed), 1);
+        assert_eq!(handle.stats().len(), 1);
+    }
+
+    #[test]
+    fn it_runs_one_shot_jobs_once() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        fn count(_id: Uuid, _data: &mut Map, _last_tick: DateTime) {
+            COUNTER.fetch_add(1, Relaxed);
+        }
+
+        let mut job = Job::new_once(DateTime::now() - Duration::from_secs(60), count);
+        assert!(!job.is_finished());
+        for _ in 0..3 {
+            job.tick();
+        }
+        assert_eq!(COUNTER.load(Relaxed), 1);
+        assert!(job.is_finished());
+        assert!(job.next_event(Local::now()).is_none());
     }
 }
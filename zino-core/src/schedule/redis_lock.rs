@@ -0,0 +1,111 @@
+use super::JobLock;
+use crate::{error::Error, extend::TomlTableExt, state::State, BoxFuture, Uuid};
+use redis::{Client, Cmd, IntoConnectionInfo};
+use std::{sync::LazyLock, time::Duration};
+use toml::Table;
+
+/// A job lock backed by the Redis service.
+///
+/// The lock is acquired by `SET key node_id NX PX ttl`, which is atomic
+/// so that only one node can hold the lock until it expires.
+#[derive(Debug, Clone)]
+pub struct RedisLock {
+    /// Redis client.
+    client: Client,
+}
+
+impl RedisLock {
+    /// Creates a new instance with the Redis client.
+    #[inline]
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Creates a new instance with the config, which has the same fields
+    /// as the `redis` accessor: `endpoint`, `username`, `password` and `db`.
+    pub fn with_config(config: &Table) -> Result<Self, Error> {
+        let endpoint = config.get_str("endpoint").unwrap_or("tcp://127.0.0.1:6379");
+        let url = match endpoint.strip_prefix("tcp://") {
+            Some(addr) => format!("redis://{addr}"),
+            None => endpoint.to_owned(),
+        };
+        let mut connection_info = url
+            .into_connection_info()
+            .map_err(|err| Error::with_source(format!("invalid endpoint `{endpoint}`"), err))?;
+        if let Some(username) = config.get_str("username") {
+            connection_info.redis.username = Some(username.to_owned());
+        }
+        if let Some(password) = State::decrypt_password(config) {
+            connection_info.redis.password = Some(password.into_owned());
+        }
+        if let Some(db) = config.get_i64("db") {
+            connection_info.redis.db = db;
+        }
+
+        let client = Client::open(connection_info)
+            .map_err(|err| Error::with_source("fail to create the Redis client", err))?;
+        Ok(Self::new(client))
+    }
+
+    /// Creates a new instance with the config of the `redis` accessor with the name.
+    pub fn with_accessor(name: &str) -> Result<Self, Error> {
+        let accessors = State::shared().config().get_array("accessor");
+        let config = accessors
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_table())
+            .find(|accessor| {
+                accessor.get_str("scheme") == Some("redis")
+                    && accessor.get_str("name").unwrap_or("redis") == name
+            })
+            .ok_or_else(|| Error::new(format!("the redis accessor `{name}` does not exist")))?;
+        Self::with_config(config)
+    }
+
+    /// Returns the command to acquire the lock.
+    fn new_command(key: &str, ttl: Duration) -> Cmd {
+        let ttl = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key)
+            .arg(NODE_ID.as_str())
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl);
+        cmd
+    }
+}
+
+impl JobLock for RedisLock {
+    fn try_acquire<'a>(
+        &'a self,
+        key: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut conn = self
+                .client
+                .get_async_connection()
+                .await
+                .map_err(|err| Error::with_source("fail to connect to the lock store", err))?;
+            let reply = Self::new_command(key, ttl)
+                .query_async::<_, Option<String>>(&mut conn)
+                .await
+                .map_err(|err| Error::with_source("fail to acquire the lock", err))?;
+            Ok(reply.is_some())
+        })
+    }
+
+    fn try_acquire_blocking(&self, key: &str, ttl: Duration) -> Result<bool, Error> {
+        let mut conn = self
+            .client
+            .get_connection()
+            .map_err(|err| Error::with_source("fail to connect to the lock store", err))?;
+        let reply = Self::new_command(key, ttl)
+            .query::<Option<String>>(&mut conn)
+            .map_err(|err| Error::with_source("fail to acquire the lock", err))?;
+        Ok(reply.is_some())
+    }
+}
+
+/// ID of the current node.
+static NODE_ID: LazyLock<String> = LazyLock::new(|| Uuid::new_v4().to_string());
@@ -0,0 +1,62 @@
+use super::{AsyncCronJob, Job, JobStat};
+use crate::{datetime::DateTime, Uuid};
+use parking_lot::{Mutex, RwLock};
+use std::{mem, sync::Arc, time::Duration};
+
+/// A handle to a [`JobScheduler`](super::JobScheduler), which can be shared to add jobs
+/// and retrieve the execution statistics while the scheduler is running.
+/// The jobs added by the handle are picked up by the scheduler on the next tick.
+#[derive(Clone, Default)]
+pub struct SchedulerHandle {
+    /// Jobs to be added to the scheduler.
+    pending_jobs: Arc<Mutex<Vec<Job>>>,
+    /// Execution statistics of the jobs as of the last tick.
+    stats: Arc<RwLock<Vec<JobStat>>>,
+}
+
+impl SchedulerHandle {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a job to the scheduler and returns the job ID.
+    pub fn add(&self, job: Job) -> Uuid {
+        let job_id = job.id();
+        self.pending_jobs.lock().push(job);
+        job_id
+    }
+
+    /// Adds a one-shot async job to the scheduler which is executed after the delay,
+    /// and returns the job ID. The scheduler should tick asynchronously.
+    #[inline]
+    pub fn run_after(&self, delay: Duration, exec: AsyncCronJob) -> Uuid {
+        self.run_at(DateTime::now() + delay, exec)
+    }
+
+    /// Adds a one-shot async job to the scheduler which is executed at the datetime,
+    /// and returns the job ID. The scheduler should tick asynchronously.
+    #[inline]
+    pub fn run_at(&self, datetime: DateTime, exec: AsyncCronJob) -> Uuid {
+        self.add(Job::new_async_once(datetime, exec))
+    }
+
+    /// Returns the execution statistics of the jobs as of the last tick.
+    #[inline]
+    pub fn stats(&self) -> Vec<JobStat> {
+        self.stats.read().clone()
+    }
+
+    /// Takes the jobs to be added to the scheduler.
+    #[inline]
+    pub(super) fn take_pending_jobs(&self) -> Vec<Job> {
+        mem::take(&mut *self.pending_jobs.lock())
+    }
+
+    /// Updates the execution statistics of the jobs.
+    #[inline]
+    pub(super) fn update_stats(&self, stats: Vec<JobStat>) {
+        *self.stats.write() = stats;
+    }
+}
@@ -1,10 +1,12 @@
 //! Application or request scoped state.
 
-use crate::{application, crypto, extend::TomlTableExt, format::base64, Map};
+use crate::{application, crypto, error::Error, extend::TomlTableExt, format::base64, Map};
+use parking_lot::{Mutex, RwLock};
 use std::{
     borrow::Cow,
     env, fs,
     net::{IpAddr, SocketAddr},
+    path::Path,
     sync::LazyLock,
 };
 use toml::value::{Table, Value};
@@ -32,20 +34,23 @@ impl State {
     }
 
     /// Loads the config file according to the specific env.
+    /// The profile config `config.{env}.toml` is merged over the base config `config.toml`
+    /// if it exists, where the arrays of tables with the `name` field are merged by names.
+    /// Values can be overridden by the env vars prefixed with `ZINO__`,
+    /// where the nested keys and array indexes are delimited by `__`,
+    /// such as `ZINO__ACCESSOR__0__ENDPOINT` for the `endpoint` of the first accessor.
+    /// String values can refer to secrets with `${env:VAR}` or `${file:/path}`,
+    /// which are resolved after the overrides.
     pub fn load_config(&mut self) {
-        let env = self.env;
-        let config_file = application::PROJECT_DIR.join(format!("./config/config.{env}.toml"));
-        let config: Value = fs::read_to_string(&config_file)
-            .unwrap_or_else(|err| {
-                let config_file = config_file.to_string_lossy();
-                panic!("fail to read the config file `{config_file}`: {err}");
-            })
-            .parse()
-            .expect("fail to parse toml value");
-        match config {
-            Value::Table(table) => self.config = table,
-            _ => panic!("toml config file should be a table"),
-        }
+        self.config = read_config(self.env).unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /// Replaces the config and returns the dotted paths of the changed values.
+    pub fn update_config(&mut self, config: Table) -> Vec<String> {
+        let mut changes = Vec::new();
+        diff_config(&self.config, &config, "", &mut changes);
+        self.config = config;
+        changes
     }
 
     /// Set the state data.
@@ -115,39 +120,49 @@ impl State {
     }
 
     /// Encrypts the password in the config.
+    #[inline]
     pub fn encrypt_password(config: &Table) -> Option<Cow<'_, str>> {
-        let password = config.get_str("password")?;
-        application::SECRET_KEY.get().and_then(|key| {
-            if let Ok(data) = base64::decode(password) &&
-                crypto::decrypt(key, &data).is_ok()
+        Self::encrypt_secret(config, "password")
+    }
+
+    /// Decrypts the password in the config.
+    #[inline]
+    pub fn decrypt_password(config: &Table) -> Option<Cow<'_, str>> {
+        Self::decrypt_secret(config, "password")
+    }
+
+    /// Encrypts the secret value corresponding to the key in the config.
+    pub fn encrypt_secret<'a>(config: &'a Table, key: &str) -> Option<Cow<'a, str>> {
+        let secret = config.get_str(key)?;
+        application::SECRET_KEY.get().and_then(|secret_key| {
+            if let Ok(data) = base64::decode(secret) &&
+                crypto::decrypt(secret_key, &data).is_ok()
             {
-                Some(password.into())
+                Some(secret.into())
             } else {
-                crypto::encrypt(key, password.as_bytes())
-                    .inspect_err(|_| tracing::error!("fail to encrypt the password"))
+                crypto::encrypt(secret_key, secret.as_bytes())
+                    .inspect_err(|_| tracing::error!("fail to encrypt the `{key}` value"))
                     .ok()
                     .map(|bytes| base64::encode(bytes).into())
             }
         })
     }
 
-    /// Decrypts the password in the config.
-    pub fn decrypt_password(config: &Table) -> Option<Cow<'_, str>> {
-        let password = config.get_str("password")?;
-        if let Ok(data) = base64::decode(password) {
-            if let Some(key) = application::SECRET_KEY.get() &&
-                let Ok(plaintext) = crypto::decrypt(key, &data)
+    /// Decrypts the secret value corresponding to the key in the config.
+    /// The raw value is returned if it has not been encrypted.
+    pub fn decrypt_secret<'a>(config: &'a Table, key: &str) -> Option<Cow<'a, str>> {
+        let secret = config.get_str(key)?;
+        if let Ok(data) = base64::decode(secret) {
+            if let Some(secret_key) = application::SECRET_KEY.get() &&
+                let Ok(plaintext) = crypto::decrypt(secret_key, &data)
             {
                 return Some(plaintext.into());
             }
         }
-        if let Some(encrypted_password) = Self::encrypt_password(config).as_deref() {
-            tracing::warn!(
-                encrypted_password,
-                "raw passowrd `{password}` should be encypted"
-            );
+        if let Some(encrypted_value) = Self::encrypt_secret(config, key).as_deref() {
+            tracing::warn!(encrypted_value, "raw value of `{key}` should be encypted");
         }
-        Some(password.into())
+        Some(secret.into())
     }
 
     /// Formats the authority in the config.
@@ -182,6 +197,268 @@ impl State {
     pub(crate) fn shared() -> &'static State {
         LazyLock::force(&SHARED_STATE)
     }
+
+    /// Re-reads the config file of the shared state and applies the changes
+    /// to the reloadable subsystems, such as the log filter and rate limits.
+    /// It returns the dotted paths of the changed values.
+    ///
+    /// Note that the shared state itself is immutable, and the subsystems built from it
+    /// at startup, including the accessors, connectors and database pools, require a restart.
+    pub fn reload() -> Result<Vec<String>, Error> {
+        let mut state = RELOADABLE_STATE.write();
+        let config = read_config(state.env)?;
+        let changes = state.update_config(config);
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+
+        let config = state.config.clone();
+        drop(state);
+        tracing::info!(changes = changes.join(", "), "config has been reloaded");
+        for hook in RELOAD_HOOKS.lock().iter() {
+            hook(&config);
+        }
+        Ok(changes)
+    }
+
+    /// Registers a hook which is called with the new config after a reload.
+    #[inline]
+    pub fn on_reload(hook: fn(&Table)) {
+        RELOAD_HOOKS.lock().push(hook);
+    }
+}
+
+/// Prefix of the env vars overriding the config values.
+const CONFIG_ENV_PREFIX: &str = "ZINO__";
+
+/// Reads the config for the env with the overrides from env vars
+/// and resolves the secret references. The precedence from low to high is
+/// the base config, the profile config, and the env vars.
+fn read_config(env: &str) -> Result<Table, Error> {
+    let config_dir = application::PROJECT_DIR.join("./config");
+    let base_config_file = config_dir.join("config.toml");
+    let mut config = if base_config_file.exists() {
+        read_config_file(&base_config_file)?
+    } else {
+        Table::new()
+    };
+    let profile_config = read_config_file(&config_dir.join(format!("config.{env}.toml")))?;
+    merge_config(&mut config, profile_config);
+    override_config(&mut config, env::vars())?;
+    for (key, value) in config.iter_mut() {
+        resolve_value(value, key)?;
+    }
+    Ok(config)
+}
+
+/// Reads the config file as a toml table.
+fn read_config_file(config_file: &Path) -> Result<Table, Error> {
+    let config_file_name = config_file.to_string_lossy();
+    let config = fs::read_to_string(config_file).map_err(|err| {
+        let message = format!("fail to read the config file `{config_file_name}`");
+        Error::with_source(message, err)
+    })?;
+    match config.parse() {
+        Ok(Value::Table(table)) => Ok(table),
+        Ok(_) => Err(Error::new("toml config file should be a table")),
+        Err(err) => {
+            let message = format!("fail to parse the config file `{config_file_name}`");
+            Err(Error::with_source(message, err))
+        }
+    }
+}
+
+/// Merges the overlay config into the base config. Tables are merged recursively.
+/// If every element of an overlay array is a table with the `name` field,
+/// the element is merged into the base table with the same name or appended otherwise.
+/// Other values are replaced.
+fn merge_config(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge_config(base, overlay),
+            (Some(Value::Array(base)), Value::Array(overlay))
+                if overlay
+                    .iter()
+                    .all(|v| v.get("name").is_some_and(|v| v.is_str())) =>
+            {
+                for value in overlay {
+                    let name = value.get("name").cloned();
+                    let base_table = base.iter_mut().find_map(|v| match v {
+                        Value::Table(table) if table.get("name") == name.as_ref() => Some(table),
+                        _ => None,
+                    });
+                    match (base_table, value) {
+                        (Some(base), Value::Table(overlay)) => merge_config(base, overlay),
+                        (_, value) => base.push(value),
+                    }
+                }
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Overrides the config values with the prefixed env vars.
+fn override_config(
+    config: &mut Table,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<(), Error> {
+    for (name, value) in vars {
+        if let Some(path) = name.strip_prefix(CONFIG_ENV_PREFIX) {
+            let segments = path.split("__").collect::<Vec<_>>();
+            let Some((key, segments)) = segments.split_first() else {
+                continue;
+            };
+            if key.is_empty() {
+                continue;
+            }
+            let entry = table_entry(config, key, segments);
+            override_value(entry, segments, &value).map_err(|err| {
+                let message = format!("fail to override the config with the env var `{name}`");
+                Error::with_source(message, err)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Overrides the value at the path with the raw value of an env var.
+fn override_value(target: &mut Value, segments: &[&str], value: &str) -> Result<(), Error> {
+    let Some((segment, segments)) = segments.split_first() else {
+        *target = coerce_value(target, value)?;
+        return Ok(());
+    };
+    let entry = match target {
+        Value::Table(table) => table_entry(table, segment, segments),
+        Value::Array(array) => {
+            let index = segment.parse::<usize>().map_err(|err| {
+                Error::with_source(format!("`{segment}` is not a valid array index"), err)
+            })?;
+            if index == array.len() {
+                array.push(new_value(segments));
+            }
+            array
+                .get_mut(index)
+                .ok_or_else(|| Error::new(format!("array index `{index}` is out of bounds")))?
+        }
+        _ => return Err(Error::new(format!("`{segment}` is not a nested key"))),
+    };
+    override_value(entry, segments, value)
+}
+
+/// Returns the entry for the key in the env var case, which is inserted if absent.
+/// The key matches a config key case-insensitively with `_` in place of `-`.
+fn table_entry<'a>(table: &'a mut Table, key: &str, segments: &[&str]) -> &'a mut Value {
+    let key = table
+        .keys()
+        .find(|k| k.replace('-', "_").eq_ignore_ascii_case(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_ascii_lowercase().replace('_', "-"));
+    table.entry(key).or_insert_with(|| new_value(segments))
+}
+
+/// Creates a new value for the remaining path.
+fn new_value(segments: &[&str]) -> Value {
+    match segments.first() {
+        Some(segment) if segment.parse::<usize>().is_ok() => Value::Array(Vec::new()),
+        Some(_) => Value::Table(Table::new()),
+        None => Value::String(String::new()),
+    }
+}
+
+/// Coerces the raw value of an env var to the type of the value it overrides.
+/// Arrays and tables are parsed as inline toml values.
+fn coerce_value(target: &Value, value: &str) -> Result<Value, Error> {
+    let value = match target {
+        Value::String(_) => Value::String(value.to_owned()),
+        Value::Integer(_) => Value::Integer(value.parse()?),
+        Value::Float(_) => Value::Float(value.parse()?),
+        Value::Boolean(_) => Value::Boolean(value.parse()?),
+        Value::Datetime(_) => Value::Datetime(value.parse()?),
+        Value::Array(_) | Value::Table(_) => {
+            let mut table = toml::from_str::<Table>(&format!("value = {value}"))?;
+            table.remove("value").unwrap_or_default()
+        }
+    };
+    Ok(value)
+}
+
+/// Resolves the `${env:VAR}` and `${file:/path}` references in the string values.
+fn resolve_value(value: &mut Value, path: &str) -> Result<(), Error> {
+    match value {
+        Value::String(s) if s.contains("${") => {
+            *s = resolve_references(s).map_err(|err| {
+                let message = format!("fail to resolve the config value of `{path}`");
+                Error::with_source(message, err)
+            })?;
+        }
+        Value::Array(array) => {
+            for (index, value) in array.iter_mut().enumerate() {
+                resolve_value(value, &format!("{path}[{index}]"))?;
+            }
+        }
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                resolve_value(value, &format!("{path}.{key}"))?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Replaces the references in a str with the env vars or the file contents.
+/// Trailing whitespaces of the file contents are trimmed,
+/// and the placeholders in other forms are kept as they are.
+fn resolve_references(s: &str) -> Result<String, Error> {
+    let mut resolved = String::new();
+    let mut remaining = s;
+    while let Some(start) = remaining.find("${") &&
+        let Some(len) = remaining[start..].find('}')
+    {
+        let end = start + len + 1;
+        let reference = &remaining[start + 2..end - 1];
+        resolved.push_str(&remaining[..start]);
+        if let Some(var) = reference.strip_prefix("env:") {
+            let value = env::var(var).map_err(|err| {
+                Error::with_source(format!("fail to get the env var `{var}`"), err)
+            })?;
+            resolved.push_str(&value);
+        } else if let Some(file) = reference.strip_prefix("file:") {
+            let value = fs::read_to_string(file).map_err(|err| {
+                Error::with_source(format!("fail to read the secret file `{file}`"), err)
+            })?;
+            resolved.push_str(value.trim_end());
+        } else {
+            resolved.push_str(&remaining[start..end]);
+        }
+        remaining = &remaining[end..];
+    }
+    resolved.push_str(remaining);
+    Ok(resolved)
+}
+
+/// Collects the dotted paths of the values which differ between two tables.
+fn diff_config(old: &Table, new: &Table, prefix: &str, changes: &mut Vec<String>) {
+    let keys = old
+        .keys()
+        .chain(new.keys().filter(|key| !old.contains_key(*key)));
+    for key in keys {
+        let path = if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match (old.get(key), new.get(key)) {
+            (Some(Value::Table(old)), Some(Value::Table(new))) => {
+                diff_config(old, new, &path, changes);
+            }
+            (old, new) if old != new => changes.push(path),
+            _ => (),
+        }
+    }
 }
 
 impl Default for State {
@@ -190,9 +467,12 @@ impl Default for State {
     }
 }
 
-/// Shared application state.
+/// Shared application state. The env is specified by the `--env` flag,
+/// or the `ZINO_ENV` env var, and defaults to `dev`.
 pub(crate) static SHARED_STATE: LazyLock<State> = LazyLock::new(|| {
-    let mut app_env = "dev";
+    let mut app_env = env::var("ZINO_ENV")
+        .map(|value| &*value.leak())
+        .unwrap_or("dev");
     for arg in env::args().skip(1) {
         if let Some(value) = arg.strip_prefix("--env=") {
             app_env = value.to_owned().leak();
@@ -203,3 +483,210 @@ pub(crate) static SHARED_STATE: LazyLock<State> = LazyLock::new(|| {
     state.load_config();
     state
 });
+
+/// Reloadable application state.
+static RELOADABLE_STATE: LazyLock<RwLock<State>> =
+    LazyLock::new(|| RwLock::new(SHARED_STATE.clone()));
+
+/// Hooks called after a reload.
+static RELOAD_HOOKS: Mutex<Vec<fn(&Table)>> = Mutex::new(Vec::new());
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_config, override_config, read_config_file, resolve_value, State};
+    use crate::{application::SECRET_KEY, extend::TomlTableExt};
+    use std::{env, fs};
+    use toml::Table;
+
+    #[test]
+    fn it_decrypts_secret() {
+        SECRET_KEY.get_or_init(|| [7; 64]);
+
+        let mut config = Table::new();
+        config.insert("secret-access-key".to_owned(), "plaintext".into());
+        let encrypted_value = State::encrypt_secret(&config, "secret-access-key")
+            .unwrap()
+            .into_owned();
+        assert_ne!(encrypted_value, "plaintext");
+
+        config.insert("secret-access-key".to_owned(), encrypted_value.into());
+        assert_eq!(
+            State::decrypt_secret(&config, "secret-access-key").as_deref(),
+            Some("plaintext"),
+        );
+        assert_eq!(State::decrypt_secret(&config, "absent-key"), None);
+    }
+
+    #[test]
+    fn it_reloads_config() {
+        let config_file = env::temp_dir().join("zino-reload-test.toml");
+        fs::write(&config_file, "[rate-limit]\nrate = 10.0\nburst = 20\n").unwrap();
+
+        let mut state = State::new("test");
+        state.update_config(read_config_file(&config_file).unwrap());
+        fs::write(&config_file, "[rate-limit]\nrate = 5.0\nburst = 20\n").unwrap();
+        let changes = state.update_config(read_config_file(&config_file).unwrap());
+        assert_eq!(changes, vec!["rate-limit.rate"]);
+        assert_eq!(
+            state
+                .config()
+                .get_table("rate-limit")
+                .and_then(|config| config.get_f64("rate")),
+            Some(5.0)
+        );
+        assert!(state
+            .update_config(read_config_file(&config_file).unwrap())
+            .is_empty());
+
+        fs::write(&config_file, "rate-limit = 5").unwrap();
+        let changes = state.update_config(read_config_file(&config_file).unwrap());
+        assert_eq!(changes, vec!["rate-limit"]);
+        fs::remove_file(&config_file).unwrap();
+        assert!(read_config_file(&config_file).is_err());
+    }
+
+    #[test]
+    fn it_overrides_config_with_env_vars() {
+        let mut config = toml::from_str::<Table>(
+            r#"
+            [rate-limit]
+            rate = 10.0
+            burst = 20
+
+            [[accessor]]
+            scheme = "s3"
+            name = "assets"
+            endpoint = "http://127.0.0.1:9000"
+            "#,
+        )
+        .unwrap();
+        let vars = [
+            ("ZINO__ACCESSOR__0__ENDPOINT", "https://s3.amazonaws.com"),
+            ("ZINO__ACCESSOR__0__SECRET_ACCESS_KEY", "secret"),
+            ("ZINO__ACCESSOR__1__NAME", "backups"),
+            ("ZINO__RATE_LIMIT__BURST", "30"),
+            ("ZINO__RATE_LIMIT__KEY_HEADER", "x-api-key"),
+            ("CARGO_PKG_NAME", "zino-core"),
+        ];
+        let vars = vars.map(|(name, value)| (name.to_owned(), value.to_owned()));
+        override_config(&mut config, vars).unwrap();
+
+        let accessors = config.get_array("accessor").unwrap();
+        let accessor = accessors[0].as_table().unwrap();
+        assert_eq!(
+            accessor.get_str("endpoint"),
+            Some("https://s3.amazonaws.com")
+        );
+        assert_eq!(accessor.get_str("secret-access-key"), Some("secret"));
+        assert_eq!(
+            accessors[1].as_table().and_then(|v| v.get_str("name")),
+            Some("backups")
+        );
+
+        let rate_limit = config.get_table("rate-limit").unwrap();
+        assert_eq!(rate_limit.get_i64("burst"), Some(30));
+        assert_eq!(rate_limit.get_str("key-header"), Some("x-api-key"));
+        assert!(!config.contains_key("cargo-pkg-name"));
+
+        let vars = [("ZINO__RATE_LIMIT__BURST".to_owned(), "many".to_owned())];
+        assert!(override_config(&mut config, vars).is_err());
+        let vars = [("ZINO__ACCESSOR__3__NAME".to_owned(), "logs".to_owned())];
+        assert!(override_config(&mut config, vars).is_err());
+    }
+
+    #[test]
+    fn it_resolves_secret_references() {
+        let secret_file = env::temp_dir().join("zino-secret-test.txt");
+        fs::write(&secret_file, "file-secret\n").unwrap();
+        env::set_var("ZINO_TEST_ACCESS_KEY_ID", "env-secret");
+
+        let secret_file = secret_file.to_string_lossy();
+        let mut accessor = Table::new();
+        accessor.insert(
+            "access-key-id".to_owned(),
+            "${env:ZINO_TEST_ACCESS_KEY_ID}".into(),
+        );
+        accessor.insert(
+            "secret-access-key".to_owned(),
+            format!("${{file:{secret_file}}}").into(),
+        );
+        accessor.insert("endpoint".to_owned(), "http://${host}:9000".into());
+        let mut value = toml::Value::Array(vec![accessor.into()]);
+        resolve_value(&mut value, "accessor").unwrap();
+
+        let accessor = value[0].as_table().unwrap();
+        assert_eq!(accessor.get_str("access-key-id"), Some("env-secret"));
+        assert_eq!(accessor.get_str("secret-access-key"), Some("file-secret"));
+        assert_eq!(accessor.get_str("endpoint"), Some("http://${host}:9000"));
+
+        let mut value = toml::Value::from("${env:ZINO_TEST_MISSING_KEY}");
+        let err = resolve_value(&mut value, "accessor[0].account-key").unwrap_err();
+        assert!(err.to_string().contains(
+            "`accessor[0].account-key`: fail to get the env var `ZINO_TEST_MISSING_KEY`"
+        ));
+        fs::remove_file(&*secret_file).unwrap();
+    }
+
+    #[test]
+    fn it_merges_profile_config() {
+        let mut config = toml::from_str::<Table>(
+            r#"
+            name = "data-cube"
+
+            [main]
+            host = "127.0.0.1"
+            port = 6080
+
+            [[standby]]
+            port = 6081
+
+            [[accessor]]
+            scheme = "s3"
+            name = "assets"
+            bucket = "assets"
+            endpoint = "http://127.0.0.1:9000"
+
+            [[accessor]]
+            scheme = "local"
+            name = "logs"
+            root = "./logs"
+            "#,
+        )
+        .unwrap();
+        let profile_config = toml::from_str::<Table>(
+            r#"
+            [main]
+            port = 8080
+
+            [[standby]]
+            port = 8081
+
+            [[accessor]]
+            name = "assets"
+            endpoint = "https://s3.amazonaws.com"
+
+            [[accessor]]
+            scheme = "memory"
+            name = "cache"
+            "#,
+        )
+        .unwrap();
+        merge_config(&mut config, profile_config);
+
+        let main = config.get_table("main").unwrap();
+        assert_eq!(main.get_str("host"), Some("127.0.0.1"));
+        assert_eq!(main.get_u16("port"), Some(8080));
+        assert_eq!(config.get_array("standby").map(|v| v.len()), Some(1));
+
+        let accessors = config.get_array("accessor").unwrap();
+        let names = accessors
+            .iter()
+            .filter_map(|v| v.as_table().and_then(|v| v.get_str("name")))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["assets", "logs", "cache"]);
+
+        let assets = accessors[0].as_table().unwrap();
+        assert_eq!(assets.get_str("bucket"), Some("assets"));
+        assert_eq!(assets.get_str("endpoint"), Some("https://s3.amazonaws.com"));
+    }
+}
@@ -5,7 +5,13 @@ mod timing_metric;
 mod trace_context;
 mod trace_state;
 
+#[cfg(feature = "opentelemetry")]
+mod propagation;
+
 pub use server_timing::ServerTiming;
 pub use timing_metric::TimingMetric;
 pub use trace_context::TraceContext;
 pub use trace_state::TraceState;
+
+#[cfg(feature = "opentelemetry")]
+pub use propagation::set_span_parent;
@@ -0,0 +1,112 @@
+use http::header::HeaderMap;
+use opentelemetry::{
+    propagation::{Extractor, TextMapPropagator},
+    sdk::propagation::TraceContextPropagator,
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Sets the parent of the span to the remote context in the `traceparent` and `tracestate`
+/// headers, so that the span is exported as a child of the caller's span.
+/// It should be called before the span is entered.
+pub fn set_span_parent(span: &Span, headers: &HeaderMap) {
+    let context = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    span.set_parent(context);
+}
+
+/// An extractor of the propagation fields in the HTTP headers.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    #[inline]
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    #[inline]
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::set_span_parent;
+    use http::header::HeaderMap;
+    use opentelemetry::{
+        sdk::{
+            export::trace::SpanData,
+            trace::{Span, SpanProcessor, TracerProvider},
+        },
+        trace::{SpanId, TraceId, TraceResult, TracerProvider as _},
+        Context,
+    };
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    /// A span processor which records the finished spans.
+    #[derive(Debug, Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanProcessor for SpanRecorder {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.0.lock().push(span);
+        }
+
+        fn force_flush(&self) -> TraceResult<()> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> TraceResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_honors_inbound_traceparent() {
+        let recorder = SpanRecorder::default();
+        let provider = TracerProvider::builder()
+            .with_span_processor(recorder.clone())
+            .build();
+        let tracer = provider.tracer("zino");
+        let subscriber =
+            Registry::default().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, || {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                    .parse()
+                    .unwrap(),
+            );
+            let span = tracing::info_span!("HTTP request");
+            set_span_parent(&span, &headers);
+            span.in_scope(|| tracing::info!("started processing request"));
+
+            let span = tracing::info_span!("HTTP request");
+            set_span_parent(&span, &HeaderMap::new());
+            span.in_scope(|| tracing::info!("started processing request"));
+        });
+
+        let spans = recorder.0.lock();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].name, "HTTP request");
+        assert_eq!(
+            spans[0].span_context.trace_id(),
+            TraceId::from_u128(0x4bf92f3577b34da6a3ce929d0e0e4736)
+        );
+        assert_eq!(
+            spans[0].parent_span_id,
+            SpanId::from_u64(0x00f067aa0ba902b7)
+        );
+        assert!(spans[0].span_context.is_sampled());
+        assert_ne!(
+            spans[1].span_context.trace_id(),
+            spans[0].span_context.trace_id()
+        );
+        assert_eq!(spans[1].parent_span_id, SpanId::INVALID);
+    }
+}
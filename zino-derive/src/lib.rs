@@ -37,9 +37,21 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
     let mut reader_name = String::from("main");
     let mut writer_name = String::from("main");
     let mut distribution_column = None;
+    let mut soft_delete_column = None;
+    let mut timestamps = false;
+    let mut id_strategy = None;
+    let mut relations = Vec::new();
     for attr in input.attrs.iter() {
+        let mut related_model = None;
+        let mut foreign_key = None;
+        let mut relation_name = None;
         for (key, value) in parser::parse_attr(attr).into_iter() {
-            if let Some(value) = value {
+            if key == "soft_delete" {
+                let column_name = value.unwrap_or_else(|| "deleted_at".to_owned());
+                soft_delete_column = Some(column_name);
+            } else if key == "timestamps" {
+                timestamps = true;
+            } else if let Some(value) = value {
                 match key.as_str() {
                     "type_name" => {
                         type_name = value;
@@ -56,10 +68,33 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
                     "distribution_column" => {
                         distribution_column = Some(value);
                     }
+                    "id_strategy" => {
+                        id_strategy = Some(value);
+                    }
+                    "has_many" => {
+                        related_model = Some(value);
+                    }
+                    "foreign_key" => {
+                        foreign_key = Some(value);
+                    }
+                    "relation" => {
+                        relation_name = Some(value);
+                    }
                     _ => panic!("struct attribute `{key}` is not supported"),
                 }
             }
         }
+        if let Some(model) = related_model {
+            let foreign_key = foreign_key
+                .unwrap_or_else(|| panic!("the `foreign_key` for `{model}` should be specified"));
+            let relation_name = relation_name.unwrap_or_else(|| {
+                let model_name = model.rsplit("::").next().unwrap_or(&model);
+                parser::to_snake_case(model_name) + "s"
+            });
+            let model_type = syn::parse_str::<syn::Type>(&model)
+                .unwrap_or_else(|_| panic!("the related model `{model}` should be a type"));
+            relations.push((relation_name, model_type, foreign_key));
+        }
     }
 
     // Columns
@@ -67,6 +102,11 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
     let mut column_fields = Vec::new();
     let mut readonly_fields = Vec::new();
     let mut writeonly_fields = Vec::new();
+    let mut version_column = None;
+    let mut created_at_column = None;
+    let mut updated_at_column = None;
+    let mut primary_key_type = None;
+    let mut validations = Vec::new();
     if let Data::Struct(data) = input.data && let Fields::Named(fields) = data.fields {
         for field in fields.named.into_iter() {
             let mut type_name = parser::get_type_name(&field.ty);
@@ -75,6 +115,11 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
                 let mut default_value = None;
                 let mut not_null = false;
                 let mut index_type = None;
+                let mut nonempty = false;
+                let mut min_length = None;
+                let mut max_length = None;
+                let mut format = None;
+                let mut manual = false;
                 for attr in field.attrs.iter() {
                     for (key, value) in parser::parse_attr(attr).into_iter() {
                         match key.as_str() {
@@ -98,6 +143,24 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
                             "writeonly" => {
                                 writeonly_fields.push(quote!{ #name });
                             }
+                            "version" => {
+                                version_column = Some(name.clone());
+                            }
+                            "manual" => {
+                                manual = true;
+                            }
+                            "nonempty" => {
+                                nonempty = true;
+                            }
+                            "min_length" => {
+                                min_length = value.map(|s| parse_length(&name, &s));
+                            }
+                            "max_length" => {
+                                max_length = value.map(|s| parse_length(&name, &s));
+                            }
+                            "format" => {
+                                format = value;
+                            }
                             "readwrite" => (),
                             "internal" => {
                                 readonly_fields.push(quote!{ #name });
@@ -107,6 +170,88 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
                         }
                     }
                 }
+                if version_column.as_ref() == Some(&name) &&
+                    !["u64", "i64", "u32", "i32"].contains(&type_name.as_str())
+                {
+                    panic!("the version column `{name}` should be an integer");
+                }
+                if name == primary_key_name {
+                    primary_key_type = Some(type_name.clone());
+                }
+                if timestamps && !manual {
+                    if name == "created_at" {
+                        created_at_column = Some(name.clone());
+                    } else if name == "updated_at" {
+                        updated_at_column = Some(name.clone());
+                    }
+                }
+                if nonempty || min_length.is_some() || max_length.is_some() || format.is_some() {
+                    let mut checks = Vec::new();
+                    let is_vec = type_name.starts_with("Vec");
+                    if !is_vec && !["String", "Option<String>"].contains(&type_name.as_str()) {
+                        panic!("the validated field `{name}` should be a string or vec");
+                    }
+                    let (length, unit) = if is_vec {
+                        (quote! { value.len() }, "items")
+                    } else {
+                        (quote! { value.chars().count() }, "characters")
+                    };
+                    if nonempty {
+                        let is_empty = if is_vec {
+                            quote! { value.is_empty() }
+                        } else {
+                            quote! { value.trim().is_empty() }
+                        };
+                        checks.push(quote! {
+                            if #is_empty {
+                                validation.record(#name, "should be nonempty");
+                            }
+                        });
+                    }
+                    if let Some(min_length) = min_length {
+                        let message = format!("should have at least {min_length} {unit}");
+                        checks.push(quote! {
+                            if #length < #min_length {
+                                validation.record(#name, #message);
+                            }
+                        });
+                    }
+                    if let Some(max_length) = max_length {
+                        let message = format!("should have at most {max_length} {unit}");
+                        checks.push(quote! {
+                            if #length > #max_length {
+                                validation.record(#name, #message);
+                            }
+                        });
+                    }
+                    if let Some(format) = format {
+                        if is_vec {
+                            panic!("the `format` rule of `{name}` should be used for a string");
+                        }
+                        checks.push(quote! {
+                            if !value.is_empty() {
+                                if let Err(err) = zino_core::request::Validation::validate_format(#format, value) {
+                                    validation.record_fail(#name, err);
+                                }
+                            }
+                        });
+                    }
+                    let validation = if type_name.starts_with("Option") {
+                        quote! {
+                            if let Some(value) = self.#ident.as_deref() {
+                                #(#checks)*
+                            }
+                        }
+                    } else {
+                        quote! {
+                            {
+                                let value = &self.#ident;
+                                #(#checks)*
+                            }
+                        }
+                    };
+                    validations.push(validation);
+                }
                 if type_name.starts_with("Option") {
                     not_null = false;
                 } else if type_name == "Uuid" {
@@ -151,7 +296,102 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
     } else {
         quote! { None }
     };
+    let quote_soft_delete_column = if let Some(column_name) = soft_delete_column {
+        quote! { Some(#column_name) }
+    } else {
+        quote! { None }
+    };
+    let quote_version_column = if let Some(column_name) = version_column {
+        quote! { Some(#column_name) }
+    } else {
+        quote! { None }
+    };
+    let quote_created_at_column = if let Some(column_name) = created_at_column {
+        quote! { Some(#column_name) }
+    } else {
+        quote! { None }
+    };
+    let quote_updated_at_column = if let Some(column_name) = updated_at_column {
+        quote! { Some(#column_name) }
+    } else {
+        quote! { None }
+    };
     let schema_primary_key = format_ident!("{}", primary_key_name);
+    let quote_id_strategy = if let Some(strategy) = id_strategy {
+        let variant = match strategy.as_str() {
+            "uuid" | "uuid_v4" => "UuidV4",
+            "uuid_v7" => "UuidV7",
+            "ulid" => "Ulid",
+            "snowflake" => "Snowflake",
+            _ => panic!("the ID strategy `{strategy}` is not supported"),
+        };
+        let primary_key_type = primary_key_type.unwrap_or_default();
+        let supported = match primary_key_type.as_str() {
+            "Uuid" => variant != "Snowflake",
+            "String" => true,
+            "i64" | "u64" => variant == "Snowflake",
+            _ => false,
+        };
+        if !supported {
+            panic!(
+                "the primary key `{primary_key_name}` is not supported by the ID strategy `{strategy}`"
+            );
+        }
+
+        let strategy_variant = format_ident!("{}", variant);
+        quote! {
+            const ID_STRATEGY: Option<zino_core::database::IdStrategy> =
+                Some(zino_core::database::IdStrategy::#strategy_variant);
+        }
+    } else {
+        quote! {}
+    };
+    let quote_load_relations = if relations.is_empty() {
+        quote! {}
+    } else {
+        let relation_keys = relations.iter().map(|relation| &relation.0);
+        let relation_names = relations.iter().map(|relation| &relation.0);
+        let relation_models = relations.iter().map(|relation| &relation.1);
+        let foreign_keys = relations.iter().map(|relation| &relation.2);
+        quote! {
+            async fn load_relations(
+                relations: &[String],
+                data: &mut [zino_core::Map],
+            ) -> Result<u64, ZinoError> {
+                let mut rows = 0;
+                for relation in relations {
+                    rows += match relation.as_str() {
+                        #(#relation_keys => {
+                            Self::load_has_many::<#relation_models>(#relation_names, #foreign_keys, data).await?
+                        })*
+                        _ => {
+                            let message = format!(
+                                "the relation `{relation}` is not declared for the model `{}`",
+                                Self::TYPE_NAME
+                            );
+                            return Err(ZinoError::new(message));
+                        }
+                    };
+                }
+                Ok(rows)
+            }
+        }
+    };
+    let quote_validate = if validations.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn validate(&self) -> Result<(), zino_core::request::Validation> {
+                let mut validation = zino_core::request::Validation::new();
+                #(#validations)*
+                if validation.is_success() {
+                    Ok(())
+                } else {
+                    Err(validation)
+                }
+            }
+        }
+    };
     let schema_columns = format_ident!("{}_COLUMNS", type_name_uppercase);
     let schema_fields = format_ident!("{}_FIELDS", type_name_uppercase);
     let schema_readonly_fields = format_ident!("{}_READONLY_FIELDS", type_name_uppercase);
@@ -222,6 +462,11 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
             const READER_NAME: &'static str = #reader_name;
             const WRITER_NAME: &'static str = #writer_name;
             const DISTRIBUTION_COLUMN: Option<&'static str> = #quote_distribution_column;
+            const SOFT_DELETE_COLUMN: Option<&'static str> = #quote_soft_delete_column;
+            const VERSION_COLUMN: Option<&'static str> = #quote_version_column;
+            const CREATED_AT_COLUMN: Option<&'static str> = #quote_created_at_column;
+            const UPDATED_AT_COLUMN: Option<&'static str> = #quote_updated_at_column;
+            #quote_id_strategy
 
             fn schema() -> &'static apache_avro::Schema {
                 std::sync::LazyLock::force(&#avro_schema)
@@ -252,6 +497,10 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
                 self.#schema_primary_key.to_string()
             }
 
+            #quote_load_relations
+
+            #quote_validate
+
             async fn acquire_reader() -> Result<&'static ConnectionPool, ZinoError> {
                 if let Some(connection_pool) = #schema_reader.get() {
                     Ok(*connection_pool)
@@ -309,3 +558,10 @@ pub fn schema_macro(item: TokenStream) -> TokenStream {
 
     TokenStream::from(output)
 }
+
+/// Parses the length in a validation rule.
+fn parse_length(name: &str, value: &str) -> usize {
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("the length for the field `{name}` should be an integer"))
+}
@@ -44,3 +44,19 @@ pub(crate) fn parse_attr(attr: &Attribute) -> Vec<(String, Option<String>)> {
     }
     arguments
 }
+
+/// Converts a type name to the snake case.
+pub(crate) fn to_snake_case(s: &str) -> String {
+    let mut snake_case = String::with_capacity(s.len() + 4);
+    for (index, ch) in s.char_indices() {
+        if ch.is_ascii_uppercase() {
+            if index > 0 {
+                snake_case.push('_');
+            }
+            snake_case.push(ch.to_ascii_lowercase());
+        } else {
+            snake_case.push(ch);
+        }
+    }
+    snake_case
+}
@@ -11,11 +11,12 @@ use zino_derive::Schema;
 #[derive(Debug, Clone, Default, Serialize, Deserialize, Schema)]
 #[serde(rename_all = "snake_case")]
 #[serde(default)]
+#[schema(soft_delete, timestamps, id_strategy = "uuid_v7")]
 pub struct User {
     // Basic fields.
     #[schema(readonly)]
     id: Uuid,
-    #[schema(not_null, index = "text")]
+    #[schema(not_null, nonempty, max_length = 64, index = "text")]
     name: String,
     #[schema(default = "User::model_namespace", index = "hash")]
     namespace: String,
@@ -29,11 +30,12 @@ pub struct User {
     // Info fields.
     #[schema(not_null, writeonly)]
     access_key_id: String,
-    #[schema(not_null, writeonly)]
+    #[schema(not_null, writeonly, min_length = 3, max_length = 64)]
     account: String,
     #[schema(not_null, writeonly)]
     password: String,
     mobile: String,
+    #[schema(format = "email")]
     email: String,
     avatar: String,
     roles: Vec<String>,
@@ -52,6 +54,9 @@ pub struct User {
     created_at: DateTime,
     #[schema(default = "now", index = "btree")]
     updated_at: DateTime,
+    #[schema(readonly)]
+    deleted_at: Option<DateTime>,
+    #[schema(version)]
     version: u64,
     edition: u32,
 }
@@ -60,7 +65,6 @@ impl Model for User {
     #[inline]
     fn new() -> Self {
         Self {
-            id: Uuid::new_v4(),
             access_key_id: AccessKeyId::new().to_string(),
             ..Self::default()
         }
@@ -243,7 +247,10 @@ static USER_ROLE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
 #[cfg(test)]
 mod tests {
     use super::User;
-    use zino_core::{extend::JsonObjectExt, model::Model, Map};
+    use zino_core::{
+        database::Schema, error::Error, extend::JsonObjectExt, model::Model, request::Validation,
+        Map,
+    };
 
     #[test]
     fn it_checks_user_roles() {
@@ -262,4 +269,47 @@ mod tests {
         assert!(alice.has_role("auditor:log"));
         assert!(!alice.has_role("auditor_record"));
     }
+
+    #[test]
+    fn it_validates_user_fields() {
+        let mut bob = User::new();
+        bob.name = "bob".to_owned();
+        bob.account = "bob".to_owned();
+        bob.email = "bob@example.com".to_owned();
+        assert!(bob.validate().is_ok());
+
+        bob.name = " ".to_owned();
+        bob.account = "b".to_owned();
+        bob.email = "bob@example".to_owned();
+
+        let validation = bob.validate().unwrap_err();
+        assert!(validation.contains_key("name"));
+        assert!(validation.contains_key("account"));
+        assert!(validation.contains_key("email"));
+
+        let err = validation.into_error().context("fail to insert the user");
+        let validation = Validation::from_error(&err).unwrap();
+        assert!(validation.contains_key("name"));
+        assert!(validation.contains_key("account"));
+        assert!(validation.contains_key("email"));
+
+        let err = Error::new(err.source().unwrap().message().to_owned());
+        assert!(Validation::from_error(&err).is_none());
+    }
+
+    #[test]
+    fn it_generates_monotonic_user_ids() {
+        let mut alice = User::new();
+        let mut bob = User::new();
+        assert!(alice.id.is_nil() && bob.id.is_nil());
+
+        assert_eq!(alice.assign_primary_key().ok(), Some(true));
+        assert_eq!(bob.assign_primary_key().ok(), Some(true));
+        assert_eq!(alice.id.get_version_num(), 7);
+        assert!(alice.id < bob.id);
+
+        let bob_id = bob.id;
+        assert_eq!(bob.assign_primary_key().ok(), Some(false));
+        assert_eq!(bob.id, bob_id);
+    }
 }
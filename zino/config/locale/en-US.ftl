@@ -0,0 +1,2 @@
+## Task
+task-accepted = The task is accepted.
@@ -1,5 +1,5 @@
+use super::shutdown::SHUTDOWN_COORDINATOR;
 use axum::{
-    body::{Bytes, Full},
     error_handling::HandleErrorLayer,
     extract::{rejection::LengthLimitError, DefaultBodyLimit},
     http::{self, StatusCode},
@@ -15,17 +15,13 @@ use tower::{
 use tower_cookies::CookieManagerLayer;
 use tower_http::{
     add_extension::AddExtensionLayer,
-    compression::{
-        predicate::{DefaultPredicate, NotForContentType, Predicate},
-        CompressionLayer,
-    },
     decompression::DecompressionLayer,
     services::{ServeDir, ServeFile},
 };
 use zino_core::{
     application::Application,
     extend::TomlTableExt,
-    response::Response,
+    response::{Response, ResponseBody},
     schedule::{AsyncCronJob, Job, JobScheduler},
     state::State,
 };
@@ -56,22 +52,30 @@ impl Application for AxumCluster {
             .enable_all()
             .build()
             .expect("fail to build Tokio runtime with the multi thread scheduler selected");
-        let mut scheduler = JobScheduler::new();
+        let mut scheduler = JobScheduler::with_handle(Self::scheduler().clone());
         for (cron_expr, exec) in async_jobs {
             scheduler.add(Job::new_async(cron_expr, exec));
         }
+        let shutdown = LazyLock::force(&SHUTDOWN_COORDINATOR);
+        runtime.spawn(shutdown.clone().listen_for_signals());
         runtime.spawn(async move {
-            loop {
+            while !shutdown.is_shutting_down() {
+                let guard = shutdown.track("job scheduler");
                 scheduler.tick_async().await;
+                drop(guard);
 
                 // Cannot use `std::thread::sleep` because it blocks the Tokio runtime.
-                tokio::time::sleep(scheduler.time_till_next_job()).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(scheduler.time_till_next_job()) => {},
+                    _ = shutdown.signaled() => {},
+                }
             }
         });
 
         // Server config.
         let mut body_limit = 100 * 1024 * 1024; // 100MB
         let mut request_timeout = Duration::from_secs(10); // 10 seconds
+        let mut shutdown_timeout = Duration::from_secs(30); // 30 seconds
         let mut public_dir = PathBuf::new();
         let default_public_dir = Self::project_dir().join("assets");
         if let Some(server) = Self::config().get_table("server") {
@@ -81,6 +85,9 @@ impl Application for AxumCluster {
             if let Some(timeout) = server.get_duration("request-timeout") {
                 request_timeout = timeout;
             }
+            if let Some(timeout) = server.get_duration("shutdown-timeout") {
+                shutdown_timeout = timeout;
+            }
             if let Some(dir) = server.get_str("public-dir") {
                 public_dir.push(dir);
             } else {
@@ -97,6 +104,7 @@ impl Application for AxumCluster {
             .precompressed_br()
             .not_found_service(ServeFile::new(not_found_file));
 
+        State::on_reload(crate::middleware::axum_rate_limit::reload_rate_limit);
         runtime.block_on(async {
             let routes = self.routes;
             let app_state = State::default();
@@ -106,11 +114,26 @@ impl Application for AxumCluster {
                 let mut app = Router::new()
                     .route_service("/", serve_file.clone())
                     .nest_service("/assets", serve_dir.clone())
+                    .route(
+                        "/healthz",
+                        routing::get(crate::endpoint::axum_health::liveness_handler),
+                    )
+                    .route(
+                        "/readyz",
+                        routing::get(crate::endpoint::axum_health::readiness_handler),
+                    )
                     .route("/sse", routing::get(crate::endpoint::axum_sse::sse_handler))
                     .route(
                         "/websocket",
                         routing::get(crate::endpoint::axum_websocket::websocket_handler),
                     );
+                #[cfg(feature = "metrics")]
+                if let Some(endpoint) = zino_core::application::MetricsExporter::endpoint() {
+                    app = app.route(
+                        endpoint,
+                        routing::get(crate::endpoint::axum_metrics::metrics_handler),
+                    );
+                }
                 for route in &routes {
                     app = app.merge(route.clone());
                 }
@@ -120,19 +143,16 @@ impl Application for AxumCluster {
                     .fallback_service(tower::service_fn(|req| async {
                         let request = crate::AxumExtractor(req);
                         let res = Response::new(StatusCode::NOT_FOUND).provide_context(&request);
-                        Ok::<http::Response<Full<Bytes>>, Infallible>(res.into())
+                        Ok::<http::Response<ResponseBody>, Infallible>(res.into())
                     }))
                     .layer(
                         ServiceBuilder::new()
                             .layer(AddExtensionLayer::new(state))
                             .layer(DefaultBodyLimit::max(body_limit))
                             .layer(CookieManagerLayer::new())
-                            .layer(
-                                CompressionLayer::new().gzip(true).br(true).compress_when(
-                                    DefaultPredicate::new()
-                                        .and(NotForContentType::new("application/msgpack")),
-                                ),
-                            )
+                            .layer(LazyLock::force(
+                                &crate::middleware::tower_compression::COMPRESSION_MIDDLEWARE,
+                            ))
                             .layer(DecompressionLayer::new().gzip(true).br(true))
                             .layer(LazyLock::force(
                                 &crate::middleware::tower_tracing::TRACING_MIDDLEWARE,
@@ -143,6 +163,18 @@ impl Application for AxumCluster {
                             .layer(middleware::from_fn(
                                 crate::middleware::axum_context::request_context,
                             ))
+                            .layer(middleware::from_fn(
+                                crate::middleware::axum_access_log::access_log,
+                            ))
+                            .layer(middleware::from_fn(
+                                crate::middleware::axum_shutdown::track_request,
+                            ))
+                            .layer(middleware::from_fn(
+                                crate::middleware::axum_rate_limit::rate_limit,
+                            ))
+                            .layer(middleware::from_fn(
+                                crate::middleware::axum_idempotency::idempotency,
+                            ))
                             .layer(HandleErrorLayer::new(|err: BoxError| async move {
                                 let status_code = if err.is::<Elapsed>() {
                                     StatusCode::REQUEST_TIMEOUT
@@ -152,18 +184,37 @@ impl Application for AxumCluster {
                                     StatusCode::INTERNAL_SERVER_ERROR
                                 };
                                 let res = Response::new(status_code);
-                                Ok::<http::Response<Full<Bytes>>, Infallible>(res.into())
+                                Ok::<http::Response<ResponseBody>, Infallible>(res.into())
                             }))
                             .layer(TimeoutLayer::new(request_timeout)),
                     );
                 tracing::warn!(env = app_env, "listen on {listener}");
-                Server::bind(listener)
+                let server = Server::bind(listener)
                     .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                    .with_graceful_shutdown(shutdown.signaled());
+                tokio::spawn(server)
             });
-            for result in future::join_all(servers).await {
-                if let Err(err) = result {
-                    tracing::error!("server error: {err}");
-                }
+            tokio::select! {
+                results = future::join_all(servers) => {
+                    for result in results {
+                        match result {
+                            Ok(Err(err)) => tracing::error!("server error: {err}"),
+                            Err(err) => tracing::error!("server task error: {err}"),
+                            _ => {}
+                        }
+                    }
+                },
+                _ = shutdown.signaled() => {},
+            }
+
+            // Waits for the in-flight requests and jobs before exiting.
+            if let Err(tasks) = shutdown.drain(shutdown_timeout).await {
+                tracing::error!(
+                    tasks = tasks.join(", "),
+                    "force to shut down after {shutdown_timeout:?} with running tasks"
+                );
+            } else {
+                tracing::warn!("all in-flight tasks have completed");
             }
         });
     }
@@ -1,2 +1,5 @@
 #[cfg(feature = "axum")]
 pub(crate) mod axum_cluster;
+
+#[cfg(feature = "axum")]
+pub(crate) mod shutdown;
@@ -0,0 +1,187 @@
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        Arc, LazyLock,
+    },
+    time::Duration,
+};
+use tokio::sync::{watch, Notify};
+
+/// A coordinator to shut down the server gracefully.
+///
+/// Once the shutdown is triggered, the new requests are rejected and the scheduler stops
+/// ticking. The in-flight requests and jobs are tracked so that they can be drained.
+#[derive(Debug, Clone)]
+pub(crate) struct ShutdownCoordinator {
+    /// Sender of the shutdown signal.
+    signal: Arc<watch::Sender<bool>>,
+    /// In-flight tasks.
+    tasks: Arc<InFlightTasks>,
+}
+
+impl ShutdownCoordinator {
+    /// Creates a new instance.
+    pub(crate) fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            signal: Arc::new(sender),
+            tasks: Arc::new(InFlightTasks::default()),
+        }
+    }
+
+    /// Triggers the shutdown.
+    #[inline]
+    pub(crate) fn trigger(&self) {
+        self.signal.send_replace(true);
+    }
+
+    /// Returns `true` if the shutdown has been triggered.
+    #[inline]
+    pub(crate) fn is_shutting_down(&self) -> bool {
+        *self.signal.borrow()
+    }
+
+    /// Waits until the shutdown is triggered.
+    pub(crate) async fn signaled(&self) {
+        let mut receiver = self.signal.subscribe();
+        while !*receiver.borrow_and_update() {
+            if receiver.changed().await.is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Waits for a termination signal from the OS, and triggers the shutdown.
+    pub(crate) async fn listen_for_signals(self) {
+        let ctrl_c = async {
+            if let Err(err) = tokio::signal::ctrl_c().await {
+                tracing::error!("fail to listen for the `Ctrl+C` signal: {err}");
+            }
+        };
+
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut stream) => {
+                    stream.recv().await;
+                }
+                Err(err) => tracing::error!("fail to listen for the `SIGTERM` signal: {err}"),
+            }
+        };
+
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => {},
+            _ = terminate => {},
+        }
+        tracing::warn!("shutdown signal received, draining in-flight tasks");
+        self.trigger();
+    }
+
+    /// Tracks an in-flight task until the returned guard is dropped.
+    pub(crate) fn track(&self, name: impl Into<String>) -> TaskGuard {
+        let id = self.tasks.counter.fetch_add(1, Relaxed);
+        self.tasks.running.lock().insert(id, name.into());
+        TaskGuard {
+            id,
+            tasks: self.tasks.clone(),
+        }
+    }
+
+    /// Waits for the in-flight tasks to complete. If the timeout elapses,
+    /// it returns the names of the tasks which are still running.
+    pub(crate) async fn drain(&self, timeout: Duration) -> Result<(), Vec<String>> {
+        let idle = async {
+            loop {
+                let notified = self.tasks.idle.notified();
+                if self.tasks.running.lock().is_empty() {
+                    break;
+                }
+                notified.await;
+            }
+        };
+        if tokio::time::timeout(timeout, idle).await.is_ok() {
+            Ok(())
+        } else {
+            let mut tasks = self
+                .tasks
+                .running
+                .lock()
+                .values()
+                .cloned()
+                .collect::<Vec<_>>();
+            tasks.sort();
+            Err(tasks)
+        }
+    }
+}
+
+/// In-flight tasks.
+#[derive(Debug, Default)]
+struct InFlightTasks {
+    /// A counter to generate the task IDs.
+    counter: AtomicU64,
+    /// Names of the running tasks keyed by the task ID.
+    running: Mutex<HashMap<u64, String>>,
+    /// Notifies the waiters when there are no running tasks.
+    idle: Notify,
+}
+
+/// A guard which marks the task as completed when it is dropped.
+#[derive(Debug)]
+pub(crate) struct TaskGuard {
+    /// Task ID.
+    id: u64,
+    /// In-flight tasks.
+    tasks: Arc<InFlightTasks>,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        let mut running = self.tasks.running.lock();
+        running.remove(&self.id);
+        if running.is_empty() {
+            self.tasks.idle.notify_waiters();
+        }
+    }
+}
+
+/// Shared shutdown coordinator.
+pub(crate) static SHUTDOWN_COORDINATOR: LazyLock<ShutdownCoordinator> =
+    LazyLock::new(ShutdownCoordinator::new);
+
+#[cfg(test)]
+mod tests {
+    use super::ShutdownCoordinator;
+    use std::time::Duration;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn it_drains_in_flight_tasks() {
+        let runtime = Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            let coordinator = ShutdownCoordinator::new();
+            assert!(!coordinator.is_shutting_down());
+
+            let guard = coordinator.track("GET /user/list");
+            let handle = tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                drop(guard);
+            });
+            coordinator.trigger();
+            coordinator.signaled().await;
+            assert!(coordinator.is_shutting_down());
+            assert_eq!(coordinator.drain(Duration::from_secs(5)).await, Ok(()));
+            handle.await.unwrap();
+
+            let _guard = coordinator.track("job scheduler");
+            let result = coordinator.drain(Duration::from_millis(20)).await;
+            assert_eq!(result, Err(vec!["job scheduler".to_owned()]));
+        });
+    }
+}
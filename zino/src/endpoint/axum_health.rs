@@ -0,0 +1,15 @@
+use axum::{http::StatusCode, Json};
+use serde_json::Value;
+use zino_core::application::HealthCheck;
+
+/// Liveness endpoint handler.
+pub(crate) async fn liveness_handler() -> (StatusCode, Json<Value>) {
+    let status = HealthCheck::liveness();
+    (status.status_code(), Json(status.into()))
+}
+
+/// Readiness endpoint handler.
+pub(crate) async fn readiness_handler() -> (StatusCode, Json<Value>) {
+    let status = HealthCheck::readiness().await;
+    (status.status_code(), Json(status.into()))
+}
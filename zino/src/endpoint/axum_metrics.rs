@@ -0,0 +1,15 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use zino_core::application::MetricsExporter;
+
+/// Metrics endpoint handler. It renders the metrics in the Prometheus text format.
+pub(crate) async fn metrics_handler() -> Response {
+    if let Some(metrics) = MetricsExporter::render() {
+        let content_type = [(header::CONTENT_TYPE, "text/plain; version=0.0.4")];
+        (content_type, metrics).into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
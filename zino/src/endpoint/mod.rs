@@ -1,3 +1,9 @@
+#[cfg(feature = "axum")]
+pub(crate) mod axum_health;
+
+#[cfg(feature = "metrics")]
+pub(crate) mod axum_metrics;
+
 #[cfg(feature = "axum")]
 pub(crate) mod axum_sse;
 
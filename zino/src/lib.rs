@@ -36,6 +36,7 @@
 
 #![feature(async_fn_in_trait)]
 #![feature(doc_auto_cfg)]
+#![feature(let_chains)]
 #![feature(once_cell)]
 #![feature(result_option_inspect)]
 #![feature(string_leak)]
@@ -50,14 +51,17 @@ mod request;
 #[doc(no_inline)]
 pub use zino_core::{
     application::Application,
-    database::Schema,
+    database::{OptimisticLockError, Schema},
     datetime::DateTime,
     error::Error,
     extend::JsonObjectExt,
-    model::{Model, Mutation, Query},
+    model::{LikeMode, Model, Mutation, Query},
     request::{RequestContext, Validation},
-    response::ExtractRejection,
-    schedule::{AsyncCronJob, CronJob},
+    response::{ExtractRejection, SseEvent},
+    schedule::{
+        AsyncCronJob, CronJob, FallibleAsyncCronJob, Job, JobConfig, JobLock, JobStat,
+        SchedulerHandle,
+    },
     BoxFuture, Map, Record, Uuid,
 };
 
@@ -65,6 +69,8 @@ pub use zino_core::{
 pub use cluster::axum_cluster::AxumCluster;
 #[cfg(feature = "axum")]
 pub use request::axum_request::AxumExtractor;
+#[cfg(feature = "axum")]
+pub use request::axum_websocket::WebSocketSession;
 
 /// A specialized request extractor for `axum`.
 #[cfg(feature = "axum")]
@@ -76,5 +82,5 @@ pub type Response = zino_core::response::Response<axum::http::StatusCode>;
 
 /// A specialized `Result` type for `axum`.
 #[cfg(feature = "axum")]
-pub type Result<T = axum::http::Response<axum::body::Full<axum::body::Bytes>>> =
+pub type Result<T = axum::http::Response<zino_core::response::ResponseBody>> =
     std::result::Result<T, T>;
@@ -0,0 +1,251 @@
+use axum::{
+    body::{Body, BoxBody},
+    http::{Request, Response, StatusCode},
+    middleware::Next,
+};
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+use toml::value::Table;
+use tracing::Level;
+use zino_core::{application::Application, extend::TomlTableExt, request::RequestContext};
+
+pub(crate) async fn access_log(
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response<BoxBody>, StatusCode> {
+    let Some(access_log) = ACCESS_LOG.as_ref() else {
+        return Ok(next.run(req).await);
+    };
+    let start_time = Instant::now();
+    let request = crate::AxumExtractor(req);
+    let method = request.request_method().as_ref().to_owned();
+    let path = request.request_path().to_owned();
+    let query = request
+        .query_string()
+        .map(|query| access_log.redact_query(query));
+    let client_ip = request.client_ip().map(|ip| ip.to_string());
+    let request_id = request.request_id().to_string();
+
+    let res = next.run(request.0).await;
+    access_log.emit(&AccessLogEntry {
+        method,
+        path,
+        query,
+        status_code: res.status().as_u16(),
+        latency: start_time.elapsed(),
+        client_ip,
+        request_id,
+    });
+    Ok(res)
+}
+
+/// An entry of the access log.
+#[derive(Debug)]
+struct AccessLogEntry {
+    /// Request method.
+    method: String,
+    /// Request path.
+    path: String,
+    /// Query string with the sensitive values redacted.
+    query: Option<String>,
+    /// Response status code.
+    status_code: u16,
+    /// Latency of the request.
+    latency: Duration,
+    /// Client IP.
+    client_ip: Option<String>,
+    /// Request ID.
+    request_id: String,
+}
+
+/// Access log which emits one structured event per request.
+#[derive(Debug)]
+struct AccessLog {
+    /// Level of the events.
+    level: Level,
+    /// Names of the query params whose values are redacted.
+    redacted_params: Vec<String>,
+}
+
+impl AccessLog {
+    /// Creates a new instance with the config.
+    fn with_config(config: &Table) -> Self {
+        let level = config
+            .get_str("level")
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(Level::INFO);
+        let redacted_params = config
+            .get_array("redacted-params")
+            .map(|params| {
+                params
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.to_ascii_lowercase())
+                    .collect()
+            })
+            .unwrap_or_else(|| vec!["access_token".to_owned(), "token".to_owned()]);
+        Self {
+            level,
+            redacted_params,
+        }
+    }
+
+    /// Replaces the values of the sensitive params in the query string.
+    fn redact_query(&self, query: &str) -> String {
+        query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((key, _)) if self.redacted_params.contains(&key.to_ascii_lowercase()) => {
+                    format!("{key}=[REDACTED]")
+                }
+                _ => pair.to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Emits an event for the entry.
+    fn emit(&self, entry: &AccessLogEntry) {
+        macro_rules! emit_event {
+            ($level:ident) => {
+                tracing::$level!(
+                    "http.method" = entry.method.as_str(),
+                    "http.path" = entry.path.as_str(),
+                    "http.query" = entry.query.as_deref(),
+                    "http.status_code" = entry.status_code,
+                    "http.server.duration" = u64::try_from(entry.latency.as_millis()).ok(),
+                    "http.client_ip" = entry.client_ip.as_deref(),
+                    "context.request_id" = entry.request_id.as_str(),
+                    "access log"
+                )
+            };
+        }
+
+        match self.level {
+            Level::ERROR => emit_event!(error),
+            Level::WARN => emit_event!(warn),
+            Level::INFO => emit_event!(info),
+            Level::DEBUG => emit_event!(debug),
+            Level::TRACE => emit_event!(trace),
+        }
+    }
+}
+
+/// Access log configured by the `access-log` table.
+/// The params named by the always-redacted keys are also redacted.
+static ACCESS_LOG: LazyLock<Option<AccessLog>> = LazyLock::new(|| {
+    crate::AxumCluster::config()
+        .get_table("access-log")
+        .map(|config| {
+            let mut access_log = AccessLog::with_config(config);
+            for key in crate::AxumCluster::redacted_keys() {
+                let key = key.to_ascii_lowercase();
+                if !access_log.redacted_params.contains(&key) {
+                    access_log.redacted_params.push(key);
+                }
+            }
+            access_log
+        })
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessLog, AccessLogEntry};
+    use parking_lot::Mutex;
+    use std::{fmt, time::Duration};
+    use toml::value::Table;
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Level, Metadata, Subscriber,
+    };
+
+    #[derive(Default)]
+    struct EventRecorder {
+        events: Mutex<Vec<(Level, Vec<(String, String)>)>>,
+    }
+
+    struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push((field.name().to_owned(), format!("{value:?}")));
+        }
+    }
+
+    impl Subscriber for &'static EventRecorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Vec::new();
+            event.record(&mut FieldVisitor(&mut fields));
+            let level = *event.metadata().level();
+            self.events.lock().push((level, fields));
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn it_emits_access_logs() {
+        let config = toml::from_str::<Table>(
+            r#"
+            level = "warn"
+            redacted-params = ["api_key"]
+            "#,
+        )
+        .unwrap();
+        let access_log = AccessLog::with_config(&config);
+        let query = access_log.redact_query("page=2&API_KEY=secret&api_key_id=1");
+        assert_eq!(query, "page=2&API_KEY=[REDACTED]&api_key_id=1");
+
+        let recorder: &'static EventRecorder = Box::leak(Box::default());
+        let entry = AccessLogEntry {
+            method: "GET".to_owned(),
+            path: "/task/execute".to_owned(),
+            query: Some(query),
+            status_code: 200,
+            latency: Duration::from_millis(15),
+            client_ip: Some("127.0.0.1".to_owned()),
+            request_id: "5f7bf4a4-7c4b-4b44-8c1d-5e2f3a9c1b2d".to_owned(),
+        };
+        tracing::subscriber::with_default(recorder, || access_log.emit(&entry));
+
+        let events = recorder.events.lock();
+        assert_eq!(events.len(), 1);
+        let (level, fields) = &events[0];
+        assert_eq!(*level, Level::WARN);
+        let get_field = |name: &str| {
+            fields
+                .iter()
+                .find_map(|(key, value)| (key == name).then_some(value.as_str()))
+        };
+        assert_eq!(get_field("http.method"), Some(r#""GET""#));
+        assert_eq!(get_field("http.path"), Some(r#""/task/execute""#));
+        assert_eq!(
+            get_field("http.query"),
+            Some(r#""page=2&API_KEY=[REDACTED]&api_key_id=1""#)
+        );
+        assert_eq!(get_field("http.status_code"), Some("200"));
+        assert_eq!(get_field("http.client_ip"), Some(r#""127.0.0.1""#));
+        assert_eq!(get_field("http.server.duration"), Some("15"));
+        assert_eq!(
+            get_field("context.request_id"),
+            Some(r#""5f7bf4a4-7c4b-4b44-8c1d-5e2f3a9c1b2d""#)
+        );
+        assert_eq!(get_field("message"), Some("access log"));
+    }
+}
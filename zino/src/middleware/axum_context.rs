@@ -1,9 +1,10 @@
 use axum::{
     body::{Body, BoxBody},
-    http::{Request, Response, StatusCode},
+    http::{HeaderValue, Request, Response, StatusCode},
     middleware::Next,
 };
-use zino_core::request::RequestContext;
+use tracing::Span;
+use zino_core::request::{Context, RequestContext};
 
 pub(crate) async fn request_context(
     req: Request<Body>,
@@ -19,5 +20,76 @@ pub(crate) async fn request_context(
     if let Some(ctx) = new_context {
         req.extensions_mut().insert(ctx);
     }
-    Ok(next.run(req).await)
+
+    // Attach the request ID to the tracing span so that it is included in the nested logs.
+    let request_id = req
+        .extensions()
+        .get::<Context>()
+        .map(|ctx| ctx.request_id().to_string());
+    if let Some(request_id) = request_id.as_deref() {
+        Span::current().record("context.request_id", request_id);
+    }
+
+    let mut res = next.run(req).await;
+    if let Some(request_id) = request_id &&
+        !res.headers().contains_key("x-request-id") &&
+        let Ok(header_value) = HeaderValue::try_from(request_id)
+    {
+        res.headers_mut().insert("x-request-id", header_value);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::request_context;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        middleware,
+        routing::get,
+        Extension, Router,
+    };
+    use futures::executor::block_on;
+    use tower::ServiceExt;
+    use zino_core::{request::Context, Uuid};
+
+    #[test]
+    fn it_propagates_request_ids() {
+        let app = Router::new()
+            .route(
+                "/task/execute",
+                get(|Extension(ctx): Extension<Context>| async move {
+                    (StatusCode::ACCEPTED, ctx.request_id().to_string())
+                }),
+            )
+            .layer(middleware::from_fn(request_context));
+
+        // The locale is negotiated with the fixture in `config/locale`,
+        // so that the default locale in the app config is not required.
+        let request_id = Uuid::new_v4().to_string();
+        let req = Request::builder()
+            .uri("/task/execute")
+            .header("accept-language", "en-US")
+            .header("x-request-id", &request_id)
+            .body(Body::empty())
+            .unwrap();
+        let res = block_on(app.clone().oneshot(req)).unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(res.headers()["x-request-id"], request_id);
+        let body = block_on(hyper::body::to_bytes(res.into_body())).unwrap();
+        assert_eq!(body, request_id);
+
+        // A new request ID is generated if the header is missing.
+        let req = Request::builder()
+            .uri("/task/execute")
+            .header("accept-language", "en-US")
+            .body(Body::empty())
+            .unwrap();
+        let res = block_on(app.oneshot(req)).unwrap();
+        let request_id = res.headers()["x-request-id"].to_str().unwrap().to_owned();
+        assert!(request_id.parse::<Uuid>().is_ok());
+        let body = block_on(hyper::body::to_bytes(res.into_body())).unwrap();
+        assert_eq!(body, request_id);
+    }
 }
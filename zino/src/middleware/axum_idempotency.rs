@@ -0,0 +1,195 @@
+use axum::{
+    body::{boxed, Body, BoxBody, Full},
+    http::{header, Request, Response, StatusCode},
+    middleware::Next,
+};
+use std::sync::LazyLock;
+use zino_core::{
+    application::Application,
+    extend::TomlTableExt,
+    request::{CachedResponse, IdempotencyCache},
+};
+
+pub(crate) async fn idempotency(
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response<BoxBody>, StatusCode> {
+    if let Some(cache) = IDEMPOTENCY_CACHE.as_ref() {
+        replay_or_run(cache, req, next).await
+    } else {
+        Ok(next.run(req).await)
+    }
+}
+
+/// Replays the cached response if the request with the same `idempotency-key` has completed,
+/// and runs the request otherwise. The concurrent duplicates wait for the first request.
+/// The key is scoped to the caller, so the responses are never replayed to another principal.
+///
+/// The server errors and timeouts are not cached so that the request can be retried.
+async fn replay_or_run(
+    cache: &IdempotencyCache,
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response<BoxBody>, StatusCode> {
+    let key = req
+        .headers()
+        .get("idempotency-key")
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| !req.method().is_safe())
+        .map(|idempotency_key| {
+            let scope = cache.scope(req.headers());
+            let method = req.method();
+            let path = req.uri().path();
+            format!("{scope} {method} {path} {idempotency_key}")
+        });
+    let Some(key) = key else {
+        return Ok(next.run(req).await);
+    };
+
+    let _guard = cache.lock(&key).await;
+    if let Some(cached_response) = cache.get(&key).await {
+        return Ok(replay(cached_response));
+    }
+
+    let res = next.run(req).await;
+    let status_code = res.status();
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    if status_code.is_server_error()
+        || status_code == StatusCode::REQUEST_TIMEOUT
+        || content_type.is_some_and(|s| s.starts_with("text/event-stream"))
+    {
+        return Ok(res);
+    }
+
+    let (parts, body) = res.into_parts();
+    let bytes = hyper::body::to_bytes(body).await.map_err(|err| {
+        tracing::error!("fail to read the response body for the idempotency key: {err}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    cache
+        .insert(&key, status_code.as_u16(), &parts.headers, bytes.clone())
+        .await;
+    Ok(Response::from_parts(parts, boxed(Full::from(bytes))))
+}
+
+/// Constructs a response from the cached one.
+fn replay(cached_response: CachedResponse) -> Response<BoxBody> {
+    let mut builder = Response::builder()
+        .status(cached_response.status_code())
+        .header("idempotent-replayed", "true");
+    for (name, value) in cached_response.headers() {
+        builder = builder.header(name, value);
+    }
+    builder
+        .body(boxed(Full::from(cached_response.into_body())))
+        .unwrap_or_default()
+}
+
+/// Idempotency cache configured by the `idempotency` table.
+static IDEMPOTENCY_CACHE: LazyLock<Option<IdempotencyCache>> = LazyLock::new(|| {
+    crate::AxumCluster::config()
+        .get_table("idempotency")
+        .map(IdempotencyCache::with_config)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::replay_or_run;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        middleware::{self, Next},
+        routing::post,
+        Router,
+    };
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering::Relaxed},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::runtime::Builder;
+    use tower::ServiceExt;
+    use zino_core::request::IdempotencyCache;
+
+    fn new_request(idempotency_key: &str) -> Request<Body> {
+        new_request_with_token(idempotency_key, "alice")
+    }
+
+    fn new_request_with_token(idempotency_key: &str, token: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/task/execute")
+            .header("idempotency-key", idempotency_key)
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn it_replays_idempotent_requests() {
+        let cache: &'static IdempotencyCache =
+            Box::leak(Box::new(IdempotencyCache::new(Duration::from_secs(60))));
+        let executions = Arc::new(AtomicUsize::new(0));
+        let counter = executions.clone();
+        let app = Router::new()
+            .route(
+                "/task/execute",
+                post(move || async move {
+                    let execution = counter.fetch_add(1, Relaxed) + 1;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    let headers = [
+                        ("location", format!("/task/{execution}")),
+                        ("set-cookie", format!("execution={execution}")),
+                    ];
+                    let body = format!("execution {execution}");
+                    (StatusCode::ACCEPTED, headers, body)
+                }),
+            )
+            .layer(middleware::from_fn(
+                move |req: Request<Body>, next: Next<Body>| replay_or_run(cache, req, next),
+            ));
+
+        let runtime = Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            let (res, duplicate_res) = tokio::join!(
+                app.clone().oneshot(new_request("abc")),
+                app.clone().oneshot(new_request("abc")),
+            );
+            let (res, duplicate_res) = (res.unwrap(), duplicate_res.unwrap());
+            assert_eq!(executions.load(Relaxed), 1);
+            assert_eq!(res.status(), StatusCode::ACCEPTED);
+            assert_eq!(duplicate_res.status(), StatusCode::ACCEPTED);
+            assert!(!res.headers().contains_key("idempotent-replayed"));
+            assert_eq!(duplicate_res.headers()["idempotent-replayed"], "true");
+            assert_eq!(duplicate_res.headers()["location"], "/task/1");
+            assert_eq!(duplicate_res.headers()["set-cookie"], "execution=1");
+            let body = hyper::body::to_bytes(duplicate_res.into_body())
+                .await
+                .unwrap();
+            assert_eq!(body, "execution 1");
+
+            let res = app.clone().oneshot(new_request("abc")).await.unwrap();
+            assert_eq!(res.headers()["idempotent-replayed"], "true");
+            assert_eq!(executions.load(Relaxed), 1);
+
+            let res = app.clone().oneshot(new_request("xyz")).await.unwrap();
+            let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+            assert_eq!(body, "execution 2");
+            assert_eq!(executions.load(Relaxed), 2);
+
+            // The same key sent by another caller is not replayed.
+            let res = app
+                .oneshot(new_request_with_token("abc", "bob"))
+                .await
+                .unwrap();
+            assert!(!res.headers().contains_key("idempotent-replayed"));
+            assert_eq!(res.headers()["location"], "/task/3");
+            assert_eq!(executions.load(Relaxed), 3);
+        });
+    }
+}
@@ -0,0 +1,62 @@
+use axum::{
+    body::{Body, BoxBody},
+    http::{Request, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use std::sync::LazyLock;
+use toml::value::Table;
+use zino_core::{
+    application::Application,
+    extend::{HeaderMapExt, TomlTableExt},
+    request::{RateLimiter, RequestContext},
+    response::ResponseBody,
+};
+
+pub(crate) async fn rate_limit(
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response<BoxBody>, StatusCode> {
+    let Some(rate_limiter) = RATE_LIMITER.as_ref() else {
+        return Ok(next.run(req).await);
+    };
+    let request = crate::AxumExtractor(req);
+    let key = KEY_HEADER
+        .as_deref()
+        .and_then(|header| request.header_map().get_str(header))
+        .map(|api_key| format!("key:{api_key}"))
+        .or_else(|| request.client_ip().map(|ip| format!("ip:{ip}")));
+    if let Some(key) = key &&
+        let Err(wait) = rate_limiter.check(&key).await
+    {
+        let mut res = crate::Response::new(StatusCode::TOO_MANY_REQUESTS).provide_context(&request);
+        res.insert_header("retry-after", wait.as_secs_f64().ceil() as u64);
+        return Ok(axum::http::Response::<ResponseBody>::from(res).into_response());
+    }
+    Ok(next.run(request.0).await)
+}
+
+/// Updates the rate limits after the config is reloaded.
+/// Enabling or disabling the rate limiting still requires a restart.
+pub(crate) fn reload_rate_limit(config: &Table) {
+    if let Some(rate_limiter) = RATE_LIMITER.as_ref() &&
+        let Some(config) = config.get_table("rate-limit")
+    {
+        rate_limiter.reconfigure(config);
+    }
+}
+
+/// Rate limiter configured by the `rate-limit` table.
+static RATE_LIMITER: LazyLock<Option<RateLimiter>> = LazyLock::new(|| {
+    crate::AxumCluster::config()
+        .get_table("rate-limit")
+        .map(RateLimiter::with_config)
+});
+
+/// Name of the header used as the client key instead of the client IP.
+static KEY_HEADER: LazyLock<Option<String>> = LazyLock::new(|| {
+    crate::AxumCluster::config()
+        .get_table("rate-limit")
+        .and_then(|config| config.get_str("key-header"))
+        .map(|header| header.to_owned())
+});
@@ -0,0 +1,25 @@
+use crate::cluster::shutdown::SHUTDOWN_COORDINATOR;
+use axum::{
+    body::{Body, BoxBody},
+    http::{Request, Response, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use zino_core::response::ResponseBody;
+
+pub(crate) async fn track_request(
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response<BoxBody>, StatusCode> {
+    if SHUTDOWN_COORDINATOR.is_shutting_down() {
+        let request = crate::AxumExtractor(req);
+        let mut res =
+            crate::Response::new(StatusCode::SERVICE_UNAVAILABLE).provide_context(&request);
+        res.insert_header("connection", "close");
+        return Ok(axum::http::Response::<ResponseBody>::from(res).into_response());
+    }
+
+    let task_name = format!("{} {}", req.method(), req.uri().path());
+    let _guard = SHUTDOWN_COORDINATOR.track(task_name);
+    Ok(next.run(req).await)
+}
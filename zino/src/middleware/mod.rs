@@ -1,6 +1,21 @@
+#[cfg(feature = "axum")]
+pub(crate) mod axum_access_log;
+
 #[cfg(feature = "axum")]
 pub(crate) mod axum_context;
 
+#[cfg(feature = "axum")]
+pub(crate) mod axum_idempotency;
+
+#[cfg(feature = "axum")]
+pub(crate) mod axum_rate_limit;
+
+#[cfg(feature = "axum")]
+pub(crate) mod axum_shutdown;
+
+#[cfg(feature = "axum")]
+pub(crate) mod tower_compression;
+
 #[cfg(feature = "axum")]
 pub(crate) mod tower_cors;
 
@@ -0,0 +1,116 @@
+use std::sync::LazyLock;
+use toml::value::Table;
+use tower_http::compression::{
+    predicate::{And, NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+};
+use zino_core::{application::Application, extend::TomlTableExt};
+
+// Type aliases.
+type NewPredicate = And<
+    And<And<And<SizeAbove, NotForContentType>, NotForContentType>, NotForContentType>,
+    NotForContentType,
+>;
+type NewCompressionLayer = CompressionLayer<NewPredicate>;
+
+// Compression middleware.
+pub(crate) static COMPRESSION_MIDDLEWARE: LazyLock<NewCompressionLayer> = LazyLock::new(|| {
+    crate::AxumCluster::config()
+        .get_table("compression")
+        .map(new_compression_layer)
+        .unwrap_or_else(|| new_compression_layer(&Table::new()))
+});
+
+/// Creates a compression layer with the config. The response body is compressed
+/// with the encoding negotiated via `accept-encoding` if its size is above `min-size`.
+/// The images, event streams, gRPC and MessagePack responses are skipped
+/// since they are either compressed already or streamed.
+fn new_compression_layer(compression: &Table) -> NewCompressionLayer {
+    let min_size = compression
+        .get_usize("min-size")
+        .unwrap_or(1024)
+        .try_into()
+        .unwrap_or(u16::MAX);
+    let predicate = SizeAbove::new(min_size)
+        .and(NotForContentType::new("image/"))
+        .and(NotForContentType::new("text/event-stream"))
+        .and(NotForContentType::new("application/grpc"))
+        .and(NotForContentType::new("application/msgpack"));
+    CompressionLayer::new()
+        .gzip(compression.get_bool("gzip").unwrap_or(true))
+        .br(compression.get_bool("br").unwrap_or(true))
+        .compress_when(predicate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_compression_layer;
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use futures::executor::block_on;
+    use serde_json::json;
+    use toml::value::Table;
+    use tower::ServiceExt;
+
+    #[test]
+    fn it_compresses_large_bodies() {
+        let config = toml::from_str::<Table>("min-size = 1024").unwrap();
+        let report = json!({
+            "rows": (0..200).map(|i| json!({ "id": i, "status": "ok" })).collect::<Vec<_>>(),
+        })
+        .to_string();
+        let report_size = report.len();
+        assert!(report_size > 1024);
+        let app = Router::new()
+            .route(
+                "/report",
+                get(move || async move { ([(header::CONTENT_TYPE, "application/json")], report) }),
+            )
+            .route(
+                "/status",
+                get(|| async { ([(header::CONTENT_TYPE, "application/json")], "{}") }),
+            )
+            .route(
+                "/avatar",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], vec![0u8; 4096]) }),
+            )
+            .layer(new_compression_layer(&config));
+        let new_request = |path: &str, accept_encoding: Option<&str>| {
+            let mut builder = Request::builder().uri(path);
+            if let Some(accept_encoding) = accept_encoding {
+                builder = builder.header(header::ACCEPT_ENCODING, accept_encoding);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let req = new_request("/report", Some("gzip"));
+        let res = block_on(app.clone().oneshot(req)).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(res.headers()[header::VARY], "accept-encoding");
+        let body = block_on(hyper::body::to_bytes(res.into_body())).unwrap();
+        assert!(body.len() < report_size);
+
+        let req = new_request("/report", Some("br"));
+        let res = block_on(app.clone().oneshot(req)).unwrap();
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "br");
+
+        let req = new_request("/report", None);
+        let res = block_on(app.clone().oneshot(req)).unwrap();
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+        let body = block_on(hyper::body::to_bytes(res.into_body())).unwrap();
+        assert_eq!(body.len(), report_size);
+
+        let req = new_request("/status", Some("gzip"));
+        let res = block_on(app.clone().oneshot(req)).unwrap();
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+
+        let req = new_request("/avatar", Some("gzip"));
+        let res = block_on(app.oneshot(req)).unwrap();
+        assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+    }
+}
@@ -1,62 +1,189 @@
 use std::{sync::LazyLock, time::Duration};
+use toml::value::{Array, Table};
 use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer, ExposeHeaders};
-use zino_core::{application::Application, extend::TomlTableExt};
+use zino_core::{application::Application, error::Error, extend::TomlTableExt};
 
 // CORS middleware.
 pub(crate) static CORS_MIDDLEWARE: LazyLock<CorsLayer> = LazyLock::new(|| {
     if let Some(cors) = crate::AxumCluster::config().get_table("cors") {
-        let allow_credentials = cors.get_bool("allow-credentials").unwrap_or(false);
-        let allow_origin = cors
-            .get_array("allow-origin")
-            .map(|values| {
+        new_cors_layer(cors).unwrap_or_else(|err| panic!("invalid `cors` config: {err}"))
+    } else {
+        CorsLayer::permissive()
+    }
+});
+
+/// Creates a CORS layer with the config. The wildcard `*` is allowed in the lists
+/// unless `allow-credentials` is enabled, since browsers forbid the combination.
+fn new_cors_layer(cors: &Table) -> Result<CorsLayer, Error> {
+    let allow_credentials = cors.get_bool("allow-credentials").unwrap_or(false);
+    for key in [
+        "allow-origin",
+        "allow-methods",
+        "allow-headers",
+        "expose-headers",
+    ] {
+        if allow_credentials && cors.get_array(key).is_some_and(contains_wildcard) {
+            let message = format!("the wildcard `*` in `{key}` is not allowed with credentials");
+            return Err(Error::new(message));
+        }
+    }
+
+    let allow_origin = cors
+        .get_array("allow-origin")
+        .map(|values| {
+            if contains_wildcard(values) {
+                AllowOrigin::any()
+            } else {
                 let origins = values
                     .iter()
                     .filter_map(|v| v.as_str().and_then(|s| s.parse().ok()))
                     .collect::<Vec<_>>();
                 AllowOrigin::list(origins)
-            })
-            .unwrap_or_else(AllowOrigin::mirror_request);
-        let allow_methods = cors
-            .get_array("allow-methods")
-            .map(|values| {
+            }
+        })
+        .unwrap_or_else(AllowOrigin::mirror_request);
+    let allow_methods = cors
+        .get_array("allow-methods")
+        .map(|values| {
+            if contains_wildcard(values) {
+                AllowMethods::any()
+            } else {
                 let methods = values
                     .iter()
                     .filter_map(|v| v.as_str().and_then(|s| s.parse().ok()))
                     .collect::<Vec<_>>();
                 AllowMethods::list(methods)
-            })
-            .unwrap_or_else(AllowMethods::mirror_request);
-        let allow_headers = cors
-            .get_array("allow-headers")
-            .map(|values| {
+            }
+        })
+        .unwrap_or_else(AllowMethods::mirror_request);
+    let allow_headers = cors
+        .get_array("allow-headers")
+        .map(|values| {
+            if contains_wildcard(values) {
+                AllowHeaders::any()
+            } else {
                 let header_names = values
                     .iter()
                     .filter_map(|v| v.as_str().and_then(|s| s.parse().ok()))
                     .collect::<Vec<_>>();
                 AllowHeaders::list(header_names)
-            })
-            .unwrap_or_else(AllowHeaders::mirror_request);
-        let expose_headers = cors
-            .get_array("expose-headers")
-            .map(|values| {
+            }
+        })
+        .unwrap_or_else(AllowHeaders::mirror_request);
+    let expose_headers = cors
+        .get_array("expose-headers")
+        .map(|values| {
+            if contains_wildcard(values) {
+                ExposeHeaders::any()
+            } else {
                 let header_names = values
                     .iter()
                     .filter_map(|v| v.as_str().and_then(|s| s.parse().ok()))
                     .collect::<Vec<_>>();
                 ExposeHeaders::list(header_names)
-            })
-            .unwrap_or_else(ExposeHeaders::any);
-        let max_age = cors
-            .get_duration("max-age")
-            .unwrap_or_else(|| Duration::from_secs(60 * 60));
-        CorsLayer::new()
-            .allow_credentials(allow_credentials)
-            .allow_origin(allow_origin)
-            .allow_methods(allow_methods)
-            .allow_headers(allow_headers)
-            .expose_headers(expose_headers)
-            .max_age(max_age)
-    } else {
-        CorsLayer::permissive()
+            }
+        })
+        .unwrap_or_else(|| {
+            if allow_credentials {
+                ExposeHeaders::list([])
+            } else {
+                ExposeHeaders::any()
+            }
+        });
+    let max_age = cors
+        .get_duration("max-age")
+        .unwrap_or_else(|| Duration::from_secs(60 * 60));
+    let layer = CorsLayer::new()
+        .allow_credentials(allow_credentials)
+        .allow_origin(allow_origin)
+        .allow_methods(allow_methods)
+        .allow_headers(allow_headers)
+        .expose_headers(expose_headers)
+        .max_age(max_age);
+    Ok(layer)
+}
+
+/// Returns `true` if the values contain the wildcard `*`.
+fn contains_wildcard(values: &Array) -> bool {
+    values.iter().any(|v| v.as_str() == Some("*"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_cors_layer;
+    use axum::{
+        body::Body,
+        http::{Method, Request, Response, StatusCode},
+    };
+    use futures::executor::block_on;
+    use std::convert::Infallible;
+    use toml::value::Table;
+    use tower::{service_fn, Layer, ServiceExt};
+
+    fn cors_config() -> Table {
+        toml::from_str(
+            r#"
+            allow-credentials = true
+            allow-origin = ["https://app.example.com"]
+            allow-methods = ["GET", "POST"]
+            allow-headers = ["content-type", "x-api-key"]
+            max-age = "10m"
+            "#,
+        )
+        .unwrap()
     }
-});
+
+    #[test]
+    fn it_handles_cors_requests() {
+        let layer = new_cors_layer(&cors_config()).unwrap();
+        let service = layer.layer(service_fn(|_req: Request<Body>| async {
+            Ok::<_, Infallible>(Response::new(Body::from("ok")))
+        }));
+
+        let req = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/task/execute")
+            .header("origin", "https://app.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "content-type")
+            .body(Body::empty())
+            .unwrap();
+        let res = block_on(service.clone().oneshot(req)).unwrap();
+        let headers = res.headers();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://app.example.com"
+        );
+        assert_eq!(headers["access-control-allow-methods"], "GET,POST");
+        assert_eq!(
+            headers["access-control-allow-headers"],
+            "content-type,x-api-key"
+        );
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        assert_eq!(headers["access-control-max-age"], "600");
+
+        let req = Request::builder()
+            .uri("/task/execute")
+            .header("origin", "https://app.example.com")
+            .body(Body::empty())
+            .unwrap();
+        let res = block_on(service.clone().oneshot(req)).unwrap();
+        assert_eq!(
+            res.headers()["access-control-allow-origin"],
+            "https://app.example.com"
+        );
+
+        let req = Request::builder()
+            .uri("/task/execute")
+            .header("origin", "https://evil.example.com")
+            .body(Body::empty())
+            .unwrap();
+        let res = block_on(service.oneshot(req)).unwrap();
+        assert!(!res.headers().contains_key("access-control-allow-origin"));
+
+        let mut config = cors_config();
+        config.insert("allow-origin".to_owned(), vec!["*"].into());
+        assert!(new_cors_layer(&config).is_err());
+    }
+}
@@ -42,7 +42,7 @@ pub(crate) static TRACING_MIDDLEWARE: LazyLock<NewTraceLayer> = LazyLock::new(||
 fn new_make_span(request: &Request<Body>) -> Span {
     let uri = request.uri();
     let headers = request.headers();
-    tracing::info_span!(
+    let span = tracing::info_span!(
         "HTTP request",
         "otel.kind" = "server",
         "otel.name" = crate::AxumCluster::name(),
@@ -64,7 +64,10 @@ fn new_make_span(request: &Request<Body>) -> Span {
         "context.span_id" = Empty,
         "context.trace_id" = Empty,
         "context.parent_id" = Empty,
-    )
+    );
+    #[cfg(feature = "opentelemetry")]
+    zino_core::trace::set_span_parent(&span, headers);
+    span
 }
 
 fn new_on_request(request: &Request<Body>, span: &Span) {
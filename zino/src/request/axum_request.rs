@@ -4,14 +4,20 @@ use axum::{
     extract::{FromRequest, MatchedPath},
     http::{HeaderMap, Method, Request},
 };
-use hyper::body::{self, Buf, HttpBody};
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use hyper::body::{Bytes, HttpBody};
 use std::{
     convert::Infallible,
-    io::Read,
+    mem,
     net::{IpAddr, SocketAddr},
     ops::{Deref, DerefMut},
     sync::LazyLock,
 };
+use tokio::time;
 use toml::value::Table;
 use tower_cookies::{Cookie, Cookies, Key};
 use zino_core::{
@@ -19,7 +25,7 @@ use zino_core::{
     channel::CloudEvent,
     error::Error,
     extend::HeaderMapExt,
-    request::{Context, RequestContext},
+    request::{BodyLimit, BodyLimitError, Context, RequestContext},
     response::Rejection,
     state::State,
     Map,
@@ -96,6 +102,11 @@ impl RequestContext for AxumExtractor<Request<Body>> {
         self.extensions().get::<Context>()
     }
 
+    #[inline]
+    fn get_context_mut(&mut self) -> Option<&mut Context> {
+        self.extensions_mut().get_mut::<Context>()
+    }
+
     #[inline]
     fn get_cookie(&self, name: &str) -> Option<Cookie<'static>> {
         let cookies = self.extensions().get::<Cookies>()?;
@@ -157,12 +168,54 @@ impl RequestContext for AxumExtractor<Request<Body>> {
     }
 
     async fn read_body_bytes(&mut self) -> Result<Vec<u8>, Error> {
-        let buffer_size = self.size_hint().lower().try_into().unwrap_or(128);
-        let body = body::aggregate(self.body_mut()).await?;
+        let max_size = self.body_limit().max_size();
+        let buffer_size = self
+            .size_hint()
+            .lower()
+            .min(max_size)
+            .try_into()
+            .unwrap_or(128);
+        let mut stream = self.take_body_stream();
         let mut bytes = Vec::with_capacity(buffer_size);
-        body.reader().read_to_end(&mut bytes)?;
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
         Ok(bytes)
     }
+
+    fn take_body_stream(&mut self) -> BoxStream<'static, Result<Bytes, Error>> {
+        let body_limit = self.body_limit();
+        let content_length = self
+            .get_header("content-length")
+            .and_then(|s| s.parse().ok());
+        if let Err(err) = body_limit.check_content_length(content_length) {
+            return stream::once(future::ready(Err(err))).boxed();
+        }
+
+        // The read timeout applies to each chunk, so that a large body uploaded steadily
+        // is not aborted while a stalled client is.
+        let read_timeout = body_limit.read_timeout();
+        let body = mem::take(self.body_mut());
+        let stream = stream::unfold(Some(body), move |body| async move {
+            let mut body = body?;
+            match time::timeout(read_timeout, body.data()).await {
+                Ok(result) => Some((result?.map_err(Error::from), Some(body))),
+                Err(_) => {
+                    let err = Error::from_typed(BodyLimitError::Timeout(read_timeout));
+                    Some((Err(err), None))
+                }
+            }
+        });
+        body_limit.limit_stream(stream)
+    }
+
+    #[inline]
+    fn body_limit(&self) -> BodyLimit {
+        self.extensions()
+            .get::<BodyLimit>()
+            .copied()
+            .unwrap_or_else(|| *BodyLimit::shared())
+    }
 }
 
 #[async_trait]
@@ -180,3 +233,169 @@ static COOKIE_PRIVATE_KEY: LazyLock<Key> = LazyLock::new(|| {
     let secret_key = crate::AxumCluster::secret_key();
     Key::try_from(secret_key).unwrap_or_else(|_| Key::generate())
 });
+
+#[cfg(test)]
+mod tests {
+    use super::AxumExtractor;
+    use axum::{
+        body::Body,
+        http::{self, Request, StatusCode},
+        routing::get,
+        Router,
+    };
+    use futures::executor::block_on;
+    use hyper::body::Bytes;
+    use std::time::Duration;
+    use tokio::runtime::Builder;
+    use tower::ServiceExt;
+    use zino_core::{
+        authentication::Principal,
+        extend::JsonObjectExt,
+        request::{BodyLimit, BodyLimitError, Context, RequestContext},
+        response::ResponseBody,
+        Uuid,
+    };
+
+    #[test]
+    fn it_rejects_oversize_and_slow_bodies() {
+        let runtime = Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            let (mut sender, body) = Body::channel();
+            let mut req = Request::new(body);
+            req.extensions_mut()
+                .insert(BodyLimit::new(8, Duration::from_secs(5)));
+            let sent_chunks = tokio::spawn(async move {
+                let mut sent_chunks = 0;
+                while sender.send_data(Bytes::from_static(b"zino!")).await.is_ok() {
+                    sent_chunks += 1;
+                }
+                sent_chunks
+            });
+            let err = AxumExtractor(req).read_body_bytes().await.unwrap_err();
+            assert!(BodyLimitError::is_too_large(&err));
+            assert!(sent_chunks.await.unwrap() < 4);
+
+            let mut req = Request::new(Body::from("zino"));
+            req.headers_mut().insert("content-length", 1024.into());
+            req.extensions_mut()
+                .insert(BodyLimit::new(8, Duration::from_secs(5)));
+            let err = AxumExtractor(req).read_body_bytes().await.unwrap_err();
+            assert!(BodyLimitError::is_too_large(&err));
+
+            let (mut sender, body) = Body::channel();
+            let mut req = Request::new(body);
+            req.extensions_mut()
+                .insert(BodyLimit::new(1024, Duration::from_millis(50)));
+            let slow_body = tokio::spawn(async move {
+                sender.send_data(Bytes::from_static(b"zino")).await.ok();
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                sender.send_data(Bytes::from_static(b"!")).await.ok();
+            });
+            let err = AxumExtractor(req).read_body_bytes().await.unwrap_err();
+            assert!(BodyLimitError::is_timeout(&err));
+            slow_body.abort();
+
+            // The body is read if each chunk arrives within the timeout.
+            let (mut sender, body) = Body::channel();
+            let mut req = Request::new(body);
+            req.extensions_mut()
+                .insert(BodyLimit::new(1024, Duration::from_millis(50)));
+            let steady_body = tokio::spawn(async move {
+                for _ in 0..5 {
+                    sender.send_data(Bytes::from_static(b"zino!")).await.ok();
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+            });
+            let bytes = AxumExtractor(req).read_body_bytes().await.unwrap();
+            assert_eq!(bytes.len(), 25);
+            steady_body.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn it_extracts_typed_params() {
+        let app = Router::new().route(
+            "/user/:id/file/*path",
+            get(|req: Request<Body>| async move {
+                let req = AxumExtractor(req);
+                let res: http::Response<ResponseBody> = match req.param::<Uuid>("id") {
+                    Ok(id) => {
+                        let params = req.params();
+                        let path = params.get_str("path").unwrap_or_default();
+                        http::Response::new(format!("{id} {path}").into())
+                    }
+                    Err(rejection) => rejection.into(),
+                };
+                res
+            }),
+        );
+
+        let user_id = Uuid::new_v4();
+        let req = Request::builder()
+            .uri(format!("/user/{user_id}/file/docs/readme.md"))
+            .body(Body::empty())
+            .unwrap();
+        let res = block_on(app.clone().oneshot(req)).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = block_on(hyper::body::to_bytes(res.into_body())).unwrap();
+        assert_eq!(body, format!("{user_id} docs/readme.md"));
+
+        let req = Request::builder()
+            .uri("/user/42/file/readme.md")
+            .body(Body::empty())
+            .unwrap();
+        let res = block_on(app.oneshot(req)).unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = block_on(hyper::body::to_bytes(res.into_body())).unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("invalid value `42` for the param `id`"));
+    }
+
+    #[test]
+    fn it_rejects_missing_api_keys() {
+        let req = Request::builder()
+            .uri("/task/execute")
+            .header("authorization", "Basic em9ubzp6aW5v")
+            .body(Body::empty())
+            .unwrap();
+        let mut req = AxumExtractor(req);
+        let rejection = block_on(req.parse_api_key()).unwrap_err();
+        let res = http::Response::<ResponseBody>::from(rejection);
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(req.principal().is_none());
+    }
+
+    #[test]
+    fn it_guards_controllers_by_roles() {
+        let new_request = |roles: Option<&[&str]>| {
+            let mut req = Request::new(Body::empty());
+            let mut ctx = Context::new(Uuid::new_v4());
+            if let Some(roles) = roles {
+                let mut principal = Principal::new("reporter");
+                principal.set_roles(roles.iter().map(|&role| role.to_owned()).collect());
+                ctx.set_principal(Some(principal));
+            }
+            req.extensions_mut().insert(ctx);
+            AxumExtractor(req)
+        };
+
+        let req = new_request(Some(&["admin", "viewer"][..]));
+        let principal = req.require_roles(&["admin"]).unwrap();
+        assert_eq!(principal.id(), "reporter");
+        assert!(req.require_roles(&["admin", "viewer"]).is_ok());
+        assert!(req.require_any_role(&["auditor", "viewer"]).is_ok());
+
+        let req = new_request(Some(&["viewer"][..]));
+        let rejection = req.require_roles(&["admin", "viewer"]).unwrap_err();
+        let res = http::Response::<ResponseBody>::from(rejection);
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let rejection = req.require_any_role(&["admin", "auditor"]).unwrap_err();
+        let res = http::Response::<ResponseBody>::from(rejection);
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let req = new_request(None);
+        let rejection = req.require_any_role(&["viewer"]).unwrap_err();
+        let res = http::Response::<ResponseBody>::from(rejection);
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
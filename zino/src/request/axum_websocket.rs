@@ -0,0 +1,121 @@
+use crate::AxumExtractor;
+use axum::{
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        FromRequestParts,
+    },
+    http::{self, Request},
+};
+use std::{future::Future, sync::LazyLock, time::Duration};
+use tokio::time::{self, Instant, Interval};
+use zino_core::{
+    application::Application,
+    error::Error,
+    extend::TomlTableExt,
+    request::RequestContext,
+    response::{Rejection, ResponseBody},
+};
+
+impl AxumExtractor<Request<Body>> {
+    /// Upgrades the connection to the WebSocket protocol and runs the handler with the session.
+    /// A request which is not a valid upgrade request is rejected with a `400 Bad Request`.
+    pub async fn upgrade_websocket<F, Fut>(self, handler: F) -> crate::Result
+    where
+        F: FnOnce(WebSocketSession) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if let Err(rejection) = self.websocket_accept_key() {
+            return Err(rejection.into());
+        }
+
+        let (mut parts, _) = self.0.into_parts();
+        let ws = match WebSocketUpgrade::from_request_parts(&mut parts, &()).await {
+            Ok(ws) => ws,
+            Err(rejection) => {
+                let err = Error::new(rejection.body_text());
+                return Err(Rejection::from_validation_entry("connection", err).into());
+            }
+        };
+        let res = ws.on_upgrade(|socket| handler(WebSocketSession::new(socket)));
+        let (parts, _) = res.into_parts();
+        Ok(http::Response::from_parts(parts, ResponseBody::default()))
+    }
+}
+
+/// A WebSocket session with the ping/pong keepalive.
+///
+/// A ping frame is sent when the session is idle for the ping interval,
+/// and the session is closed if no frame is received within the pong timeout.
+pub struct WebSocketSession {
+    /// WebSocket stream.
+    socket: WebSocket,
+    /// Interval for sending ping frames.
+    ping_interval: Interval,
+    /// Time when the last frame is received.
+    last_seen: Instant,
+}
+
+impl WebSocketSession {
+    /// Creates a new instance with the WebSocket stream.
+    fn new(socket: WebSocket) -> Self {
+        let (ping_interval, _) = *LazyLock::force(&KEEPALIVE_CONFIG);
+        Self {
+            socket,
+            ping_interval: time::interval_at(Instant::now() + ping_interval, ping_interval),
+            last_seen: Instant::now(),
+        }
+    }
+
+    /// Receives the next message. Ping and pong frames are handled internally,
+    /// and `None` is returned when the connection is closed or timed out.
+    pub async fn recv(&mut self) -> Option<Result<Message, Error>> {
+        let (_, pong_timeout) = *LazyLock::force(&KEEPALIVE_CONFIG);
+        loop {
+            tokio::select! {
+                message = self.socket.recv() => {
+                    self.last_seen = Instant::now();
+                    match message? {
+                        Ok(Message::Ping(_) | Message::Pong(_)) => (),
+                        Ok(message) => return Some(Ok(message)),
+                        Err(err) => return Some(Err(err.into())),
+                    }
+                }
+                _ = self.ping_interval.tick() => {
+                    if self.last_seen.elapsed() > pong_timeout {
+                        tracing::warn!("close the WebSocket session due to the pong timeout");
+                        self.socket.send(Message::Close(None)).await.ok();
+                        return None;
+                    }
+                    if let Err(err) = self.socket.send(Message::Ping(Vec::new())).await {
+                        return Some(Err(err.into()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends a message.
+    #[inline]
+    pub async fn send(&mut self, message: impl Into<Message>) -> Result<(), Error> {
+        self.socket.send(message.into()).await.map_err(Error::from)
+    }
+
+    /// Closes the session gracefully.
+    #[inline]
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.send(Message::Close(None)).await
+    }
+}
+
+/// Ping interval and pong timeout configured by the `websocket` table.
+static KEEPALIVE_CONFIG: LazyLock<(Duration, Duration)> = LazyLock::new(|| {
+    let config = crate::AxumCluster::config().get_table("websocket");
+    let ping_interval = config
+        .and_then(|config| config.get_duration("ping-interval"))
+        .unwrap_or_else(|| Duration::from_secs(30));
+    let pong_timeout = config
+        .and_then(|config| config.get_duration("pong-timeout"))
+        .unwrap_or(ping_interval * 2);
+    (ping_interval, pong_timeout)
+});
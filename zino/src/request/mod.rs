@@ -1,2 +1,5 @@
 #[cfg(feature = "axum")]
 pub(crate) mod axum_request;
+
+#[cfg(feature = "axum")]
+pub(crate) mod axum_websocket;